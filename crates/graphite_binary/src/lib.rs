@@ -1,3 +1,7 @@
+// Allows the slice_serializable! macro to be used in this crate's tests
+#[cfg(test)]
+extern crate self as graphite_binary;
+
 pub mod nbt;
 pub mod slice_serialization;
 pub mod varint;
@@ -0,0 +1,324 @@
+use super::*;
+
+/// Mutable reference to a compound inside an NBT tree, either the root or a nested compound
+///
+/// Allows modifying compounds that have already been inserted, eg. adding entries to a
+/// registry that may or may not exist yet
+pub struct CompoundRefMut<'a> {
+    nbt: &'a mut NBT,
+    index: Option<usize>, // None for the root compound
+}
+
+impl NBT {
+    pub fn root_mut(&mut self) -> CompoundRefMut<'_> {
+        CompoundRefMut {
+            nbt: self,
+            index: None,
+        }
+    }
+
+    /// Returns true if the root compound contains `key`
+    pub fn contains(&self, key: &str) -> bool {
+        self.root_children.find(key).is_some()
+    }
+
+    /// Removes `key` from the root compound, returning true if the key existed
+    ///
+    /// The removed value and its children are freed unless they're shared with a copy
+    pub fn remove(&mut self, key: &str) -> bool {
+        let Some(index) = self.root_children.remove(key) else {
+            return false;
+        };
+        self.release_node(index);
+        true
+    }
+}
+
+impl<'a> CompoundRefMut<'a> {
+    fn compound(&self) -> &NBTCompound {
+        match self.index {
+            None => &self.nbt.root_children,
+            Some(index) => match &self.nbt.nodes[index] {
+                NBTNode::Compound(compound) => compound,
+                _ => unreachable!("CompoundRefMut always points to a compound"),
+            },
+        }
+    }
+
+    fn compound_mut(&mut self) -> &mut NBTCompound {
+        match self.index {
+            None => &mut self.nbt.root_children,
+            Some(index) => match &mut self.nbt.nodes[index] {
+                NBTNode::Compound(compound) => compound,
+                _ => unreachable!("CompoundRefMut always points to a compound"),
+            },
+        }
+    }
+
+    pub fn contains(&self, key: &str) -> bool {
+        self.compound().find(key).is_some()
+    }
+
+    pub fn find(&self, key: &str) -> Option<&NBTNode> {
+        let index = self.compound().find(key)?;
+        Some(&self.nbt.nodes[index])
+    }
+
+    /// Iterates over the entries of the compound, sorted by key
+    pub fn iter(&self) -> NBTCompoundIterator<'_> {
+        NBTCompoundIterator {
+            nbt: self.nbt,
+            entries: self.compound().0.iter(),
+        }
+    }
+
+    /// Iterates over the entries of the compound, sorted by key, allowing the values to be modified
+    ///
+    /// Values that are shared with copies of the compound are copied first, so that modifying
+    /// them doesn't modify the copies
+    pub fn iter_mut(&mut self) -> CompoundIterMut<'_> {
+        CompoundIterMut {
+            compound: CompoundRefMut {
+                nbt: self.nbt,
+                index: self.index,
+            },
+            entry: 0,
+            key: String::new(),
+        }
+    }
+
+    /// Makes the entry the only reference to its node, copying the node if it's shared
+    fn unshare(&mut self, entry: usize) -> usize {
+        let index = self.compound().0[entry].1;
+        if self.nbt.ref_counts[index] <= 1 {
+            return index;
+        }
+
+        let copy = self.nbt.push_node(self.nbt.nodes[index].clone());
+        self.nbt.ref_counts[copy] += 1;
+        self.nbt.ref_counts[index] -= 1;
+        self.compound_mut().0[entry].1 = copy;
+        copy
+    }
+
+    /// Inserts the value, replacing any existing value with the same key
+    pub fn insert(&mut self, key: &str, value: NBTNode) {
+        let index = self.nbt.push_node(value);
+        self.nbt.ref_counts[index] += 1;
+        if let Some(replaced) = self.compound_mut().insert(key, index) {
+            self.nbt.release_node(replaced);
+        }
+    }
+
+    /// Removes `key` from the compound, returning true if the key existed
+    ///
+    /// The removed value and its children are freed unless they're shared with a copy
+    pub fn remove(&mut self, key: &str) -> bool {
+        let Some(index) = self.compound_mut().remove(key) else {
+            return false;
+        };
+        self.nbt.release_node(index);
+        true
+    }
+
+    /// Returns the compound with the given key, inserting an empty compound if the key doesn't exist
+    ///
+    /// Returns `None` if the key exists but isn't a compound, the existing value is left untouched.
+    /// A compound that is shared with copies of this compound is copied first, like `iter_mut`
+    pub fn get_or_create_compound(&mut self, key: &str) -> Option<CompoundRefMut<'_>> {
+        let index = match self.compound().binary_search(key) {
+            Ok(entry) => {
+                let index = self.compound().0[entry].1;
+                if !matches!(self.nbt.nodes[index], NBTNode::Compound(_)) {
+                    return None;
+                }
+                self.unshare(entry)
+            }
+            Err(_) => {
+                let index = self.nbt.push_node(NBTNode::Compound(Default::default()));
+                self.nbt.ref_counts[index] += 1;
+                self.compound_mut().insert(key, index);
+                index
+            }
+        };
+
+        Some(CompoundRefMut {
+            nbt: self.nbt,
+            index: Some(index),
+        })
+    }
+}
+
+/// Mutable iterator over the entries of a compound, returned by `CompoundRefMut::iter_mut`
+///
+/// Each value borrows the whole tree, so this can't implement `Iterator`. Use
+/// `while let Some((key, value)) = iter.next()` instead
+pub struct CompoundIterMut<'a> {
+    compound: CompoundRefMut<'a>,
+    entry: usize,
+    key: String,
+}
+
+impl<'a> CompoundIterMut<'a> {
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Option<(&str, NBTRefMut<'_>)> {
+        let (key, _) = self.compound.compound().0.get(self.entry)?;
+        self.key.clone_from(key);
+
+        let index = self.compound.unshare(self.entry);
+        self.entry += 1;
+        Some((
+            &self.key,
+            NBTRefMut {
+                nbt: self.compound.nbt,
+                index,
+            },
+        ))
+    }
+}
+
+/// Mutable reference to a value inside an NBT tree that isn't shared with any copies
+pub struct NBTRefMut<'a> {
+    nbt: &'a mut NBT,
+    index: usize,
+}
+
+impl<'a> NBTRefMut<'a> {
+    pub fn get(&self) -> &NBTNode {
+        &self.nbt.nodes[self.index]
+    }
+
+    /// Replaces the value, freeing the children of the old value unless they're shared with a copy
+    pub fn set(&mut self, value: NBTNode) {
+        let old = std::mem::replace(&mut self.nbt.nodes[self.index], value);
+        let children: Vec<usize> = self.nbt.nodes[self.index].children().collect();
+        for child in children {
+            self.nbt.ref_counts[child] += 1;
+        }
+        for child in old.children() {
+            self.nbt.release_node(child);
+        }
+    }
+
+    /// Returns the value as a compound, or `None` if it isn't a compound
+    pub fn as_compound_mut(&mut self) -> Option<CompoundRefMut<'_>> {
+        if !matches!(self.nbt.nodes[self.index], NBTNode::Compound(_)) {
+            return None;
+        }
+
+        Some(CompoundRefMut {
+            nbt: self.nbt,
+            index: Some(self.index),
+        })
+    }
+}
+
+#[test]
+fn get_or_create_compound_test() {
+    let mut nbt = NBT::new();
+    assert!(!nbt.contains("minecraft:dimension_type"));
+
+    let mut root = nbt.root_mut();
+    root.get_or_create_compound("minecraft:dimension_type")
+        .unwrap()
+        .insert("type", NBTNode::String("minecraft:dimension_type".into()));
+
+    // The existing compound is returned instead of being replaced
+    let mut dimension_type = root.get_or_create_compound("minecraft:dimension_type").unwrap();
+    assert!(dimension_type.contains("type"));
+    dimension_type.insert("value", NBTNode::Int(1));
+
+    // Keys that aren't compounds aren't replaced
+    root.insert("version", NBTNode::Int(1));
+    assert!(root.get_or_create_compound("version").is_none());
+
+    assert!(nbt.contains("minecraft:dimension_type"));
+    let expected = stringified::from_snbt(r#"{"minecraft:dimension_type":{type:"minecraft:dimension_type",value:1},version:1}"#).unwrap();
+    assert!(nbt.structurally_eq(&expected, Default::default()));
+}
+
+#[test]
+fn remove_test() {
+    let mut nbt = stringified::from_snbt(r#"{id:"minecraft:pig",Pos:[1.0d,2.0d,3.0d],Motion:[0.0d,0.0d,0.0d],Brain:{memories:{}}}"#).unwrap();
+    let node_count = nbt.nodes.len();
+
+    assert!(nbt.remove("Pos"));
+    assert!(!nbt.remove("Pos"));
+
+    let mut root = nbt.root_mut();
+    assert!(root.get_or_create_compound("Brain").unwrap().remove("memories"));
+    assert!(root.remove("Motion"));
+    assert!(!root.remove("Motion"));
+    assert!(!root.remove("missing"));
+
+    let expected = stringified::from_snbt(r#"{id:"minecraft:pig",Brain:{}}"#).unwrap();
+    assert!(nbt.structurally_eq(&expected, Default::default()));
+
+    // The removed values and their children are reused by later inserts
+    for key in ["Air", "Fire", "FallDistance", "PortalCooldown", "OnGround", "Invulnerable", "Rotation", "Health", "Age"] {
+        nbt.insert_root(key, NBTNode::Short(0));
+    }
+    assert_eq!(nbt.nodes.len(), node_count);
+
+    // Values shared with a copy are kept for the copy
+    let mut nbt = stringified::from_snbt(r#"{original:{pos:[1,2,3]}}"#).unwrap();
+    let copy = nbt.find_root("original").unwrap().clone();
+    nbt.insert_root("copy", copy);
+    assert!(nbt.remove("original"));
+    nbt.insert_root("inserted", NBTNode::String("Alex".into()));
+
+    let expected = stringified::from_snbt(r#"{copy:{pos:[1,2,3]},inserted:"Alex"}"#).unwrap();
+    assert!(nbt.structurally_eq(&expected, Default::default()));
+}
+
+#[test]
+fn iter_test() {
+    let input = include_bytes!("../../../../assets/bigtest.nbt");
+    let mut nbt = decode::read(&mut input.as_slice()).unwrap();
+
+    let keys: Vec<&str> = nbt.iter_root().map(|(key, _)| key).collect();
+    assert_eq!(keys.len(), 11);
+    assert!(keys.windows(2).all(|pair| pair[0] < pair[1]));
+
+    let nested = nbt.find_root("nested compound test").unwrap();
+    let keys: Vec<&str> = nbt.iter_compound(nested).unwrap().map(|(key, _)| key).collect();
+    assert_eq!(keys, ["egg", "ham"]);
+    assert!(nbt.iter_compound(nbt.find_root("intTest").unwrap()).is_none());
+
+    let mut root = nbt.root_mut();
+    assert_eq!(root.iter().count(), 11);
+    let mut entries = root.iter_mut();
+    while let Some((key, mut value)) = entries.next() {
+        if key == "intTest" {
+            value.set(NBTNode::Int(1));
+        } else if let Some(mut nested) = value.as_compound_mut() {
+            nested.insert("inserted", NBTNode::Byte(1));
+        }
+    }
+    assert_eq!(nbt.find_root("intTest").unwrap().as_int(), Some(1));
+    let nested = nbt.find_root("nested compound test").unwrap();
+    assert_eq!(nbt.find(nested, "inserted").unwrap().as_byte(), Some(1));
+
+    // Values shared with a copy aren't modified through the copy
+    let mut nbt = stringified::from_snbt(r#"{original:{a:1,b:2}}"#).unwrap();
+    let copy = nbt.find_root("original").unwrap().clone();
+    nbt.insert_root("copy", copy);
+    let mut root = nbt.root_mut();
+    let mut copy = root.get_or_create_compound("copy").unwrap();
+    let mut entries = copy.iter_mut();
+    while let Some((_, mut value)) = entries.next() {
+        value.set(NBTNode::Int(0));
+    }
+    let expected = stringified::from_snbt(r#"{original:{a:1,b:2},copy:{a:0,b:0}}"#).unwrap();
+    assert!(nbt.structurally_eq(&expected, Default::default()));
+
+    // Neither are compounds shared with a copy
+    let mut nbt = stringified::from_snbt(r#"{original:{nested:{a:1}}}"#).unwrap();
+    let copy = nbt.find_root("original").unwrap().clone();
+    nbt.insert_root("copy", copy);
+    let mut root = nbt.root_mut();
+    let mut copy = root.get_or_create_compound("copy").unwrap();
+    copy.get_or_create_compound("nested").unwrap().insert("a", NBTNode::Int(0));
+    let expected = stringified::from_snbt(r#"{original:{nested:{a:1}},copy:{nested:{a:0}}}"#).unwrap();
+    assert!(nbt.structurally_eq(&expected, Default::default()));
+}
@@ -1,4 +1,5 @@
 use std::borrow::Cow;
+use std::io::Read;
 
 use super::*;
 use crate::slice_serialization::{BigEndian, Single, SliceSerializable};
@@ -7,7 +8,31 @@ use anyhow::bail;
 
 const DECODE_CAPACITY: usize = 2_097_152;
 
+/// Maximum nesting depth of compounds and lists used by `read`
+pub const DEFAULT_MAX_DEPTH: usize = 512;
+
+/// Maximum size of decompressed NBT used by `read_gzip` and `read_zlib`
+pub const DEFAULT_MAX_DECOMPRESSED_SIZE: usize = DECODE_CAPACITY;
+
 pub fn read(bytes: &mut &[u8]) -> anyhow::Result<NBT> {
+    read_with_limit(bytes, DEFAULT_MAX_DEPTH)
+}
+
+/// Reads NBT, returning an error if compounds and lists are nested more than `max_depth` times
+///
+/// Decoding is recursive, so the limit prevents untrusted input from overflowing the stack
+pub fn read_with_limit(bytes: &mut &[u8], max_depth: usize) -> anyhow::Result<NBT> {
+    read_root(bytes, max_depth, true)
+}
+
+/// Reads "network NBT" which doesn't have a root name, as sent by 1.20.2+ clients and servers
+///
+/// The `root_name` of the returned NBT is empty
+pub fn read_unnamed(bytes: &mut &[u8]) -> anyhow::Result<NBT> {
+    read_root(bytes, DEFAULT_MAX_DEPTH, false)
+}
+
+fn read_root(bytes: &mut &[u8], max_depth: usize, named: bool) -> anyhow::Result<NBT> {
     let type_id: u8 = Single::read(bytes)?;
     if type_id == TAG_END_ID.0 {
         return Ok(NBT::new());
@@ -18,18 +43,60 @@ pub fn read(bytes: &mut &[u8]) -> anyhow::Result<NBT> {
     let mut size = 0;
 
     let mut nodes = Vec::new();
-    let name = read_string(bytes, &mut size)?;
-    let children = read_compound(bytes, &mut nodes, 0, &mut size)?;
+    let name = if named {
+        read_string(bytes, &mut size)?.into_owned()
+    } else {
+        String::new()
+    };
+    let children = read_compound(bytes, &mut nodes, 0, max_depth, &mut size)?;
 
     Ok(NBT {
-        root_name: name.into_owned(),
+        root_name: name,
         root_children: children,
+        // Every node that was read is referenced once, by its parent
+        ref_counts: vec![1; nodes.len()],
         nodes,
+        free_nodes: Vec::new(),
     })
 }
 
+/// Decompresses gzip compressed NBT and reads it, eg. `level.dat` files
+pub fn read_gzip(reader: impl Read) -> anyhow::Result<NBT> {
+    read_gzip_with_max_size(reader, DEFAULT_MAX_DECOMPRESSED_SIZE)
+}
+
+/// Reads gzip compressed NBT, returning an error if it decompresses to more than `max_size` bytes
+///
+/// Small inputs can decompress to a lot of data, the limit prevents untrusted input from using
+/// up all of the memory
+pub fn read_gzip_with_max_size(reader: impl Read, max_size: usize) -> anyhow::Result<NBT> {
+    read_decompressed(flate2::read::GzDecoder::new(reader), max_size)
+}
+
+/// Decompresses zlib compressed NBT and reads it, eg. chunks in region files
+pub fn read_zlib(reader: impl Read) -> anyhow::Result<NBT> {
+    read_zlib_with_max_size(reader, DEFAULT_MAX_DECOMPRESSED_SIZE)
+}
+
+/// Reads zlib compressed NBT, returning an error if it decompresses to more than `max_size` bytes,
+/// see `read_gzip_with_max_size`
+pub fn read_zlib_with_max_size(reader: impl Read, max_size: usize) -> anyhow::Result<NBT> {
+    read_decompressed(flate2::read::ZlibDecoder::new(reader), max_size)
+}
+
+fn read_decompressed(decoder: impl Read, max_size: usize) -> anyhow::Result<NBT> {
+    // Reading one byte more than the limit tells apart NBT that is exactly `max_size` bytes
+    let mut bytes = Vec::new();
+    decoder.take(max_size as u64 + 1).read_to_end(&mut bytes)?;
+    if bytes.len() > max_size {
+        bail!("nbt_decode: decompressed nbt exceeds {} bytes", max_size);
+    }
+    read(&mut bytes.as_slice())
+}
+
 #[inline]
-fn read_node(bytes: &mut &[u8], nodes: &mut Vec<NBTNode>, type_id: u8, depth: usize, size: &mut usize) -> anyhow::Result<usize> {
+fn read_node(bytes: &mut &[u8], nodes: &mut Vec<NBTNode>, type_id: u8, depth: usize, max_depth: usize,
+        size: &mut usize) -> anyhow::Result<usize> {
     debug_assert!(
         type_id != TAG_END_ID.0,
         "read_node must not be called with TAG_END"
@@ -63,19 +130,27 @@ fn read_node(bytes: &mut &[u8], nodes: &mut Vec<NBTNode>, type_id: u8, depth: us
         TAG_BYTE_ARRAY_ID => NBTNode::ByteArray(read_byte_array(bytes, size)?),
         TAG_STRING_ID => NBTNode::String(read_string(bytes, size)?.into_owned()),
         TAG_LIST_ID => {
-            if depth > 512 {
-                bail!("tried to read NBT tag with too high complexity, depth > 512")
+            if depth >= max_depth {
+                bail!(
+                    "tried to read NBT tag with too high complexity, depth {} > {}",
+                    depth + 1,
+                    max_depth
+                )
             }
 
-            let (type_id, children) = read_list(bytes, nodes, depth + 1, size)?;
+            let (type_id, children) = read_list(bytes, nodes, depth + 1, max_depth, size)?;
             NBTNode::List { type_id: TagType(type_id), children }
         }
         TAG_COMPOUND_ID => {
-            if depth > 512 {
-                bail!("tried to read NBT tag with too high complexity, depth > 512")
+            if depth >= max_depth {
+                bail!(
+                    "tried to read NBT tag with too high complexity, depth {} > {}",
+                    depth + 1,
+                    max_depth
+                )
             }
 
-            NBTNode::Compound(read_compound(bytes, nodes, depth + 1, size)?)
+            NBTNode::Compound(read_compound(bytes, nodes, depth + 1, max_depth, size)?)
         },
         TAG_INT_ARRAY_ID => NBTNode::IntArray(read_int_array(bytes, size)?),
         TAG_LONG_ARRAY_ID => NBTNode::LongArray(read_long_array(bytes, size)?),
@@ -85,7 +160,8 @@ fn read_node(bytes: &mut &[u8], nodes: &mut Vec<NBTNode>, type_id: u8, depth: us
     Ok(nodes.len() - 1)
 }
 
-fn read_compound(bytes: &mut &[u8], nodes: &mut Vec<NBTNode>, depth: usize, size: &mut usize) -> anyhow::Result<NBTCompound> {
+fn read_compound(bytes: &mut &[u8], nodes: &mut Vec<NBTNode>, depth: usize, max_depth: usize,
+        size: &mut usize) -> anyhow::Result<NBTCompound> {
     let mut children = NBTCompound(Vec::new());
 
     loop {
@@ -96,7 +172,7 @@ fn read_compound(bytes: &mut &[u8], nodes: &mut Vec<NBTNode>, depth: usize, size
             *size += 8;
 
             let name = read_string(bytes, size)?;
-            let node = read_node(bytes, nodes, type_id, depth, size)?;
+            let node = read_node(bytes, nodes, type_id, depth, max_depth, size)?;
 
             match children.binary_search(name.as_ref()) {
                 Ok(_) => bail!("read_compound: duplicate key"),
@@ -149,17 +225,19 @@ fn read_string<'a>(bytes: &mut &'a [u8], size: &mut usize) -> anyhow::Result<Cow
     Ok(cesu8::from_java_cesu8(str_bytes)?)
 }
 
-fn read_list(bytes: &mut &[u8], nodes: &mut Vec<NBTNode>, depth: usize, size: &mut usize) -> anyhow::Result<(u8, Vec<usize>)> {
+fn read_list(bytes: &mut &[u8], nodes: &mut Vec<NBTNode>, depth: usize, max_depth: usize,
+        size: &mut usize) -> anyhow::Result<(u8, Vec<usize>)> {
     let type_id: u8 = Single::read(bytes)?;
 
     let length: i32 = BigEndian::read(bytes)?;
 
     if length <= 0 {
         Ok((type_id, Vec::new()))
-    } else if bytes.len() < length as _ {
-        bail!("read_list: not enough bytes to read list");
     } else if type_id == TAG_END_ID.0 {
         bail!("read_list: type cannot be TAG_END for non-zero length list");
+    } else if bytes.len() / min_encoded_size(type_id) < length as _ {
+        // Also ensures that the capacity of `children` can't exceed what the input could contain
+        bail!("read_list: not enough bytes to read list");
     } else {
         let length = length as usize;
 
@@ -171,13 +249,26 @@ fn read_list(bytes: &mut &[u8], nodes: &mut Vec<NBTNode>, depth: usize, size: &m
         let mut children = Vec::with_capacity(length);
 
         for _ in 0..length {
-            children.push(read_node(bytes, nodes, type_id, depth, size)?);
+            children.push(read_node(bytes, nodes, type_id, depth, max_depth, size)?);
         }
 
         Ok((type_id, children))
     }
 }
 
+/// Minimum number of bytes needed to encode a list element of the given type
+fn min_encoded_size(type_id: u8) -> usize {
+    match TagType(type_id) {
+        TAG_SHORT_ID => 2,
+        TAG_INT_ID | TAG_FLOAT_ID => 4,
+        TAG_LONG_ID | TAG_DOUBLE_ID => 8,
+        TAG_BYTE_ARRAY_ID | TAG_INT_ARRAY_ID | TAG_LONG_ARRAY_ID => 4, // length
+        TAG_STRING_ID => 2, // length
+        TAG_LIST_ID => 5, // type id + length
+        _ => 1, // byte, compound (TAG_END) or unknown
+    }
+}
+
 #[inline]
 fn read_int_array(bytes: &mut &[u8], size: &mut usize) -> anyhow::Result<Vec<i32>> {
     let length: i32 = BigEndian::read(bytes)?;
@@ -217,3 +308,40 @@ fn read_long_array(bytes: &mut &[u8], size: &mut usize) -> anyhow::Result<Vec<i6
     byteorder::BigEndian::read_i64_into(&bytes[..length*8], values.as_mut_slice());
     Ok(values)
 }
+
+#[test]
+fn read_list_length_too_large() {
+    // Root compound containing a list that claims to have 2 billion compounds, with a 4 byte body
+    let mut data: Vec<u8> = vec![TAG_COMPOUND_ID.0, 0, 0, TAG_LIST_ID.0, 0, 1, b'a', TAG_COMPOUND_ID.0];
+    data.extend_from_slice(&2_000_000_000_i32.to_be_bytes());
+    data.extend_from_slice(&[0, 0, 0, 0]);
+    assert!(read(&mut data.as_slice()).is_err());
+
+    // List claiming 3 longs with only enough bytes for 2
+    let mut data: Vec<u8> = vec![TAG_COMPOUND_ID.0, 0, 0, TAG_LIST_ID.0, 0, 1, b'a', TAG_LONG_ID.0];
+    data.extend_from_slice(&3_i32.to_be_bytes());
+    data.extend_from_slice(&[0; 16]);
+    data.push(TAG_END_ID.0);
+    assert!(read(&mut data.as_slice()).is_err());
+}
+
+#[test]
+fn read_depth_limit() {
+    // Root compound containing `depth` nested compounds
+    fn nested(depth: usize) -> Vec<u8> {
+        let mut data = vec![TAG_COMPOUND_ID.0, 0, 0];
+        for _ in 0..depth {
+            data.extend_from_slice(&[TAG_COMPOUND_ID.0, 0, 0]);
+        }
+        data.resize(data.len() + depth + 1, TAG_END_ID.0);
+        data
+    }
+
+    assert!(read(&mut nested(100_000).as_slice()).is_err());
+    assert!(read(&mut nested(DEFAULT_MAX_DEPTH).as_slice()).is_ok());
+    assert!(read(&mut nested(DEFAULT_MAX_DEPTH + 1).as_slice()).is_err());
+
+    assert!(read_with_limit(&mut nested(8).as_slice(), 8).is_ok());
+    let err = read_with_limit(&mut nested(9).as_slice(), 8).unwrap_err();
+    assert_eq!(err.to_string(), "tried to read NBT tag with too high complexity, depth 9 > 8");
+}
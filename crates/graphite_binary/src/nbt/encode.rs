@@ -14,6 +14,18 @@ pub fn write_into(nbt: &NBT, vec: &mut Vec<u8>) {
     write_compound(vec, &nbt.nodes, &nbt.root_children);
 }
 
+/// Writes "network NBT" which doesn't have a root name, as expected by 1.20.2+ clients
+pub fn write_unnamed(nbt: &NBT) -> Vec<u8> {
+    let mut vec = Vec::new();
+    write_unnamed_into(nbt, &mut vec);
+    vec
+}
+
+pub fn write_unnamed_into(nbt: &NBT, vec: &mut Vec<u8>) {
+    vec.push(TAG_COMPOUND_ID.0);
+    write_compound(vec, &nbt.nodes, &nbt.root_children);
+}
+
 fn write_node(vec: &mut Vec<u8>, nodes: &Vec<NBTNode>, name: Option<&str>, node: &NBTNode) {
     match node {
         NBTNode::Byte(value) => {
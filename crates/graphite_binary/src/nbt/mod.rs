@@ -1,12 +1,16 @@
 use std::{fmt::Debug, result};
 
 mod cached_nbt;
+mod compound_ref;
 pub mod decode;
 pub mod encode;
 mod pretty;
 pub mod stringified;
+mod structural_eq;
 
 pub use cached_nbt::CachedNBT;
+pub use compound_ref::{CompoundIterMut, CompoundRefMut, NBTRefMut};
+pub use structural_eq::StructuralEqOptions;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct TagType(pub(crate) u8);
@@ -30,6 +34,12 @@ pub struct NBT {
     pub root_name: String,
     root_children: NBTCompound,
     nodes: Vec<NBTNode>,
+    // Number of times each node is referenced by the root or by another node, cloning a
+    // compound or list and inserting it shares its children. Nodes are only freed once
+    // nothing references them anymore
+    ref_counts: Vec<u32>,
+    // Indices of nodes that were freed, these are reused by the next inserted nodes
+    free_nodes: Vec<usize>,
 }
 
 impl Debug for NBT {
@@ -52,6 +62,8 @@ impl NBT {
             root_name,
             root_children: NBTCompound(Vec::new()),
             nodes: Vec::new(),
+            ref_counts: Vec::new(),
+            free_nodes: Vec::new(),
         }
     }
 
@@ -61,9 +73,11 @@ impl NBT {
     }
 
     pub fn insert_root(&mut self, key: &str, value: NBTNode) {
-        let idx = self.nodes.len();
-        self.nodes.push(value);
-        self.root_children.insert(key, idx);
+        let idx = self.push_node(value);
+        self.ref_counts[idx] += 1;
+        if let Some(replaced) = self.root_children.insert(key, idx) {
+            self.release_node(replaced);
+        }
     }
 
     pub fn find(&self, node: &NBTNode, key: &str) -> Option<&NBTNode> {
@@ -76,11 +90,14 @@ impl NBT {
         }
     }
 
+    /// Inserts the value into `node`, which must be a compound
+    ///
+    /// `node` isn't part of the tree until it is inserted itself, so a value that it
+    /// replaces may still be used by copies of `node` and isn't freed
     pub fn insert(&mut self, node: &mut NBTNode, key: &str, value: NBTNode) {
         match node {
             NBTNode::Compound(ref mut compound) => {
-                let idx = self.nodes.len();
-                self.nodes.push(value);
+                let idx = self.push_node(value);
                 compound.insert(key, idx);
             }
             _ => panic!("nbt insert: node is not a compound"),
@@ -101,6 +118,25 @@ impl NBT {
         }
     }
 
+    /// Iterates over the entries of the root compound, sorted by key
+    pub fn iter_root(&self) -> NBTCompoundIterator<'_> {
+        NBTCompoundIterator {
+            nbt: self,
+            entries: self.root_children.0.iter(),
+        }
+    }
+
+    /// Iterates over the entries of a compound, sorted by key. Returns `None` if the node isn't a compound
+    pub fn iter_compound<'a>(&'a self, node: &'a NBTNode) -> Option<NBTCompoundIterator<'a>> {
+        match node {
+            NBTNode::Compound(compound) => Some(NBTCompoundIterator {
+                nbt: self,
+                entries: compound.0.iter(),
+            }),
+            _ => None,
+        }
+    }
+
     pub fn append(&mut self, node: &mut NBTNode, value: NBTNode) {
         match node {
             NBTNode::List {
@@ -110,13 +146,50 @@ impl NBT {
                 if *type_id != value.get_type() {
                     panic!("nbt append: tag type is incorrect")
                 }
-                let idx = self.nodes.len();
-                self.nodes.push(value);
+                let idx = self.push_node(value);
                 children.push(idx);
             },
             _ => panic!("nbt append: node is not a list"),
         }
     }
+
+    /// Adds the node, reusing the index of a node that was freed if possible
+    ///
+    /// The children of the node gain a reference, the node itself has none until it is
+    /// inserted into the tree
+    fn push_node(&mut self, value: NBTNode) -> usize {
+        for child in value.children() {
+            self.ref_counts[child] += 1;
+        }
+
+        if let Some(idx) = self.free_nodes.pop() {
+            self.nodes[idx] = value;
+            self.ref_counts[idx] = 0;
+            idx
+        } else {
+            self.nodes.push(value);
+            self.ref_counts.push(0);
+            self.nodes.len() - 1
+        }
+    }
+
+    /// Removes a reference to the node, freeing it once nothing references it anymore.
+    /// Freeing a compound or list releases its children in the same way
+    fn release_node(&mut self, idx: usize) {
+        let mut stack = vec![idx];
+        while let Some(idx) = stack.pop() {
+            self.ref_counts[idx] -= 1;
+            if self.ref_counts[idx] > 0 {
+                // Still used by a copy of its parent
+                continue;
+            }
+
+            // Replacing the node drops any heap allocations it owns
+            let node = std::mem::replace(&mut self.nodes[idx], NBTNode::Byte(0));
+            stack.extend(node.children());
+            self.free_nodes.push(idx);
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -154,6 +227,16 @@ impl NBTNode {
         }
     }
 
+    /// Indices of the children of a compound or list
+    fn children(&self) -> impl Iterator<Item = usize> + '_ {
+        let (list, compound): (&[usize], &[(String, usize)]) = match self {
+            NBTNode::List { type_id: _, children } => (children, &[]),
+            NBTNode::Compound(compound) => (&[], &compound.0),
+            _ => (&[], &[]),
+        };
+        list.iter().copied().chain(compound.iter().map(|(_, idx)| *idx))
+    }
+
     pub fn as_byte(&self) -> Option<i8> {
         match self {
             NBTNode::Byte(value) => Some(*value),
@@ -245,6 +328,20 @@ impl<'a> Iterator for NBTIterator<'a> {
     }
 }
 
+pub struct NBTCompoundIterator<'a> {
+    nbt: &'a NBT,
+    entries: std::slice::Iter<'a, (String, usize)>,
+}
+
+impl<'a> Iterator for NBTCompoundIterator<'a> {
+    type Item = (&'a str, &'a NBTNode);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (key, index) = self.entries.next()?;
+        Some((key.as_str(), &self.nbt.nodes[*index]))
+    }
+}
+
 // Note: Using SmartString instead of String results in worse perf
 #[derive(Debug, Clone, Default)]
 pub struct NBTCompound(Vec<(String, usize)>);
@@ -266,22 +363,78 @@ impl NBTCompound {
         }
     }
 
-    fn insert(&mut self, key: &str, value: usize) {
+    /// Returns the index of the node that was replaced, which must be released using `NBT::release_node`
+    fn insert(&mut self, key: &str, value: usize) -> Option<usize> {
         match self.binary_search(key) {
-            Ok(index) => {
-                let _ = std::mem::replace(&mut self.0[index].1, value);
-            }
+            Ok(index) => Some(std::mem::replace(&mut self.0[index].1, value)),
             Err(index) => {
                 self.0.insert(index, (key.into(), value));
+                None
             }
         }
     }
 
+    fn remove(&mut self, key: &str) -> Option<usize> {
+        let index = self.binary_search(key).ok()?;
+        Some(self.0.remove(index).1)
+    }
+
     fn binary_search(&self, key: &str) -> result::Result<usize, usize> {
         self.0.binary_search_by_key(&key, |v| v.0.as_str())
     }
 }
 
+#[test]
+fn replace_reuses_nodes() {
+    let mut nbt = NBT::new();
+    for i in 0..10_000 {
+        nbt.insert_root("value", NBTNode::Int(i));
+
+        let mut compound = NBTNode::Compound(Default::default());
+        nbt.insert(&mut compound, "name", NBTNode::String(format!("name {}", i)));
+        nbt.insert(&mut compound, "count", NBTNode::Int(i));
+        nbt.insert_root("compound", compound);
+    }
+
+    assert!(nbt.nodes.len() <= 8, "nodes weren't reused, {} nodes", nbt.nodes.len());
+    assert_eq!(nbt.find_root("value").unwrap().as_int(), Some(9999));
+    let compound = nbt.find_root("compound").unwrap();
+    assert_eq!(nbt.find(compound, "name").unwrap().as_string(), Some(&"name 9999".into()));
+    assert_eq!(nbt.find(compound, "count").unwrap().as_int(), Some(9999));
+}
+
+#[test]
+fn replace_keeps_shared_nodes() {
+    let mut nbt = stringified::from_snbt(r#"{original:{name:"Steve",pos:[1,2,3]}}"#).unwrap();
+
+    // The copy shares its children with the original
+    let copy = nbt.find_root("original").unwrap().clone();
+    nbt.insert_root("copy", copy);
+
+    // Replacing the original must not free the children of the copy
+    nbt.insert_root("original", NBTNode::Int(0));
+    nbt.insert_root("inserted", NBTNode::String("Alex".into()));
+    let mut list = NBTNode::List { type_id: TAG_INT_ID, children: Vec::new() };
+    for i in 4..8 {
+        nbt.append(&mut list, NBTNode::Int(i));
+    }
+    nbt.insert_root("list", list);
+
+    let expected = stringified::from_snbt(
+        r#"{original:0,copy:{name:"Steve",pos:[1,2,3]},inserted:"Alex",list:[4,5,6,7]}"#,
+    )
+    .unwrap();
+    assert!(nbt.structurally_eq(&expected, Default::default()));
+
+    // Once the copy is replaced as well, it and its children are reused
+    nbt.insert_root("copy", NBTNode::Int(0));
+    let node_count = nbt.nodes.len();
+    for i in 0..6 {
+        nbt.insert_root(&format!("value {}", i), NBTNode::Int(i));
+    }
+    assert_eq!(nbt.nodes.len(), node_count);
+}
+
 #[test]
 fn read_test() {
     // https://wiki.vg/NBT#bigtest.nbt
@@ -306,6 +459,52 @@ fn read_and_write_test() {
     verify_bigtest_nbt(&nbt);
 }
 
+#[test]
+fn read_and_write_unnamed_test() {
+    let input = include_bytes!("../../../../assets/bigtest.nbt");
+    let nbt = decode::read(&mut input.as_slice()).unwrap();
+    let input = encode::write_unnamed(&nbt);
+    let nbt = decode::read_unnamed(&mut input.as_slice()).unwrap();
+
+    assert_eq!(nbt.root_name.as_str(), "");
+    verify_bigtest_nbt(&nbt);
+}
+
+#[test]
+fn read_compressed_test() {
+    use std::io::Write;
+
+    let input = include_bytes!("../../../../assets/bigtest.nbt");
+    let nbt = decode::read(&mut input.as_slice()).unwrap();
+    let input = encode::write(&nbt);
+
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(&input).unwrap();
+    let compressed = encoder.finish().unwrap();
+    let nbt = decode::read_gzip(compressed.as_slice()).unwrap();
+    verify_bigtest_nbt(&nbt);
+
+    let mut encoder = flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(&input).unwrap();
+    let compressed = encoder.finish().unwrap();
+    let nbt = decode::read_zlib(compressed.as_slice()).unwrap();
+    verify_bigtest_nbt(&nbt);
+
+    // Uncompressed input isn't accepted
+    assert!(decode::read_gzip(input.as_slice()).is_err());
+
+    // Input that decompresses to more than the maximum size isn't accepted
+    let nbt = decode::read_zlib_with_max_size(compressed.as_slice(), input.len()).unwrap();
+    verify_bigtest_nbt(&nbt);
+    let error = decode::read_zlib_with_max_size(compressed.as_slice(), input.len() - 1).unwrap_err();
+    assert!(error.to_string().contains("exceeds"));
+
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(&vec![0; decode::DEFAULT_MAX_DECOMPRESSED_SIZE + 1]).unwrap();
+    let compressed = encoder.finish().unwrap();
+    assert!(decode::read_gzip(compressed.as_slice()).is_err());
+}
+
 #[test]
 fn to_from_snbt_test() {
     // https://wiki.vg/NBT#bigtest.nbt
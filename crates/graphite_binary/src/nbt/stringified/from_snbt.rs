@@ -31,7 +31,10 @@ pub fn from_snbt(mut snbt: &str) -> anyhow::Result<NBT> {
     Ok(NBT {
         root_name: String::new(),
         root_children: children,
+        // Every node that was read is referenced once, by its parent
+        ref_counts: vec![1; nodes.len()],
         nodes,
+        free_nodes: Vec::new(),
     })
 }
 
@@ -0,0 +1,212 @@
+use super::*;
+
+/// Options for `NBT::structurally_eq`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StructuralEqOptions {
+    /// Lists are equal if they contain the same elements in any order
+    pub unordered_lists: bool,
+    /// Numbers of different types are equal if they have the same value, eg. `1b` and `1`
+    pub widen_numbers: bool,
+}
+
+impl NBT {
+    /// Compares the contents of two NBT trees, ignoring the root name
+    ///
+    /// Compounds are always compared by key. Lists and numbers are compared
+    /// exactly unless relaxed by `options`
+    pub fn structurally_eq(&self, other: &NBT, options: StructuralEqOptions) -> bool {
+        compound_eq(self, &self.root_children, other, &other.root_children, options)
+    }
+}
+
+fn compound_eq(
+    nbt: &NBT,
+    compound: &NBTCompound,
+    other_nbt: &NBT,
+    other: &NBTCompound,
+    options: StructuralEqOptions,
+) -> bool {
+    if compound.0.len() != other.0.len() {
+        return false;
+    }
+
+    compound.0.iter().all(|(key, index)| match other.find(key) {
+        Some(other_index) => node_eq(
+            nbt,
+            &nbt.nodes[*index],
+            other_nbt,
+            &other_nbt.nodes[other_index],
+            options,
+        ),
+        None => false,
+    })
+}
+
+fn node_eq(
+    nbt: &NBT,
+    node: &NBTNode,
+    other_nbt: &NBT,
+    other: &NBTNode,
+    options: StructuralEqOptions,
+) -> bool {
+    if options.widen_numbers {
+        if let (Some(value), Some(other_value)) = (as_number(node), as_number(other)) {
+            return number_eq(value, other_value);
+        }
+    }
+
+    match (node, other) {
+        (NBTNode::Byte(value), NBTNode::Byte(other_value)) => value == other_value,
+        (NBTNode::Short(value), NBTNode::Short(other_value)) => value == other_value,
+        (NBTNode::Int(value), NBTNode::Int(other_value)) => value == other_value,
+        (NBTNode::Long(value), NBTNode::Long(other_value)) => value == other_value,
+        // Floats are compared by their bits so that NaN is equal to itself
+        (NBTNode::Float(value), NBTNode::Float(other_value)) => value.to_bits() == other_value.to_bits(),
+        (NBTNode::Double(value), NBTNode::Double(other_value)) => value.to_bits() == other_value.to_bits(),
+        (NBTNode::ByteArray(value), NBTNode::ByteArray(other_value)) => value == other_value,
+        (NBTNode::String(value), NBTNode::String(other_value)) => value == other_value,
+        (NBTNode::IntArray(value), NBTNode::IntArray(other_value)) => value == other_value,
+        (NBTNode::LongArray(value), NBTNode::LongArray(other_value)) => value == other_value,
+        (NBTNode::Compound(compound), NBTNode::Compound(other_compound)) => {
+            compound_eq(nbt, compound, other_nbt, other_compound, options)
+        }
+        (
+            NBTNode::List { children, .. },
+            NBTNode::List { children: other_children, .. },
+        ) => {
+            // The type of an empty list doesn't matter, otherwise the elements are compared
+            if children.len() != other_children.len() {
+                return false;
+            }
+
+            let element_eq = |index: usize, other_index: usize| {
+                node_eq(
+                    nbt,
+                    &nbt.nodes[index],
+                    other_nbt,
+                    &other_nbt.nodes[other_index],
+                    options,
+                )
+            };
+
+            if options.unordered_lists {
+                let mut matched = vec![false; other_children.len()];
+                children.iter().all(|index| {
+                    let found = other_children.iter().enumerate().position(|(i, other_index)| {
+                        !matched[i] && element_eq(*index, *other_index)
+                    });
+                    match found {
+                        Some(i) => {
+                            matched[i] = true;
+                            true
+                        }
+                        None => false,
+                    }
+                })
+            } else {
+                children
+                    .iter()
+                    .zip(other_children)
+                    .all(|(index, other_index)| element_eq(*index, *other_index))
+            }
+        }
+        _ => false,
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Number {
+    Integer(i64),
+    Float(f64),
+}
+
+fn as_number(node: &NBTNode) -> Option<Number> {
+    match node {
+        NBTNode::Byte(value) => Some(Number::Integer(*value as i64)),
+        NBTNode::Short(value) => Some(Number::Integer(*value as i64)),
+        NBTNode::Int(value) => Some(Number::Integer(*value as i64)),
+        NBTNode::Long(value) => Some(Number::Integer(*value)),
+        NBTNode::Float(value) => Some(Number::Float(*value as f64)),
+        NBTNode::Double(value) => Some(Number::Float(*value)),
+        _ => None,
+    }
+}
+
+// Integers are compared as integers, as large longs can't be represented by a double.
+// An integer is only equal to a float if the float has exactly the same value
+fn number_eq(value: Number, other: Number) -> bool {
+    match (value, other) {
+        (Number::Integer(value), Number::Integer(other)) => value == other,
+        (Number::Float(value), Number::Float(other)) => value.to_bits() == other.to_bits(),
+        (Number::Integer(integer), Number::Float(float))
+        | (Number::Float(float), Number::Integer(integer)) => {
+            // 2^63 is the first float that is too large for an i64
+            float.fract() == 0.0
+                && float >= i64::MIN as f64
+                && float < i64::MAX as f64
+                && float as i64 == integer
+        }
+    }
+}
+
+#[test]
+fn unordered_list_test() {
+    let nbt = stringified::from_snbt(r#"{items:[{id:"stone",count:1b},{id:"dirt",count:2b}]}"#).unwrap();
+    let reordered = stringified::from_snbt(r#"{items:[{count:2b,id:"dirt"},{id:"stone",count:1b}]}"#).unwrap();
+
+    // Compounds are compared by key, but lists are ordered by default
+    assert!(!nbt.structurally_eq(&reordered, Default::default()));
+
+    let options = StructuralEqOptions {
+        unordered_lists: true,
+        ..Default::default()
+    };
+    assert!(nbt.structurally_eq(&reordered, options));
+
+    // Each element can only be matched once
+    let duplicated = stringified::from_snbt(r#"{items:[{id:"stone",count:1b},{id:"stone",count:1b}]}"#).unwrap();
+    assert!(!nbt.structurally_eq(&duplicated, options));
+}
+
+#[test]
+fn widen_numbers_test() {
+    let nbt = stringified::from_snbt("{nested:{list:[1b,2b]},value:0.5f}").unwrap();
+    let widened = stringified::from_snbt("{nested:{list:[1L,2L]},value:0.5d}").unwrap();
+
+    assert!(!nbt.structurally_eq(&widened, Default::default()));
+
+    let options = StructuralEqOptions {
+        widen_numbers: true,
+        ..Default::default()
+    };
+    assert!(nbt.structurally_eq(&widened, options));
+
+    // Longs are compared exactly, even when they can't be represented by a double
+    let nbt = stringified::from_snbt(&format!("{{value:{}L}}", i64::MAX)).unwrap();
+    let other = stringified::from_snbt(&format!("{{value:{}L}}", i64::MAX - 1)).unwrap();
+    assert!(!nbt.structurally_eq(&other, options));
+    assert!(nbt.structurally_eq(&nbt.clone(), options));
+
+    // Integers only equal floats with exactly the same value
+    let nbt = stringified::from_snbt("{value:9007199254740993L}").unwrap();
+    let other = stringified::from_snbt("{value:9007199254740992.0d}").unwrap();
+    assert!(!nbt.structurally_eq(&other, options));
+    let nbt = stringified::from_snbt("{value:3}").unwrap();
+    assert!(nbt.structurally_eq(&stringified::from_snbt("{value:3.0f}").unwrap(), options));
+    assert!(!nbt.structurally_eq(&stringified::from_snbt("{value:3.5f}").unwrap(), options));
+}
+
+#[test]
+fn nan_eq_test() {
+    let mut nbt = NBT::new();
+    nbt.insert_root("float", NBTNode::Float(f32::NAN));
+    nbt.insert_root("double", NBTNode::Double(f64::NAN));
+
+    assert!(nbt.structurally_eq(&nbt, Default::default()));
+
+    let options = StructuralEqOptions {
+        widen_numbers: true,
+        ..Default::default()
+    };
+    assert!(nbt.structurally_eq(&nbt, options));
+}
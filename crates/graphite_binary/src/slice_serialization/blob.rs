@@ -1,6 +1,6 @@
 use std::borrow::Cow;
 
-use crate::nbt::{decode, CachedNBT};
+use crate::nbt::{decode, encode, CachedNBT};
 
 use super::*;
 
@@ -30,6 +30,59 @@ impl<'a> SliceSerializable<'a, Cow<'a, CachedNBT>> for NBTBlob {
     }
 }
 
+/// NBT without a root name, used by the protocol since 1.20.2
+pub enum UnnamedNBTBlob {}
+
+impl<'a> SliceSerializable<'a, Cow<'a, CachedNBT>> for UnnamedNBTBlob {
+    type CopyType = &'a CachedNBT;
+
+    fn read(bytes: &mut &'a [u8]) -> anyhow::Result<Cow<'a, CachedNBT>> {
+        let nbt = decode::read_unnamed(bytes)?;
+        Ok(Cow::Owned(nbt.into()))
+    }
+
+    fn get_write_size(data: &CachedNBT) -> usize {
+        match split_root_name(data.to_bytes()) {
+            Ok((tag_type, unnamed)) => tag_type.len() + unnamed.len(),
+            Err(_) => encode::write_unnamed(data).len(),
+        }
+    }
+
+    unsafe fn write<'b>(bytes: &'b mut [u8], data: &CachedNBT) -> &'b mut [u8] {
+        // The cached bytes are reused, skipping over the root name
+        let (tag_type, unnamed) = match split_root_name(data.to_bytes()) {
+            Ok(split) => split,
+            Err(_) => {
+                let unnamed = encode::write_unnamed(data);
+                bytes[0..unnamed.len()].clone_from_slice(&unnamed);
+                return &mut bytes[unnamed.len()..];
+            }
+        };
+        bytes[0..tag_type.len()].clone_from_slice(tag_type);
+        let bytes = &mut bytes[tag_type.len()..];
+        bytes[0..unnamed.len()].clone_from_slice(unnamed);
+        &mut bytes[unnamed.len()..]
+    }
+
+    #[inline(always)]
+    fn as_copy_type(t: &'a Cow<'a, CachedNBT>) -> Self::CopyType {
+        t
+    }
+}
+
+// Splits named NBT into the root tag type and the bytes following the root name
+fn split_root_name(named: &[u8]) -> anyhow::Result<(&[u8], &[u8])> {
+    if named.len() < 3 {
+        return Err(BinaryReadError::NotEnoughRemainingBytes.into());
+    }
+
+    let name_length = u16::from_be_bytes([named[1], named[2]]) as usize;
+    if named.len() < 3 + name_length {
+        return Err(BinaryReadError::NotEnoughRemainingBytes.into());
+    }
+    Ok((&named[..1], &named[3 + name_length..]))
+}
+
 pub enum WriteOnlyBlob {}
 
 impl<'a> SliceSerializable<'a, &'a [u8]> for WriteOnlyBlob {
@@ -192,3 +245,37 @@ impl<'a, const MAX_SIZE: usize> SliceSerializable<'a, String> for SizedString<MA
         t
     }
 }
+
+#[test]
+fn unnamed_nbt_blob_test() {
+    let input = include_bytes!("../../../../assets/bigtest.nbt");
+    let nbt: CachedNBT = decode::read(&mut input.as_slice()).unwrap().into();
+    assert_eq!(nbt.root_name, "Level");
+
+    let size = UnnamedNBTBlob::get_write_size(&nbt);
+    let mut bytes = vec![0; size];
+    unsafe {
+        let remaining = UnnamedNBTBlob::write(&mut bytes, &nbt);
+        assert!(remaining.is_empty());
+    }
+    assert_eq!(bytes, crate::nbt::encode::write_unnamed(&nbt));
+    assert_eq!(size, nbt.to_bytes().len() - "Level".len() - 2);
+
+    let mut slice = bytes.as_slice();
+    let read = UnnamedNBTBlob::read(&mut slice).unwrap();
+    assert!(slice.is_empty());
+    assert_eq!(read.root_name, "");
+    assert!(read.structurally_eq(&nbt, Default::default()));
+}
+
+#[test]
+fn split_root_name_test() {
+    let (tag_type, unnamed) = split_root_name(&[10, 0, 2, b'h', b'i', 0]).unwrap();
+    assert_eq!(tag_type, [10]);
+    assert_eq!(unnamed, [0]);
+
+    // Truncated input is an error instead of a panic
+    assert!(split_root_name(&[]).is_err());
+    assert!(split_root_name(&[10, 0]).is_err());
+    assert!(split_root_name(&[10, 0, 5, b'h', b'i']).is_err());
+}
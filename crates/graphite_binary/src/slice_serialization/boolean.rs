@@ -0,0 +1,80 @@
+use super::*;
+
+/// Single byte boolean which must be either 0 or 1
+///
+/// Unlike `bool as Single`, any other value is rejected when reading
+pub enum Bool {}
+
+impl SliceSerializable<'_, bool> for Bool {
+    type CopyType = bool;
+
+    fn read(bytes: &mut &[u8]) -> anyhow::Result<bool> {
+        if bytes.is_empty() {
+            return Err(BinaryReadError::NotEnoughRemainingBytes.into());
+        }
+
+        let ret = match bytes[0] {
+            0 => false,
+            1 => true,
+            byte => return Err(BinaryReadError::InvalidBool(byte).into()),
+        };
+
+        *bytes = &bytes[1..];
+        Ok(ret)
+    }
+
+    fn get_write_size(_: bool) -> usize {
+        1
+    }
+
+    unsafe fn write(bytes: &mut [u8], data: bool) -> &mut [u8] {
+        debug_assert!(
+            !bytes.is_empty(),
+            "invariant: slice must contain at least 1 byte to perform write"
+        );
+
+        bytes[0] = data as u8;
+        &mut bytes[1..]
+    }
+
+    #[inline(always)]
+    fn as_copy_type(t: &bool) -> Self::CopyType {
+        *t
+    }
+}
+
+#[cfg(test)]
+slice_serializable! {
+    #[derive(Debug, PartialEq)]
+    struct BoolTest {
+        pub enabled: bool as Bool,
+        pub id: u8 as Single
+    }
+}
+
+#[test]
+fn bool_test() {
+    let value = BoolTest {
+        enabled: true,
+        id: 7,
+    };
+
+    assert_eq!(BoolTest::get_write_size(&value), 2);
+
+    let mut bytes = vec![0; BoolTest::get_write_size(&value)];
+    unsafe {
+        let remaining = BoolTest::write(&mut bytes, &value);
+        assert!(remaining.is_empty());
+    }
+    assert_eq!(bytes, [1, 7]);
+
+    let mut slice = bytes.as_slice();
+    assert_eq!(BoolTest::read_fully(&mut slice).unwrap(), value);
+
+    let mut slice: &[u8] = &[0, 7];
+    assert!(!BoolTest::read_fully(&mut slice).unwrap().enabled);
+
+    // Values other than 0 and 1 are rejected
+    let mut slice: &[u8] = &[2, 7];
+    assert!(BoolTest::read(&mut slice).is_err());
+}
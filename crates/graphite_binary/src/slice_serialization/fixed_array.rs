@@ -0,0 +1,79 @@
+use std::marker::PhantomData;
+
+use super::*;
+
+/// Array of exactly `N` elements, written without a length prefix
+pub struct FixedArray<S, const N: usize> {
+    _a: PhantomData<S>,
+}
+
+impl<'a, T: 'a, S: SliceSerializable<'a, T>, const N: usize> SliceSerializable<'a, [T; N]>
+    for FixedArray<S, N>
+{
+    type CopyType = &'a [T; N];
+
+    fn read(bytes: &mut &'a [u8]) -> anyhow::Result<[T; N]> {
+        let mut vec = Vec::with_capacity(N);
+        for _ in 0..N {
+            vec.push(S::read(bytes)?);
+        }
+
+        match vec.try_into() {
+            Ok(array) => Ok(array),
+            Err(_) => unreachable!("exactly N elements were read"),
+        }
+    }
+
+    fn get_write_size(entries: &'a [T; N]) -> usize {
+        let mut size = 0;
+        for entry in entries {
+            size += S::get_write_size(S::as_copy_type(entry));
+        }
+        size
+    }
+
+    unsafe fn write<'b>(mut bytes: &'b mut [u8], entries: &'a [T; N]) -> &'b mut [u8] {
+        for entry in entries {
+            bytes = S::write(bytes, S::as_copy_type(entry));
+        }
+        bytes
+    }
+
+    #[inline(always)]
+    fn as_copy_type(t: &'a [T; N]) -> Self::CopyType {
+        t
+    }
+}
+
+#[cfg(test)]
+slice_serializable! {
+    #[derive(Debug, PartialEq)]
+    struct FixedArrayTest {
+        values: [i32; 4] as FixedArray<BigEndian, 4>,
+        salt: [u8; 3] as FixedArray<Single, 3>
+    }
+}
+
+#[test]
+fn fixed_array_test() {
+    let value = FixedArrayTest {
+        values: [1, -2, 3, i32::MAX],
+        salt: [7, 8, 9],
+    };
+
+    assert_eq!(FixedArrayTest::get_write_size(&value), 4 * 4 + 3);
+
+    let mut bytes = vec![0; FixedArrayTest::get_write_size(&value)];
+    unsafe {
+        let remaining = FixedArrayTest::write(&mut bytes, &value);
+        assert!(remaining.is_empty());
+    }
+    assert_eq!(&bytes[..4], &[0, 0, 0, 1]);
+
+    let mut slice = bytes.as_slice();
+    assert_eq!(FixedArrayTest::read_fully(&mut slice).unwrap(), value);
+
+    // Fewer than N elements remaining
+    let mut slice = &bytes[..bytes.len() - 1];
+    assert!(FixedArrayTest::read(&mut slice).is_err());
+}
@@ -5,9 +5,18 @@ mod option;
 mod sized_array;
 pub use sized_array::SizedArray;
 
+mod fixed_array;
+pub use fixed_array::FixedArray;
+
 mod single_byte;
 pub use single_byte::Single;
 
+mod boolean;
+pub use boolean::Bool;
+
+mod uuid;
+pub use uuid::Uuid;
+
 mod varint;
 pub use varint::VarInt;
 
@@ -16,6 +25,7 @@ pub use blob::GreedyBlob;
 pub use blob::NBTBlob;
 pub use blob::SizedBlob;
 pub use blob::SizedString;
+pub use blob::UnnamedNBTBlob;
 pub use blob::WriteOnlyBlob;
 
 mod primitive;
@@ -35,6 +45,8 @@ pub enum BinaryReadError {
     StringCharsExceedMaxSize(usize, usize),
     #[error("didn't fully consume buffer, {0} byte(s) remained")]
     DidntFullyConsume(usize),
+    #[error("invalid bool, expected 0 or 1, got {0}")]
+    InvalidBool(u8),
 }
 
 pub trait SliceSerializable<'a, T = Self> {
@@ -61,3 +61,34 @@ for_primitive!(f32, BigEndian, from_be_bytes, to_be_bytes);
 for_primitive!(f64, BigEndian, from_be_bytes, to_be_bytes);
 
 for_primitive!(u16, LittleEndian, from_le_bytes, to_le_bytes);
+for_primitive!(f32, LittleEndian, from_le_bytes, to_le_bytes);
+for_primitive!(f64, LittleEndian, from_le_bytes, to_le_bytes);
+
+#[test]
+fn float_test() {
+    let mut bytes = vec![0; 12];
+    unsafe {
+        let remaining = <BigEndian as SliceSerializable<f32>>::write(&mut bytes, 0.5);
+        let remaining = <BigEndian as SliceSerializable<f64>>::write(remaining, -1.0);
+        assert!(remaining.is_empty());
+    }
+    assert_eq!(&bytes[..4], &0.5f32.to_be_bytes());
+
+    let mut slice = bytes.as_slice();
+    assert_eq!(<BigEndian as SliceSerializable<f32>>::read(&mut slice).unwrap(), 0.5);
+    assert_eq!(<BigEndian as SliceSerializable<f64>>::read(&mut slice).unwrap(), -1.0);
+    assert!(slice.is_empty());
+
+    let mut bytes = vec![0; 12];
+    unsafe {
+        let remaining = <LittleEndian as SliceSerializable<f32>>::write(&mut bytes, 0.5);
+        let remaining = <LittleEndian as SliceSerializable<f64>>::write(remaining, -1.0);
+        assert!(remaining.is_empty());
+    }
+    assert_eq!(&bytes[..4], &0.5f32.to_le_bytes());
+
+    let mut slice = bytes.as_slice();
+    assert_eq!(<LittleEndian as SliceSerializable<f32>>::read(&mut slice).unwrap(), 0.5);
+    assert_eq!(<LittleEndian as SliceSerializable<f64>>::read(&mut slice).unwrap(), -1.0);
+    assert!(<LittleEndian as SliceSerializable<f64>>::read(&mut slice).is_err());
+}
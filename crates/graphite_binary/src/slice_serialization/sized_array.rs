@@ -2,6 +2,9 @@ use std::marker::PhantomData;
 
 use super::*;
 
+/// Array prefixed with its length as a VarInt, with each element serialized using `S`
+///
+/// `S` can also be a `slice_serializable!` struct, eg. `Vec<Entry> as SizedArray<Entry>`
 pub struct SizedArray<S> {
     _a: PhantomData<S>,
 }
@@ -16,7 +19,15 @@ impl<'a, T: 'a, S: SliceSerializable<'a, T>> SliceSerializable<'a, Vec<T>> for S
             return Ok(vec![]);
         }
 
-        let mut vec = Vec::with_capacity(array_length as usize);
+        // Every element takes at least one byte, so a length larger than the remaining bytes is invalid
+        if array_length > bytes.len() {
+            return Err(BinaryReadError::NotEnoughRemainingBytes.into());
+        }
+
+        // Don't preallocate more memory than the size of the input, elements can be much larger
+        // in memory than when serialized, so a malicious length can't cause a huge allocation
+        let max_capacity = bytes.len() / std::mem::size_of::<T>().max(1);
+        let mut vec = Vec::with_capacity(array_length.min(max_capacity));
         for _ in 0..array_length {
             vec.push(S::read(bytes)?);
         }
@@ -45,3 +56,14 @@ impl<'a, T: 'a, S: SliceSerializable<'a, T>> SliceSerializable<'a, Vec<T>> for S
         t
     }
 }
+
+#[test]
+fn length_larger_than_input() {
+    // Claims 1000 elements, but only contains one
+    let mut bytes: &[u8] = &[0xE8, 0x07, 1];
+    assert!(<SizedArray<Single> as SliceSerializable<Vec<u8>>>::read(&mut bytes).is_err());
+
+    let mut bytes: &[u8] = &[3, 1, 2, 3];
+    let vec = <SizedArray<Single> as SliceSerializable<Vec<u8>>>::read(&mut bytes).unwrap();
+    assert_eq!(vec, [1, 2, 3]);
+}
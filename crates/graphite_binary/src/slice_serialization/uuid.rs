@@ -0,0 +1,49 @@
+use super::*;
+
+/// 16 byte UUID, written as the most significant 8 bytes followed by the least significant 8 bytes
+///
+/// This is the same as `u128 as BigEndian`, but makes packet definitions clearer
+pub enum Uuid {}
+
+impl SliceSerializable<'_, u128> for Uuid {
+    type CopyType = u128;
+
+    fn read(bytes: &mut &[u8]) -> anyhow::Result<u128> {
+        <BigEndian as SliceSerializable<u128>>::read(bytes)
+    }
+
+    fn get_write_size(_: u128) -> usize {
+        16
+    }
+
+    unsafe fn write(bytes: &mut [u8], data: u128) -> &mut [u8] {
+        <BigEndian as SliceSerializable<u128>>::write(bytes, data)
+    }
+
+    #[inline(always)]
+    fn as_copy_type(t: &u128) -> Self::CopyType {
+        *t
+    }
+}
+
+#[test]
+fn uuid_test() {
+    // Notch, 069a79f4-44e9-4726-a5be-fca90e38aaf5
+    let uuid: u128 = 0x069a79f444e94726a5befca90e38aaf5;
+    let most_significant: u64 = 0x069a79f444e94726;
+    let least_significant: u64 = 0xa5befca90e38aaf5;
+
+    let mut bytes = vec![0; Uuid::get_write_size(uuid)];
+    unsafe {
+        let remaining = Uuid::write(&mut bytes, uuid);
+        assert!(remaining.is_empty());
+    }
+    assert_eq!(&bytes[..8], &most_significant.to_be_bytes());
+    assert_eq!(&bytes[8..], &least_significant.to_be_bytes());
+
+    let mut slice = bytes.as_slice();
+    assert_eq!(Uuid::read_fully(&mut slice).unwrap(), uuid);
+
+    let mut slice = &bytes[..15];
+    assert!(Uuid::read(&mut slice).is_err());
+}
@@ -128,6 +128,10 @@ enum BrigadierAttribute {
         span: Span,
         modifiers: Punctuated<syn::Expr, Token![;]>,
     },
+    Permission {
+        span: Span,
+        level: syn::LitInt,
+    },
 }
 
 impl Parse for BrigadierAttribute {
@@ -141,6 +145,18 @@ impl Parse for BrigadierAttribute {
                 span,
                 modifiers: Punctuated::parse_terminated(&content)?, // todo: be more specific than syn::Expr
             })
+        } else if input.peek(syn::Ident) {
+            // `permission = level`
+            let ident: syn::Ident = input.parse()?;
+            if ident != "permission" {
+                return Err(syn::Error::new(ident.span(), "expected `permission`"));
+            }
+            let _eq_token: Token![=] = input.parse()?;
+
+            Ok(Self::Permission {
+                span,
+                level: input.parse()?,
+            })
         } else {
             let aliases = if input.peek(token::Bracket) {
                 let content;
@@ -270,7 +286,7 @@ pub fn brigadier(attr: TokenStream, item: TokenStream) -> TokenStream {
 
     let id = input.sig.ident.clone();
 
-    let mut generic_player_types = vec![];
+    let mut context_types = vec![];
     let mut resolved_bounds = HashMap::new();
 
     for generic in &input.sig.generics.params {
@@ -357,16 +373,17 @@ pub fn brigadier(attr: TokenStream, item: TokenStream) -> TokenStream {
                     if let Some(generic_type) = check_player_type_and_get_generic(type_path) {
                         if !brigadier_players.is_empty() {
                             for player in brigadier_players {
-                                generic_player_types.push(player);
+                                context_types.push(quote!(Player<#player>));
                             }
                         } else {
-                            generic_player_types.push(generic_type);
+                            context_types.push(quote!(Player<#generic_type>));
                         }
-
-                        true
                     } else {
-                        false
+                        // Any other type is used as the context directly, eg. a mock context for testing
+                        context_types.push(type_path.to_token_stream());
                     }
+
+                    ref_ty.mutability.is_some()
                 }
                 _ => false,
             },
@@ -374,9 +391,9 @@ pub fn brigadier(attr: TokenStream, item: TokenStream) -> TokenStream {
         }
     };
     if !has_correct_first_argument {
-        throw_error!(id => "first argument of command function must be of type `&mut Player<P>`");
+        throw_error!(id => "first argument of command function must be of type `&mut Player<P>` or a mutable reference to a context");
     }
-    if generic_player_types.is_empty() {
+    if context_types.is_empty() {
         throw_error!(id => "unknown generic types for player argument");
     }
 
@@ -390,6 +407,7 @@ pub fn brigadier(attr: TokenStream, item: TokenStream) -> TokenStream {
     // Validate attributes
     let mut attribute_literal_count = 0;
     let mut attribute_argument_count = 0;
+    let mut permission: Option<u8> = None;
     for attribute in &attributes.attributes {
         match attribute {
             BrigadierAttribute::Literal { aliases } => {
@@ -405,8 +423,19 @@ pub fn brigadier(attr: TokenStream, item: TokenStream) -> TokenStream {
             } => {
                 attribute_argument_count += 1;
             }
+            BrigadierAttribute::Permission { span, level } => {
+                if permission.is_some() {
+                    throw_error!(*span, id => "duplicate permission level");
+                }
+                let level = check_result!(level.span(), id => level.base10_parse::<u8>());
+                if level > 4 {
+                    throw_error!(*span, id => "permission level must be between 0 and 4");
+                }
+                permission = Some(level);
+            }
         }
     }
+    let permission = permission.unwrap_or(0);
 
     // Error message if attribute argument count != function argument count
     if attribute_argument_count != function_argument_count {
@@ -434,6 +463,7 @@ pub fn brigadier(attr: TokenStream, item: TokenStream) -> TokenStream {
             for attribute in attributes.attributes {
                 match attribute {
                     BrigadierAttribute::Literal { aliases: _ } => (),
+                    BrigadierAttribute::Permission { span: _, level: _ } => (),
                     BrigadierAttribute::Argument { span, modifiers: _ } => {
                         index += 1;
                         if index > function_argument_count {
@@ -460,8 +490,10 @@ pub fn brigadier(attr: TokenStream, item: TokenStream) -> TokenStream {
             literals: std::collections::BTreeMap::new(),
             aliases: std::collections::BTreeMap::new(),
             numeric_parser: None,
+            bool_parser: None,
             string_parser: None,
             executor: Some(#command_identifier_parse),
+            permission: #permission,
         }
     );
 
@@ -528,27 +560,48 @@ pub fn brigadier(attr: TokenStream, item: TokenStream) -> TokenStream {
                                 map
                             },
                             numeric_parser: None,
+                            bool_parser: None,
                             string_parser: None,
                             executor: None,
+                            permission: 0,
                         }
                     );
                 }
             }
-            BrigadierAttribute::Argument { span: _, modifiers } => {
+            BrigadierAttribute::Argument { span, modifiers } => {
+                let (suggestions, modifiers) =
+                    check_result!(*span, id => split_suggestions_modifier(modifiers));
+                let modifiers = &modifiers;
+
+                // Attributes are processed in reverse, so the first one processed is the last
+                let is_last_attribute = attribute_literal_index == 0 && attribute_argument_index == 0;
                 attribute_argument_index += 1;
                 let function_arg_index = attribute_argument_count - attribute_argument_index;
                 let function_arg = &input.sig.arguments[function_arg_index + 1];
                 let function_arg_ident = &function_arg.ident;
                 let ty = &function_arg.ty;
 
-                let parser_num;
+                let parser_kind;
                 let parser_expr;
                 let parser_validate;
 
                 let deconstruct_index =
                     proc_macro2::Literal::usize_unsuffixed(function_arg_index + 2);
 
+                // `String` arguments are parsed like `&str` and own the slice when the command is executed
+                let mut is_owned_string = false;
+
                 match ty {
+                    syn::Type::Path(type_path) if type_path.path.is_ident("String") => {
+                        parse_function_data_args = quote! (
+                            &'static str,
+                            #parse_function_data_args
+                        );
+
+                        (parser_kind, parser_expr, parser_validate) = check_result!(type_path.span(), id =>
+                            process_str_arg(modifiers, is_last_attribute));
+                        is_owned_string = true;
+                    }
                     syn::Type::Path(type_path) => {
                         let path_segments = &type_path.path.segments;
                         let last_segment_ident = &path_segments[path_segments.len() - 1].ident;
@@ -561,37 +614,71 @@ pub fn brigadier(attr: TokenStream, item: TokenStream) -> TokenStream {
 
                         match ident_str {
                             "u8" => {
-                                (parser_num, parser_expr, parser_validate) = check_result!(type_path.span(), id =>
+                                (parser_kind, parser_expr, parser_validate) = check_result!(type_path.span(), id =>
                                     process_num_arg(quote!(u8), quote!(U8), deconstruct_index.clone(), modifiers));
                             }
                             "u16" => {
-                                (parser_num, parser_expr, parser_validate) = check_result!(type_path.span(), id =>
+                                (parser_kind, parser_expr, parser_validate) = check_result!(type_path.span(), id =>
                                     process_num_arg(quote!(u16), quote!(U16), deconstruct_index.clone(), modifiers))
                             }
                             "u64" => {
-                                (parser_num, parser_expr, parser_validate) = check_result!(type_path.span(), id =>
+                                (parser_kind, parser_expr, parser_validate) = check_result!(type_path.span(), id =>
                                     process_num_arg(quote!(u64), quote!(U64), deconstruct_index.clone(), modifiers))
                             }
                             "usize" => {
-                                (parser_num, parser_expr, parser_validate) = check_result!(type_path.span(), id =>
+                                (parser_kind, parser_expr, parser_validate) = check_result!(type_path.span(), id =>
                                     process_num_arg(quote!(usize), quote!(USize), deconstruct_index.clone(), modifiers))
                             }
                             "isize" => {
-                                (parser_num, parser_expr, parser_validate) = check_result!(type_path.span(), id =>
+                                (parser_kind, parser_expr, parser_validate) = check_result!(type_path.span(), id =>
                                     process_num_arg(quote!(isize), quote!(ISize), deconstruct_index.clone(), modifiers))
                             }
+                            "bool" => {
+                                (parser_kind, parser_expr, parser_validate) = check_result!(type_path.span(), id =>
+                                    process_bool_arg(modifiers))
+                            }
+                            "i32" => {
+                                (parser_kind, parser_expr, parser_validate) = check_result!(type_path.span(), id =>
+                                    process_num_arg(quote!(i32), quote!(I32), deconstruct_index.clone(), modifiers))
+                            }
+                            "i64" => {
+                                (parser_kind, parser_expr, parser_validate) = check_result!(type_path.span(), id =>
+                                    process_num_arg(quote!(i64), quote!(I64), deconstruct_index.clone(), modifiers))
+                            }
+                            "f32" => {
+                                (parser_kind, parser_expr, parser_validate) = check_result!(type_path.span(), id =>
+                                    process_num_arg(quote!(f32), quote!(F32), deconstruct_index.clone(), modifiers))
+                            }
+                            "f64" => {
+                                (parser_kind, parser_expr, parser_validate) = check_result!(type_path.span(), id =>
+                                    process_num_arg(quote!(f64), quote!(F64), deconstruct_index.clone(), modifiers))
+                            }
                             _ => {
                                 throw_error!(ty.span(), id => "type does not correspond to a known Brigadier argument")
                             }
                         }
                     }
+                    syn::Type::Reference(ref_ty) if is_str_type(&ref_ty.elem) => {
+                        parse_function_data_args = quote! (
+                            &'static str,
+                            #parse_function_data_args
+                        );
+
+                        (parser_kind, parser_expr, parser_validate) = check_result!(ref_ty.span(), id =>
+                            process_str_arg(modifiers, is_last_attribute));
+                    }
                     _ => {
                         throw_error!(ty.span(), id => "type does not correspond to a known Brigadier argument")
                     }
                 }
 
+                let data_arg = if is_owned_string {
+                    quote!(String::from(data.#deconstruct_index))
+                } else {
+                    quote!(data.#deconstruct_index)
+                };
                 parse_function_data_args_deconstruct = quote! (
-                    data.#deconstruct_index,
+                    #data_arg,
                     #parse_function_data_args_deconstruct
                 );
                 parse_function_validate_args.extend(parser_validate);
@@ -600,49 +687,47 @@ pub fn brigadier(attr: TokenStream, item: TokenStream) -> TokenStream {
                     Some(graphite_command::minecraft::MinecraftArgumentNode {
                         name: stringify!(#function_arg_ident),
                         parse: #parser_expr,
+                        suggestions: #suggestions,
                         dispatch_node: Box::from(#dispatch_node),
                     })
                 );
 
-                if parser_num {
-                    dispatch_node = quote!(
-                        graphite_command::minecraft::MinecraftDispatchNode {
-                            literals: std::collections::BTreeMap::new(),
-                            aliases: std::collections::BTreeMap::new(),
-                            numeric_parser: #parser_node,
-                            string_parser: None,
-                            executor: None,
-                        }
-                    )
-                } else {
-                    dispatch_node = quote!(
-                        graphite_command::minecraft::MinecraftDispatchNode {
-                            literals: std::collections::BTreeMap::new(),
-                            aliases: std::collections::BTreeMap::new(),
-                            numeric_parser: None,
-                            string_parser: #parser_node,
-                            executor: None,
-                        }
-                    )
-                }
+                let (numeric_parser, bool_parser, string_parser) = match parser_kind {
+                    ParserKind::Numeric => (parser_node, quote!(None), quote!(None)),
+                    ParserKind::Bool => (quote!(None), parser_node, quote!(None)),
+                    ParserKind::String => (quote!(None), quote!(None), parser_node),
+                };
+                dispatch_node = quote!(
+                    graphite_command::minecraft::MinecraftDispatchNode {
+                        literals: std::collections::BTreeMap::new(),
+                        aliases: std::collections::BTreeMap::new(),
+                        numeric_parser: #numeric_parser,
+                        bool_parser: #bool_parser,
+                        string_parser: #string_parser,
+                        executor: None,
+                        permission: 0,
+                    }
+                )
             }
+            BrigadierAttribute::Permission { span: _, level: _ } => (),
         }
     }
 
     let mut player_type_checks = quote!();
 
-    for (index, player_type) in generic_player_types.iter().enumerate() {
+    // The context is only used if its type id matches, see `ParseState::push_context`
+    for (index, context_type) in context_types.iter().enumerate() {
         if index == 0 {
             player_type_checks = quote!(
-                if data.1 == std::any::TypeId::of::<#player_type>() {
-                    graphite_command::types::CommandDispatchResult::Success(#id(unsafe { &mut *(std::mem::transmute::<*mut (), *mut Player<#player_type>>(data.0) )}, #parse_function_data_args_deconstruct))
+                if data.1 == std::any::TypeId::of::<#context_type>() {
+                    graphite_command::types::CommandDispatchResult::Success(#id(unsafe { &mut *(std::mem::transmute::<*mut (), *mut #context_type>(data.0) )}, #parse_function_data_args_deconstruct))
                 }
             );
         } else {
             player_type_checks = quote!(
                 #player_type_checks
-                else if data.1 == std::any::TypeId::of::<#player_type>() {
-                    graphite_command::types::CommandDispatchResult::Success(#id(unsafe { &mut *(std::mem::transmute::<*mut (), *mut Player<#player_type>>(data.0) )}, #parse_function_data_args_deconstruct))
+                else if data.1 == std::any::TypeId::of::<#context_type>() {
+                    graphite_command::types::CommandDispatchResult::Success(#id(unsafe { &mut *(std::mem::transmute::<*mut (), *mut #context_type>(data.0) )}, #parse_function_data_args_deconstruct))
                 }
             );
         }
@@ -704,29 +789,76 @@ fn add_from_where_clause(where_clause: WhereClause, resolved_bounds: &mut HashMa
     }
 }
 
+/// Removes the `complete = function` modifier, returning the suggestion function
+/// and the remaining modifiers
+fn split_suggestions_modifier(
+    modifiers: &Punctuated<syn::Expr, token::Semi>,
+) -> result::Result<(proc_macro2::TokenStream, Punctuated<syn::Expr, token::Semi>), &'static str> {
+    let mut function = None;
+    let mut remaining = Punctuated::new();
+    for modifier in modifiers {
+        match modifier {
+            syn::Expr::Assign(assign) if is_ident_expr(&assign.left, "complete") => {
+                if function.is_some() {
+                    return Err("duplicate `complete` modifier");
+                }
+                function = Some(&assign.right);
+            }
+            _ => remaining.push(modifier.clone()),
+        }
+    }
+
+    let suggestions = match function {
+        Some(function) => quote!(Some(#function)),
+        None => quote!(None),
+    };
+    Ok((suggestions, remaining))
+}
+
+fn is_ident_expr(expr: &syn::Expr, ident: &str) -> bool {
+    matches!(expr, syn::Expr::Path(path) if path.path.is_ident(ident))
+}
+
+/// Which parser of `MinecraftDispatchNode` an argument uses
+enum ParserKind {
+    Numeric,
+    Bool,
+    String,
+}
+
 fn process_num_arg(
     raw_typ: proc_macro2::TokenStream,
     parser_typ: proc_macro2::TokenStream,
     deconstruct_index: proc_macro2::Literal,
     modifiers: &Punctuated<syn::Expr, token::Semi>,
-) -> result::Result<(bool, proc_macro2::TokenStream, proc_macro2::TokenStream), &'static str> {
+) -> result::Result<(ParserKind, proc_macro2::TokenStream, proc_macro2::TokenStream), &'static str> {
+    // Floats also accept integer bounds, eg. `{0..10}`, which need to be cast
+    let is_float = matches!(raw_typ.to_string().as_str(), "f32" | "f64");
+    let bound_expr = |expr: &syn::Expr| {
+        if is_float {
+            quote!((#expr) as #raw_typ)
+        } else {
+            expr.to_token_stream()
+        }
+    };
+
     let mut min_expr = quote!(#raw_typ::MIN);
     let mut max_expr = quote!(#raw_typ::MAX);
     for modifier in modifiers {
         match modifier {
             syn::Expr::Range(range) => {
                 if let Some(from) = &range.from {
-                    min_expr = from.to_token_stream();
+                    min_expr = bound_expr(from);
                 }
                 if let Some(to) = &range.to {
-                    max_expr = to.to_token_stream();
+                    max_expr = bound_expr(to);
                 }
             }
-            _ => return Err("invalid modifier for integer"),
+            _ => return Err("invalid modifier for number"),
         }
     }
     Ok((
-        true,
+        ParserKind::Numeric,
         quote!(
             graphite_command::minecraft::NumericParser::#parser_typ {
                 min: #min_expr,
@@ -751,10 +883,73 @@ fn process_num_arg(
     ))
 }
 
-fn check_literal(literal: &str) -> result::Result<(), &'static str> {
+fn process_bool_arg(
+    modifiers: &Punctuated<syn::Expr, token::Semi>,
+) -> result::Result<(ParserKind, proc_macro2::TokenStream, proc_macro2::TokenStream), &'static str> {
+    if !modifiers.is_empty() {
+        return Err("invalid modifier for bool");
+    }
+    Ok((
+        ParserKind::Bool,
+        quote!(graphite_command::minecraft::BoolParser),
+        quote!(),
+    ))
+}
+
+fn is_str_type(ty: &syn::Type) -> bool {
+    match ty {
+        syn::Type::Path(type_path) => type_path.path.is_ident("str"),
+        _ => false,
+    }
+}
+
+fn process_str_arg(
+    modifiers: &Punctuated<syn::Expr, token::Semi>,
+    is_last_attribute: bool,
+) -> result::Result<(ParserKind, proc_macro2::TokenStream, proc_macro2::TokenStream), &'static str> {
+    if modifiers.len() > 1 {
+        return Err("string can only have one modifier");
+    }
+
+    let parser = match modifiers.first() {
+        None => quote!(Word),
+        // `{greedy}` takes the rest of the command instead of a single word
+        Some(modifier) if is_ident_expr(modifier, "greedy") => {
+            if !is_last_attribute {
+                return Err("greedy string must be the last argument");
+            }
+            quote!(Greedy)
+        }
+        // `{block_pos}` takes the three coordinates of a block, eg. `~ ~-1 ~`
+        Some(modifier) if is_ident_expr(modifier, "block_pos") => quote!(BlockPos),
+        // `{block_state}` takes a block state, eg. `minecraft:oak_stairs[facing=east]`
+        Some(modifier) if is_ident_expr(modifier, "block_state") => quote!(BlockState),
+        Some(_) => return Err("invalid modifier for string"),
+    };
+
+    Ok((
+        ParserKind::String,
+        quote!(graphite_command::minecraft::StringParser::#parser),
+        quote!(),
+    ))
+}
+
+fn check_literal(literal: &str) -> result::Result<(), String> {
+    if literal.is_empty() {
+        return Err("literal cannot be empty".into());
+    }
+    if literal.starts_with('/') {
+        return Err("literal cannot start with `/`".into());
+    }
     for char in literal.chars() {
+        // The client splits commands on spaces and treats quotes and backslashes as part of
+        // quoted strings, so literals containing them can't be typed or suggested correctly
         if char == ' ' {
-            return Err("literal cannot contain a space");
+            return Err("literal cannot contain a space".into());
+        } else if char.is_whitespace() || char.is_control() {
+            return Err(format!("literal cannot contain whitespace or control character {:?}", char));
+        } else if matches!(char, '"' | '\'' | '\\') {
+            return Err(format!("literal cannot contain `{}`", char));
         }
     }
     Ok(())
@@ -1,7 +1,8 @@
 use std::collections::{BTreeMap, HashMap};
 
 use crate::types::{
-    CommandDispatchResult, CommandParseResult, DispatchFunction, ParseState, SpannedWord,
+    CommandDispatchResult, CommandParseResult, CommandSuggestions, DispatchFunction, ParseState,
+    SpannedWord, SuggestionFunction,
 };
 
 // Node implemenatations
@@ -17,6 +18,46 @@ impl RootDispatchNode {
         self.dispatch_with(parse_state)
     }
 
+    /// Dispatches the command, passing `context` as the first argument of the command function
+    ///
+    /// Returns `CommandDispatchResult::UnknownPlayerService` if the command function doesn't
+    /// accept contexts of type `C`, see `ParseState::push_context`
+    pub fn dispatch_with_context<C: 'static>(&self, input: &str, context: &mut C) -> CommandDispatchResult {
+        let mut parse_state = ParseState::new(input);
+        parse_state.push_context(context);
+        self.dispatch_with(parse_state)
+    }
+
+    /// Suggests values for the last argument of `input`, using the suggestion functions
+    /// of the arguments it could be
+    ///
+    /// Returns `None` if none of those arguments have a suggestion function. Literals
+    /// aren't suggested, the client suggests them using the brigadier packet
+    pub fn suggest(&self, input: &str) -> Option<CommandSuggestions> {
+        // The argument being typed is everything after the last whitespace
+        let (whitespace_index, whitespace) = input
+            .char_indices()
+            .rfind(|(_, char)| char.is_whitespace())?;
+        let start = whitespace_index + whitespace.len_utf8();
+        let partial = &input[start..];
+
+        let mut parse_state = ParseState::new(&input[..start]);
+        let spanned_word = parse_state.pop_input()?;
+        let literal_name = self
+            .aliases
+            .get(spanned_word.word)
+            .unwrap_or(&spanned_word.word);
+        let literal = self.literals.get(literal_name)?;
+
+        // The client expects offsets in UTF-16 code units, like Java strings
+        let suggestions = literal.suggest(&mut parse_state, partial)?;
+        Some(CommandSuggestions {
+            start: input[..start].encode_utf16().count(),
+            length: partial.encode_utf16().count(),
+            suggestions,
+        })
+    }
+
     pub fn dispatch_with(&self, mut parse_state: ParseState) -> CommandDispatchResult {
         if let Some(spanned_word) = parse_state.pop_input() {
             if let Some(aliased) = self.aliases.get(spanned_word.word) {
@@ -50,6 +91,8 @@ pub(crate) struct DispatchNode {
     pub(crate) aliases: BTreeMap<&'static str, &'static str>,
     pub(crate) parsers: Vec<ArgumentNode>,
     pub(crate) executor: Option<DispatchFunction>,
+    /// Permission level required to use the executor
+    pub(crate) permission: u8,
 }
 
 impl DispatchNode {
@@ -71,9 +114,8 @@ impl DispatchNode {
             } else {
                 // No literal match, try to parse the input
                 let mut result: Option<CommandDispatchResult> = None;
+                let checkpoint = remaining.checkpoint();
                 for arg in &self.parsers {
-                    let prev_cursor = remaining.cursor();
-
                     let parse_result = arg.parse(next_word, remaining);
                     match parse_result {
                         CommandDispatchResult::ParseError {
@@ -92,12 +134,9 @@ impl DispatchNode {
                         _ => return parse_result,
                     }
 
-                    // Parse failed, try next parser
-                    // Also debug assert that the cursor didn't change
-                    debug_assert!(
-                        remaining.cursor() == prev_cursor,
-                        "cursor was updated by an argument node that failed"
-                    );
+                    // Parse failed, undo anything this parser (or the nodes after it)
+                    // consumed and try the next parser
+                    remaining.restore(checkpoint);
                 }
                 match result {
                     Some(dispatch_result) => dispatch_result,
@@ -108,6 +147,10 @@ impl DispatchNode {
             // There is no input remaining, see if this node is an executor
 
             if let Some(executor) = self.executor {
+                if remaining.permission_level() < self.permission {
+                    return CommandDispatchResult::MissingPermission;
+                }
+
                 // This node is an executor, lets execute!
                 let (arguments, spans) = remaining.get_arguments();
                 executor(arguments, spans)
@@ -117,12 +160,43 @@ impl DispatchNode {
             }
         }
     }
+
+    fn suggest(&self, remaining: &mut ParseState, partial: &str) -> Option<Vec<String>> {
+        if let Some(next_word) = remaining.pop_input() {
+            // Follow the input to the node of the argument being typed
+            let literal_name = self.aliases.get(next_word.word).unwrap_or(&next_word.word);
+            if let Some(literal) = self.literals.get(literal_name) {
+                return literal.suggest(remaining, partial);
+            }
+
+            for arg in &self.parsers {
+                if let CommandParseResult::Ok = (arg.parse)(next_word, remaining) {
+                    return arg.dispatch_node.suggest(remaining, partial);
+                }
+            }
+
+            None
+        } else {
+            // Only suggestions that match what has been typed so far are kept
+            let mut suggestions: Option<Vec<String>> = None;
+            for arg in &self.parsers {
+                if let Some(suggestion_function) = arg.suggestions {
+                    let matching = suggestion_function(partial)
+                        .into_iter()
+                        .filter(|suggestion| suggestion.starts_with(partial));
+                    suggestions.get_or_insert_with(Vec::new).extend(matching);
+                }
+            }
+            suggestions
+        }
+    }
 }
 
 // Argument node
 
 pub(crate) struct ArgumentNode {
     pub(crate) parse: fn(SpannedWord, &mut ParseState) -> CommandParseResult,
+    pub(crate) suggestions: Option<SuggestionFunction>,
     pub(crate) dispatch_node: DispatchNode,
 }
 
@@ -133,7 +207,8 @@ impl ArgumentNode {
 
         match parse_result {
             CommandParseResult::Ok => {
-                // Parse succeeded, continue dispatching
+                // Parse succeeded, continue dispatching. If a later argument fails, the
+                // caller restores the parse state so that other parsers can still be tried
                 self.dispatch_node.dispatch(remaining)
             }
             CommandParseResult::Err {
@@ -160,7 +235,9 @@ mod tests {
 
     use crate::dispatcher::{ArgumentNode, DispatchNode, RootDispatchNode};
     use crate::types::ParseState;
-    use crate::types::{CommandDispatchResult, CommandParseResult, Span, SpannedWord};
+    use crate::types::{
+        CommandDispatchResult, CommandParseResult, DispatchFunction, Span, SpannedWord,
+    };
 
     #[test]
     pub fn dispatch_with_parse() {
@@ -190,35 +267,42 @@ mod tests {
                     parsers: vec![
                         ArgumentNode {
                             parse: parse_u8,
+                            suggestions: None,
                             dispatch_node: DispatchNode {
                                 literals: BTreeMap::new(),
                                 aliases: BTreeMap::new(),
                                 parsers: vec![
                                     ArgumentNode {
                                         parse: parse_str,
+                                        suggestions: None,
                                         dispatch_node: DispatchNode {
                                             literals: BTreeMap::new(),
                                             aliases: BTreeMap::new(),
                                             parsers: vec![
                                                 ArgumentNode {
                                                     parse: parse_u16,
+                                                    suggestions: None,
                                                     dispatch_node: DispatchNode {
                                                         literals: BTreeMap::new(),
                                                         aliases: BTreeMap::new(),
                                                         parsers: vec![],
-                                                        executor: Some(hello_world)
+                                                        executor: Some(hello_world),
+                                                        permission: 0,
                                                     }
                                                 }
                                             ],
                                             executor: None,
+                                            permission: 0,
                                         }
                                     }
                                 ],
                                 executor: None,
+                                permission: 0,
                             }
                         }
                     ],
                     executor: None,
+                    permission: 0,
                 }
             ),
             aliases: HashMap::new(),
@@ -255,7 +339,8 @@ mod tests {
                     literals: BTreeMap::new(),
                     aliases: BTreeMap::new(),
                     parsers: vec![],
-                    executor: Some(my_command)
+                    executor: Some(my_command),
+                    permission: 0,
                 }
             ),
             aliases: HashMap::new(),
@@ -269,6 +354,75 @@ mod tests {
         assert!(unsafe { DISPATCH_EXECUTED });
     }
 
+    #[test]
+    pub fn dispatch_overlapping_parsers() {
+        static mut EXECUTED: Option<&'static str> = None;
+
+        fn with_numbers(_: &[u8], _: &[Span]) -> CommandDispatchResult {
+            unsafe { EXECUTED = Some("numbers") };
+            CommandDispatchResult::Success(Ok(()))
+        }
+
+        fn with_strings(data: &[u8], spans: &[Span]) -> CommandDispatchResult {
+            #[repr(C)]
+            struct Data(&'static str, &'static str);
+
+            debug_assert_eq!(spans.len(), 2);
+            debug_assert_eq!(data.len(), std::mem::size_of::<Data>());
+            let data: &Data = unsafe { &*(data as *const _ as *const Data) };
+
+            assert_eq!(data.0, "5");
+            assert_eq!(data.1, "abc");
+            unsafe { EXECUTED = Some("strings") };
+            CommandDispatchResult::Success(Ok(()))
+        }
+
+        fn argument(parse: fn(SpannedWord, &mut ParseState) -> CommandParseResult,
+                dispatch_node: DispatchNode) -> ArgumentNode {
+            ArgumentNode { parse, suggestions: None, dispatch_node }
+        }
+
+        fn executor(executor: DispatchFunction) -> DispatchNode {
+            DispatchNode {
+                literals: BTreeMap::new(),
+                aliases: BTreeMap::new(),
+                parsers: vec![],
+                executor: Some(executor),
+                permission: 0,
+            }
+        }
+
+        fn parsers(parsers: Vec<ArgumentNode>) -> DispatchNode {
+            DispatchNode {
+                literals: BTreeMap::new(),
+                aliases: BTreeMap::new(),
+                parsers,
+                executor: None,
+                permission: 0,
+            }
+        }
+
+        // set <u8> <u8> and set <str> <str>
+        let root = RootDispatchNode {
+            literals: hashmap!(
+                "set" => parsers(vec![
+                    argument(parse_u8, parsers(vec![argument(parse_u8, executor(with_numbers))])),
+                    argument(parse_str, parsers(vec![argument(parse_str, executor(with_strings))])),
+                ])
+            ),
+            aliases: HashMap::new(),
+        };
+
+        // "5" is a number, but "abc" isn't, so the string parsers must be tried
+        let result = root.dispatch("set 5 abc");
+        assert!(matches!(result, CommandDispatchResult::Success(Ok(()))));
+        assert_eq!(unsafe { EXECUTED }, Some("strings"));
+
+        let result = root.dispatch("set 5 6");
+        assert!(matches!(result, CommandDispatchResult::Success(Ok(()))));
+        assert_eq!(unsafe { EXECUTED }, Some("numbers"));
+    }
+
     // Parser functions
 
     fn parse_u8(input: SpannedWord, state: &mut ParseState) -> CommandParseResult {
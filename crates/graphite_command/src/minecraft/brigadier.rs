@@ -1,6 +1,9 @@
 use std::collections::{BTreeMap, HashMap};
 
-use graphite_mc_protocol::{play::server, types::CommandNode};
+use graphite_mc_protocol::{
+    play::server,
+    types::{CommandNode, SuggestionType},
+};
 
 use crate::dispatcher::{ArgumentNode, DispatchNode, RootDispatchNode};
 
@@ -9,8 +12,27 @@ use super::{
     proto_nodes::{MinecraftArgumentNode, MinecraftDispatchNode, MinecraftRootDispatchNode},
 };
 
+/// Creates the dispatcher and the brigadier packet for players without any permissions
+///
+/// Players with a higher permission level should be sent `create_brigadier_packet` instead
 pub fn create_dispatcher_and_brigadier_packet(
     root: MinecraftRootDispatchNode,
+) -> (RootDispatchNode, server::Commands) {
+    create_dispatcher_and_brigadier_packet_for(&root, 0)
+}
+
+/// Creates the brigadier packet for players with `permission_level`, commands requiring
+/// a higher level aren't included
+pub fn create_brigadier_packet(
+    root: &MinecraftRootDispatchNode,
+    permission_level: u8,
+) -> server::Commands {
+    create_dispatcher_and_brigadier_packet_for(root, permission_level).1
+}
+
+fn create_dispatcher_and_brigadier_packet_for(
+    root: &MinecraftRootDispatchNode,
+    permission_level: u8,
 ) -> (RootDispatchNode, server::Commands) {
     let mut command_nodes = Vec::new();
 
@@ -23,12 +45,22 @@ pub fn create_dispatcher_and_brigadier_packet(
     // Process literals
     let mut literals = HashMap::new();
     for (literal_name, literal_node) in &root.literals {
-        let child = process_named_dispatch_node(literal_name, literal_node, &mut command_nodes);
+        let child = process_named_dispatch_node(
+            literal_name,
+            literal_node,
+            permission_level,
+            &mut command_nodes,
+        );
         let (child_dispatch_node, child_command_node) = child;
 
         // Insert dispatch node (graphite)
         literals.insert(*literal_name, child_dispatch_node);
 
+        // Skip command node (brigadier) if the player can't use the literal
+        let Some(child_command_node) = child_command_node else {
+            continue;
+        };
+
         // Push graphite -> brigadier mapping for aliases
         graphite_alias_map.insert(literal_name, child_command_node.clone());
 
@@ -40,8 +72,10 @@ pub fn create_dispatcher_and_brigadier_packet(
 
     // Process aliases
     for (alias_from, alias_to) in &root.aliases {
-        // Get brigadier index
-        let alias_for = graphite_alias_map.remove(alias_to).unwrap();
+        // Get brigadier node, skipping aliases of literals the player can't use
+        let Some(alias_for) = graphite_alias_map.get(alias_to).cloned() else {
+            continue;
+        };
 
         match alias_for {
             // todo: use a redirect instead of cloning the node
@@ -73,7 +107,7 @@ pub fn create_dispatcher_and_brigadier_packet(
     // Create root dispatch node (graphite)
     let root_dispatch_node = RootDispatchNode {
         literals,
-        aliases: root.aliases,
+        aliases: root.aliases.clone(),
     };
 
     // Create root command node (brigadier)
@@ -91,13 +125,26 @@ pub fn create_dispatcher_and_brigadier_packet(
     (root_dispatch_node, brigadier_packet)
 }
 
+/// Whether players with `permission_level` can use the executor of the node
+fn is_executable(dispatch: &MinecraftDispatchNode, permission_level: u8) -> bool {
+    dispatch.executor.is_some() && dispatch.permission <= permission_level
+}
+
+/// Returns `None` for the command node (brigadier) if the player can't use the node
+/// or any of its children
 fn process_named_dispatch_node(
     name: &'static str,
     dispatch: &MinecraftDispatchNode,
+    permission_level: u8,
     command_nodes: &mut Vec<CommandNode>,
-) -> (DispatchNode, CommandNode) {
-    let is_executable = dispatch.executor.is_some();
-    let (dispatch_node, brig_children) = process_dispatch_node(dispatch, command_nodes);
+) -> (DispatchNode, Option<CommandNode>) {
+    let is_executable = is_executable(dispatch, permission_level);
+    let (dispatch_node, brig_children) =
+        process_dispatch_node(dispatch, permission_level, command_nodes);
+
+    if !is_executable && brig_children.is_empty() {
+        return (dispatch_node, None);
+    }
 
     let command_node = CommandNode::Literal {
         children: brig_children,
@@ -106,11 +153,12 @@ fn process_named_dispatch_node(
         name,
     };
 
-    (dispatch_node, command_node)
+    (dispatch_node, Some(command_node))
 }
 
 fn process_dispatch_node(
     dispatch: &MinecraftDispatchNode,
+    permission_level: u8,
     command_nodes: &mut Vec<CommandNode>,
 ) -> (DispatchNode, Vec<i32>) {
     let literal_count = dispatch.literals.len();
@@ -122,12 +170,18 @@ fn process_dispatch_node(
     // Process literals
     let mut literals = BTreeMap::new();
     for (literal_name, literal_node) in &dispatch.literals {
-        let child = process_named_dispatch_node(literal_name, literal_node, command_nodes);
+        let child =
+            process_named_dispatch_node(literal_name, literal_node, permission_level, command_nodes);
         let (child_dispatch_node, child_command_node) = child;
 
         // Insert dispatch node (graphite)
         literals.insert(*literal_name, child_dispatch_node);
 
+        // Skip command node (brigadier) if the player can't use the literal
+        let Some(child_command_node) = child_command_node else {
+            continue;
+        };
+
         // Push graphite -> brigadier mapping for aliases
         graphite_alias_map.insert(literal_name, child_command_node.clone());
 
@@ -139,8 +193,10 @@ fn process_dispatch_node(
 
     // Process aliases
     for (alias_from, alias_to) in &dispatch.aliases {
-        // Get brigadier index
-        let alias_for = graphite_alias_map.remove(alias_to).unwrap();
+        // Get brigadier node, skipping aliases of literals the player can't use
+        let Some(alias_for) = graphite_alias_map.get(alias_to).cloned() else {
+            continue;
+        };
 
         match alias_for {
             // todo: use a redirect instead of cloning the node
@@ -173,30 +229,50 @@ fn process_dispatch_node(
 
     // Process numeric parser
     if let Some(numeric_parser) = dispatch.numeric_parser.as_ref() {
-        let argument = process_argument_node(numeric_parser, command_nodes);
+        let argument = process_argument_node(numeric_parser, permission_level, command_nodes);
         let (argument_node, command_node) = argument;
 
         // Insert dispatch node (graphite)
         parsers.push(argument_node);
 
         // Push command node (brigadier)
-        let brigadier_index = command_nodes.len() as i32;
-        children.push(brigadier_index);
-        command_nodes.push(command_node);
+        if let Some(command_node) = command_node {
+            let brigadier_index = command_nodes.len() as i32;
+            children.push(brigadier_index);
+            command_nodes.push(command_node);
+        }
+    }
+
+    // Process bool parser
+    if let Some(bool_parser) = dispatch.bool_parser.as_ref() {
+        let argument = process_argument_node(bool_parser, permission_level, command_nodes);
+        let (argument_node, command_node) = argument;
+
+        // Insert dispatch node (graphite)
+        parsers.push(argument_node);
+
+        // Push command node (brigadier)
+        if let Some(command_node) = command_node {
+            let brigadier_index = command_nodes.len() as i32;
+            children.push(brigadier_index);
+            command_nodes.push(command_node);
+        }
     }
 
     // Process string parser
     if let Some(string_parser) = dispatch.string_parser.as_ref() {
-        let argument = process_argument_node(string_parser, command_nodes);
+        let argument = process_argument_node(string_parser, permission_level, command_nodes);
         let (argument_node, command_node) = argument;
 
         // Insert dispatch node (graphite)
         parsers.push(argument_node);
 
         // Push command node (brigadier)
-        let brigadier_index = command_nodes.len() as i32;
-        children.push(brigadier_index);
-        command_nodes.push(command_node);
+        if let Some(command_node) = command_node {
+            let brigadier_index = command_nodes.len() as i32;
+            children.push(brigadier_index);
+            command_nodes.push(command_node);
+        }
     }
 
     let dispatch_node = DispatchNode {
@@ -204,31 +280,41 @@ fn process_dispatch_node(
         aliases: dispatch.aliases.clone(),
         parsers,
         executor: dispatch.executor,
+        permission: dispatch.permission,
     };
 
     (dispatch_node, children)
 }
 
+/// Returns `None` for the command node (brigadier) if the player can't use the argument
+/// or any of its children
 fn process_argument_node<T: MinecraftParser>(
     argument: &MinecraftArgumentNode<T>,
+    permission_level: u8,
     command_nodes: &mut Vec<CommandNode>,
-) -> (ArgumentNode, CommandNode) {
+) -> (ArgumentNode, Option<CommandNode>) {
+    let is_executable = is_executable(&argument.dispatch_node, permission_level);
     let (dispatch_node, brig_children) =
-        process_dispatch_node(&argument.dispatch_node, command_nodes);
+        process_dispatch_node(&argument.dispatch_node, permission_level, command_nodes);
 
     let argument_node = ArgumentNode {
         parse: argument.parse.get_parse_func(),
+        suggestions: argument.suggestions,
         dispatch_node,
     };
 
+    if !is_executable && brig_children.is_empty() {
+        return (argument_node, None);
+    }
+
     let command_node = CommandNode::Argument {
         children: brig_children,
-        is_executable: argument.dispatch_node.executor.is_some(),
+        is_executable,
         redirect: None,
-        suggestion: None,
+        suggestion: argument.suggestions.map(|_| SuggestionType::AskServer),
         name: argument.name,
         parser: argument.parse.get_brigadier_parser(),
     };
 
-    (argument_node, command_node)
+    (argument_node, Some(command_node))
 }
@@ -14,13 +14,17 @@ pub trait MinecraftParser {
 
 // Numeric parsers
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[derive(Debug, PartialEq, Clone, Copy)]
 pub enum NumericParser {
     U8 { min: u8, max: u8 },
     U16 { min: u16, max: u16 },
     U64 { min: u64, max: u64 },
     USize { min: usize, max: usize },
     ISize { min: isize, max: isize },
+    I32 { min: i32, max: i32 },
+    I64 { min: i64, max: i64 },
+    F32 { min: f32, max: f32 },
+    F64 { min: f64, max: f64 },
 }
 
 impl MinecraftParser for NumericParser {
@@ -31,6 +35,10 @@ impl MinecraftParser for NumericParser {
             NumericParser::U64 { min: _, max: _ } => parse_from_string::<u64>,
             NumericParser::USize { min: _, max: _ } => parse_from_string::<usize>,
             NumericParser::ISize { min: _, max: _ } => parse_from_string::<isize>,
+            NumericParser::I32 { min: _, max: _ } => parse_from_string::<i32>,
+            NumericParser::I64 { min: _, max: _ } => parse_from_string::<i64>,
+            NumericParser::F32 { min: _, max: _ } => parse_finite_from_string::<f32>,
+            NumericParser::F64 { min: _, max: _ } => parse_finite_from_string::<f64>,
         }
     }
 
@@ -56,6 +64,22 @@ impl MinecraftParser for NumericParser {
                 min: (*min).try_into().ok(),
                 max: (*max).try_into().ok(),
             },
+            NumericParser::I32 { min, max } => CommandNodeParser::Integer {
+                min: Some(*min),
+                max: Some(*max),
+            },
+            NumericParser::I64 { min, max } => CommandNodeParser::Long {
+                min: Some(*min),
+                max: Some(*max),
+            },
+            NumericParser::F32 { min, max } => CommandNodeParser::Float {
+                min: Some(*min),
+                max: Some(*max),
+            },
+            NumericParser::F64 { min, max } => CommandNodeParser::Double {
+                min: Some(*min),
+                max: Some(*max),
+            },
         }
     }
 
@@ -68,7 +92,7 @@ impl MinecraftParser for NumericParser {
 #[error("failed to parse from string")]
 pub struct ParseFromStringError;
 
-fn parse_from_string<T: FromStr + Ord + NoUninit>(
+fn parse_from_string<T: FromStr + NoUninit>(
     input: SpannedWord,
     state: &mut ParseState,
 ) -> CommandParseResult {
@@ -85,17 +109,80 @@ fn parse_from_string<T: FromStr + Ord + NoUninit>(
     }
 }
 
+// Rust parses `NaN` and `inf` as floats, but brigadier doesn't, so they are rejected
+fn parse_finite_from_string<T: FromStr + NoUninit + Into<f64>>(
+    input: SpannedWord,
+    state: &mut ParseState,
+) -> CommandParseResult {
+    match input.word.parse::<T>() {
+        Ok(parsed) if parsed.into().is_finite() => {
+            state.push_arg(parsed, input.span);
+            CommandParseResult::Ok
+        }
+        _ => CommandParseResult::Err {
+            span: input.span,
+            errmsg: "failed to parse from string".into(),
+            continue_parsing: true,
+        },
+    }
+}
+
+// Bool parser
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct BoolParser;
+
+impl MinecraftParser for BoolParser {
+    fn get_parse_func(&self) -> fn(SpannedWord, &mut ParseState) -> CommandParseResult {
+        parse_bool
+    }
+
+    fn get_brigadier_parser(&self) -> CommandNodeParser {
+        CommandNodeParser::Bool
+    }
+
+    fn is_equal(&self, other: Self) -> bool {
+        *self == other
+    }
+}
+
+// Only `true` and `false` are accepted, same as brigadier (which is case-sensitive)
+fn parse_bool(input: SpannedWord, state: &mut ParseState) -> CommandParseResult {
+    let parsed = match input.word {
+        "true" => true,
+        "false" => false,
+        _ => {
+            return CommandParseResult::Err {
+                span: input.span,
+                errmsg: "expected true or false".into(),
+                continue_parsing: true,
+            }
+        }
+    };
+    state.push_arg(parsed, input.span);
+    CommandParseResult::Ok
+}
+
 // String parsers
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum StringParser {
     Word,
+    /// Takes the rest of the command, eg. the message of `/say`
+    Greedy,
+    /// Takes the three coordinates of a block position as a single string, eg. `~ ~-1 ~`
+    BlockPos,
+    /// Takes a block state, eg. `minecraft:oak_stairs[facing=east]`
+    BlockState,
 }
 
 impl MinecraftParser for StringParser {
     fn get_parse_func(&self) -> fn(SpannedWord, &mut ParseState) -> CommandParseResult {
         match self {
             StringParser::Word => parse_word,
+            StringParser::Greedy => parse_greedy,
+            StringParser::BlockPos => parse_block_pos,
+            StringParser::BlockState => parse_word,
         }
     }
 
@@ -104,6 +191,11 @@ impl MinecraftParser for StringParser {
             StringParser::Word => CommandNodeParser::String {
                 mode: StringParserMode::SingleWord,
             },
+            StringParser::Greedy => CommandNodeParser::String {
+                mode: StringParserMode::GreedyPhrase,
+            },
+            StringParser::BlockPos => CommandNodeParser::BlockPos,
+            StringParser::BlockState => CommandNodeParser::BlockState,
         }
     }
 
@@ -116,3 +208,23 @@ fn parse_word(input: SpannedWord, state: &mut ParseState) -> CommandParseResult
     state.push_str(input.word, input.span);
     CommandParseResult::Ok
 }
+
+fn parse_greedy(input: SpannedWord, state: &mut ParseState) -> CommandParseResult {
+    let remaining = state.pop_remaining(input.span);
+    state.push_str(remaining.word, remaining.span);
+    CommandParseResult::Ok
+}
+
+fn parse_block_pos(input: SpannedWord, state: &mut ParseState) -> CommandParseResult {
+    match state.pop_joined(input.span, 2) {
+        Some(coordinates) => {
+            state.push_str(coordinates.word, coordinates.span);
+            CommandParseResult::Ok
+        }
+        None => CommandParseResult::Err {
+            span: input.span,
+            errmsg: "expected three coordinates".into(),
+            continue_parsing: true,
+        },
+    }
+}
@@ -4,22 +4,35 @@ use std::{collections::HashMap, result};
 
 use thiserror::Error;
 
-use crate::types::DispatchFunction;
+use crate::types::{DispatchFunction, SuggestionFunction};
 
+use super::parsers::BoolParser;
 use super::parsers::MinecraftParser;
 use super::parsers::NumericParser;
 use super::parsers::StringParser;
 
 // Merge error enum
 
+/// Error when merging command trees, `path` is the command path where the conflict happened, eg. `/hello <number>`
 #[derive(Debug, Error, PartialEq, Eq)]
 pub enum MergeError {
-    #[error("DispatchNode already had an executor, merge attempted to override")]
-    DuplicateExecutor,
-    #[error("New parser conflicts with existing parser")]
-    AmbiguousNewParser,
-    #[error("New parser contained an alias with a duplicate key")]
-    DuplicateAlias,
+    #[error("`{path}` already had an executor, merge attempted to override")]
+    DuplicateExecutor { path: String },
+    #[error("`{path}` has a {new} parser that conflicts with the existing {existing} parser")]
+    AmbiguousNewParser {
+        path: String,
+        existing: String,
+        new: String,
+    },
+    #[error("alias `{alias}` in `{path}` already points to `{existing}`, merge attempted to point it to `{new}`")]
+    DuplicateAlias {
+        path: String,
+        alias: &'static str,
+        existing: &'static str,
+        new: &'static str,
+    },
+    #[error("alias `{alias}` in `{path}` has the same name as a literal, which it would shadow")]
+    AliasShadowsLiteral { path: String, alias: &'static str },
 }
 
 #[derive(Debug)]
@@ -62,7 +75,7 @@ impl MinecraftRootDispatchNode {
             for new_literal in other.literals {
                 if let Some(existing_literal) = self.literals.get_mut(new_literal.0) {
                     // Merge with existing literal
-                    existing_literal.merge(new_literal.1)?;
+                    existing_literal.merge(new_literal.1, &format!("/{}", new_literal.0))?;
                 } else {
                     // No conflict with existing, insert into `literals`
                     self.literals.insert(new_literal.0, new_literal.1);
@@ -78,7 +91,12 @@ impl MinecraftRootDispatchNode {
                 if let Some(existing_alias) = self.aliases.get(new_alias.0) {
                     // Check if value is the same
                     if new_alias.1 != *existing_alias {
-                        return Err(MergeError::DuplicateAlias);
+                        return Err(MergeError::DuplicateAlias {
+                            path: "/".into(),
+                            alias: new_alias.0,
+                            existing: existing_alias,
+                            new: new_alias.1,
+                        });
                     }
                 } else {
                     // Insert the new alias
@@ -87,6 +105,14 @@ impl MinecraftRootDispatchNode {
             }
         }
 
+        // Aliases are checked before literals when dispatching, so they can't share a name
+        if let Some(alias) = self.aliases.keys().find(|alias| self.literals.contains_key(*alias)) {
+            return Err(MergeError::AliasShadowsLiteral {
+                path: "/".into(),
+                alias,
+            });
+        }
+
         Ok(())
     }
 }
@@ -96,8 +122,12 @@ pub struct MinecraftDispatchNode {
     pub literals: BTreeMap<&'static str, MinecraftDispatchNode>,
     pub aliases: BTreeMap<&'static str, &'static str>,
     pub numeric_parser: Option<MinecraftArgumentNode<NumericParser>>,
+    pub bool_parser: Option<MinecraftArgumentNode<BoolParser>>,
     pub string_parser: Option<MinecraftArgumentNode<StringParser>>,
     pub executor: Option<DispatchFunction>,
+    /// Permission level required to use the executor, players below it aren't sent the
+    /// executor by `create_brigadier_packet`
+    pub permission: u8,
 }
 
 impl Debug for MinecraftDispatchNode {
@@ -106,43 +136,48 @@ impl Debug for MinecraftDispatchNode {
             .field("literals", &self.literals)
             .field("aliases", &self.aliases)
             .field("numeric_parser", &self.numeric_parser)
+            .field("bool_parser", &self.bool_parser)
             .field("string_parser", &self.string_parser)
             .field("has_executor", &self.executor.is_some())
+            .field("permission", &self.permission)
             .finish()
     }
 }
 
 impl MinecraftDispatchNode {
-    pub(crate) fn merge(&mut self, node: MinecraftDispatchNode) -> result::Result<(), MergeError> {
+    /// Merges `node` into this node, `path` is the command path of this node and is used for errors
+    pub(crate) fn merge(&mut self, node: MinecraftDispatchNode, path: &str) -> result::Result<(), MergeError> {
         // Try to merge executor
         if self.executor.is_none() {
             self.executor = node.executor;
+            self.permission = node.permission;
         } else if node.executor.is_some() {
             // Both self.executor and node.executor exist
-            return Err(MergeError::DuplicateExecutor);
+            return Err(MergeError::DuplicateExecutor { path: path.into() });
         }
 
         // Merge the numeric parser
         if let Some(new_numeric_parser) = node.numeric_parser {
             if let Some(numeric_parser) = self.numeric_parser.as_mut() {
-                if numeric_parser.parse.is_equal(new_numeric_parser.parse) {
-                    numeric_parser
-                        .dispatch_node
-                        .merge(*new_numeric_parser.dispatch_node)?;
-                }
+                numeric_parser.merge(new_numeric_parser, path)?;
             } else {
                 self.numeric_parser = Some(new_numeric_parser);
             }
         }
 
+        // Merge the bool parser
+        if let Some(new_bool_parser) = node.bool_parser {
+            if let Some(bool_parser) = self.bool_parser.as_mut() {
+                bool_parser.merge(new_bool_parser, path)?;
+            } else {
+                self.bool_parser = Some(new_bool_parser);
+            }
+        }
+
         // Merge the string parser
         if let Some(new_string_parser) = node.string_parser {
             if let Some(string_parser) = self.string_parser.as_mut() {
-                if string_parser.parse.is_equal(new_string_parser.parse) {
-                    string_parser
-                        .dispatch_node
-                        .merge(*new_string_parser.dispatch_node)?;
-                }
+                string_parser.merge(new_string_parser, path)?;
             } else {
                 self.string_parser = Some(new_string_parser);
             }
@@ -155,7 +190,7 @@ impl MinecraftDispatchNode {
             for new_literal in node.literals {
                 if let Some(existing_literal) = self.literals.get_mut(new_literal.0) {
                     // Merge with existing literal
-                    existing_literal.merge(new_literal.1)?;
+                    existing_literal.merge(new_literal.1, &format!("{} {}", path, new_literal.0))?;
                 } else {
                     // No conflict with existing, insert into `literals`
                     self.literals.insert(new_literal.0, new_literal.1);
@@ -171,7 +206,12 @@ impl MinecraftDispatchNode {
                 if let Some(existing_alias) = self.aliases.get(new_alias.0) {
                     // Check if value is the same
                     if new_alias.1 != *existing_alias {
-                        return Err(MergeError::DuplicateAlias);
+                        return Err(MergeError::DuplicateAlias {
+                            path: path.into(),
+                            alias: new_alias.0,
+                            existing: existing_alias,
+                            new: new_alias.1,
+                        });
                     }
                 } else {
                     // Insert the new alias
@@ -180,6 +220,14 @@ impl MinecraftDispatchNode {
             }
         }
 
+        // Aliases are checked before literals when dispatching, so they can't share a name
+        if let Some(alias) = self.aliases.keys().find(|alias| self.literals.contains_key(*alias)) {
+            return Err(MergeError::AliasShadowsLiteral {
+                path: path.into(),
+                alias,
+            });
+        }
+
         Ok(())
     }
 }
@@ -188,9 +236,33 @@ impl MinecraftDispatchNode {
 pub struct MinecraftArgumentNode<P: MinecraftParser> {
     pub name: &'static str,
     pub parse: P,
+    /// Clients ask the server for suggestions when this is set, see `RootDispatchNode::suggest`
+    pub suggestions: Option<SuggestionFunction>,
     pub dispatch_node: Box<MinecraftDispatchNode>,
 }
 
+impl<P: MinecraftParser + Debug + Copy> MinecraftArgumentNode<P> {
+    fn merge(&mut self, node: MinecraftArgumentNode<P>, path: &str) -> result::Result<(), MergeError> {
+        let path = format!("{} <{}>", path, self.name);
+
+        // Parsers of the same kind can only be merged if they are identical, otherwise
+        // it would be ambiguous which one should parse the argument
+        if !self.parse.is_equal(node.parse) {
+            return Err(MergeError::AmbiguousNewParser {
+                path,
+                existing: format!("{:?}", self.parse),
+                new: format!("{:?}", node.parse),
+            });
+        }
+
+        if self.suggestions.is_none() {
+            self.suggestions = node.suggestions;
+        }
+
+        self.dispatch_node.merge(*node.dispatch_node, &path)
+    }
+}
+
 // Tests
 
 #[cfg(test)]
@@ -213,8 +285,10 @@ mod tests {
             literals: BTreeMap::new(),
             aliases: BTreeMap::new(),
             numeric_parser: None,
+            bool_parser: None,
             string_parser: None,
             executor: None,
+            permission: 0,
         }
     }
 
@@ -227,14 +301,17 @@ mod tests {
                 min: u8::MIN,
                 max: u8::MAX,
             },
+            suggestions: None,
             dispatch_node: Box::from(dispatch),
         };
         MinecraftDispatchNode {
             literals: BTreeMap::new(),
             aliases: BTreeMap::new(),
             numeric_parser: Some(numeric_parser),
+            bool_parser: None,
             string_parser: None,
             executor: None,
+            permission: 0,
         }
     }
 
@@ -242,14 +319,17 @@ mod tests {
         let string_parser = MinecraftArgumentNode {
             name: "argument",
             parse: StringParser::Word,
+            suggestions: None,
             dispatch_node: Box::from(dispatch),
         };
         MinecraftDispatchNode {
             literals: BTreeMap::new(),
             aliases: BTreeMap::new(),
             numeric_parser: None,
+            bool_parser: None,
             string_parser: Some(string_parser),
             executor: None,
+            permission: 0,
         }
     }
 
@@ -263,8 +343,10 @@ mod tests {
             literals: BTreeMap::new(),
             aliases: BTreeMap::new(),
             numeric_parser: None,
+            bool_parser: None,
             string_parser: None,
             executor: Some(hello),
+            permission: 0,
         }
     }
 
@@ -352,7 +434,9 @@ mod tests {
 
         assert_eq!(
             root.merge_named(dispatch, "hello", vec![]),
-            Err(MergeError::DuplicateExecutor)
+            Err(MergeError::DuplicateExecutor {
+                path: "/hello <argument>".into()
+            })
         );
     }
 
@@ -378,7 +462,9 @@ mod tests {
         let dispatch = dispatch_node_with_executor();
         assert_eq!(
             root.merge_named(dispatch, "hello", vec![]),
-            Err(MergeError::DuplicateExecutor)
+            Err(MergeError::DuplicateExecutor {
+                path: "/hello".into()
+            })
         );
     }
 
@@ -395,7 +481,134 @@ mod tests {
         let dispatch = dispatch_node_with_executor();
         assert_eq!(
             root.merge_named(dispatch, "world", vec!["world1", "special"]),
-            Err(MergeError::DuplicateAlias)
+            Err(MergeError::DuplicateAlias {
+                path: "/".into(),
+                alias: "special",
+                existing: "hello",
+                new: "world"
+            })
+        );
+    }
+
+    #[test]
+    fn duplicate_argument_executor_merge() {
+        use crate::types::{CommandDispatchResult, Span};
+        fn other_hello(_: &[u8], _: &[Span]) -> CommandDispatchResult {
+            CommandDispatchResult::Success(Err("other".into()))
+        }
+
+        let mut root = empty_root();
+
+        // `/hello <u8>`
+        let dispatch = dispatch_node_with_numeric_parser(dispatch_node_with_executor());
+        assert_eq!(root.merge_named(dispatch, "hello", vec![]), Ok(()));
+
+        // `/hello <u8>` from another command, with a different executor
+        let dispatch = dispatch_node_with_numeric_parser(MinecraftDispatchNode {
+            executor: Some(other_hello),
+            ..empty_dispatch_node()
+        });
+        assert_eq!(
+            root.merge_named(dispatch, "hello", vec![]),
+            Err(MergeError::DuplicateExecutor {
+                path: "/hello <argument>".into()
+            })
+        );
+    }
+
+    #[test]
+    fn alias_shadows_literal_merge() {
+        let mut root = empty_root();
+
+        let dispatch = dispatch_node_with_executor();
+        assert_eq!(root.merge_named(dispatch, "hello", vec!["hi"]), Ok(()));
+
+        // A new literal with the same name as an existing alias
+        let dispatch = dispatch_node_with_executor();
+        assert_eq!(
+            root.merge_named(dispatch, "hi", vec![]),
+            Err(MergeError::AliasShadowsLiteral {
+                path: "/".into(),
+                alias: "hi"
+            })
+        );
+
+        // A new alias with the same name as an existing literal
+        let mut root = empty_root();
+        let dispatch = dispatch_node_with_executor();
+        assert_eq!(root.merge_named(dispatch, "hello", vec![]), Ok(()));
+        let dispatch = dispatch_node_with_executor();
+        assert_eq!(
+            root.merge_named(dispatch, "bye", vec!["hello"]),
+            Err(MergeError::AliasShadowsLiteral {
+                path: "/".into(),
+                alias: "hello"
+            })
+        );
+
+        // Also applies to literals after the first one
+        let mut root = empty_root();
+        let mut dispatch = empty_dispatch_node();
+        dispatch.literals.insert("world", dispatch_node_with_executor());
+        assert_eq!(root.merge_named(dispatch, "greet", vec![]), Ok(()));
+        let mut dispatch = empty_dispatch_node();
+        dispatch.aliases.insert("world", "earth");
+        dispatch.literals.insert("earth", empty_dispatch_node());
+        assert_eq!(
+            root.merge_named(dispatch, "greet", vec![]),
+            Err(MergeError::AliasShadowsLiteral {
+                path: "/greet".into(),
+                alias: "world"
+            })
+        );
+    }
+
+    #[test]
+    fn conflict_after_shared_prefix() {
+        let mut root = empty_root();
+
+        // `/hello world <argument>`
+        let mut dispatch = empty_dispatch_node();
+        dispatch.literals.insert(
+            "world",
+            dispatch_node_with_numeric_parser(dispatch_node_with_executor()),
+        );
+        assert_eq!(root.merge_named(dispatch.clone(), "hello", vec![]), Ok(()));
+
+        // `/hello there` shares the `/hello` prefix without conflicting
+        let mut compatible = empty_dispatch_node();
+        compatible.literals.insert("there", dispatch_node_with_executor());
+        assert_eq!(root.merge_named(compatible, "hello", vec![]), Ok(()));
+        assert_eq!(root.literals["hello"].literals.len(), 2);
+
+        // `/hello world <argument>` again, conflicts at the executor
+        let error = root.merge_named(dispatch, "hello", vec![]).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "`/hello world <argument>` already had an executor, merge attempted to override"
+        );
+
+        // `/hello world <argument>` with a different numeric parser
+        let mut dispatch = empty_dispatch_node();
+        dispatch.literals.insert(
+            "world",
+            MinecraftDispatchNode {
+                numeric_parser: Some(MinecraftArgumentNode {
+                    name: "argument",
+                    parse: NumericParser::U16 { min: 0, max: 10 },
+                    suggestions: None,
+                    dispatch_node: Box::from(dispatch_node_with_executor()),
+                }),
+                ..empty_dispatch_node()
+            },
+        );
+        assert_eq!(
+            root.merge_named(dispatch, "hello", vec![]),
+            Err(MergeError::AmbiguousNewParser {
+                path: "/hello world <argument>".into(),
+                existing: "U8 { min: 0, max: 255 }".into(),
+                new: "U16 { min: 0, max: 10 }".into()
+            })
         );
     }
 }
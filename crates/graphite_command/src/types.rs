@@ -1,4 +1,4 @@
-use std::{alloc::Layout, result};
+use std::{alloc::Layout, any::TypeId, result};
 
 use bytemuck::NoUninit;
 use bytes::BufMut;
@@ -16,6 +16,7 @@ pub struct SpannedWord<'a> {
 }
 
 pub struct ParseState<'a> {
+    input: &'a str,
     finalized: bool,
     argument_layout: Layout,
     arguments: Vec<u8>,
@@ -23,6 +24,7 @@ pub struct ParseState<'a> {
     pub(crate) words: Vec<SpannedWord<'a>>,
     pub(crate) cursor: usize,
     pub full_span: Span,
+    permission_level: u8,
 }
 
 impl<'a> ParseState<'a> {
@@ -31,7 +33,8 @@ impl<'a> ParseState<'a> {
         let mut start = 0;
         let mut end = 0;
         let mut in_word = false;
-        for (index, char) in input.chars().enumerate() {
+        // Spans are byte indices, so that they can be used to slice the input
+        for (index, char) in input.char_indices() {
             if char.is_whitespace() {
                 if in_word {
                     words.push(SpannedWord {
@@ -45,7 +48,7 @@ impl<'a> ParseState<'a> {
                     in_word = true;
                     start = index;
                 }
-                end = index;
+                end = index + char.len_utf8() - 1;
             }
         }
         if in_word {
@@ -56,6 +59,7 @@ impl<'a> ParseState<'a> {
         }
 
         Self {
+            input,
             finalized: false,
             argument_layout: unsafe { Layout::from_size_align_unchecked(0, 1) },
             arguments: Vec::new(),
@@ -63,9 +67,22 @@ impl<'a> ParseState<'a> {
             words,
             cursor: 0,
             full_span: Span { start: 0, end },
+            permission_level: 0,
         }
     }
 
+    /// Sets the permission level of whoever is executing the command, 0 by default
+    ///
+    /// Dispatching fails with `CommandDispatchResult::MissingPermission` if the executor
+    /// requires a higher level
+    pub fn set_permission_level(&mut self, permission_level: u8) {
+        self.permission_level = permission_level;
+    }
+
+    pub(crate) fn permission_level(&self) -> u8 {
+        self.permission_level
+    }
+
     pub(crate) fn get_arguments(&mut self) -> (&[u8], &[Span]) {
         debug_assert!(!self.finalized);
         self.finalized = true;
@@ -89,8 +106,24 @@ impl<'a> ParseState<'a> {
         (self.arguments.as_slice(), self.argument_spans.as_slice())
     }
 
-    pub(crate) fn cursor(&self) -> usize {
-        self.cursor
+    /// Saves the cursor and the arguments that have been pushed, see `restore`
+    pub(crate) fn checkpoint(&self) -> ParseCheckpoint {
+        ParseCheckpoint {
+            cursor: self.cursor,
+            argument_layout: self.argument_layout,
+            arguments_len: self.arguments.len(),
+            argument_spans_len: self.argument_spans.len(),
+        }
+    }
+
+    /// Undoes everything that was parsed after the checkpoint, so that a different
+    /// argument can be tried instead
+    pub(crate) fn restore(&mut self, checkpoint: ParseCheckpoint) {
+        debug_assert!(!self.finalized);
+        self.cursor = checkpoint.cursor;
+        self.argument_layout = checkpoint.argument_layout;
+        self.arguments.truncate(checkpoint.arguments_len);
+        self.argument_spans.truncate(checkpoint.argument_spans_len);
     }
 
     #[allow(dead_code)] // todo: use this method
@@ -111,6 +144,49 @@ impl<'a> ParseState<'a> {
         }
     }
 
+    /// Consumes `count` more words, returning them together with `word` (the word that
+    /// was just popped), including any whitespace in between
+    ///
+    /// Returns `None` without consuming anything if there aren't enough words remaining
+    pub(crate) fn pop_joined(&mut self, word: Span, count: usize) -> Option<SpannedWord<'a>> {
+        if count > self.words.len().saturating_sub(self.cursor) {
+            return None;
+        }
+        self.cursor += count;
+
+        let end = match count {
+            0 => word.end,
+            _ => self.words[self.cursor - 1].span.end,
+        };
+        let span = Span {
+            start: word.start,
+            end,
+        };
+        Some(SpannedWord {
+            span,
+            word: &self.input[span.start..=span.end],
+        })
+    }
+
+    /// Consumes the remaining input, returning it from the start of `word` (the word that
+    /// was just popped) to the end of the input, including any whitespace in between
+    pub(crate) fn pop_remaining(&mut self, word: Span) -> SpannedWord<'a> {
+        let end = match self.words.last() {
+            Some(last) if !self.is_finished() => last.span.end,
+            _ => word.end,
+        };
+        self.cursor = self.words.len();
+
+        let span = Span {
+            start: word.start,
+            end,
+        };
+        SpannedWord {
+            span,
+            word: &self.input[span.start..=span.end],
+        }
+    }
+
     pub(crate) fn is_finished(&self) -> bool {
         self.cursor >= self.words.len()
     }
@@ -122,8 +198,9 @@ impl<'a> ParseState<'a> {
     }
 
     pub fn push_str(&mut self, arg: &str, span: Span) {
-        let raw_slice: u128 = unsafe { std::mem::transmute(arg) };
-        self.push_arg(raw_slice, span);
+        // The layout of `&str` is used instead of `u128`, which can have a larger alignment
+        let raw_slice: [u8; std::mem::size_of::<&str>()] = unsafe { std::mem::transmute(arg) };
+        self.push_bytes(Layout::new::<&str>(), &raw_slice, span);
     }
 
     pub fn push_ref<T>(&mut self, arg: &T, span: Span) {
@@ -131,25 +208,63 @@ impl<'a> ParseState<'a> {
         self.push_arg(raw_reference, span);
     }
 
+    /// Pushes the context that is passed as the first argument of command functions,
+    /// eg. the `Player` that executed the command
+    ///
+    /// Command functions check the `TypeId` of the context before using it, so any type
+    /// can be used as a context, including mock contexts for testing commands
+    pub fn push_context<C: 'static>(&mut self, context: &mut C) {
+        let raw_reference: usize = context as *mut C as usize;
+        self.push_arg(raw_reference, self.full_span);
+
+        // TypeId doesn't implement NoUninit, so its bytes are copied manually
+        let type_id = TypeId::of::<C>();
+        let bytes: &[u8] = unsafe {
+            std::slice::from_raw_parts(&type_id as *const TypeId as *const u8, std::mem::size_of::<TypeId>())
+        };
+        self.push_bytes(Layout::new::<TypeId>(), bytes, self.full_span);
+    }
+
     pub fn push_arg<T: NoUninit>(&mut self, arg: T, span: Span) {
-        // Get layout for argument
-        let arg_layout = Layout::new::<T>();
+        self.push_bytes(Layout::new::<T>(), bytemuck::bytes_of(&arg), span);
+    }
 
+    fn push_bytes(&mut self, arg_layout: Layout, bytes: &[u8], span: Span) {
         // Update layout
         let (new_layout, offset) = self.argument_layout.extend(arg_layout).unwrap();
         self.argument_layout = new_layout;
         self.arguments.resize(offset, 0);
 
         // Put bytes and span
-        let bytes: &[u8] = bytemuck::bytes_of(&arg);
         debug_assert_eq!(arg_layout.size(), bytes.len());
         self.arguments.put_slice(bytes);
         self.argument_spans.push(span);
     }
 }
 
+#[derive(Clone, Copy)]
+pub(crate) struct ParseCheckpoint {
+    cursor: usize,
+    argument_layout: Layout,
+    arguments_len: usize,
+    argument_spans_len: usize,
+}
+
 pub type DispatchFunction = fn(&[u8], &[Span]) -> CommandDispatchResult;
 
+/// Suggests values for an argument, given what has been typed of the argument so far
+pub type SuggestionFunction = fn(&str) -> Vec<String>;
+
+/// Suggestions for the argument that is being typed
+///
+/// `start` and `length` are in UTF-16 code units, as expected by the client, not in bytes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandSuggestions {
+    pub start: usize,
+    pub length: usize,
+    pub suggestions: Vec<String>,
+}
+
 pub type CommandResult = result::Result<(), String>;
 
 #[derive(Debug)]
@@ -173,5 +288,6 @@ pub enum CommandDispatchResult {
     UnknownCommand,
     IncompleteCommand,
     TooManyArguments,
+    MissingPermission,
     UnknownPlayerService
 }
@@ -0,0 +1,5 @@
+#[test]
+pub fn invalid_argument() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/invalid_argument.rs");
+}
@@ -0,0 +1,328 @@
+use graphite_command::brigadier;
+use graphite_command::types::{CommandDispatchResult, CommandResult, ParseState};
+use graphite_mc_protocol::types::{
+    CommandNode, CommandNodeParser, StringParserMode, SuggestionType,
+};
+
+struct MockContext {
+    messages: Vec<String>,
+}
+
+struct OtherContext;
+
+#[test]
+pub fn dispatch_with_mock_context() {
+    #[brigadier("greet", {})]
+    fn greet(context: &mut MockContext, times: u8) -> CommandResult {
+        for _ in 0..times {
+            context.messages.push("Hello!".into());
+        }
+        Ok(())
+    }
+
+    let (dispatcher, _) = graphite_command::minecraft::create_dispatcher_and_brigadier_packet(greet);
+
+    // Dispatch with the context type that the command accepts
+    let mut context = MockContext { messages: Vec::new() };
+    let result = dispatcher.dispatch_with_context("greet 3", &mut context);
+    assert!(matches!(result, CommandDispatchResult::Success(Ok(()))));
+    assert_eq!(context.messages, vec!["Hello!"; 3]);
+
+    // Dispatch with a context type that the command doesn't accept
+    let result = dispatcher.dispatch_with_context("greet 3", &mut OtherContext);
+    assert!(matches!(result, CommandDispatchResult::UnknownPlayerService));
+}
+
+#[test]
+pub fn dispatch_with_string_arguments() {
+    #[brigadier("say", {}, {}, {})]
+    fn say(context: &mut MockContext, prefix: &str, times: u8, message: &str) -> CommandResult {
+        for _ in 0..times {
+            context.messages.push(format!("{} {}", prefix, message));
+        }
+        Ok(())
+    }
+
+    let (dispatcher, _) = graphite_command::minecraft::create_dispatcher_and_brigadier_packet(say);
+
+    let mut context = MockContext { messages: Vec::new() };
+    let result = dispatcher.dispatch_with_context("say [Server] 2 hello", &mut context);
+    assert!(matches!(result, CommandDispatchResult::Success(Ok(()))));
+    assert_eq!(context.messages, vec!["[Server] hello"; 2]);
+}
+
+#[test]
+pub fn dispatch_with_greedy_string() {
+    #[brigadier("say", {greedy})]
+    fn say(context: &mut MockContext, message: &str) -> CommandResult {
+        context.messages.push(message.into());
+        Ok(())
+    }
+
+    let (dispatcher, packet) = graphite_command::minecraft::create_dispatcher_and_brigadier_packet(say);
+
+    // The message is everything after the literal, including whitespace between words
+    let mut context = MockContext { messages: Vec::new() };
+    let result = dispatcher.dispatch_with_context("say hello  world", &mut context);
+    assert!(matches!(result, CommandDispatchResult::Success(Ok(()))));
+    let result = dispatcher.dispatch_with_context("say héllo", &mut context);
+    assert!(matches!(result, CommandDispatchResult::Success(Ok(()))));
+    assert_eq!(context.messages, vec!["hello  world", "héllo"]);
+
+    // The message can't be empty
+    let result = dispatcher.dispatch_with_context("say", &mut context);
+    assert!(matches!(result, CommandDispatchResult::IncompleteCommand));
+
+    // The client is told to send the rest of the command
+    assert!(packet.nodes.iter().any(|node| matches!(
+        node,
+        CommandNode::Argument {
+            parser: CommandNodeParser::String {
+                mode: StringParserMode::GreedyPhrase
+            },
+            ..
+        }
+    )));
+}
+
+#[test]
+pub fn dispatch_with_owned_strings() {
+    #[brigadier("tell", {}, {greedy})]
+    fn tell(context: &mut MockContext, target: String, message: String) -> CommandResult {
+        context.messages.push(format!("{}: {}", target, message));
+        Ok(())
+    }
+
+    let (dispatcher, _) = graphite_command::minecraft::create_dispatcher_and_brigadier_packet(tell);
+
+    // Owned strings are parsed the same way as `&str`
+    let mut context = MockContext { messages: Vec::new() };
+    let result = dispatcher.dispatch_with_context("tell Steve hello  world", &mut context);
+    assert!(matches!(result, CommandDispatchResult::Success(Ok(()))));
+    assert_eq!(context.messages, vec!["Steve: hello  world"]);
+}
+
+#[test]
+pub fn dispatch_with_block_arguments() {
+    #[brigadier("setblock", {block_pos}, {block_state})]
+    fn setblock(context: &mut MockContext, pos: &str, block: &str) -> CommandResult {
+        context.messages.push(format!("{} = {}", pos, block));
+        Ok(())
+    }
+
+    let (dispatcher, packet) =
+        graphite_command::minecraft::create_dispatcher_and_brigadier_packet(setblock);
+
+    // The position is all three coordinates, including whitespace between them
+    let mut context = MockContext { messages: Vec::new() };
+    let result =
+        dispatcher.dispatch_with_context("setblock ~ ~-1  ^2 minecraft:stone", &mut context);
+    assert!(matches!(result, CommandDispatchResult::Success(Ok(()))));
+    let result =
+        dispatcher.dispatch_with_context("setblock 1 2 3 oak_stairs[facing=east]", &mut context);
+    assert!(matches!(result, CommandDispatchResult::Success(Ok(()))));
+    assert_eq!(
+        context.messages,
+        vec!["~ ~-1  ^2 = minecraft:stone", "1 2 3 = oak_stairs[facing=east]"]
+    );
+
+    // The position always takes three words, even if one of them was meant as the block
+    let result = dispatcher.dispatch_with_context("setblock 1 2 stone", &mut context);
+    assert!(matches!(result, CommandDispatchResult::IncompleteCommand));
+    let result = dispatcher.dispatch_with_context("setblock 1 2", &mut context);
+    assert!(matches!(result, CommandDispatchResult::ParseError { .. }));
+
+    // The client is told to use the block parsers, which allow `~` and `:`
+    assert!(packet.nodes.iter().any(|node| matches!(
+        node,
+        CommandNode::Argument {
+            parser: CommandNodeParser::BlockPos,
+            ..
+        }
+    )));
+    assert!(packet.nodes.iter().any(|node| matches!(
+        node,
+        CommandNode::Argument {
+            parser: CommandNodeParser::BlockState,
+            ..
+        }
+    )));
+}
+
+#[test]
+pub fn dispatch_with_signed_and_float_arguments() {
+    #[brigadier("tp", {-30000000..30000000}, {-64..320}, {-30000000..30000000}, {-180.0..180.0})]
+    fn tp(context: &mut MockContext, x: i32, y: f64, z: i64, yaw: f32) -> CommandResult {
+        context.messages.push(format!("{} {} {} {}", x, y, z, yaw));
+        Ok(())
+    }
+
+    let (dispatcher, _) = graphite_command::minecraft::create_dispatcher_and_brigadier_packet(tp);
+
+    let mut context = MockContext { messages: Vec::new() };
+    let result = dispatcher.dispatch_with_context("tp -100 64.5 -20000000 -90", &mut context);
+    assert!(matches!(result, CommandDispatchResult::Success(Ok(()))));
+    assert_eq!(context.messages, vec!["-100 64.5 -20000000 -90"]);
+
+    // Outside of the range
+    let result = dispatcher.dispatch_with_context("tp -30000001 0 0 0", &mut context);
+    assert!(matches!(result, CommandDispatchResult::ParseError { .. }));
+    let result = dispatcher.dispatch_with_context("tp 0 -64.5 0 0", &mut context);
+    assert!(matches!(result, CommandDispatchResult::ParseError { .. }));
+
+    // Not a number
+    let result = dispatcher.dispatch_with_context("tp 0 NaN 0 0", &mut context);
+    assert!(matches!(result, CommandDispatchResult::ParseError { .. }));
+    let result = dispatcher.dispatch_with_context("tp 0.5 0 0 0", &mut context);
+    assert!(matches!(result, CommandDispatchResult::ParseError { .. }));
+
+    assert_eq!(context.messages.len(), 1);
+}
+
+#[test]
+pub fn dispatch_with_bool_argument() {
+    #[brigadier("pvp", {})]
+    fn pvp(context: &mut MockContext, enabled: bool) -> CommandResult {
+        context.messages.push(format!("pvp: {}", enabled));
+        Ok(())
+    }
+
+    let (dispatcher, packet) = graphite_command::minecraft::create_dispatcher_and_brigadier_packet(pvp);
+
+    let mut context = MockContext { messages: Vec::new() };
+    let result = dispatcher.dispatch_with_context("pvp true", &mut context);
+    assert!(matches!(result, CommandDispatchResult::Success(Ok(()))));
+    let result = dispatcher.dispatch_with_context("pvp false", &mut context);
+    assert!(matches!(result, CommandDispatchResult::Success(Ok(()))));
+    assert_eq!(context.messages, vec!["pvp: true", "pvp: false"]);
+
+    // Only lowercase `true` and `false` are accepted
+    let result = dispatcher.dispatch_with_context("pvp TRUE", &mut context);
+    assert!(matches!(result, CommandDispatchResult::ParseError { .. }));
+    let result = dispatcher.dispatch_with_context("pvp 1", &mut context);
+    assert!(matches!(result, CommandDispatchResult::ParseError { .. }));
+
+    assert!(packet.nodes.iter().any(|node| matches!(
+        node,
+        CommandNode::Argument {
+            parser: CommandNodeParser::Bool,
+            ..
+        }
+    )));
+}
+
+#[test]
+pub fn suggest_with_completion_function() {
+    fn items(_: &str) -> Vec<String> {
+        vec!["diamond".into(), "diamond_sword".into(), "dirt".into()]
+    }
+
+    #[brigadier("give", {complete = items}, {})]
+    fn give(context: &mut MockContext, item: &str, count: u8) -> CommandResult {
+        context.messages.push(format!("{} {}", count, item));
+        Ok(())
+    }
+
+    let (dispatcher, packet) = graphite_command::minecraft::create_dispatcher_and_brigadier_packet(give);
+
+    // Only suggestions matching what has been typed so far are returned
+    let suggestions = dispatcher.suggest("give dia").unwrap();
+    assert_eq!(suggestions.start, 5);
+    assert_eq!(suggestions.length, 3);
+    assert_eq!(suggestions.suggestions, vec!["diamond", "diamond_sword"]);
+
+    let suggestions = dispatcher.suggest("give ").unwrap();
+    assert_eq!(suggestions.suggestions.len(), 3);
+
+    // Offsets are in UTF-16 code units instead of bytes
+    let suggestions = dispatcher.suggest("give\u{3000}dï").unwrap();
+    assert_eq!((suggestions.start, suggestions.length), (5, 2));
+    let suggestions = dispatcher.suggest("give \u{1F48E}").unwrap();
+    assert_eq!((suggestions.start, suggestions.length), (5, 2));
+
+    // The count argument doesn't have a completion function
+    assert!(dispatcher.suggest("give dirt ").is_none());
+    assert!(dispatcher.suggest("take ").is_none());
+
+    // The completion function doesn't affect dispatching
+    let mut context = MockContext { messages: Vec::new() };
+    let result = dispatcher.dispatch_with_context("give dirt 64", &mut context);
+    assert!(matches!(result, CommandDispatchResult::Success(Ok(()))));
+    assert_eq!(context.messages, vec!["64 dirt"]);
+
+    // The client is told to ask the server for suggestions
+    assert!(packet.nodes.iter().any(|node| matches!(
+        node,
+        CommandNode::Argument {
+            name: "item",
+            suggestion: Some(SuggestionType::AskServer),
+            ..
+        }
+    )));
+}
+
+#[test]
+pub fn brigadier_packet_with_permission() {
+    #[brigadier(["stop", "halt"], permission = 4)]
+    fn stop(context: &mut MockContext) -> CommandResult {
+        context.messages.push("Stopping".into());
+        Ok(())
+    }
+
+    #[brigadier("help")]
+    fn help(context: &mut MockContext) -> CommandResult {
+        context.messages.push("Help".into());
+        Ok(())
+    }
+
+    fn literal_names(packet: &graphite_mc_protocol::play::server::Commands) -> Vec<&str> {
+        let mut names: Vec<&str> = packet
+            .nodes
+            .iter()
+            .filter_map(|node| match node {
+                CommandNode::Literal { name, .. } => Some(*name),
+                _ => None,
+            })
+            .collect();
+        names.sort();
+        names
+    }
+
+    stop.merge(help).unwrap();
+    assert_eq!(stop.literals["stop"].permission, 4);
+    assert_eq!(stop.literals["help"].permission, 0);
+
+    let op_packet = graphite_command::minecraft::create_brigadier_packet(&stop, 4);
+    let moderator_packet = graphite_command::minecraft::create_brigadier_packet(&stop, 3);
+    let (dispatcher, packet) = graphite_command::minecraft::create_dispatcher_and_brigadier_packet(stop);
+
+    // Players below the permission level aren't sent the command or its aliases
+    assert_eq!(literal_names(&op_packet), vec!["halt", "help", "stop"]);
+    assert_eq!(literal_names(&moderator_packet), vec!["help"]);
+    assert_eq!(literal_names(&packet), vec!["help"]);
+    assert_eq!(packet.nodes.len(), 2);
+
+    // The dispatcher still contains every command, but checks the permission level
+    let mut context = MockContext { messages: Vec::new() };
+    let result = dispatcher.dispatch_with_context("halt", &mut context);
+    assert!(matches!(result, CommandDispatchResult::MissingPermission));
+    assert!(context.messages.is_empty());
+
+    let mut parse_state = ParseState::new("halt");
+    parse_state.set_permission_level(3);
+    parse_state.push_context(&mut context);
+    let result = dispatcher.dispatch_with(parse_state);
+    assert!(matches!(result, CommandDispatchResult::MissingPermission));
+    assert!(context.messages.is_empty());
+
+    let mut parse_state = ParseState::new("halt");
+    parse_state.set_permission_level(4);
+    parse_state.push_context(&mut context);
+    let result = dispatcher.dispatch_with(parse_state);
+    assert!(matches!(result, CommandDispatchResult::Success(Ok(()))));
+    assert_eq!(context.messages, vec!["Stopping"]);
+
+    let result = dispatcher.dispatch_with_context("help", &mut context);
+    assert!(matches!(result, CommandDispatchResult::Success(Ok(()))));
+    assert_eq!(context.messages, vec!["Stopping", "Help"]);
+}
@@ -0,0 +1,27 @@
+use graphite_command::brigadier;
+use graphite_command::types::CommandResult;
+
+struct MockContext;
+
+fn main() {
+    #[brigadier("say", {greedy}, {})]
+    fn greedy_before_argument(_context: &mut MockContext, _message: &str, _times: u8) -> CommandResult {
+        Ok(())
+    }
+
+    #[brigadier("say", {greedy}, "loudly")]
+    fn greedy_before_literal(_context: &mut MockContext, _message: &str) -> CommandResult {
+        Ok(())
+    }
+
+    #[brigadier("say", {quoted})]
+    fn unknown_modifier(_context: &mut MockContext, _message: &str) -> CommandResult {
+        Ok(())
+    }
+
+    // Greedy strings can come after other arguments
+    #[brigadier("tell", {}, {greedy})]
+    fn tell(_context: &mut MockContext, _target: &str, _message: &str) -> CommandResult {
+        Ok(())
+    }
+}
@@ -0,0 +1,32 @@
+use graphite_command::brigadier;
+use graphite_command::types::CommandResult;
+
+struct MockContext;
+
+fn main() {
+    #[brigadier("")]
+    fn empty(_context: &mut MockContext) -> CommandResult {
+        Ok(())
+    }
+
+    #[brigadier("say\"hello")]
+    fn quote(_context: &mut MockContext) -> CommandResult {
+        Ok(())
+    }
+
+    #[brigadier(["greet", "/greet"])]
+    fn slash(_context: &mut MockContext) -> CommandResult {
+        Ok(())
+    }
+
+    #[brigadier("say\thello")]
+    fn tab(_context: &mut MockContext) -> CommandResult {
+        Ok(())
+    }
+
+    // Namespaced literals are valid
+    #[brigadier("minecraft:give")]
+    fn give(_context: &mut MockContext) -> CommandResult {
+        Ok(())
+    }
+}
@@ -0,0 +1,302 @@
+use std::{
+    sync::{
+        mpsc::{self, Receiver, SyncSender, TrySendError},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use anyhow::bail;
+use graphite_mc_protocol::types::{GameProfile, GameProfileProperty};
+use num_bigint::BigInt;
+use rsa::{
+    pkcs1v15::{Signature, VerifyingKey},
+    pkcs8::{DecodePublicKey, EncodePublicKey},
+    signature::Verifier,
+    PaddingScheme, RsaPrivateKey, RsaPublicKey,
+};
+use serde_derive::Deserialize;
+use sha1::{Digest, Sha1};
+use sha2::Sha256;
+
+// Online mode authentication: https://wiki.vg/Protocol_Encryption
+//
+// The client and server agree on a shared secret using the key pair of the server, and then
+// both tell the session server about it using a hash of the secret. The player is authenticated
+// once the session server confirms that the client made the same request
+
+pub const MOJANG_SESSION_SERVER: &str = "https://sessionserver.mojang.com";
+
+/// Clients that haven't joined within this time are disconnected
+const SESSION_SERVER_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Key pair used to encrypt the shared secret sent by clients
+pub struct ServerKey {
+    private_key: RsaPrivateKey,
+    /// The public key, encoded as DER
+    pub public_key: Vec<u8>,
+}
+
+impl ServerKey {
+    /// Generates a new 1024-bit key pair, same as vanilla
+    pub fn generate() -> anyhow::Result<Self> {
+        let private_key = RsaPrivateKey::new(&mut rand::thread_rng(), 1024)?;
+        let public_key = private_key.to_public_key().to_public_key_der()?.into_vec();
+
+        Ok(Self {
+            private_key,
+            public_key,
+        })
+    }
+
+    /// Decrypts bytes that the client encrypted using the public key
+    pub fn decrypt(&self, bytes: &[u8]) -> anyhow::Result<Vec<u8>> {
+        Ok(self
+            .private_key
+            .decrypt(PaddingScheme::new_pkcs1v15_encrypt(), bytes)?)
+    }
+}
+
+/// Hash of the server id, shared secret and public key that is given to the session server
+///
+/// Minecraft formats the SHA-1 digest as a signed number, eg. `-7c9d5b0044c130109a5d7b5fb5c317c02b4e28c1`
+pub fn server_hash(server_id: &str, shared_secret: &[u8], public_key: &[u8]) -> String {
+    let digest = Sha1::new()
+        .chain_update(server_id)
+        .chain_update(shared_secret)
+        .chain_update(public_key)
+        .finalize();
+    BigInt::from_signed_bytes_be(&digest).to_str_radix(16)
+}
+
+/// Checks the signature that clients with a profile key send instead of the encrypted verify token
+///
+/// The client signs the verify token followed by the salt using SHA256withRSA. `public_key` is the
+/// profile key (encoded as DER) that the client sent in the Hello packet
+pub fn verify_token_signature(
+    public_key: &[u8],
+    verify_token: &[u8],
+    salt: i64,
+    signature: &[u8],
+) -> anyhow::Result<()> {
+    let public_key = RsaPublicKey::from_public_key_der(public_key)?;
+    let verifying_key = VerifyingKey::<Sha256>::new_with_prefix(public_key);
+
+    let mut message = verify_token.to_vec();
+    message.extend_from_slice(&salt.to_be_bytes());
+    if verifying_key.verify(&message, &Signature::from(signature.to_vec())).is_err() {
+        bail!("verify token signature doesn't match the profile key");
+    }
+    Ok(())
+}
+
+#[derive(Deserialize)]
+struct HasJoinedResponse {
+    id: String,
+    name: String,
+    #[serde(default)]
+    properties: Vec<ProfileProperty>,
+}
+
+/// Property of a profile, as formatted in the JSON of the session server
+#[derive(Deserialize)]
+pub struct ProfileProperty {
+    name: String,
+    value: String,
+    signature: Option<String>,
+}
+
+impl From<ProfileProperty> for GameProfileProperty {
+    fn from(property: ProfileProperty) -> Self {
+        GameProfileProperty {
+            id: property.name,
+            value: property.value,
+            signature: property.signature,
+        }
+    }
+}
+
+/// Asks the session server whether the player joined using the server hash, returning
+/// the profile of the player (including their skin) if they did
+///
+/// This blocks until the session server responds
+pub fn has_joined(
+    session_server: &str,
+    username: &str,
+    server_hash: &str,
+) -> anyhow::Result<GameProfile> {
+    let agent = ureq::AgentBuilder::new()
+        .timeout(SESSION_SERVER_TIMEOUT)
+        .build();
+    let response = agent
+        .get(&format!("{}/session/minecraft/hasJoined", session_server))
+        .query("username", username)
+        .query("serverId", server_hash)
+        .call()?;
+
+    // The session server responds with no content if the player didn't join
+    if response.status() == 204 {
+        bail!(
+            "{} hasn't joined the server according to the session server",
+            username
+        );
+    }
+
+    let response: HasJoinedResponse = response.into_json()?;
+    Ok(GameProfile {
+        uuid: u128::from_str_radix(&response.id, 16)?,
+        username: response.name,
+        properties: response.properties.into_iter().map(Into::into).collect(),
+    })
+}
+
+/// Result of asking the session server, received once one of the threads has responded
+pub type AuthenticationReceiver = Receiver<anyhow::Result<GameProfile>>;
+
+struct AuthenticationRequest {
+    session_server: String,
+    username: String,
+    server_hash: String,
+    result: mpsc::Sender<anyhow::Result<GameProfile>>,
+}
+
+/// Fixed number of threads that ask the session server, so that requests don't block the
+/// connections of other players
+///
+/// At most `max_queued` requests can wait for a thread. Further requests are rejected
+/// until the session server catches up, instead of spawning a thread for every login
+pub struct AuthenticationPool {
+    sender: SyncSender<AuthenticationRequest>,
+}
+
+impl AuthenticationPool {
+    pub fn new(threads: usize, max_queued: usize) -> Self {
+        let (sender, receiver) = mpsc::sync_channel::<AuthenticationRequest>(max_queued);
+        let receiver = Arc::new(Mutex::new(receiver));
+
+        for _ in 0..threads {
+            let receiver = receiver.clone();
+            std::thread::spawn(move || loop {
+                // The lock is released before the request is handled
+                let request = receiver.lock().unwrap().recv();
+                let Ok(request) = request else {
+                    // The pool has been dropped
+                    return;
+                };
+
+                let result =
+                    has_joined(&request.session_server, &request.username, &request.server_hash);
+                let _ = request.result.send(result);
+            });
+        }
+
+        Self { sender }
+    }
+
+    /// Queues a hasJoined request, see `has_joined`
+    ///
+    /// Fails if too many requests are already waiting for the session server
+    pub fn request(
+        &self,
+        session_server: String,
+        username: String,
+        server_hash: String,
+    ) -> anyhow::Result<AuthenticationReceiver> {
+        let (result, receiver) = mpsc::channel();
+        let request = AuthenticationRequest {
+            session_server,
+            username,
+            server_hash,
+            result,
+        };
+
+        match self.sender.try_send(request) {
+            Ok(()) => Ok(receiver),
+            Err(TrySendError::Full(_)) => bail!("too many players are being authenticated"),
+            Err(TrySendError::Disconnected(_)) => bail!("authentication threads have stopped"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+
+    use rsa::{pkcs1v15::SigningKey, pkcs8::EncodePublicKey, signature::Signer, RsaPrivateKey};
+    use sha2::Sha256;
+
+    use super::{server_hash, verify_token_signature, AuthenticationPool};
+
+    #[test]
+    fn server_hash_is_signed() {
+        // Examples from https://wiki.vg/Protocol_Encryption
+        assert_eq!(
+            server_hash("Notch", &[], &[]),
+            "4ed1f46bbe04bc756bcb17c0c7ce3e4632f06a48"
+        );
+        assert_eq!(
+            server_hash("jeb_", &[], &[]),
+            "-7c9d5b0044c130109a5d7b5fb5c317c02b4e28c1"
+        );
+        assert_eq!(
+            server_hash("simon", &[], &[]),
+            "88e16a1019277b15d58faf0541e11910eb756f6"
+        );
+
+        // Every part is hashed in order
+        assert_eq!(
+            server_hash("", b"Notch", &[]),
+            server_hash("Notch", &[], &[])
+        );
+        assert_eq!(
+            server_hash("No", b"tc", b"h"),
+            server_hash("Notch", &[], &[])
+        );
+    }
+
+    #[test]
+    fn pool_rejects_when_full() {
+        // Session server that never responds, keeping the thread busy
+        let session_server = TcpListener::bind("127.0.0.1:0").unwrap();
+        let session_server_addr = format!("http://{}", session_server.local_addr().unwrap());
+        let request = |pool: &AuthenticationPool| {
+            pool.request(session_server_addr.clone(), "Moulberry".into(), "hash".into())
+        };
+
+        let pool = AuthenticationPool::new(1, 1);
+
+        // The first request is taken by the thread
+        let _first = request(&pool).unwrap();
+        let _connection = session_server.accept().unwrap();
+
+        // The second request waits in the queue, and the third doesn't fit
+        let _second = request(&pool).unwrap();
+        assert!(request(&pool).is_err());
+    }
+
+    #[test]
+    fn verify_token_signature_matches_key() {
+        // Profile keys are 2048-bit
+        let private_key = RsaPrivateKey::new(&mut rand::thread_rng(), 2048).unwrap();
+        let public_key = private_key.to_public_key().to_public_key_der().unwrap();
+        let signing_key = SigningKey::<Sha256>::new_with_prefix(private_key);
+
+        let verify_token = [1, 2, 3, 4];
+        let salt: i64 = -1234567890123;
+        let mut message = verify_token.to_vec();
+        message.extend_from_slice(&salt.to_be_bytes());
+        let mut signature = signing_key.sign(&message).to_vec();
+
+        let public_key = public_key.as_bytes();
+        assert!(verify_token_signature(public_key, &verify_token, salt, &signature).is_ok());
+
+        // The token, salt and signature all need to match
+        assert!(verify_token_signature(public_key, &[1, 2, 3, 5], salt, &signature).is_err());
+        assert!(verify_token_signature(public_key, &verify_token, salt + 1, &signature).is_err());
+        signature[0] ^= 1;
+        assert!(verify_token_signature(public_key, &verify_token, salt, &signature).is_err());
+
+        // Keys that aren't valid DER are rejected
+        assert!(verify_token_signature(&[0; 16], &verify_token, salt, &signature).is_err());
+    }
+}
@@ -0,0 +1,89 @@
+use anyhow::bail;
+use graphite_mc_protocol::{handshake, login, play, status};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ConnectionState {
+    Handshake,
+    Status,
+    Login,
+    /// Introduced in 1.20.2. The protocol implemented here has no configuration packets,
+    /// so nothing is accepted in this state
+    Configuration,
+    Play,
+}
+
+impl ConnectionState {
+    /// Returns true if the client is allowed to send a packet with the id in this state
+    pub fn accepts_packet(self, packet_id: u8) -> bool {
+        match self {
+            ConnectionState::Handshake => handshake::client::PacketId::try_from(packet_id).is_ok(),
+            ConnectionState::Status => status::client::PacketId::try_from(packet_id).is_ok(),
+            ConnectionState::Login => login::client::PacketId::try_from(packet_id).is_ok(),
+            ConnectionState::Configuration => false,
+            ConnectionState::Play => play::client::PacketId::try_from(packet_id).is_ok(),
+        }
+    }
+
+    /// Returns true if a connection in this state is allowed to move to `next`
+    ///
+    /// Clients before 1.20.2 go from login straight to play, newer clients go through
+    /// configuration and can be sent back to configuration from play
+    pub fn can_transition_to(self, next: ConnectionState) -> bool {
+        matches!(
+            (self, next),
+            (ConnectionState::Handshake, ConnectionState::Status)
+                | (ConnectionState::Handshake, ConnectionState::Login)
+                | (ConnectionState::Login, ConnectionState::Configuration)
+                | (ConnectionState::Login, ConnectionState::Play)
+                | (ConnectionState::Configuration, ConnectionState::Play)
+                | (ConnectionState::Play, ConnectionState::Configuration)
+        )
+    }
+
+    pub fn transition_to(&mut self, next: ConnectionState) -> anyhow::Result<()> {
+        if !self.can_transition_to(next) {
+            bail!("invalid connection state transition from {:?} to {:?}", self, next);
+        }
+        *self = next;
+        Ok(())
+    }
+
+    /// Rejects packets that aren't valid in this state, logging the violation
+    pub fn check_packet(self, packet_id: u8) -> anyhow::Result<()> {
+        if !self.accepts_packet(packet_id) {
+            println!("rejected packet_id {} during {:?}", packet_id, self);
+            bail!("unexpected packet_id {} during {:?}", packet_id, self);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn play_packet_during_login() {
+        let swing = play::client::PacketId::Swing as u8;
+        assert!(ConnectionState::Play.check_packet(swing).is_ok());
+        assert!(ConnectionState::Login.check_packet(swing).is_err());
+
+        let hello = login::client::PacketId::Hello as u8;
+        assert!(ConnectionState::Login.check_packet(hello).is_ok());
+        assert!(ConnectionState::Configuration.check_packet(hello).is_err());
+    }
+
+    #[test]
+    fn transitions() {
+        let mut state = ConnectionState::Handshake;
+        assert!(state.transition_to(ConnectionState::Play).is_err());
+        state.transition_to(ConnectionState::Login).unwrap();
+        state.transition_to(ConnectionState::Configuration).unwrap();
+        state.transition_to(ConnectionState::Play).unwrap();
+
+        // Servers can send the client back to configuration
+        state.transition_to(ConnectionState::Configuration).unwrap();
+        assert!(state.transition_to(ConnectionState::Login).is_err());
+        assert_eq!(state, ConnectionState::Configuration);
+    }
+}
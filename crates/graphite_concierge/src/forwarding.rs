@@ -0,0 +1,120 @@
+use std::net::IpAddr;
+
+use anyhow::{anyhow, bail};
+use graphite_mc_protocol::types::GameProfileProperty;
+
+use crate::authentication::ProfileProperty;
+
+/// How players connect to the concierge, see `ConciergeService::FORWARDING_MODE`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ForwardingMode {
+    /// Players connect directly, without a proxy
+    None,
+    /// Players connect through a proxy using BungeeCord's `ip_forward`, which Velocity
+    /// also supports as `legacy` forwarding
+    ///
+    /// Proxies authenticate players themselves, so the concierge must not be reachable directly
+    Legacy,
+}
+
+/// Information about a player that was forwarded by a proxy
+#[derive(Debug, Clone)]
+pub struct ForwardedPlayer {
+    pub address: IpAddr,
+    pub uuid: u128,
+    pub properties: Vec<GameProfileProperty>,
+}
+
+/// Parses the host name of a handshake using legacy forwarding, returning the original
+/// host name and the forwarded player
+///
+/// Proxies send `host\0address\0uuid\0properties`, where the properties are optional
+pub fn parse_legacy(host_name: &str) -> anyhow::Result<(&str, ForwardedPlayer)> {
+    let mut parts = host_name.split('\0');
+
+    let host_name = parts.next().unwrap_or_default();
+    let (address, uuid) = match (parts.next(), parts.next()) {
+        (Some(address), Some(uuid)) => (address, uuid),
+        // Either the player connected directly, or the proxy isn't configured to forward
+        _ => bail!("handshake doesn't contain forwarded player information"),
+    };
+    let properties = parts.next();
+    if parts.next().is_some() {
+        bail!("too many parts in forwarded handshake");
+    }
+
+    let address = address
+        .parse()
+        .map_err(|_| anyhow!("invalid forwarded address: {}", address))?;
+
+    // Uuids are forwarded without dashes
+    if uuid.len() != 32 {
+        bail!("invalid forwarded uuid: {}", uuid);
+    }
+    let uuid = u128::from_str_radix(uuid, 16)?;
+
+    let properties = match properties {
+        Some(properties) => serde_json::from_str::<Vec<ProfileProperty>>(properties)?
+            .into_iter()
+            .map(Into::into)
+            .collect(),
+        None => Vec::new(),
+    };
+
+    Ok((
+        host_name,
+        ForwardedPlayer {
+            address,
+            uuid,
+            properties,
+        },
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_legacy;
+
+    #[test]
+    fn legacy_forwarding() {
+        let host_name = concat!(
+            "play.example.com\0",
+            "203.0.113.7\0",
+            "d8d5a9237b2043d8883b1150148d6955\0",
+            r#"[{"name":"textures","value":"dGV4dHVyZXM=","signature":"c2lnbmF0dXJl"}]"#
+        );
+
+        let (host_name, forwarded) = parse_legacy(host_name).unwrap();
+        assert_eq!(host_name, "play.example.com");
+        assert_eq!(
+            forwarded.address,
+            "203.0.113.7".parse::<std::net::IpAddr>().unwrap()
+        );
+        assert_eq!(forwarded.uuid, 0xd8d5a9237b2043d8883b1150148d6955);
+        assert_eq!(forwarded.properties.len(), 1);
+        assert_eq!(forwarded.properties[0].id, "textures");
+        assert_eq!(forwarded.properties[0].value, "dGV4dHVyZXM=");
+        assert_eq!(
+            forwarded.properties[0].signature.as_deref(),
+            Some("c2lnbmF0dXJl")
+        );
+
+        // Properties are optional, and addresses can be ipv6
+        let (_, forwarded) =
+            parse_legacy("localhost\0::1\0d8d5a9237b2043d8883b1150148d6955").unwrap();
+        assert!(forwarded.address.is_loopback());
+        assert!(forwarded.properties.is_empty());
+    }
+
+    #[test]
+    fn legacy_forwarding_invalid() {
+        // Player connected directly instead of through the proxy
+        assert!(parse_legacy("play.example.com").is_err());
+
+        assert!(parse_legacy("localhost\0not an ip\0d8d5a9237b2043d8883b1150148d6955").is_err());
+        assert!(
+            parse_legacy("localhost\0127.0.0.1\0d8d5a923-7b20-43d8-883b-1150148d6955").is_err()
+        );
+        assert!(parse_legacy("localhost\0127.0.0.1\0d8d5a9237b2043d8883b1150148d6955\0{").is_err());
+    }
+}
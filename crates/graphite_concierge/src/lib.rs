@@ -1,6 +1,10 @@
-use std::{marker::PhantomData, time::Duration};
+use std::{
+    marker::PhantomData,
+    sync::mpsc::TryRecvError,
+    time::{Duration, Instant},
+};
 
-use anyhow::bail;
+use anyhow::{anyhow, bail};
 use graphite_binary::slice_serialization::Single;
 use graphite_binary::slice_serialization::SliceSerializable;
 use graphite_net::{
@@ -19,20 +23,39 @@ use graphite_mc_protocol::{
 };
 use rand::Rng;
 
+mod authentication;
+mod connection_state;
+mod forwarding;
+pub mod serverlist;
+pub use authentication::MOJANG_SESSION_SERVER;
+use authentication::{AuthenticationPool, AuthenticationReceiver, ServerKey};
+pub use connection_state::ConnectionState;
+pub use forwarding::{ForwardedPlayer, ForwardingMode};
+
 pub struct ConciergeConnection<T> {
     _phantom: PhantomData<T>,
     connection_state: ConnectionState,
     pub game_profile: Option<GameProfile>,
+    /// The player information sent by the proxy, see `ConciergeService::FORWARDING_MODE`
+    pub forwarded_player: Option<ForwardedPlayer>,
+    online_login: Option<OnlineLogin>,
+    // Shared secret that encryption is enabled with once the current packets have been read
+    enable_encryption: Option<[u8; 16]>,
 }
 
-#[derive(Debug, PartialEq, Eq)]
-pub enum ConnectionState {
-    Handshake,
-    Status,
-    Login,
+// A login in online mode, from the Hello packet until the session server has responded
+struct OnlineLogin {
+    username: String,
+    verify_token: [u8; 4],
+    // Profile key sent by the client in the Hello packet, encoded as DER
+    public_key: Option<Vec<u8>>,
+    // Receives the response of the session server, which is requested on another thread
+    authentication: Option<AuthenticationReceiver>,
 }
 
 impl<T: ConciergeService + 'static> ConnectionService for ConciergeConnection<T> {
+    // Status and login only take a few packets, clients have no reason to stay quiet for long
+    const IDLE_TIMEOUT: Option<Duration> = Some(Duration::from_secs(10));
     type NetworkManagerServiceType = Concierge<T>;
 
     fn on_receive(
@@ -42,6 +65,22 @@ impl<T: ConciergeService + 'static> ConnectionService for ConciergeConnection<T>
         let mut bytes = connection.read_bytes();
         let mut write_buffer: WriteBuffer = WriteBuffer::new();
 
+        // Legacy server list ping from 2013 (1.4 to 1.6): https://wiki.vg/Server_List_Ping#1.6
+        if self.connection_state == ConnectionState::Handshake {
+            match serverlist::is_legacy_ping(bytes) {
+                Some(true) => {
+                    let concierge = &connection.get_network_manager().service;
+                    let legacy_response = serverlist::legacy_response(&concierge.serverlist_response)?;
+                    connection.write(legacy_response);
+                    connection.request_close();
+                    return Ok(0);
+                }
+                Some(false) => (),
+                // Wait for the rest of the ping
+                None => return Ok(bytes.len() as u32),
+            }
+        }
+
         let mut should_consume = false;
 
         loop {
@@ -50,6 +89,13 @@ impl<T: ConciergeService + 'static> ConnectionService for ConciergeConnection<T>
                 PacketReadResult::Complete(bytes) => {
                     should_consume =
                         self.handle_framed_packet(connection, &mut write_buffer, bytes)?;
+                    if should_consume {
+                        // Remaining packets are handled by the service the connection is given to
+                        break;
+                    } else if self.enable_encryption.is_some() {
+                        // Remaining packets are encrypted, they are decrypted once it's enabled
+                        break;
+                    }
                 }
                 PacketReadResult::Partial => break,
                 PacketReadResult::Empty => break,
@@ -61,18 +107,12 @@ impl<T: ConciergeService + 'static> ConnectionService for ConciergeConnection<T>
         let to_write = write_buffer.into_written();
         connection.write(to_write);
 
+        if let Some(shared_secret) = self.enable_encryption.take() {
+            connection.enable_encryption(shared_secret);
+        }
+
         if should_consume {
-            if self.connection_state == ConnectionState::Login {
-                connection.request_redirect(
-                    |service: &mut Concierge<T>, connection, connection_service| {
-                        service
-                            .service
-                            .accept_player(connection, connection_service);
-                    },
-                );
-            } else {
-                connection.request_close();
-            }
+            self.consume(connection);
         }
 
         Ok(remaining_bytes)
@@ -80,53 +120,71 @@ impl<T: ConciergeService + 'static> ConnectionService for ConciergeConnection<T>
 }
 
 impl<T: ConciergeService + 'static> ConciergeConnection<T> {
+    // Gives the connection to the service once login is complete, or closes it if login failed
+    fn consume(&self, connection: &mut Connection<Concierge<T>>) {
+        if self.connection_state == ConnectionState::Play {
+            connection.request_redirect(
+                |service: &mut Concierge<T>, connection, connection_service| {
+                    service
+                        .service
+                        .accept_player(connection, connection_service);
+                },
+            );
+        } else {
+            connection.request_close();
+        }
+    }
+
     fn handle_framed_packet(
         &mut self,
         connection: &Connection<Concierge<T>>,
         write_buffer: &mut WriteBuffer,
         mut bytes: &[u8],
     ) -> anyhow::Result<bool> {
+        if self.connection_state == ConnectionState::Handshake && bytes.len() < 3 {
+            bail!("insufficient bytes for handshake");
+        }
+
+        let packet_id: u8 = Single::read(&mut bytes)?;
+        self.connection_state.check_packet(packet_id)?;
+
         match self.connection_state {
             ConnectionState::Handshake => {
                 // Handshake: https://wiki.vg/Handshake
-                self.handle_handshake(&mut bytes)
+                self.handle_handshake(packet_id, &mut bytes)
             }
             ConnectionState::Status => {
                 // Server List Ping: https://wiki.vg/Server_List_Ping
-                self.handle_status(&mut bytes, connection, write_buffer)
+                self.handle_status(packet_id, &mut bytes, connection, write_buffer)
             }
             ConnectionState::Login => {
                 // Login: https://wiki.vg/Protocol#Login
-                self.handle_login(&mut bytes, write_buffer)
+                self.handle_login(packet_id, &mut bytes, connection, write_buffer)
+            }
+            ConnectionState::Configuration | ConnectionState::Play => {
+                unreachable!("connection is redirected once login is complete")
             }
         }
     }
 
-    fn handle_handshake(&mut self, bytes: &mut &[u8]) -> anyhow::Result<bool> {
-        if bytes.len() < 3 {
-            bail!("insufficient bytes for handshake");
-        } else if bytes[0..3] == [0xFE, 0x01, 0xFA] {
-            bail!("legacy server list ping from 2013 is not supported");
-        } else {
-            let packet_id: u8 = Single::read(bytes)?;
-            if let Ok(packet_id) = handshake::client::PacketId::try_from(packet_id) {
-                match packet_id {
-                    handshake::client::PacketId::Intention => {
-                        let intention_packet = Intention::read_fully(bytes)?;
-
-                        self.connection_state = match intention_packet.intention {
-                            1 => ConnectionState::Status,
-                            2 => ConnectionState::Login,
-                            next => bail!("unknown intention ({}) during initial handshake", next),
-                        };
-                    }
+    fn handle_handshake(&mut self, packet_id: u8, bytes: &mut &[u8]) -> anyhow::Result<bool> {
+        match handshake::client::PacketId::try_from(packet_id)? {
+            handshake::client::PacketId::Intention => {
+                let intention_packet = Intention::read_fully(bytes)?;
+
+                let next = match intention_packet.intention {
+                    1 => ConnectionState::Status,
+                    2 => ConnectionState::Login,
+                    next => bail!("unknown intention ({}) during initial handshake", next),
+                };
+
+                // Proxies only forward player information when logging in
+                if next == ConnectionState::Login && T::FORWARDING_MODE == ForwardingMode::Legacy {
+                    let (_, forwarded_player) =
+                        forwarding::parse_legacy(intention_packet.host_name)?;
+                    self.forwarded_player = Some(forwarded_player);
                 }
-            } else {
-                bail!(
-                    "unknown packet_id {} during {:?}",
-                    packet_id,
-                    self.connection_state
-                );
+                self.connection_state.transition_to(next)?;
             }
         }
 
@@ -135,31 +193,23 @@ impl<T: ConciergeService + 'static> ConciergeConnection<T> {
 
     fn handle_status(
         &self,
+        packet_id: u8,
         bytes: &mut &[u8],
         connection: &Connection<Concierge<T>>,
         write_buffer: &mut WriteBuffer,
     ) -> anyhow::Result<bool> {
-        let packet_id: u8 = Single::read(bytes)?;
-        if let Ok(packet_id) = status::client::PacketId::try_from(packet_id) {
-            match packet_id {
-                status::client::PacketId::StatusRequest => {
-                    let concierge = &connection.get_network_manager().service;
-                    let server_response = StatusResponse {
-                        json: &concierge.serverlist_response,
-                    };
-                    graphite_net::packet_helper::try_write_packet(write_buffer, &server_response);
-                }
-                status::client::PacketId::PingRequest => {
-                    let ping_request = PingRequest::read_fully(bytes)?;
-                    graphite_net::packet_helper::try_write_packet(write_buffer, &ping_request);
-                }
+        match status::client::PacketId::try_from(packet_id)? {
+            status::client::PacketId::StatusRequest => {
+                let concierge = &connection.get_network_manager().service;
+                let server_response = StatusResponse {
+                    json: &concierge.serverlist_response,
+                };
+                graphite_net::packet_helper::try_write_packet(write_buffer, &server_response);
+            }
+            status::client::PacketId::PingRequest => {
+                let ping_request = PingRequest::read_fully(bytes)?;
+                graphite_net::packet_helper::try_write_packet(write_buffer, &ping_request);
             }
-        } else {
-            bail!(
-                "unknown packet_id {} during {:?}",
-                packet_id,
-                self.connection_state
-            );
         }
 
         Ok(false)
@@ -167,102 +217,702 @@ impl<T: ConciergeService + 'static> ConciergeConnection<T> {
 
     fn handle_login(
         &mut self,
+        packet_id: u8,
         bytes: &mut &[u8],
+        connection: &Connection<Concierge<T>>,
         write_buffer: &mut WriteBuffer,
     ) -> anyhow::Result<bool> {
-        let packet_id: u8 = Single::read(bytes)?;
-        if let Ok(packet_id) = login::client::PacketId::try_from(packet_id) {
-            match packet_id {
-                login::client::PacketId::Hello => {
-                    let login_start_packet = login::client::Hello::read_fully(bytes)?;
-
-                    let uuid = rand::thread_rng().gen(); //login_start_packet.uuid.ok_or(anyhow!("invalid uuid"))?;
+        match login::client::PacketId::try_from(packet_id)? {
+            login::client::PacketId::Hello => {
+                let login_start_packet = login::client::Hello::read_fully(bytes)?;
 
-                    println!("player joined with uuid: {:x}", uuid);
+                if self.online_login.is_some() {
+                    bail!("duplicate hello packet");
+                }
 
-                    // todo: download skin
+                if let Some(forwarded_player) = &self.forwarded_player {
+                    // The proxy has already authenticated the player
                     let game_profile = GameProfile {
-                        uuid,
+                        uuid: forwarded_player.uuid,
                         username: login_start_packet.username.into(),
-                        properties: vec![
-                            GameProfileProperty {
-                                id: "textures".into(),
-                                value: "ewogICJ0aW1lc3RhbXAiIDogMTY1OTAyMDI4NjQ0OCwKICAicHJvZmlsZUlkIiA6ICJkMGUwNWRlNzYwNjc0NTRkYmVhZWM2ZDE5ZDg4NjE5MSIsCiAgInByb2ZpbGVOYW1lIiA6ICJNb3VsYmVycnkiLAogICJzaWduYXR1cmVSZXF1aXJlZCIgOiB0cnVlLAogICJ0ZXh0dXJlcyIgOiB7CiAgICAiU0tJTiIgOiB7CiAgICAgICJ1cmwiIDogImh0dHA6Ly90ZXh0dXJlcy5taW5lY3JhZnQubmV0L3RleHR1cmUvYmNlMTU1MjI0ZWE0YmM0OWE4ZTkxOTA3MzdjYjA0MTdkOGE3YzM4YTAzN2Q4ZDAzODJkZGU0ODI5YzEwMzU5MCIsCiAgICAgICJtZXRhZGF0YSIgOiB7CiAgICAgICAgIm1vZGVsIiA6ICJzbGltIgogICAgICB9CiAgICB9CiAgfQp9".into(),
-                                signature: Some("XMgxJ45DZlaKr3BozEJ9tYUpqqhN/WIvHt8T8KGnbYjUFGq5q3WOodpR/2hlBE5dgTL+wk3QFXXuBYzDmcKVPl3Nh/Qv3ZqETOZQ1hC5hLTpNwCKH55QGRqEQYwLEZ+4fz2bdTqd+nISehl6fEwHLb3mSXIj7n/ICxJ0jPw9+1BDndY2omKRjnD8G3VRf3gAhcwMw5mCTy3RMOa+3VIe4YTUqQFSOqQ7H1JTmD1mzXbGaqJaOg6DOFlI+nXXNuajfqr2TEiK78ieZk78mzvYB5K/5NH2NttKmuDYNVyR9u5f9IyRpEFba0tIEC1DpfbSu7TgNb5tIXTxzr9W0sG+OyVN2+/hO1vxejvYpSFJki/O1E5UHLKAilVr4IVjnpMNsY/6TS6C83UTz3UGXghSuSiX77xMGikzgJmUaNFjUoCe1jzdu3aBA/PPCXVQh17CBilVWFFUE5qapKphp9rPD2KpaOjPyRv9dWEx1c0VFhAUWDcoM4/6dnqdpR8AGzZSBLNpAL+DfaZ83qwfZ8GIqvDdYbvz09A9DHEOhgy3qoPvgwCMKTdsTsrQhVOVxKo0s0hNDiDu3ZKpF3SA2OXcaRES+B/xWSQ9Lcq1G9++v+0TWiKS+3oyecUCIQcdrQZQDxKXgVPUUo1XXUEgCjEdCUy0OuWmSQCrSBhWG6bfguk=".into())
-                            }
-                        ]
+                        properties: forwarded_player.properties.clone(),
                     };
 
-                    // Write login success
-                    let login_success_packet = login::server::LoginSuccess {
-                        profile: game_profile,
+                    let service = &connection.get_network_manager().service.service;
+                    return self.complete_login(service, write_buffer, game_profile);
+                }
+
+                if let Some(server_key) = &connection.get_network_manager().service.server_key {
+                    // Online mode, the player is authenticated once encryption has been set up
+                    let verify_token: [u8; 4] = rand::thread_rng().gen();
+                    let encryption_request = login::server::EncryptionRequest {
+                        server_id: "",
+                        public_key: &server_key.public_key,
+                        verify_token: &verify_token,
                     };
-                    graphite_net::packet_helper::try_write_packet(write_buffer, &login_success_packet);
+                    graphite_net::packet_helper::try_write_packet(
+                        write_buffer,
+                        &encryption_request,
+                    );
 
-                    // Set game profile
-                    self.game_profile = Some(login_success_packet.profile);
+                    self.online_login = Some(OnlineLogin {
+                        username: login_start_packet.username.into(),
+                        verify_token,
+                        public_key: login_start_packet
+                            .signature_data
+                            .map(|signature_data| signature_data.public_key.to_vec()),
+                        authentication: None,
+                    });
+                    return Ok(false);
+                }
 
-                    Ok(true) // Consume the connection
+                let uuid = rand::thread_rng().gen(); //login_start_packet.uuid.ok_or(anyhow!("invalid uuid"))?;
+
+                println!("player joined with uuid: {:x}", uuid);
+
+                // todo: download skin
+                let game_profile = GameProfile {
+                    uuid,
+                    username: login_start_packet.username.into(),
+                    properties: vec![
+                        GameProfileProperty {
+                            id: "textures".into(),
+                            value: "ewogICJ0aW1lc3RhbXAiIDogMTY1OTAyMDI4NjQ0OCwKICAicHJvZmlsZUlkIiA6ICJkMGUwNWRlNzYwNjc0NTRkYmVhZWM2ZDE5ZDg4NjE5MSIsCiAgInByb2ZpbGVOYW1lIiA6ICJNb3VsYmVycnkiLAogICJzaWduYXR1cmVSZXF1aXJlZCIgOiB0cnVlLAogICJ0ZXh0dXJlcyIgOiB7CiAgICAiU0tJTiIgOiB7CiAgICAgICJ1cmwiIDogImh0dHA6Ly90ZXh0dXJlcy5taW5lY3JhZnQubmV0L3RleHR1cmUvYmNlMTU1MjI0ZWE0YmM0OWE4ZTkxOTA3MzdjYjA0MTdkOGE3YzM4YTAzN2Q4ZDAzODJkZGU0ODI5YzEwMzU5MCIsCiAgICAgICJtZXRhZGF0YSIgOiB7CiAgICAgICAgIm1vZGVsIiA6ICJzbGltIgogICAgICB9CiAgICB9CiAgfQp9".into(),
+                            signature: Some("XMgxJ45DZlaKr3BozEJ9tYUpqqhN/WIvHt8T8KGnbYjUFGq5q3WOodpR/2hlBE5dgTL+wk3QFXXuBYzDmcKVPl3Nh/Qv3ZqETOZQ1hC5hLTpNwCKH55QGRqEQYwLEZ+4fz2bdTqd+nISehl6fEwHLb3mSXIj7n/ICxJ0jPw9+1BDndY2omKRjnD8G3VRf3gAhcwMw5mCTy3RMOa+3VIe4YTUqQFSOqQ7H1JTmD1mzXbGaqJaOg6DOFlI+nXXNuajfqr2TEiK78ieZk78mzvYB5K/5NH2NttKmuDYNVyR9u5f9IyRpEFba0tIEC1DpfbSu7TgNb5tIXTxzr9W0sG+OyVN2+/hO1vxejvYpSFJki/O1E5UHLKAilVr4IVjnpMNsY/6TS6C83UTz3UGXghSuSiX77xMGikzgJmUaNFjUoCe1jzdu3aBA/PPCXVQh17CBilVWFFUE5qapKphp9rPD2KpaOjPyRv9dWEx1c0VFhAUWDcoM4/6dnqdpR8AGzZSBLNpAL+DfaZ83qwfZ8GIqvDdYbvz09A9DHEOhgy3qoPvgwCMKTdsTsrQhVOVxKo0s0hNDiDu3ZKpF3SA2OXcaRES+B/xWSQ9Lcq1G9++v+0TWiKS+3oyecUCIQcdrQZQDxKXgVPUUo1XXUEgCjEdCUy0OuWmSQCrSBhWG6bfguk=".into())
+                        }
+                    ]
+                };
+
+                let service = &connection.get_network_manager().service.service;
+                self.complete_login(service, write_buffer, game_profile)
+            }
+            login::client::PacketId::EncryptionResponse => {
+                let encryption_response = login::client::EncryptionResponse::read_fully(bytes)?;
+
+                let online_login = match &mut self.online_login {
+                    Some(online_login) if online_login.authentication.is_none() => online_login,
+                    _ => bail!("unexpected encryption response"),
+                };
+                let server_key = connection
+                    .get_network_manager()
+                    .service
+                    .server_key
+                    .as_ref()
+                    .ok_or_else(|| anyhow!("encryption response in offline mode"))?;
+
+                let shared_secret: [u8; 16] = server_key
+                    .decrypt(encryption_response.shared_secret)?
+                    .try_into()
+                    .map_err(|_| anyhow!("shared secret must be 16 bytes"))?;
+
+                match encryption_response.verification {
+                    login::client::EncryptionVerification::VerifyToken { verify_token } => {
+                        if server_key.decrypt(verify_token)? != online_login.verify_token {
+                            bail!("verify token doesn't match");
+                        }
+                    }
+                    login::client::EncryptionVerification::MessageSignature { salt, signature } => {
+                        // The client signed the verify token using its profile key instead
+                        let public_key = online_login
+                            .public_key
+                            .as_ref()
+                            .ok_or_else(|| anyhow!("message signature without a profile key"))?;
+                        authentication::verify_token_signature(
+                            public_key,
+                            &online_login.verify_token,
+                            salt,
+                            signature,
+                        )?;
+                    }
                 }
+
+                let server_hash =
+                    authentication::server_hash("", &shared_secret, &server_key.public_key);
+                let concierge = &connection.get_network_manager().service;
+                let session_server = concierge.service.get_session_server();
+
+                // Ask the session server on another thread, instead of blocking every other
+                // connection. The response is handled by `Concierge::tick`
+                let authentication_pool = concierge
+                    .authentication_pool
+                    .as_ref()
+                    .ok_or_else(|| anyhow!("encryption response in offline mode"))?;
+                let receiver = authentication_pool.request(
+                    session_server,
+                    online_login.username.clone(),
+                    server_hash,
+                )?;
+                online_login.authentication = Some(receiver);
+                self.enable_encryption = Some(shared_secret);
+
+                Ok(false)
             }
-        } else {
-            bail!(
-                "unknown packet_id {} during {:?}",
-                packet_id,
-                self.connection_state
-            );
         }
     }
+
+    // Lets the service accept or reject the login, and writes the result
+    fn complete_login(
+        &mut self,
+        service: &T,
+        write_buffer: &mut WriteBuffer,
+        game_profile: GameProfile,
+    ) -> anyhow::Result<bool> {
+        if let LoginAction::Reject(reason) = service.accept_login(&game_profile) {
+            if let Some(reason) = reason {
+                let disconnect_packet = login::server::LoginDisconnect { reason: &reason };
+                graphite_net::packet_helper::try_write_packet(write_buffer, &disconnect_packet);
+            }
+
+            // Connection is closed while still in the login state
+            return Ok(true);
+        }
+
+        // Write login success
+        let login_success_packet = login::server::LoginSuccess {
+            profile: game_profile,
+        };
+        graphite_net::packet_helper::try_write_packet(write_buffer, &login_success_packet);
+
+        // Set game profile
+        self.game_profile = Some(login_success_packet.profile);
+        self.connection_state.transition_to(ConnectionState::Play)?;
+
+        Ok(true) // Consume the connection
+    }
+
+    // Completes an online mode login if the session server has responded
+    fn poll_authentication(&mut self, connection: &mut Connection<Concierge<T>>, service: &T) {
+        let receiver = match self
+            .online_login
+            .as_ref()
+            .and_then(|online_login| online_login.authentication.as_ref())
+        {
+            Some(receiver) => receiver,
+            None => return,
+        };
+
+        let authentication_result = match receiver.try_recv() {
+            Ok(result) => result,
+            Err(TryRecvError::Empty) => return,
+            Err(TryRecvError::Disconnected) => Err(anyhow!("session server request panicked")),
+        };
+        self.online_login = None;
+
+        let mut write_buffer = WriteBuffer::new();
+        let login_result = authentication_result
+            .and_then(|game_profile| self.complete_login(service, &mut write_buffer, game_profile));
+
+        if let Err(err) = login_result {
+            eprintln!("error: unable to authenticate player: {:?}", err);
+            let disconnect_packet = login::server::LoginDisconnect {
+                reason: r#"{"translate": "multiplayer.disconnect.unverified_username"}"#,
+            };
+            graphite_net::packet_helper::try_write_packet(&mut write_buffer, &disconnect_packet);
+        }
+
+        connection.write(write_buffer.into_written());
+        self.consume(connection);
+    }
 }
 
+/// How often `ConciergeService::get_serverlist_response` is called
+const SERVERLIST_RESPONSE_INTERVAL: Duration = Duration::from_secs(10);
+/// Threads used to ask the session server in online mode
+const AUTHENTICATION_THREADS: usize = 4;
+/// Logins that can wait for an authentication thread, further logins are disconnected
+const MAX_QUEUED_AUTHENTICATIONS: usize = 256;
+
 pub struct Concierge<T: ConciergeService> {
     serverlist_response: String,
+    serverlist_response_updated: Instant,
+    // Only generated in online mode, see `ConciergeService::ONLINE_MODE`
+    server_key: Option<ServerKey>,
+    authentication_pool: Option<AuthenticationPool>,
     service: T,
 }
 
 impl<T: ConciergeService + 'static> NetworkManagerService for Concierge<T> {
-    const TICK_RATE: Option<Duration> = Some(Duration::from_secs(10));
+    const TICK_RATE: Option<Duration> = Some(Duration::from_millis(50));
     type ConnectionServiceType = ConciergeConnection<T>;
 
-    fn new_connection_service(&mut self) -> ConciergeConnection<T> {
-        ConciergeConnection {
+    fn new_connection_service(&mut self) -> Option<ConciergeConnection<T>> {
+        Some(ConciergeConnection {
             _phantom: PhantomData,
             game_profile: None,
             connection_state: ConnectionState::Handshake,
-        }
+            forwarded_player: None,
+            online_login: None,
+            enable_encryption: None,
+        })
     }
 
     fn tick(
         &mut self,
-        _: &mut ConnectionSlab<Self>,
+        connections: &mut ConnectionSlab<Self>,
         _: NewConnectionAccepter<Self>,
     ) -> anyhow::Result<()> {
-        self.serverlist_response = self.service.get_serverlist_response();
+        if self.serverlist_response_updated.elapsed() >= SERVERLIST_RESPONSE_INTERVAL {
+            self.serverlist_response = self.service.get_serverlist_response();
+            self.serverlist_response_updated = Instant::now();
+        }
+
+        for (_, (connection, connection_service)) in connections.iter_mut() {
+            connection_service.poll_authentication(connection, &self.service);
+        }
+
         Ok(())
     }
 }
 
 impl<T: ConciergeService + 'static> Concierge<T> {
     pub fn bind(addr: &str, mut service: T) -> anyhow::Result<()> {
+        let (server_key, authentication_pool) =
+            if T::ONLINE_MODE && T::FORWARDING_MODE == ForwardingMode::None {
+                let authentication_pool =
+                    AuthenticationPool::new(AUTHENTICATION_THREADS, MAX_QUEUED_AUTHENTICATIONS);
+                (Some(ServerKey::generate()?), Some(authentication_pool))
+            } else {
+                (None, None)
+            };
+
         let concierge = Concierge {
             serverlist_response: service.get_serverlist_response(),
+            serverlist_response_updated: Instant::now(),
+            server_key,
+            authentication_pool,
             service,
         };
 
-        graphite_net::network_handler::start(concierge, Some(addr)).unwrap();
-
-        Ok(())
+        graphite_net::network_handler::start(concierge, Some(addr))
     }
 }
 
+pub enum LoginAction {
+    Accept,
+    /// Disconnects the player before the connection is given to `ConciergeService::accept_player`
+    ///
+    /// The reason is a JSON text component that is shown on the disconnect screen.
+    /// If there is no reason, the connection is closed without a message
+    Reject(Option<String>),
+}
+
 pub trait ConciergeService
 where
     Self: Sized + 'static,
 {
+    /// Whether players are authenticated using the session server, enabling encryption.
+    /// When disabled (offline mode), players can log in using any username
+    ///
+    /// Ignored when forwarding is enabled, as the proxy authenticates players instead
+    const ONLINE_MODE: bool = false;
+
+    /// Whether players connect through a proxy which forwards their information,
+    /// such as their address and uuid
+    const FORWARDING_MODE: ForwardingMode = ForwardingMode::None;
+
     fn get_serverlist_response(&mut self) -> String;
+
+    /// The session server used to authenticate players in online mode
+    fn get_session_server(&self) -> String {
+        MOJANG_SESSION_SERVER.into()
+    }
+
+    /// Called when a player logs in, allowing the login to be rejected (e.g. for a whitelist)
+    fn accept_login(&self, _profile: &GameProfile) -> LoginAction {
+        LoginAction::Accept
+    }
+
     fn accept_player(
         &mut self,
         player_connection: UninitializedConnection,
         player_service: ConciergeConnection<Self>,
     );
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{Read, Write},
+        net::{TcpListener, TcpStream},
+        sync::mpsc::{self, Sender},
+        time::Duration,
+    };
+
+    use graphite_mc_protocol::login::server::LoginDisconnect;
+    use graphite_net::{encryption::ConnectionCipher, packet_helper};
+    use rsa::{pkcs8::DecodePublicKey, PaddingScheme, PublicKey};
+
+    use super::*;
+
+    struct WhitelistService {
+        whitelist: Vec<&'static str>,
+    }
+
+    impl ConciergeService for WhitelistService {
+        fn get_serverlist_response(&mut self) -> String {
+            String::new()
+        }
+
+        fn accept_login(&self, profile: &GameProfile) -> LoginAction {
+            if self.whitelist.contains(&profile.username.as_str()) {
+                LoginAction::Accept
+            } else {
+                LoginAction::Reject(Some(r#"{"text": "You are not whitelisted"}"#.into()))
+            }
+        }
+
+        fn accept_player(&mut self, _: UninitializedConnection, _: ConciergeConnection<Self>) {
+            panic!("player should have been rejected");
+        }
+    }
+
+    #[test]
+    fn reject_login() {
+        // Find a free port for the concierge
+        let addr = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().to_string();
+
+        let concierge_addr = addr.clone();
+        std::thread::spawn(move || {
+            let service = WhitelistService {
+                whitelist: vec!["Moulberry"],
+            };
+            Concierge::bind(&concierge_addr, service).unwrap();
+        });
+
+        let mut client = loop {
+            match TcpStream::connect(&addr) {
+                Ok(client) => break client,
+                Err(_) => std::thread::sleep(Duration::from_millis(10)),
+            }
+        };
+        client.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+
+        let mut write_buffer = WriteBuffer::new();
+        packet_helper::write_packet(
+            &mut write_buffer,
+            &Intention {
+                protocol_version: 760,
+                host_name: "localhost",
+                port: 25565,
+                intention: 2,
+            },
+        )
+        .unwrap();
+        packet_helper::write_packet(
+            &mut write_buffer,
+            &login::client::Hello {
+                username: "Griefer",
+                signature_data: None,
+                uuid: None,
+            },
+        )
+        .unwrap();
+        client.write_all(write_buffer.get_written()).unwrap();
+
+        // The disconnect reason is sent, and then the connection is closed
+        let mut received = Vec::new();
+        client.read_to_end(&mut received).unwrap();
+
+        let mut bytes = received.as_slice();
+        match packet_helper::try_read_packet(&mut bytes).unwrap() {
+            PacketReadResult::Complete(mut packet_bytes) => {
+                let packet_id: u8 = Single::read(&mut packet_bytes).unwrap();
+                assert_eq!(packet_id, login::server::PacketId::LoginDisconnect as u8);
+
+                let packet = LoginDisconnect::read_fully(&mut packet_bytes).unwrap();
+                assert_eq!(packet.reason, r#"{"text": "You are not whitelisted"}"#);
+            }
+            _ => panic!("expected a complete packet"),
+        }
+        assert!(bytes.is_empty());
+    }
+
+    struct StatusService;
+
+    impl ConciergeService for StatusService {
+        fn get_serverlist_response(&mut self) -> String {
+            serverlist::StatusBuilder::new()
+                .players(20, 0)
+                .description("Hello world".into())
+                .into()
+        }
+
+        fn accept_player(&mut self, _: UninitializedConnection, _: ConciergeConnection<Self>) {
+            panic!("player should not have logged in");
+        }
+    }
+
+    #[test]
+    fn legacy_ping() {
+        // Find a free port for the concierge
+        let addr = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().to_string();
+
+        let concierge_addr = addr.clone();
+        std::thread::spawn(move || {
+            Concierge::bind(&concierge_addr, StatusService).unwrap();
+        });
+
+        let mut client = loop {
+            match TcpStream::connect(&addr) {
+                Ok(client) => break client,
+                Err(_) => std::thread::sleep(Duration::from_millis(10)),
+            }
+        };
+        client.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+
+        // Ping sent by a 1.6 client
+        let utf16 = |string: &str| -> Vec<u8> {
+            string.encode_utf16().flat_map(u16::to_be_bytes).collect()
+        };
+        let mut ping = vec![0xFE, 0x01, 0xFA, 0x00, 0x0B];
+        ping.extend(utf16("MC|PingHost"));
+        ping.extend((7 + 2 * "localhost".len() as u16).to_be_bytes());
+        ping.push(74);
+        ping.extend(("localhost".len() as u16).to_be_bytes());
+        ping.extend(utf16("localhost"));
+        ping.extend(25565_i32.to_be_bytes());
+        client.write_all(&ping).unwrap();
+
+        // The legacy response is sent, and then the connection is closed
+        let mut received = Vec::new();
+        client.read_to_end(&mut received).unwrap();
+
+        let expected = "§1\0760\01.19.2\0Hello world\00\020";
+        let mut expected_bytes = vec![0xFF];
+        expected_bytes.extend((expected.encode_utf16().count() as u16).to_be_bytes());
+        expected_bytes.extend(utf16(expected));
+        assert_eq!(received, expected_bytes);
+    }
+
+    #[test]
+    fn handshake_length_254() {
+        // Find a free port for the concierge
+        let addr = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().to_string();
+
+        let concierge_addr = addr.clone();
+        std::thread::spawn(move || {
+            Concierge::bind(&concierge_addr, StatusService).unwrap();
+        });
+
+        let mut client = loop {
+            match TcpStream::connect(&addr) {
+                Ok(client) => break client,
+                Err(_) => std::thread::sleep(Duration::from_millis(10)),
+            }
+        };
+        client.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+
+        // A handshake with a length of 254 starts with 0xFE 0x01, like a legacy ping
+        let host_name = "a".repeat(246);
+        let mut write_buffer = WriteBuffer::new();
+        packet_helper::write_packet(
+            &mut write_buffer,
+            &Intention {
+                protocol_version: 760,
+                host_name: &host_name,
+                port: 25565,
+                intention: 1,
+            },
+        )
+        .unwrap();
+        packet_helper::write_packet(&mut write_buffer, &status::client::StatusRequest {}).unwrap();
+
+        // Packets are written with 3 byte lengths, clients use the shortest encoding
+        let mut written = write_buffer.get_written();
+        let mut sent = Vec::new();
+        while let PacketReadResult::Complete(packet) = packet_helper::try_read_packet(&mut written).unwrap() {
+            graphite_binary::varint::encode::extend_i32(&mut sent, packet.len() as i32);
+            sent.extend_from_slice(packet);
+        }
+        assert_eq!(sent[..3], [0xFE, 0x01, 0x00]);
+        client.write_all(&sent).unwrap();
+
+        // The status response is sent, not the legacy response
+        let mut received = vec![0; 3];
+        client.read_exact(&mut received).unwrap();
+        let (length, length_bytes) = graphite_binary::varint::decode::i32(&received).unwrap();
+        received.resize(length as usize + length_bytes, 0);
+        client.read_exact(&mut received[3..]).unwrap();
+
+        let mut bytes = received.as_slice();
+        let (packet_id, mut packet_bytes) = read_complete_packet(&mut bytes);
+        assert_eq!(packet_id, status::server::PacketId::StatusResponse as u8);
+        let packet = StatusResponse::read_fully(&mut packet_bytes).unwrap();
+        assert!(packet.json.contains("Hello world"));
+    }
+
+    struct OnlineModeService {
+        session_server: String,
+        accepted: Sender<GameProfile>,
+    }
+
+    impl ConciergeService for OnlineModeService {
+        const ONLINE_MODE: bool = true;
+
+        fn get_serverlist_response(&mut self) -> String {
+            String::new()
+        }
+
+        fn get_session_server(&self) -> String {
+            self.session_server.clone()
+        }
+
+        fn accept_player(
+            &mut self,
+            _: UninitializedConnection,
+            player_service: ConciergeConnection<Self>,
+        ) {
+            self.accepted.send(player_service.game_profile.unwrap()).unwrap();
+        }
+    }
+
+    fn read_complete_packet<'a>(bytes: &mut &'a [u8]) -> (u8, &'a [u8]) {
+        match packet_helper::try_read_packet(bytes).unwrap() {
+            PacketReadResult::Complete(mut packet_bytes) => {
+                let packet_id: u8 = Single::read(&mut packet_bytes).unwrap();
+                (packet_id, packet_bytes)
+            }
+            _ => panic!("expected a complete packet"),
+        }
+    }
+
+    #[test]
+    fn online_mode_login() {
+        // Mock session server, responding to a single hasJoined request
+        let session_server = TcpListener::bind("127.0.0.1:0").unwrap();
+        let session_server_addr = format!("http://{}", session_server.local_addr().unwrap());
+        let (request_sender, request_receiver) = mpsc::channel();
+        std::thread::spawn(move || {
+            let (mut stream, _) = session_server.accept().unwrap();
+            let mut request = Vec::new();
+            while !request.ends_with(b"\r\n\r\n") {
+                let mut byte = [0];
+                stream.read_exact(&mut byte).unwrap();
+                request.push(byte[0]);
+            }
+
+            let body = r#"{
+                "id": "d8d5a9237b2043d8883b1150148d6955",
+                "name": "Moulberry",
+                "properties": [{"name": "textures", "value": "dGV4dHVyZXM=", "signature": "c2lnbmF0dXJl"}]
+            }"#;
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            )
+            .unwrap();
+            request_sender.send(String::from_utf8(request).unwrap()).unwrap();
+        });
+
+        // Find a free port for the concierge
+        let addr = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().to_string();
+
+        let concierge_addr = addr.clone();
+        let (accepted_sender, accepted_receiver) = mpsc::channel();
+        std::thread::spawn(move || {
+            let service = OnlineModeService {
+                session_server: session_server_addr,
+                accepted: accepted_sender,
+            };
+            Concierge::bind(&concierge_addr, service).unwrap();
+        });
+
+        // Generating the server key takes a while in debug builds
+        let mut client = loop {
+            match TcpStream::connect(&addr) {
+                Ok(client) => break client,
+                Err(_) => std::thread::sleep(Duration::from_millis(10)),
+            }
+        };
+        client.set_read_timeout(Some(Duration::from_secs(10))).unwrap();
+
+        let mut write_buffer = WriteBuffer::new();
+        packet_helper::write_packet(
+            &mut write_buffer,
+            &Intention {
+                protocol_version: 760,
+                host_name: "localhost",
+                port: 25565,
+                intention: 2,
+            },
+        )
+        .unwrap();
+        packet_helper::write_packet(
+            &mut write_buffer,
+            &login::client::Hello {
+                username: "Moulberry",
+                signature_data: None,
+                uuid: None,
+            },
+        )
+        .unwrap();
+        client.write_all(write_buffer.get_written()).unwrap();
+
+        // Read the encryption request
+        let mut received = vec![0; 1024];
+        let received_len = client.read(&mut received).unwrap();
+        let mut bytes = &received[..received_len];
+        let (packet_id, mut packet_bytes) = read_complete_packet(&mut bytes);
+        assert_eq!(packet_id, login::server::PacketId::EncryptionRequest as u8);
+        let encryption_request =
+            login::server::EncryptionRequest::read_fully(&mut packet_bytes).unwrap();
+        assert!(bytes.is_empty());
+
+        // Respond using the public key of the server
+        let public_key =
+            rsa::RsaPublicKey::from_public_key_der(encryption_request.public_key).unwrap();
+        let shared_secret: [u8; 16] = rand::thread_rng().gen();
+        let encrypt = |bytes: &[u8]| {
+            public_key
+                .encrypt(&mut rand::thread_rng(), PaddingScheme::new_pkcs1v15_encrypt(), bytes)
+                .unwrap()
+        };
+        let encrypted_secret = encrypt(&shared_secret);
+        let encrypted_token = encrypt(encryption_request.verify_token);
+
+        let mut write_buffer = WriteBuffer::new();
+        packet_helper::write_packet(
+            &mut write_buffer,
+            &login::client::EncryptionResponse {
+                shared_secret: &encrypted_secret,
+                verification: login::client::EncryptionVerification::VerifyToken {
+                    verify_token: &encrypted_token,
+                },
+            },
+        )
+        .unwrap();
+        client.write_all(write_buffer.get_written()).unwrap();
+
+        // The session server is asked using the server hash
+        let request = request_receiver.recv_timeout(Duration::from_secs(10)).unwrap();
+        let server_hash =
+            authentication::server_hash("", &shared_secret, encryption_request.public_key);
+        assert!(request.starts_with(&format!(
+            "GET /session/minecraft/hasJoined?username=Moulberry&serverId={} ",
+            server_hash
+        )));
+
+        // The player is accepted using the profile from the session server
+        let profile = accepted_receiver.recv_timeout(Duration::from_secs(10)).unwrap();
+        assert_eq!(profile.uuid, 0xd8d5a9237b2043d8883b1150148d6955);
+        assert_eq!(profile.username, "Moulberry");
+        assert_eq!(profile.properties.len(), 1);
+        assert_eq!(profile.properties[0].id, "textures");
+        assert_eq!(profile.properties[0].value, "dGV4dHVyZXM=");
+        assert_eq!(profile.properties[0].signature.as_deref(), Some("c2lnbmF0dXJl"));
+
+        // Login success is sent encrypted
+        let mut received = Vec::new();
+        client.read_to_end(&mut received).unwrap();
+        ConnectionCipher::new(shared_secret).decrypt(&mut received);
+
+        let mut bytes = received.as_slice();
+        let (packet_id, mut packet_bytes) = read_complete_packet(&mut bytes);
+        assert_eq!(packet_id, login::server::PacketId::LoginSuccess as u8);
+        let login_success = login::server::LoginSuccess::read_fully(&mut packet_bytes).unwrap();
+        assert_eq!(login_success.profile.uuid, profile.uuid);
+        assert_eq!(login_success.profile.username, profile.username);
+    }
+}
@@ -0,0 +1,384 @@
+use std::path::Path;
+
+use anyhow::bail;
+use graphite_mc_protocol::types::GameProfile;
+use graphite_text_component::TextComponent;
+use serde_derive::Serialize;
+
+/// Width and height of server icons, other sizes aren't shown by the client
+pub const FAVICON_SIZE: u32 = 64;
+
+/// Reads a 64x64 PNG, returning it as a data uri for the `favicon` of the serverlist response
+pub fn favicon_from_png(path: &Path) -> anyhow::Result<String> {
+    let bytes = std::fs::read(path)?;
+
+    // Only the header is decoded, the image itself is sent as-is
+    let reader = png::Decoder::new(bytes.as_slice()).read_info()?;
+    let info = reader.info();
+    if info.width != FAVICON_SIZE || info.height != FAVICON_SIZE {
+        bail!(
+            "favicon must be {}x{}, got {}x{}",
+            FAVICON_SIZE,
+            FAVICON_SIZE,
+            info.width,
+            info.height
+        );
+    }
+
+    Ok(format!("data:image/png;base64,{}", base64::encode(&bytes)))
+}
+
+/// Builds the JSON of the serverlist response, see `ConciergeService::get_serverlist_response`
+#[derive(Debug, Clone, Serialize)]
+pub struct StatusBuilder {
+    version: StatusVersion,
+    players: StatusPlayers,
+    description: serde_json::Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    favicon: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct StatusVersion {
+    name: String,
+    protocol: i32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct StatusPlayers {
+    max: usize,
+    online: usize,
+    sample: Vec<StatusPlayer>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct StatusPlayer {
+    name: String,
+    id: String,
+}
+
+impl StatusBuilder {
+    /// Creates a response for 1.19.2 with no players and an empty description
+    pub fn new() -> Self {
+        Self {
+            version: StatusVersion {
+                name: "1.19.2".into(),
+                protocol: 760,
+            },
+            players: StatusPlayers {
+                max: 0,
+                online: 0,
+                sample: Vec::new(),
+            },
+            description: serde_json::Value::String(String::new()),
+            favicon: None,
+        }
+    }
+
+    /// Sets the version, clients with a different protocol show it as incompatible
+    pub fn version(mut self, name: impl Into<String>, protocol: i32) -> Self {
+        self.version = StatusVersion {
+            name: name.into(),
+            protocol,
+        };
+        self
+    }
+
+    pub fn players(mut self, max: usize, online: usize) -> Self {
+        self.players.max = max;
+        self.players.online = online;
+        self
+    }
+
+    /// Sets the players shown when hovering over the player count
+    pub fn sample<'a>(mut self, profiles: impl IntoIterator<Item = &'a GameProfile>) -> Self {
+        self.players.sample = profiles
+            .into_iter()
+            .map(|profile| StatusPlayer {
+                name: profile.username.clone(),
+                id: format_uuid(profile.uuid),
+            })
+            .collect();
+        self
+    }
+
+    /// Sets the message of the day
+    pub fn description(mut self, description: TextComponent) -> Self {
+        self.description = serde_json::from_str(description.to_json())
+            .expect("text components should be valid json");
+        self
+    }
+
+    /// Sets the server icon, see `favicon_from_png`
+    pub fn favicon(mut self, favicon: impl Into<String>) -> Self {
+        self.favicon = Some(favicon.into());
+        self
+    }
+
+    pub fn build(&self) -> String {
+        serde_json::to_string(self).expect("status response should be serializable")
+    }
+}
+
+impl Default for StatusBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl From<StatusBuilder> for String {
+    fn from(builder: StatusBuilder) -> Self {
+        builder.build()
+    }
+}
+
+/// Checks whether the first bytes received from a client are a legacy server list ping
+///
+/// Legacy pings are a lone 0xFE (before 1.4), 0xFE 0x01 (1.4 to 1.5), or 0xFE 0x01 0xFA
+/// followed by the `MC|PingHost` plugin message (1.6). Modern handshakes can also start with
+/// 0xFE when their length is 254, eg. 0xFE 0x01 0x00 for a 254 byte handshake, so anything else
+/// is a modern packet. Returns None if more bytes are needed to tell them apart
+pub(crate) fn is_legacy_ping(bytes: &[u8]) -> Option<bool> {
+    const PING_HOST_SIGNATURE: &[u8] = &[
+        0xFE, 0x01, 0xFA, 0x00, 0x0B, 0x00, b'M', 0x00, b'C', 0x00, b'|', 0x00, b'P', 0x00, b'i',
+        0x00, b'n', 0x00, b'g', 0x00, b'H', 0x00, b'o', 0x00, b's', 0x00, b't',
+    ];
+
+    match bytes {
+        [0xFE] | [0xFE, 0x01] => Some(true),
+        [0xFE, 0x01, 0xFA, ..] if bytes.len() < PING_HOST_SIGNATURE.len() => {
+            if PING_HOST_SIGNATURE.starts_with(bytes) {
+                None
+            } else {
+                Some(false)
+            }
+        }
+        _ => Some(bytes.starts_with(PING_HOST_SIGNATURE)),
+    }
+}
+
+/// Converts a serverlist response into the response to a legacy server list ping,
+/// which is sent by clients from 2013 (1.4 to 1.6) and some server monitors
+///
+/// Legacy responses are a kick packet (0xFF) containing `§1`, the protocol, version,
+/// description, online players and max players, each separated by a null character
+pub fn legacy_response(serverlist_response: &str) -> anyhow::Result<Vec<u8>> {
+    let json: serde_json::Value = serde_json::from_str(serverlist_response)?;
+
+    let mut description = String::new();
+    push_plain_text(&mut description, &json["description"]);
+
+    let fields = [
+        "§1".into(),
+        json["version"]["protocol"]
+            .as_i64()
+            .unwrap_or_default()
+            .to_string(),
+        json["version"]["name"].as_str().unwrap_or_default().into(),
+        description,
+        json["players"]["online"]
+            .as_u64()
+            .unwrap_or_default()
+            .to_string(),
+        json["players"]["max"]
+            .as_u64()
+            .unwrap_or_default()
+            .to_string(),
+    ];
+    let legacy_string: Vec<u16> = fields.join("\0").encode_utf16().collect();
+    if legacy_string.len() > u16::MAX as usize {
+        bail!("legacy response is too long");
+    }
+
+    // Strings are prefixed with their length in characters, and encoded as UTF-16BE
+    let mut bytes = Vec::with_capacity(3 + legacy_string.len() * 2);
+    bytes.push(0xFF);
+    bytes.extend_from_slice(&(legacy_string.len() as u16).to_be_bytes());
+    for character in legacy_string {
+        bytes.extend_from_slice(&character.to_be_bytes());
+    }
+    Ok(bytes)
+}
+
+// Appends the text of a text component, ignoring formatting and translations
+fn push_plain_text(result: &mut String, component: &serde_json::Value) {
+    match component {
+        serde_json::Value::String(text) => result.push_str(text),
+        serde_json::Value::Array(components) => {
+            for component in components {
+                push_plain_text(result, component);
+            }
+        }
+        serde_json::Value::Object(_) => {
+            if let Some(text) = component["text"].as_str() {
+                result.push_str(text);
+            }
+            push_plain_text(result, &component["extra"]);
+        }
+        _ => {}
+    }
+}
+
+// Formats the uuid with dashes, eg. `d8d5a923-7b20-43d8-883b-1150148d6955`
+fn format_uuid(uuid: u128) -> String {
+    let hex = format!("{:032x}", uuid);
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[0..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..32]
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use graphite_mc_protocol::types::GameProfile;
+
+    use super::{favicon_from_png, is_legacy_ping, legacy_response, StatusBuilder};
+
+    #[test]
+    fn status_builder() {
+        let profile = GameProfile {
+            uuid: 0xd8d5a9237b2043d8883b1150148d6955,
+            username: "Moulberry".into(),
+            properties: Vec::new(),
+        };
+        let response = StatusBuilder::new()
+            .players(20, 1)
+            .sample([&profile])
+            .description("Hello \"world\"".into())
+            .favicon("data:image/png;base64,")
+            .build();
+
+        let json: serde_json::Value = serde_json::from_str(&response).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "version": { "name": "1.19.2", "protocol": 760 },
+                "players": {
+                    "max": 20,
+                    "online": 1,
+                    "sample": [{ "name": "Moulberry", "id": "d8d5a923-7b20-43d8-883b-1150148d6955" }]
+                },
+                "description": { "text": "Hello \"world\"" },
+                "favicon": "data:image/png;base64,"
+            })
+        );
+
+        // Favicon is left out unless set
+        let response: String = StatusBuilder::new().version("Graphite", 123).into();
+        let json: serde_json::Value = serde_json::from_str(&response).unwrap();
+        assert_eq!(json["version"]["protocol"], 123);
+        assert!(json.get("favicon").is_none());
+    }
+
+    fn write_png(name: &str, width: u32, height: u32) -> PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("graphite_{}_{}.png", name, std::process::id()));
+        let file = std::fs::File::create(&path).unwrap();
+
+        let mut encoder = png::Encoder::new(file, width, height);
+        encoder.set_color(png::ColorType::Rgba);
+        let mut writer = encoder.write_header().unwrap();
+        writer
+            .write_image_data(&vec![0; (width * height * 4) as usize])
+            .unwrap();
+
+        path
+    }
+
+    #[test]
+    fn favicon() {
+        let path = write_png("favicon", 64, 64);
+        let favicon = favicon_from_png(&path).unwrap();
+
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let encoded = favicon.strip_prefix("data:image/png;base64,").unwrap();
+        assert_eq!(base64::decode(encoded).unwrap(), bytes);
+    }
+
+    #[test]
+    fn favicon_wrong_size() {
+        let path = write_png("favicon_wrong_size", 32, 64);
+        let result = favicon_from_png(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "favicon must be 64x64, got 32x64"
+        );
+    }
+
+    #[test]
+    fn favicon_not_png() {
+        let path = std::env::temp_dir().join(format!(
+            "graphite_favicon_not_png_{}.png",
+            std::process::id()
+        ));
+        std::fs::write(&path, b"GIF89a").unwrap();
+        let result = favicon_from_png(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn legacy_ping_detection() {
+        let mut ping = vec![0xFE, 0x01, 0xFA, 0x00, 0x0B];
+        ping.extend("MC|PingHost".encode_utf16().flat_map(u16::to_be_bytes));
+        ping.extend([0x00, 0x07]);
+
+        assert_eq!(is_legacy_ping(&[0xFE]), Some(true));
+        assert_eq!(is_legacy_ping(&[0xFE, 0x01]), Some(true));
+        assert_eq!(is_legacy_ping(&ping), Some(true));
+        assert_eq!(is_legacy_ping(&ping[..10]), None);
+
+        // Modern handshakes with a length of 254 start with 0xFE 0x01
+        assert_eq!(is_legacy_ping(&[0xFE, 0x01, 0x00, 0xF8, 0x05]), Some(false));
+        assert_eq!(is_legacy_ping(&[0xFE, 0x01, 0xFA, 0x01]), Some(false));
+        assert_eq!(is_legacy_ping(&[0x10, 0x00]), Some(false));
+    }
+
+    #[test]
+    fn legacy() {
+        let response: String = StatusBuilder::new()
+            .players(20, 3)
+            .description("Hello world".into())
+            .into();
+        let bytes = legacy_response(&response).unwrap();
+
+        let expected = "§1\0760\01.19.2\0Hello world\03\020";
+        let expected_utf16: Vec<u16> = expected.encode_utf16().collect();
+        assert_eq!(bytes[0], 0xFF);
+        assert_eq!(
+            u16::from_be_bytes([bytes[1], bytes[2]]) as usize,
+            expected_utf16.len()
+        );
+
+        let string: Vec<u16> = bytes[3..]
+            .chunks_exact(2)
+            .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+            .collect();
+        assert_eq!(String::from_utf16(&string).unwrap(), expected);
+
+        // Formatting is removed from the description
+        let response = r#"{"version": {"name": "1.19.2", "protocol": 760},
+            "players": {"max": 1, "online": 0},
+            "description": {"text": "Hello ", "bold": true, "extra": [{"text": "world"}, "!"]}}"#;
+        let bytes = legacy_response(response).unwrap();
+        let string: Vec<u16> = bytes[3..]
+            .chunks_exact(2)
+            .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+            .collect();
+        assert_eq!(
+            String::from_utf16(&string).unwrap(),
+            "§1\0760\01.19.2\0Hello world!\00\01"
+        );
+    }
+}
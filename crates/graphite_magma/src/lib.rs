@@ -11,12 +11,29 @@ use bytes::BufMut;
 
 use graphite_server::world::chunk::Chunk;
 use graphite_server::world::chunk_section::ChunkSection;
+use graphite_server::world::paletted_container::ArrayContainer;
 use graphite_server::world::paletted_container::BiomePalettedContainer;
 use graphite_server::world::paletted_container::BlockPalettedContainer;
 use graphite_server::world::{chunk_list::ChunkGrid, paletted_container::PalettedContainer};
+use std::collections::HashMap;
+use std::io::Read;
+use std::ops::RangeInclusive;
+
 use thiserror::Error;
 
-const MAGIC: u16 = 0x8C73;
+const MAGIC: u16 = 0x8C74;
+/// Version 1 files didn't have a format version, so they used a different magic
+const MAGIC_V1: u16 = 0x8C73;
+/// Version of the layout of magma files, independent of the Minecraft data version
+const FORMAT_VERSION: u32 = 2;
+
+/// Compressed files start with this magic instead, followed by the compression version
+/// and a zstd frame containing the uncompressed file
+const COMPRESSED_MAGIC: u16 = 0x8C7A;
+const COMPRESSION_VERSION: u32 = 1;
+/// Compressed files that decompress to more than this many bytes are rejected by default,
+/// see `MagmaReadOptions::max_decompressed_size`
+pub const DEFAULT_MAX_DECOMPRESSED_SIZE: usize = 256 * 1024 * 1024;
 const DATA_VERSION: u32 = 760;
 
 #[derive(Debug, Error)]
@@ -24,15 +41,34 @@ pub enum MagmaEncodeError {
     #[error("the reported dimensions were incorrect")]
     WrongSize,
     #[error("the block counts of an array palette did not sum to 4096")]
-    InvalidPaletteCount
+    InvalidPaletteCount,
+    #[error("the biome counts of an array palette did not sum to 64")]
+    InvalidBiomePaletteCount,
+    #[error("failed to compress: {0}")]
+    CompressionFailed(#[from] std::io::Error)
+}
+
+/// Options for writing magma files, see `to_magma_with_options`
+#[derive(Debug, Clone, Default)]
+pub struct MagmaWriteOptions {
+    /// Leaves out unused entries of array palettes instead of writing placeholders for them,
+    /// which makes files of heavily edited worlds smaller but is slower to write
+    pub defragment_palettes: bool,
 }
 
 pub fn to_magma(chunks: &ChunkGrid, custom_data: u32) -> Result<Vec<u8>, MagmaEncodeError> {
+    to_magma_with_options(chunks, custom_data, &MagmaWriteOptions::default())
+}
+
+pub fn to_magma_with_options(chunks: &ChunkGrid, custom_data: u32, options: &MagmaWriteOptions) -> Result<Vec<u8>, MagmaEncodeError> {
     let mut out = Vec::new();
 
     // Magic
     out.put_u16(MAGIC);
 
+    // Format Version
+    varint::encode::extend_i32(&mut out, FORMAT_VERSION as i32);
+
     // Dimensions
     let (size_x, size_y, size_z) = chunks.dimensions();
     varint::encode::extend_i32(&mut out, size_x as i32);
@@ -53,31 +89,153 @@ pub fn to_magma(chunks: &ChunkGrid, custom_data: u32) -> Result<Vec<u8>, MagmaEn
         }
         
         for section in sections {
-            let _flag_index = out.len();
+            // The lower 2 bits of the flags are for blocks, the next 2 bits are for biomes
+            let flag_index = out.len();
 
-            write_block_palette(section.get_block_palette(), section.get_non_air_count(), &mut out)?;
+            write_block_palette(section.get_block_palette(), section.get_non_air_count(), options, &mut out)?;
 
-            // todo: biomes
+            let biome_flags = write_biome_palette(section.get_biome_palette(), options, &mut out)?;
+            out[flag_index] |= biome_flags << 2;
         }
     }
 
     Ok(out)
 }
 
-pub fn from_magma(mut bytes: &[u8]) -> anyhow::Result<(ChunkGrid, u32)> {
+/// Same as `to_magma`, but compressed using zstd. Use `from_magma_compressed` to read the output
+pub fn to_magma_compressed(chunks: &ChunkGrid, custom_data: u32) -> Result<Vec<u8>, MagmaEncodeError> {
+    let uncompressed = to_magma(chunks, custom_data)?;
+
+    let mut out = Vec::new();
+    out.put_u16(COMPRESSED_MAGIC);
+    varint::encode::extend_i32(&mut out, COMPRESSION_VERSION as i32);
+    zstd::stream::copy_encode(uncompressed.as_slice(), &mut out, zstd::DEFAULT_COMPRESSION_LEVEL)?;
+
+    Ok(out)
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum MagmaDecodeError {
+    #[error("magma format version {format_version} is not supported, expected version {supported}")]
+    UnsupportedFormatVersion {
+        format_version: u32,
+        supported: u32,
+    },
+    #[error("data version {data_version} is not supported, accepted versions are {accepted:?}")]
+    UnsupportedDataVersion {
+        data_version: u32,
+        accepted: RangeInclusive<u32>,
+    },
+}
+
+/// Options for reading magma files, see `from_magma_with_options`
+#[derive(Debug, Clone)]
+pub struct MagmaReadOptions {
+    /// Data versions that can be read, other versions fail with `MagmaDecodeError::UnsupportedDataVersion`
+    pub accepted_data_versions: RangeInclusive<u32>,
+    /// Block id remapping tables for files written with other data versions, indexed by the block id in the file.
+    /// Block ids that aren't in the table, or versions without a table, are read as is
+    pub block_remaps: HashMap<u32, Vec<u16>>,
+    /// Compressed files that decompress to more than this many bytes fail to be read, preventing
+    /// small malicious files from using up all memory
+    pub max_decompressed_size: usize,
+}
+
+impl Default for MagmaReadOptions {
+    fn default() -> Self {
+        Self {
+            accepted_data_versions: DATA_VERSION..=DATA_VERSION,
+            block_remaps: HashMap::new(),
+            max_decompressed_size: DEFAULT_MAX_DECOMPRESSED_SIZE,
+        }
+    }
+}
+
+/// Converts a file written with an older format version to the current format version,
+/// files using the current format version are returned unchanged
+pub fn upgrade_magma(bytes: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let mut remaining = bytes;
+    let magic: u16 = BigEndian::read(&mut remaining)?;
+    match magic {
+        MAGIC_V1 => {
+            // Version 2 added the format version after the magic, sections are unchanged
+            let mut out = Vec::with_capacity(bytes.len() + 1);
+            out.put_u16(MAGIC);
+            varint::encode::extend_i32(&mut out, FORMAT_VERSION as i32);
+            out.put_slice(remaining);
+            Ok(out)
+        }
+        MAGIC => Ok(bytes.to_vec()),
+        _ => bail!("file is not a magma world format"),
+    }
+}
+
+pub fn from_magma(bytes: &[u8]) -> anyhow::Result<(ChunkGrid, u32)> {
+    from_magma_with_options(bytes, &MagmaReadOptions::default())
+}
+
+/// Reads files written by `to_magma_compressed`, uncompressed files are also accepted
+pub fn from_magma_compressed(bytes: &[u8]) -> anyhow::Result<(ChunkGrid, u32)> {
+    from_magma_compressed_with_options(bytes, &MagmaReadOptions::default())
+}
+
+/// Same as `from_magma_compressed`, see `from_magma_with_options`
+pub fn from_magma_compressed_with_options(bytes: &[u8], options: &MagmaReadOptions) -> anyhow::Result<(ChunkGrid, u32)> {
+    let mut remaining = bytes;
+    let magic: u16 = BigEndian::read(&mut remaining)?;
+    if magic != COMPRESSED_MAGIC {
+        return from_magma_with_options(bytes, options);
+    }
+
+    let compression_version: u32 = VarInt::read(&mut remaining)?;
+    if compression_version != COMPRESSION_VERSION {
+        bail!("magma compression version {} is not supported", compression_version);
+    }
+
+    // Reading one byte more than the limit tells apart files that are exactly the maximum size
+    let max_size = options.max_decompressed_size;
+    let mut uncompressed = Vec::new();
+    zstd::stream::read::Decoder::new(remaining)?
+        .take(max_size as u64 + 1)
+        .read_to_end(&mut uncompressed)?;
+    if uncompressed.len() > max_size {
+        bail!("decompressed magma file exceeds {} bytes", max_size);
+    }
+
+    from_magma_with_options(&uncompressed, options)
+}
+
+pub fn from_magma_with_options(mut bytes: &[u8], options: &MagmaReadOptions) -> anyhow::Result<(ChunkGrid, u32)> {
     let magic: u16 = BigEndian::read(&mut bytes)?;
-    if magic != MAGIC {
+    if magic == MAGIC_V1 {
+        return Err(MagmaDecodeError::UnsupportedFormatVersion {
+            format_version: 1,
+            supported: FORMAT_VERSION,
+        }.into());
+    } else if magic != MAGIC {
         bail!("file is not a magma world format");
     }
 
+    let format_version: u32 = VarInt::read(&mut bytes)?;
+    if format_version != FORMAT_VERSION {
+        return Err(MagmaDecodeError::UnsupportedFormatVersion {
+            format_version,
+            supported: FORMAT_VERSION,
+        }.into());
+    }
+
     let size_x: usize = VarInt::read(&mut bytes)?;
     let size_y: usize = VarInt::read(&mut bytes)?;
     let size_z: usize = VarInt::read(&mut bytes)?;
 
     let data_version: u32 = VarInt::read(&mut bytes)?;
-    if data_version != DATA_VERSION {
-        bail!("wrong data version");
+    if !options.accepted_data_versions.contains(&data_version) {
+        return Err(MagmaDecodeError::UnsupportedDataVersion {
+            data_version,
+            accepted: options.accepted_data_versions.clone(),
+        }.into());
     }
+    let block_remap = options.block_remaps.get(&data_version).map(Vec::as_slice);
 
     let custom_data: i32 = VarInt::read(&mut bytes)?;
     let custom_data = custom_data as u32;
@@ -90,7 +248,7 @@ pub fn from_magma(mut bytes: &[u8]) -> anyhow::Result<(ChunkGrid, u32)> {
                 let flags: u8 = Single::read(&mut bytes)?;
                 
                 let block_flags = flags & 0b11;
-                let (non_air_blocks, block_palette) = match block_flags {
+                let (mut non_air_blocks, mut block_palette) = match block_flags {
                      0 => {
                         (0, BlockPalettedContainer::filled(0))
                      }
@@ -100,7 +258,7 @@ pub fn from_magma(mut bytes: &[u8]) -> anyhow::Result<(ChunkGrid, u32)> {
                         (non_air_blocks, BlockPalettedContainer::filled(v))
                      }
                      2 => {
-                        let non_air_count: u16 = LittleEndian::read(&mut bytes)?;
+                        let non_air_count = read_non_air_count(&mut bytes)?;
 
                         let palette_size: u8 = Single::read(&mut bytes)?;
 
@@ -115,25 +273,20 @@ pub fn from_magma(mut bytes: &[u8]) -> anyhow::Result<(ChunkGrid, u32)> {
                             palette.push((block, count)).unwrap();
                         }
 
-                        if bytes.len() < 2048 {
-                            bail!("not enough bytes");
-                        }
-
-                        let (contents, remaining) = bytes.split_at(2048);
-                        bytes = remaining;
+                        let contents = take_bytes(&mut bytes, 2048)?;
+                        validate_array_palette(&palette, contents, "block", 16*16*16)?;
 
-                        // todo: validate requirements of BlockPalettedContainer::array
+                        // Safety: the requirements of BlockPalettedContainer::array were checked above
                         unsafe {
                             (non_air_count, BlockPalettedContainer::array(palette, contents.try_into().unwrap()))
                         }
                      }
                      3 => {
-                        let non_air_count: u16 = LittleEndian::read(&mut bytes)?;
+                        let non_air_count = read_non_air_count(&mut bytes)?;
 
                         let mut contents = [0_u64; 1024];
 
-                        let (byte_contents, remaining) = bytes.split_at(8192);
-                        bytes = remaining;
+                        let byte_contents = take_bytes(&mut bytes, 8192)?;
 
                         byteorder::LittleEndian::read_u64_into(byte_contents, &mut contents);
 
@@ -142,8 +295,14 @@ pub fn from_magma(mut bytes: &[u8]) -> anyhow::Result<(ChunkGrid, u32)> {
                      _ => unreachable!()
                 };
 
-                let chunk_section = ChunkSection::new(non_air_blocks, block_palette, 
-                    BiomePalettedContainer::filled(0));
+                if let Some(block_remap) = block_remap {
+                    non_air_blocks = remap_blocks(&mut block_palette, block_remap);
+                }
+
+                let biome_flags = (flags >> 2) & 0b11;
+                let biome_palette = read_biome_palette(biome_flags, &mut bytes)?;
+
+                let chunk_section = ChunkSection::new(non_air_blocks, block_palette, biome_palette);
                 sections.push(chunk_section);
             }
             chunks.push(Chunk::new(sections));
@@ -154,7 +313,153 @@ pub fn from_magma(mut bytes: &[u8]) -> anyhow::Result<(ChunkGrid, u32)> {
     Ok((chunk_list, custom_data))
 }
 
-fn write_block_palette(palette: &BlockPalettedContainer, non_air_count: u16, out: &mut Vec<u8>) -> Result<(), MagmaEncodeError> {
+fn read_biome_palette(biome_flags: u8, bytes: &mut &[u8]) -> anyhow::Result<BiomePalettedContainer> {
+    match biome_flags {
+        0 => Ok(BiomePalettedContainer::filled(0)),
+        1 => Ok(BiomePalettedContainer::filled(read_biome(bytes)?)),
+        2 => {
+            let palette_size: u8 = Single::read(bytes)?;
+
+            if palette_size > 16 {
+                bail!("palette size exceeds maximum")
+            }
+
+            let mut palette: heapless::Vec<(u8, usize), 16> = heapless::Vec::new();
+            for _ in 0..palette_size {
+                let biome = read_biome(bytes)?;
+                let count: usize = VarInt::read(bytes)?;
+                palette.push((biome, count)).unwrap();
+            }
+
+            let contents = take_bytes(bytes, 32)?;
+            validate_array_palette(&palette, contents, "biome", 4*4*4)?;
+
+            // Safety: the requirements of BiomePalettedContainer::array were checked above
+            unsafe {
+                Ok(BiomePalettedContainer::array(palette, contents.try_into().unwrap()))
+            }
+        }
+        3 => {
+            let mut contents = [0_u64; 4];
+
+            let byte_contents = take_bytes(bytes, 32)?;
+
+            byteorder::LittleEndian::read_u64_into(byte_contents, &mut contents);
+
+            Ok(BiomePalettedContainer::direct(contents))
+        }
+        _ => unreachable!()
+    }
+}
+
+fn read_biome(bytes: &mut &[u8]) -> anyhow::Result<u8> {
+    let biome: u16 = VarInt::read(bytes)?;
+    biome.try_into().map_err(|_| anyhow::anyhow!("biome id {} exceeds maximum", biome))
+}
+
+/// Replaces every block in the palette using the remap table, returns the new non-air block count
+fn remap_blocks(palette: &mut BlockPalettedContainer, block_remap: &[u16]) -> u16 {
+    let remap = |block: u16| block_remap.get(block as usize).copied().unwrap_or(block);
+
+    match palette {
+        PalettedContainer::Single(v) => {
+            *v = remap(*v);
+            if *v == 0 { 0 } else { 16*16*16 }
+        },
+        PalettedContainer::Array(array) => {
+            // Blocks that are remapped to the same block are merged into one palette entry
+            let mut indices: heapless::Vec<(u16, usize), 16> = heapless::Vec::new();
+            let mut new_index = [0_u8; 16];
+            for (index, (block, count)) in array.indices.iter().enumerate() {
+                let block = remap(*block);
+                if let Some(existing) = indices.iter().position(|(value, _)| *value == block) {
+                    indices[existing].1 += *count;
+                    new_index[index] = existing as u8;
+                } else {
+                    new_index[index] = indices.len() as u8;
+                    indices.push((block, *count)).unwrap();
+                }
+            }
+
+            if indices.len() < array.indices.len() {
+                for byte in array.contents.iter_mut() {
+                    *byte = new_index[(*byte & 0xF) as usize] | (new_index[(*byte >> 4) as usize] << 4);
+                }
+            }
+            array.indices = indices;
+
+            let mut non_air_count = 0;
+            for (block, count) in array.indices.iter() {
+                if *block != 0 {
+                    non_air_count += *count as u16;
+                }
+            }
+            non_air_count
+        },
+        PalettedContainer::Direct(_) => {
+            let mut non_air_count = 0;
+            for y in 0..16 {
+                for z in 0..16 {
+                    for x in 0..16 {
+                        let block = remap(palette.get(x, y, z));
+                        palette.set(x, y, z, block);
+                        if block != 0 {
+                            non_air_count += 1;
+                        }
+                    }
+                }
+            }
+            non_air_count
+        },
+    }
+}
+
+/// Splits `len` bytes off the front of `bytes`, erroring instead of panicking if the file is truncated
+fn take_bytes<'a>(bytes: &mut &'a [u8], len: usize) -> anyhow::Result<&'a [u8]> {
+    if bytes.len() < len {
+        bail!("not enough bytes: expected {}, got {}", len, bytes.len());
+    }
+
+    let (taken, remaining) = bytes.split_at(len);
+    *bytes = remaining;
+    Ok(taken)
+}
+
+fn read_non_air_count(bytes: &mut &[u8]) -> anyhow::Result<u16> {
+    let non_air_count: u16 = LittleEndian::read(bytes)?;
+    if non_air_count > 16*16*16 {
+        bail!("non-air block count {} exceeds section size", non_air_count);
+    }
+    Ok(non_air_count)
+}
+
+/// Checks the invariants of an array palette, see `PalettedContainer::array`
+fn validate_array_palette<T>(palette: &[(T, usize)], contents: &[u8], kind: &str, capacity: usize) -> anyhow::Result<()> {
+    let mut total_count: usize = 0;
+    for (_, count) in palette {
+        total_count = total_count.saturating_add(*count);
+    }
+    if total_count != capacity {
+        bail!("palette {} counts sum to {}, expected {}", kind, total_count, capacity);
+    }
+
+    let mut counts = [0_usize; 16];
+    for byte in contents {
+        counts[(byte & 0xF) as usize] += 1;
+        counts[(byte >> 4) as usize] += 1;
+    }
+    for (index, count) in counts.iter().enumerate() {
+        let expected = palette.get(index).map(|(_, count)| *count).unwrap_or(0);
+        if *count != expected {
+            bail!("palette index {} is referenced {} times, expected {}", index, count, expected);
+        }
+    }
+
+    Ok(())
+}
+
+fn write_block_palette(palette: &BlockPalettedContainer, non_air_count: u16, options: &MagmaWriteOptions,
+        out: &mut Vec<u8>) -> Result<(), MagmaEncodeError> {
     match palette {
         PalettedContainer::Single(v) => {
             if *v == 0 {
@@ -168,33 +473,9 @@ fn write_block_palette(palette: &BlockPalettedContainer, non_air_count: u16, out
             out.push(2);
             out.put_u16_le(non_air_count);
 
-            let mut total_count = 0;
-            let mut end = 0;
-            for (index, (_, count)) in array.indices.iter().enumerate() {
-                if *count > 0 {
-                    end = index + 1;
-                    total_count += *count;
-                }
-            }
-
-            if end == 0 || total_count != 4096 {
+            if !write_array_palette(array, 16*16*16, options, out) {
                 return Err(MagmaEncodeError::InvalidPaletteCount)
             }
-
-            out.push(end as u8);
-            for index in 0..end {
-                let (block, count) = array.indices[index];
-                if count > 0 {
-                    varint::encode::extend_i32(out, block as i32);
-                    varint::encode::extend_i32(out, count as i32);
-                } else {
-                    // We could "defragment" the palette to avoid this, but it isn't worth it
-                    out.push(0);
-                    out.push(0);
-                }
-            }
-
-            out.put_slice(&array.contents);
         },
         PalettedContainer::Direct(direct) => {
             out.push(3);
@@ -209,4 +490,420 @@ fn write_block_palette(palette: &BlockPalettedContainer, non_air_count: u16, out
         },
     }
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Writes the biomes, returns the flags which the caller puts in the flag byte of the section
+fn write_biome_palette(palette: &BiomePalettedContainer, options: &MagmaWriteOptions,
+        out: &mut Vec<u8>) -> Result<u8, MagmaEncodeError> {
+    match palette {
+        PalettedContainer::Single(v) => {
+            if *v == 0 {
+                Ok(0)
+            } else {
+                varint::encode::extend_i32(out, *v as i32);
+                Ok(1)
+            }
+        },
+        PalettedContainer::Array(array) => {
+            if !write_array_palette(array, 4*4*4, options, out) {
+                return Err(MagmaEncodeError::InvalidBiomePaletteCount)
+            }
+            Ok(2)
+        },
+        PalettedContainer::Direct(direct) => {
+            let direct_index = out.len();
+            out.resize(out.len() + direct.contents.len()*8, 0);
+            let dst = &mut out[direct_index..];
+
+            byteorder::LittleEndian::write_u64_into(direct.contents.as_slice(), dst);
+            Ok(3)
+        },
+    }
+}
+
+/// Writes the palette and contents of an array palette, returns false if the counts don't sum to `capacity`
+fn write_array_palette<T: Copy + Into<i32>, const HALF_CAP: usize>(array: &ArrayContainer<T, HALF_CAP>,
+        capacity: usize, options: &MagmaWriteOptions, out: &mut Vec<u8>) -> bool {
+    let mut total_count = 0;
+    let mut end = 0;
+    for (index, (_, count)) in array.indices.iter().enumerate() {
+        if *count > 0 {
+            end = index + 1;
+            total_count += *count;
+        }
+    }
+
+    if end == 0 || total_count != capacity {
+        return false;
+    }
+
+    if options.defragment_palettes {
+        // Only write the entries that are used, moving them to the front of the palette
+        let mut remap = [0_u8; 16];
+        let mut used = 0;
+        for (index, (_, count)) in array.indices.iter().enumerate() {
+            if *count > 0 {
+                remap[index] = used;
+                used += 1;
+            }
+        }
+
+        out.push(used);
+        for (value, count) in &array.indices {
+            if *count > 0 {
+                varint::encode::extend_i32(out, (*value).into());
+                varint::encode::extend_i32(out, *count as i32);
+            }
+        }
+
+        // Each byte contains two 4-bit palette indices
+        out.extend(array.contents.iter().map(|byte| {
+            remap[(byte & 0xF) as usize] | (remap[(byte >> 4) as usize] << 4)
+        }));
+        return true;
+    }
+
+    out.push(end as u8);
+    for index in 0..end {
+        let (value, count) = array.indices[index];
+        if count > 0 {
+            varint::encode::extend_i32(out, value.into());
+            varint::encode::extend_i32(out, count as i32);
+        } else {
+            // Unused entry, see `MagmaWriteOptions::defragment_palettes`
+            out.push(0);
+            out.push(0);
+        }
+    }
+
+    out.put_slice(&array.contents);
+    true
+}
+#[cfg(test)]
+mod tests {
+    use bytes::BufMut;
+    use graphite_binary::varint;
+    use graphite_server::world::{
+        chunk::{BlockStorage, Chunk},
+        chunk_list::ChunkGrid,
+        chunk_section::ChunkSection,
+        paletted_container::{BiomePalettedContainer, BlockPalettedContainer, PalettedContainer},
+    };
+
+    use crate::{
+        from_magma, from_magma_compressed, from_magma_compressed_with_options,
+        from_magma_with_options, to_magma, to_magma_compressed, to_magma_with_options,
+        upgrade_magma, MagmaDecodeError, MagmaReadOptions, MagmaWriteOptions,
+        COMPRESSED_MAGIC, COMPRESSION_VERSION, DATA_VERSION, FORMAT_VERSION, MAGIC, MAGIC_V1,
+    };
+
+    fn write_header(out: &mut Vec<u8>, data_version: u32) {
+        out.put_u16(MAGIC);
+        varint::encode::extend_i32(out, FORMAT_VERSION as i32);
+        varint::encode::extend_i32(out, 1);
+        varint::encode::extend_i32(out, 1);
+        varint::encode::extend_i32(out, 1);
+        varint::encode::extend_i32(out, data_version as i32);
+        varint::encode::extend_i32(out, 0);
+    }
+
+    #[test]
+    fn truncated_direct_section() {
+        let mut bytes = Vec::new();
+        write_header(&mut bytes, DATA_VERSION);
+
+        // Direct section with only 100 of the 8192 content bytes
+        bytes.push(3);
+        bytes.put_u16_le(4096);
+        bytes.resize(bytes.len() + 100, 0);
+
+        let error = from_magma(&bytes).err().unwrap();
+        assert_eq!(error.to_string(), "not enough bytes: expected 8192, got 100");
+    }
+
+    #[test]
+    fn invalid_array_section() {
+        let mut bytes = Vec::new();
+        write_header(&mut bytes, DATA_VERSION);
+
+        // Array section where the palette claims more blocks than the section contains
+        bytes.push(2);
+        bytes.put_u16_le(4096);
+        bytes.push(1);
+        varint::encode::extend_i32(&mut bytes, 1);
+        varint::encode::extend_i32(&mut bytes, 5000);
+        bytes.resize(bytes.len() + 2048, 0);
+
+        let error = from_magma(&bytes).err().unwrap();
+        assert_eq!(error.to_string(), "palette block counts sum to 5000, expected 4096");
+    }
+
+    #[test]
+    fn array_section_round_trip() {
+        let mut chunk = Chunk::new_empty(1);
+        chunk.set_block(0, 0, 0, 1);
+        chunk.set_block(3, 7, 5, 2);
+
+        let bytes = to_magma(&ChunkGrid::new(vec![chunk], 1, 1, 1), 0).unwrap();
+        let (chunks, _) = from_magma(&bytes).unwrap();
+
+        let (_, _, chunk) = chunks.enumerate().next().unwrap();
+        assert_eq!(chunk.get_block(0, 0, 0), Some(1));
+        assert_eq!(chunk.get_block(3, 7, 5), Some(2));
+        assert_eq!(chunk.get_block(1, 0, 0), Some(0));
+    }
+
+    #[test]
+    fn defragment_sparse_palette() {
+        // Fill the palette, then remove most of the blocks again
+        let mut chunk = Chunk::new_empty(1);
+        for block in 1..=10 {
+            chunk.set_block(block as usize, 0, 0, block);
+        }
+        for block in 1..=8 {
+            chunk.set_block(block as usize, 0, 0, 0);
+        }
+        let chunks = ChunkGrid::new(vec![chunk], 1, 1, 1);
+
+        let fragmented = to_magma(&chunks, 0).unwrap();
+        let options = MagmaWriteOptions {
+            defragment_palettes: true,
+        };
+        let defragmented = to_magma_with_options(&chunks, 0, &options).unwrap();
+
+        // Each of the 8 unused entries was written as 2 placeholder bytes
+        assert_eq!(defragmented.len(), fragmented.len() - 16);
+
+        let (fragmented, _) = from_magma(&fragmented).unwrap();
+        let (defragmented, _) = from_magma(&defragmented).unwrap();
+        let (_, _, fragmented) = fragmented.enumerate().next().unwrap();
+        let (_, _, defragmented) = defragmented.enumerate().next().unwrap();
+        for y in 0..16 {
+            for z in 0..16 {
+                for x in 0..16 {
+                    assert_eq!(defragmented.get_block(x, y, z), fragmented.get_block(x, y, z));
+                }
+            }
+        }
+        assert_eq!(defragmented.get_block(9, 0, 0), Some(9));
+        assert_eq!(defragmented.get_block(10, 0, 0), Some(10));
+        assert_eq!(
+            defragmented.get_block_sections()[0].get_non_air_count(),
+            fragmented.get_block_sections()[0].get_non_air_count()
+        );
+    }
+
+    #[test]
+    fn compressed_round_trip() {
+        let mut chunks = Vec::new();
+        for x in 0..4 {
+            let mut chunk = Chunk::new_default(4);
+            chunk.set_block(x, 20, 3, 2);
+            chunk.set_block(7, 40, x, 5);
+            chunks.push(chunk);
+        }
+        let chunks = ChunkGrid::new(chunks, 2, 4, 2);
+
+        let uncompressed = to_magma(&chunks, 7).unwrap();
+        let compressed = to_magma_compressed(&chunks, 7).unwrap();
+        assert!(compressed.len() < uncompressed.len());
+
+        // Decodes to the same chunks, which encode to the same bytes
+        let (decoded, custom_data) = from_magma_compressed(&compressed).unwrap();
+        assert_eq!(custom_data, 7);
+        assert_eq!(decoded.dimensions(), chunks.dimensions());
+        assert_eq!(to_magma(&decoded, 7).unwrap(), uncompressed);
+
+        // Uncompressed files are detected and read as is
+        let (decoded, _) = from_magma_compressed(&uncompressed).unwrap();
+        assert_eq!(to_magma(&decoded, 7).unwrap(), uncompressed);
+    }
+
+    #[test]
+    fn compressed_max_size() {
+        let chunks = ChunkGrid::new(vec![Chunk::new_default(4)], 1, 4, 1);
+        let uncompressed = to_magma(&chunks, 0).unwrap();
+        let compressed = to_magma_compressed(&chunks, 0).unwrap();
+
+        // Files up to the maximum size are accepted
+        let options = MagmaReadOptions {
+            max_decompressed_size: uncompressed.len(),
+            ..Default::default()
+        };
+        assert!(from_magma_compressed_with_options(&compressed, &options).is_ok());
+
+        // Larger files are rejected
+        let options = MagmaReadOptions {
+            max_decompressed_size: uncompressed.len() - 1,
+            ..Default::default()
+        };
+        let error = from_magma_compressed_with_options(&compressed, &options).err().unwrap();
+        assert!(error.to_string().contains("exceeds"));
+    }
+
+    #[test]
+    fn biome_round_trip() {
+        let mut array = BiomePalettedContainer::filled(1);
+        array.set(0, 0, 0, 2);
+        array.set(2, 1, 1, 5);
+        assert!(matches!(array, PalettedContainer::Array(_)));
+
+        let biome_palettes = [
+            BiomePalettedContainer::filled(0),
+            BiomePalettedContainer::filled(3),
+            array,
+            BiomePalettedContainer::direct([0x0123456789abcdef, 0, 0, u64::MAX]),
+        ];
+
+        let sections = biome_palettes.iter().map(|biome_palette| {
+            ChunkSection::new(0, BlockPalettedContainer::filled(0), biome_palette.clone())
+        }).collect();
+        let bytes = to_magma(&ChunkGrid::new(vec![Chunk::new(sections)], 1, 4, 1), 0).unwrap();
+        let (chunks, _) = from_magma(&bytes).unwrap();
+
+        let (_, _, chunk) = chunks.enumerate().next().unwrap();
+        let sections = chunk.get_block_sections();
+        for (section, expected) in sections.iter().zip(&biome_palettes) {
+            let biome_palette = section.get_biome_palette();
+            assert_eq!(
+                std::mem::discriminant(biome_palette),
+                std::mem::discriminant(expected)
+            );
+            for (x, y, z) in [(0, 0, 0), (2, 1, 1), (1, 0, 0), (3, 0, 3)] {
+                assert_eq!(biome_palette.get(x, y, z), expected.get(x, y, z));
+            }
+        }
+        assert_eq!(sections[1].get_biome_palette().get(3, 3, 3), 3);
+        assert_eq!(sections[2].get_biome_palette().get(2, 1, 1), 5);
+    }
+
+    #[test]
+    fn unsupported_format_version() {
+        // Version 1 files have a different magic and no format version
+        let mut bytes = Vec::new();
+        bytes.put_u16(MAGIC_V1);
+        varint::encode::extend_i32(&mut bytes, 1);
+        varint::encode::extend_i32(&mut bytes, 1);
+        varint::encode::extend_i32(&mut bytes, 1);
+        varint::encode::extend_i32(&mut bytes, DATA_VERSION as i32);
+        varint::encode::extend_i32(&mut bytes, 1);
+        bytes.push(0);
+
+        let error = from_magma(&bytes).err().unwrap();
+        assert_eq!(
+            error.downcast_ref::<MagmaDecodeError>(),
+            Some(&MagmaDecodeError::UnsupportedFormatVersion {
+                format_version: 1,
+                supported: FORMAT_VERSION
+            })
+        );
+
+        // Which can be upgraded to the current format version
+        let upgraded = upgrade_magma(&bytes).unwrap();
+        let (chunks, custom_data) = from_magma(&upgraded).unwrap();
+        assert_eq!(chunks.dimensions(), (1, 1, 1));
+        assert_eq!(custom_data, 1);
+        assert_eq!(upgrade_magma(&upgraded).unwrap(), upgraded);
+
+        // Files from a newer version
+        let mut bytes = Vec::new();
+        bytes.put_u16(MAGIC);
+        varint::encode::extend_i32(&mut bytes, FORMAT_VERSION as i32 + 1);
+
+        let error = from_magma(&bytes).err().unwrap();
+        assert_eq!(
+            error.to_string(),
+            format!("magma format version {} is not supported, expected version 2", FORMAT_VERSION + 1)
+        );
+    }
+
+    #[test]
+    fn remap_older_data_version() {
+        let mut bytes = Vec::new();
+        write_header(&mut bytes, DATA_VERSION - 1);
+
+        // Single section filled with block 2
+        bytes.push(1);
+        varint::encode::extend_i32(&mut bytes, 2);
+
+        // Rejected by default
+        let error = from_magma(&bytes).err().unwrap();
+        assert_eq!(
+            error.downcast_ref::<MagmaDecodeError>(),
+            Some(&MagmaDecodeError::UnsupportedDataVersion {
+                data_version: DATA_VERSION - 1,
+                accepted: DATA_VERSION..=DATA_VERSION
+            })
+        );
+
+        // Accepted with block 2 remapped to block 5
+        let options = MagmaReadOptions {
+            accepted_data_versions: DATA_VERSION - 1..=DATA_VERSION,
+            block_remaps: [(DATA_VERSION - 1, vec![0, 1, 5])].into(),
+            ..Default::default()
+        };
+        let (chunks, _) = from_magma_with_options(&bytes, &options).unwrap();
+
+        let (_, _, chunk) = chunks.enumerate().next().unwrap();
+        assert_eq!(chunk.get_block(8, 8, 8), Some(5));
+        assert_eq!(chunk.get_block_sections()[0].get_non_air_count(), 4096);
+
+        // Compressed files use the same options
+        let mut compressed = Vec::new();
+        compressed.put_u16(COMPRESSED_MAGIC);
+        varint::encode::extend_i32(&mut compressed, COMPRESSION_VERSION as i32);
+        compressed.extend(zstd::stream::encode_all(bytes.as_slice(), 0).unwrap());
+        assert!(from_magma_compressed(&compressed).is_err());
+
+        let (chunks, _) = from_magma_compressed_with_options(&compressed, &options).unwrap();
+        let (_, _, chunk) = chunks.enumerate().next().unwrap();
+        assert_eq!(chunk.get_block(8, 8, 8), Some(5));
+    }
+
+    #[test]
+    fn remap_merges_palette_entries() {
+        let mut bytes = Vec::new();
+        write_header(&mut bytes, DATA_VERSION - 1);
+
+        // Array section with blocks 1, 2 and 3
+        bytes.push(2);
+        bytes.put_u16_le(4096);
+        bytes.push(3);
+        for (block, count) in [(1, 4093), (2, 1), (3, 2)] {
+            varint::encode::extend_i32(&mut bytes, block);
+            varint::encode::extend_i32(&mut bytes, count);
+        }
+        bytes.push(0x10);
+        bytes.push(0x22);
+        bytes.resize(bytes.len() + 2046, 0);
+
+        // Blocks 1 and 2 are both remapped to block 5
+        let options = MagmaReadOptions {
+            accepted_data_versions: DATA_VERSION - 1..=DATA_VERSION,
+            block_remaps: [(DATA_VERSION - 1, vec![0, 5, 5, 3])].into(),
+            ..Default::default()
+        };
+        let (chunks, _) = from_magma_with_options(&bytes, &options).unwrap();
+
+        let (_, _, chunk) = chunks.enumerate().next().unwrap();
+        let mut counts = [0; 6];
+        for y in 0..16 {
+            for z in 0..16 {
+                for x in 0..16 {
+                    counts[chunk.get_block(x, y, z).unwrap() as usize] += 1;
+                }
+            }
+        }
+        assert_eq!(counts, [0, 0, 0, 2, 0, 4094]);
+        assert_eq!(chunk.get_block_sections()[0].get_non_air_count(), 4096);
+
+        // The merged palette is written without duplicate entries
+        match chunk.get_block_sections()[0].get_block_palette() {
+            PalettedContainer::Array(array) => assert_eq!(array.indices.as_slice(), &[(5, 4094), (3, 2)]),
+            _ => panic!("expected an array palette"),
+        }
+        let (chunks, _) = from_magma(&to_magma(&chunks, 0).unwrap()).unwrap();
+        let (_, _, chunk) = chunks.enumerate().next().unwrap();
+        assert_eq!(chunk.get_block(0, 0, 0), Some(5));
+    }
+}
@@ -111,6 +111,7 @@ pub fn write_block_states() -> anyhow::Result<(
 
     let mut block_name_to_state_ids: BTreeMap<String, Vec<usize>> = BTreeMap::new();
     let mut block_name_to_id: BTreeMap<String, usize> = BTreeMap::new();
+    let mut block_name_to_definition: BTreeMap<String, String> = BTreeMap::new();
 
     let mut block_def = String::new();
     let mut u16_from_block_def = String::new();
@@ -151,6 +152,12 @@ pub fn write_block_states() -> anyhow::Result<(
 
         block_name_to_id.insert(block_name.clone(), block_id);
         block_id += 1;
+
+        // Block Definition (for string to state lookup)
+        block_name_to_definition.insert(
+            block_name.clone(),
+            get_block_state_definition(block_name, block, min_state_count)?,
+        );
     }
 
     let mut write_buffer = String::new();
@@ -177,6 +184,9 @@ pub fn write_block_states() -> anyhow::Result<(
     // Block Attributes
     write_u16_to_attributes(&mut write_buffer, state_count, state_attributes_lut)?;
 
+    // String to state + LUT
+    write_string_to_state(&mut write_buffer, block_name_to_definition)?;
+
     Ok((
         parameter_writer.get_placement_method_returns().clone(),
         parameter_writer.get_aliases().clone(),
@@ -199,6 +209,73 @@ fn write_state_to_item(write_buffer: &mut String, state_count: usize, item_lut:
     Ok(())
 }
 
+fn get_block_state_definition(block_name: &str, block: &Block, min_state: usize) -> anyhow::Result<String> {
+    let mut properties = String::new();
+
+    // States are ordered with the last property changing the fastest,
+    // so the default state is calculated starting from the last property
+    let mut default_offset = 0;
+    let mut stride = 1;
+    for (name, property) in block.properties.iter().rev() {
+        let (values, default_index) = match property {
+            Property::Int { values, default_value, .. } => (
+                values.iter().map(|v| v.to_string()).collect::<Vec<_>>(),
+                values.iter().position(|v| v == default_value),
+            ),
+            Property::Bool { default_value, .. } => (
+                vec!["true".into(), "false".into()],
+                Some(if *default_value { 0 } else { 1 }),
+            ),
+            Property::String { values, default_value, .. } => (
+                values.clone(),
+                values.iter().position(|v| v == default_value),
+            ),
+        };
+
+        let Some(default_index) = default_index else {
+            bail!("default value of {} for {} isn't a valid value", name, block_name);
+        };
+        default_offset += default_index * stride;
+        stride *= values.len();
+
+        let values: Vec<String> = values.iter().map(|v| format!("\"{}\"", v)).collect();
+        properties.insert_str(0, &format!("(\"{}\", &[{}]), ", name, values.join(", ")));
+    }
+
+    Ok(format!(
+        "\tBlockStateDefinition {{ name: \"{}\", min_state: {}, default_state: {}, properties: &[{}] }},\n",
+        block_name,
+        min_state,
+        min_state + default_offset,
+        properties.trim_end_matches(", ")
+    ))
+}
+
+fn write_string_to_state(write_buffer: &mut String, block_name_to_definition: BTreeMap<String, String>) -> Result<(), anyhow::Error> {
+    write_buffer.push_str("struct BlockStateDefinition {\n");
+    write_buffer.push_str("\tname: &'static str,\n");
+    write_buffer.push_str("\tmin_state: u16,\n");
+    write_buffer.push_str("\tdefault_state: u16,\n");
+    write_buffer.push_str("\tproperties: &'static [(&'static str, &'static [&'static str])],\n");
+    write_buffer.push_str("}\n\n");
+
+    // Sorted by name, so that a binary search can be used for lookup
+    writeln!(
+        write_buffer,
+        "const BLOCK_STATE_DEFINITIONS: [BlockStateDefinition; {}] = [",
+        block_name_to_definition.len()
+    )?;
+    for (_, definition) in block_name_to_definition {
+        write_buffer.push_str(&definition);
+    }
+    write_buffer.push_str("];");
+
+    let mut f = crate::file_out("string_to_state.rs");
+    f.write_all(write_buffer.as_bytes())?;
+    write_buffer.clear();
+    Ok(())
+}
+
 fn write_block_tags(block_name_to_state_ids: BTreeMap<String, Vec<usize>>, block_name_to_id: BTreeMap<String, usize>, write_buffer: &mut String) -> Result<(), anyhow::Error> {
     let tags_data = include_str!("../data/tags/block_tags.json");
     let block_tags: IndexMap<String, IndexMap<String, Vec<String>>> = serde_json::from_str(tags_data)?;
@@ -332,7 +409,8 @@ fn write_block_rs(write_buffer: &mut String, block_def: String) -> Result<(), an
     write_buffer.push_str("include!(concat!(env!(\"OUT_DIR\"), \"/u16_to_block.rs\"));\n");
     write_buffer.push_str("include!(concat!(env!(\"OUT_DIR\"), \"/u16_to_item.rs\"));\n");
     write_buffer
-        .push_str("include!(concat!(env!(\"OUT_DIR\"), \"/u16_to_block_attributes.rs\"));\n\n");
+        .push_str("include!(concat!(env!(\"OUT_DIR\"), \"/u16_to_block_attributes.rs\"));\n");
+    write_buffer.push_str("include!(concat!(env!(\"OUT_DIR\"), \"/string_to_state.rs\"));\n\n");
 
     // Item Lookup
     write_buffer.push_str("pub fn state_to_item(id: u16) -> Result<crate::item::Item, NoSuchBlockError> {\n");
@@ -341,6 +419,9 @@ fn write_block_rs(write_buffer: &mut String, block_def: String) -> Result<(), an
     write_buffer.push_str("\tOk(ITEM_LUT[id as usize])\n");
     write_buffer.push_str("}\n");
 
+    // String to State
+    write_buffer.push_str(BLOCKSTATE_FROM_STRING);
+
     // Block Attributes
     write_buffer.push_str("#[derive(Debug)]\n");
     write_buffer.push_str("pub struct BlockAttributes {\n");
@@ -373,6 +454,50 @@ fn write_block_rs(write_buffer: &mut String, block_def: String) -> Result<(), an
     Ok(())
 }
 
+const BLOCKSTATE_FROM_STRING: &str = r#"
+/// Resolves a block state from a string such as `minecraft:oak_stairs[facing=east,half=top]`
+/// Properties that aren't specified will use the value from the default state
+pub fn blockstate_from_string(string: &str) -> Option<u16> {
+	let string: String = string.chars().filter(|c| !c.is_whitespace()).collect();
+	let (name, properties) = match string.split_once('[') {
+		Some((name, properties)) => (name, Some(properties.strip_suffix(']')?)),
+		None => (string.as_str(), None),
+	};
+	let name = name.strip_prefix("minecraft:").unwrap_or(name);
+
+	let index = BLOCK_STATE_DEFINITIONS.binary_search_by(|definition| definition.name.cmp(name)).ok()?;
+	let definition = &BLOCK_STATE_DEFINITIONS[index];
+
+	let mut state = definition.default_state;
+	for property in properties.into_iter().flat_map(|properties| properties.split(',')) {
+		if property.is_empty() {
+			continue;
+		}
+		let (key, value) = property.split_once('=')?;
+
+		// The last property changes the fastest
+		let mut stride = 1;
+		let mut found = false;
+		for (property_name, values) in definition.properties.iter().rev() {
+			let value_count = values.len() as u16;
+			if *property_name == key {
+				let new_index = values.iter().position(|v| *v == value)? as u16;
+				let old_index = ((state - definition.min_state) / stride) % value_count;
+				state = state - old_index * stride + new_index * stride;
+				found = true;
+				break;
+			}
+			stride *= value_count;
+		}
+		if !found {
+			return None;
+		}
+	}
+
+	Some(state)
+}
+"#;
+
 fn write_u16_to_attributes(write_buffer: &mut String, state_count: usize, state_attributes_lut: String) -> Result<(), anyhow::Error> {
     write_buffer.push_str("impl TryFrom<u16> for &BlockAttributes {\n");
     write_buffer.push_str("\ttype Error = NoSuchBlockError;");
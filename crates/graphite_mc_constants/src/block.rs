@@ -8,11 +8,54 @@ include!(concat!(env!("OUT_DIR"), "/block_to_u16.rs"));
 include!(concat!(env!("OUT_DIR"), "/u16_to_block.rs"));
 include!(concat!(env!("OUT_DIR"), "/u16_to_item.rs"));
 include!(concat!(env!("OUT_DIR"), "/u16_to_block_attributes.rs"));
+include!(concat!(env!("OUT_DIR"), "/string_to_state.rs"));
 
 pub fn state_to_item(id: u16) -> Result<crate::item::Item, NoSuchBlockError> {
 	if id >= ITEM_LUT.len() as _ { return Err(NoSuchBlockError(id)); }
 	Ok(ITEM_LUT[id as usize])
 }
+
+/// Resolves a block state from a string such as `minecraft:oak_stairs[facing=east,half=top]`
+/// Properties that aren't specified will use the value from the default state
+pub fn blockstate_from_string(string: &str) -> Option<u16> {
+	let string: String = string.chars().filter(|c| !c.is_whitespace()).collect();
+	let (name, properties) = match string.split_once('[') {
+		Some((name, properties)) => (name, Some(properties.strip_suffix(']')?)),
+		None => (string.as_str(), None),
+	};
+	let name = name.strip_prefix("minecraft:").unwrap_or(name);
+
+	let index = BLOCK_STATE_DEFINITIONS.binary_search_by(|definition| definition.name.cmp(name)).ok()?;
+	let definition = &BLOCK_STATE_DEFINITIONS[index];
+
+	let mut state = definition.default_state;
+	for property in properties.into_iter().flat_map(|properties| properties.split(',')) {
+		if property.is_empty() {
+			continue;
+		}
+		let (key, value) = property.split_once('=')?;
+
+		// The last property changes the fastest
+		let mut stride = 1;
+		let mut found = false;
+		for (property_name, values) in definition.properties.iter().rev() {
+			let value_count = values.len() as u16;
+			if *property_name == key {
+				let new_index = values.iter().position(|v| *v == value)? as u16;
+				let old_index = ((state - definition.min_state) / stride) % value_count;
+				state = state - old_index * stride + new_index * stride;
+				found = true;
+				break;
+			}
+			stride *= value_count;
+		}
+		if !found {
+			return None;
+		}
+	}
+
+	Some(state)
+}
 #[derive(Debug)]
 pub struct BlockAttributes {
 	pub hardness: f32,
@@ -9,6 +9,7 @@ use crate::item::Item;
 use crate::block::Block;
 
 pub trait PlacementContext {
+	fn get_chest_type(&mut self) -> ChestType;
 	fn get_clicked_axis(&mut self) -> Axis3D;
 	fn get_clicked_half(&mut self) -> Half;
 	fn get_facing_big_dripleaf(&mut self) -> Direction;
@@ -642,9 +643,9 @@ impl Item {
 			}),
 			Item::Spawner => Some(Block::Spawner),
 			Item::Chest => Some(Block::Chest {
-				facing: Direction::North,
-				block_type: ChestType::Single,
-				waterlogged: false,
+				facing: ctx.get_facing_look_horizontal_opposite(),
+				block_type: ctx.get_chest_type(),
+				waterlogged: ctx.is_in_water(),
 			}),
 			Item::CraftingTable => Some(Block::CraftingTable),
 			Item::Farmland => Some(Block::Farmland {
@@ -1876,9 +1877,9 @@ impl Item {
 				powered: false,
 			}),
 			Item::TrappedChest => Some(Block::TrappedChest {
-				facing: Direction::North,
-				block_type: ChestType::Single,
-				waterlogged: false,
+				facing: ctx.get_facing_look_horizontal_opposite(),
+				block_type: ctx.get_chest_type(),
+				waterlogged: ctx.is_in_water(),
 			}),
 			Item::Tnt => Some(Block::Tnt {
 				unstable: false,
@@ -0,0 +1,48 @@
+use graphite_mc_constants::{
+    block::{blockstate_from_string, Block},
+    block_parameter::{Direction, Half, StairShape},
+};
+
+#[test]
+fn blockstate_from_string_with_properties() {
+    let expected = Block::OakStairs {
+        facing: Direction::East,
+        half: Half::Top,
+        shape: StairShape::Straight,
+        waterlogged: false,
+    }
+    .to_id();
+
+    assert_eq!(
+        blockstate_from_string("minecraft:oak_stairs[facing=east,half=top]"),
+        Some(expected)
+    );
+    assert_eq!(
+        blockstate_from_string(" oak_stairs [ half = top , facing = east ] "),
+        Some(expected)
+    );
+}
+
+#[test]
+fn blockstate_from_string_default() {
+    let default = Block::OakStairs {
+        facing: Direction::North,
+        half: Half::Bottom,
+        shape: StairShape::Straight,
+        waterlogged: false,
+    }
+    .to_id();
+
+    assert_eq!(blockstate_from_string("minecraft:oak_stairs"), Some(default));
+    assert_eq!(blockstate_from_string("minecraft:oak_stairs[]"), Some(default));
+    assert_eq!(blockstate_from_string("stone"), Some(Block::Stone.to_id()));
+}
+
+#[test]
+fn blockstate_from_string_invalid() {
+    assert_eq!(blockstate_from_string("minecraft:not_a_block"), None);
+    assert_eq!(blockstate_from_string("minecraft:oak_stairs[colour=red]"), None);
+    assert_eq!(blockstate_from_string("minecraft:oak_stairs[facing=up]"), None);
+    assert_eq!(blockstate_from_string("minecraft:oak_stairs[facing]"), None);
+    assert_eq!(blockstate_from_string("minecraft:oak_stairs[facing=east"), None);
+}
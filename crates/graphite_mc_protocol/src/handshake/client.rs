@@ -13,7 +13,9 @@ slice_serializable! {
     #[derive(Debug)]
     pub struct Intention<'a> {
         pub protocol_version: i32 as VarInt,
-        pub host_name: &'a str as SizedString<256>,
+        // Vanilla hosts are at most 255 characters, but proxies using legacy forwarding
+        // append the player's address, uuid and properties
+        pub host_name: &'a str as SizedString,
         pub port: u16 as BigEndian,
         pub intention: i32 as VarInt
     }
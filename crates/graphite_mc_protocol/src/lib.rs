@@ -13,6 +13,17 @@ pub trait IdentifiedPacket<I: Debug>: Debug {
     fn get_packet_id_as_u8(&self) -> u8;
 }
 
+/// What a `PacketHandler` should do when it receives a packet with an unknown id
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnknownPacketHandling {
+    /// Silently ignore the packet
+    Ignore,
+    /// Print the packet id, then ignore the packet
+    Log,
+    /// Return an error, usually causing the connection to be closed
+    Error,
+}
+
 macro_rules! identify_packets {
     { $enum_name:ident, $( $packet:ident $(<$life:lifetime>)? = $val:tt ),* } => {
         #[derive(Debug, TryFromPrimitive, Eq, PartialEq)]
@@ -79,6 +90,8 @@ macro_rules! identify_packets {
 
         pub trait PacketHandler {
             const DEBUG: bool = false;
+            /// Unknown packets are ignored by default, to allow for newer clients sending extra packets
+            const UNKNOWN_PACKET_HANDLING: $crate::UnknownPacketHandling = $crate::UnknownPacketHandling::Ignore;
 
             paste::paste! {
                 $(
@@ -115,8 +128,16 @@ macro_rules! identify_packets {
                         )*
                     }
                 } else {
-                    Ok(())
-                    // anyhow::bail!("unknown packet_id 0x{:x}", packet_id_byte)
+                    match Self::UNKNOWN_PACKET_HANDLING {
+                        $crate::UnknownPacketHandling::Ignore => Ok(()),
+                        $crate::UnknownPacketHandling::Log => {
+                            println!("Unknown packet_id: 0x{:x}", packet_id_byte);
+                            Ok(())
+                        }
+                        $crate::UnknownPacketHandling::Error => {
+                            anyhow::bail!("unknown packet_id 0x{:x}", packet_id_byte)
+                        }
+                    }
                 }
             }
         }
@@ -124,3 +145,23 @@ macro_rules! identify_packets {
 }
 
 pub(crate) use identify_packets;
+
+#[cfg(test)]
+mod tests {
+    use crate::{play::client::PacketHandler, UnknownPacketHandling};
+
+    struct LenientHandler;
+    impl PacketHandler for LenientHandler {}
+
+    struct StrictHandler;
+    impl PacketHandler for StrictHandler {
+        const UNKNOWN_PACKET_HANDLING: UnknownPacketHandling = UnknownPacketHandling::Error;
+    }
+
+    #[test]
+    fn unknown_packet() {
+        let bytes = [0xFF, 1, 2, 3];
+        assert!(LenientHandler.parse_and_handle(&bytes).is_ok());
+        assert!(StrictHandler.parse_and_handle(&bytes).is_err());
+    }
+}
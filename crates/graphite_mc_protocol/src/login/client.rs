@@ -7,7 +7,8 @@ use num_enum::TryFromPrimitive;
 
 identify_packets! {
     PacketId,
-    Hello<'_> = 0x00
+    Hello<'_> = 0x00,
+    EncryptionResponse<'_> = 0x01
 }
 
 slice_serializable! {
@@ -18,3 +19,25 @@ slice_serializable! {
         pub uuid: Option<u128> as Option<BigEndian>
     }
 }
+
+slice_serializable! {
+    #[derive(Debug)]
+    pub enum EncryptionVerification<'a> {
+        // Sent instead of the verify token by clients that sent `Hello::signature_data`
+        MessageSignature {
+            salt: i64 as BigEndian,
+            signature: &'a [u8] as SizedBlob
+        },
+        VerifyToken {
+            verify_token: &'a [u8] as SizedBlob
+        }
+    }
+}
+
+slice_serializable! {
+    #[derive(Debug)]
+    pub struct EncryptionResponse<'a> {
+        pub shared_secret: &'a [u8] as SizedBlob,
+        pub verification: EncryptionVerification<'a>
+    }
+}
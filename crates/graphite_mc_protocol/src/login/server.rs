@@ -7,9 +7,27 @@ use num_enum::TryFromPrimitive;
 
 identify_packets! {
     PacketId,
+    LoginDisconnect<'_> = 0x00,
+    EncryptionRequest<'_> = 0x01,
     LoginSuccess = 0x02
 }
 
+slice_serializable! {
+    #[derive(Debug)]
+    pub struct LoginDisconnect<'a> {
+        pub reason: &'a str as SizedString
+    }
+}
+
+slice_serializable! {
+    #[derive(Debug)]
+    pub struct EncryptionRequest<'a> {
+        pub server_id: &'a str as SizedString<20>,
+        pub public_key: &'a [u8] as SizedBlob,
+        pub verify_token: &'a [u8] as SizedBlob
+    }
+}
+
 slice_serializable! {
     #[derive(Debug)]
     pub struct LoginSuccess {
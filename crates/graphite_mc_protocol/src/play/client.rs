@@ -5,6 +5,7 @@ use crate::types::ArmPosition;
 use crate::types::BlockHitResult;
 use crate::types::BlockPosition;
 use crate::types::ChatVisibility;
+use crate::types::ClickType;
 use crate::types::Direction;
 use crate::types::Hand;
 use crate::types::HandAction;
@@ -24,10 +25,10 @@ identify_packets! {
     // ChatPreview = 0x06
     // ClientCommand = 0x07,
     ClientInformation<'_> = 0x08,
-    // CommandSuggestion = 0x09,
+    CommandSuggestion<'_> = 0x09,
     // ContainerButtonClick = 0x0a,
-    // ContainerClick = 0x0b,
-    // ContainerClose = 0x0c,
+    ContainerClick<'_> = 0x0b,
+    ContainerClose = 0x0c,
     CustomPayload<'_> = 0x0d,
     // EditBook = 0x0e,
     // EntityTagQuery = 0x0f,
@@ -47,7 +48,7 @@ identify_packets! {
     PlayerHandAction = 0x1d,
     PlayerMoveAction = 0x1e,
     // PlayerInput = 0x1f,
-    // Pong = 0x20,
+    Pong = 0x20,
     // RecipeBookChangeSettings = 0x21,
     // RecipeBookSeenRecipe = 0x22,
     // RenameItem = 0x23,
@@ -61,7 +62,7 @@ identify_packets! {
     SetCreativeModeSlot<'_> = 0x2b,
     // SetJigsawBlock = 0x2c,
     // SetStructureBlock = 0x2d,
-    // UpdateSign = 0x2e,
+    UpdateSign<'_> = 0x2e,
     Swing = 0x2f,
     // TeleportToEntity = 0x30,
     UseItemOn = 0x31,
@@ -90,7 +91,7 @@ slice_serializable! {
 slice_serializable! {
     #[derive(Debug)]
     pub struct ReceivedMessage<'a> {
-        pub from_uuid: u128 as BigEndian,
+        pub from_uuid: u128 as Uuid,
         pub bytes: &'a [u8] as SizedBlob<300>
     }
 }
@@ -127,6 +128,45 @@ slice_serializable! {
     }
 }
 
+// Command Suggestion
+slice_serializable! {
+    #[derive(Debug)]
+    pub struct CommandSuggestion<'a> {
+        pub id: i32 as VarInt,
+        pub command: &'a str as SizedString<32500>
+    }
+}
+
+// Container Click
+slice_serializable! {
+    #[derive(Debug)]
+    pub struct ContainerClickChangedSlot<'a> {
+        pub slot: i16 as BigEndian,
+        pub item: Option<ProtocolItemStack<'a>>
+    }
+}
+
+slice_serializable! {
+    #[derive(Debug)]
+    pub struct ContainerClick<'a> {
+        pub window_id: u8 as Single,
+        pub state_id: i32 as VarInt,
+        pub slot: i16 as BigEndian, // -999 when clicking outside of the window
+        pub button: i8 as Single,
+        pub click_type: ClickType as AttemptFrom<Single, u8>,
+        pub changed_slots: Vec<ContainerClickChangedSlot<'a>> as SizedArray<ContainerClickChangedSlot>,
+        pub carried_item: Option<ProtocolItemStack<'a>>
+    }
+}
+
+// Container Close
+slice_serializable! {
+    #[derive(Debug)]
+    pub struct ContainerClose {
+        pub window_id: u8 as Single
+    }
+}
+
 // Custom Payload
 slice_serializable! {
     #[derive(Debug)]
@@ -239,6 +279,15 @@ slice_serializable! {
     }
 }
 
+// Pong
+// Response to a play-state Ping, not to be confused with the status PongResponse
+slice_serializable! {
+    #[derive(Debug)]
+    pub struct Pong {
+        pub id: i32 as BigEndian
+    }
+}
+
 // Set Carried Item
 slice_serializable! {
     #[derive(Debug)]
@@ -256,6 +305,18 @@ slice_serializable! {
     }
 }
 
+// Update Sign
+slice_serializable! {
+    #[derive(Debug)]
+    pub struct UpdateSign<'a> {
+        pub pos: BlockPosition,
+        pub line1: &'a str as SizedString<384>,
+        pub line2: &'a str as SizedString<384>,
+        pub line3: &'a str as SizedString<384>,
+        pub line4: &'a str as SizedString<384>
+    }
+}
+
 // Swing
 slice_serializable! {
     #[derive(Debug)]
@@ -19,17 +19,17 @@ identify_packets! {
     // AwardStats = 0x04,
     BlockChangedAck = 0x05,
     BlockDestruction = 0x06,
-    // BlockEntityData = 0x07,
+    BlockEntityData<'_> = 0x07,
     // BlockEvent = 0x08,
     BlockUpdate = 0x09,
     // BossEvent = 0x0a,
     // ChangeDifficulty = 0x0b,
     // ChatPreview = 0x0c,
     // ClearTitles = 0x0d,
-    // CommandSuggestions = 0x0e,
+    CommandSuggestions<'_> = 0x0e,
     Commands = 0x0f,
-    // ContainerClose = 0x10,
-    // ContainerSetContent = 0x11,
+    ContainerClose = 0x10,
+    ContainerSetContent<'_> = 0x11,
     // ContainerSetData = 0x12,
     ContainerSetSlot<'_> = 0x13,
     // Cooldown = 0x14,
@@ -37,7 +37,7 @@ identify_packets! {
     CustomPayload<'_> = 0x16,
     // CustomSound = 0x17,
     // DeleteChat = 0x18,
-    // Disconnect = 0x19,
+    Disconnect<'_> = 0x19,
     // EntityEvent = 0x1a,
     // Explode = 0x1b,
     ForgetLevelChunk = 0x1c,
@@ -57,9 +57,9 @@ identify_packets! {
     MoveEntityRot = 0x2a,
     // MoveVehicle = 0x2b,
     // OpenBook = 0x2c,
-    // OpenScreen = 0x2d,
-    // OpenSignEditor = 0x2e,
-    // Ping = 0x2f,
+    OpenScreen<'_> = 0x2d,
+    OpenSignEditor = 0x2e,
+    Ping = 0x2f,
     // PlaceGhostRecipe = 0x30,
     PlayerAbilities = 0x31,
     // PlayerChatHeader = 0x32,
@@ -68,7 +68,7 @@ identify_packets! {
     // PlayerCombatEnter = 0x35,
     // PlayerCombatKill = 0x36,
     PlayerInfo<'_> = 0x37,
-    // PlayerLookAt = 0x38,
+    PlayerLookAt = 0x38,
     PlayerPosition = 0x39,
     // UnlockRecipe = 0x3a,
     RemoveEntities = 0x3b,
@@ -76,7 +76,7 @@ identify_packets! {
     // ResourcePack = 0x3d,
     Respawn<'_> = 0x3e,
     RotateHead = 0x3f,
-    // SectionBlocksUpdate = 0x40,
+    SectionBlocksUpdate = 0x40,
     // SelectAdvancementTab = 0x41,
     // ServerData = 0x42,
     // SetActionBarText = 0x43,
@@ -86,7 +86,7 @@ identify_packets! {
     // SetBorderWarningDelay = 0x47,
     // SetBorderWarningDistance = 0x48,
     // SetCamera = 0x49,
-    // SetCarriedItem = 0x4a,
+    SetCarriedItem = 0x4a,
     SetChunkCacheCenter = 0x4b,
     // SetChunkCacheRadius = 0x4c,
     // SetDefaultSpawnPosition = 0x4d,
@@ -96,15 +96,15 @@ identify_packets! {
     // SetEntityLink = 0x51,
     // SetEntityMotion = 0x52,
     SetEquipment<'_> = 0x53,
-    // SetExperience = 0x54,
-    // SetHealth = 0x55,
+    SetExperience = 0x54,
+    SetHealth = 0x55,
     // SetObjective = 0x56,
     // SetPassengers = 0x57,
-    // SetPlayerTeam = 0x58,
+    SetPlayerTeam<'_> = 0x58,
     // SetScore = 0x59,
     // SetSimulationDistance = 0x5a,
     // SetSubtitleText = 0x5b,
-    // SetTime = 0x5c,
+    SetTime = 0x5c,
     // SetTitleText = 0x5d,
     // SetTitleAnimation = 0x5e,
     // SoundEntity = 0x5f,
@@ -116,7 +116,7 @@ identify_packets! {
     // TakeItemEntity = 0x65,
     TeleportEntity = 0x66,
     // UpdateAdvancements = 0x67,
-    // UpdateAttributes = 0x68,
+    UpdateAttributes<'_> = 0x68,
     // UpdateMobEffect = 0x69,
     // UpdateRecipes = 0x6f,
     UpdateTags<'_> = 0x6b
@@ -128,7 +128,7 @@ slice_serializable! {
     #[derive(Debug)]
     pub struct AddEntity {
         pub id: i32 as VarInt,
-        pub uuid: u128 as BigEndian,
+        pub uuid: u128 as Uuid,
         pub entity_type: i32 as VarInt,
         pub x: f64 as BigEndian,
         pub y: f64 as BigEndian,
@@ -148,7 +148,7 @@ slice_serializable! {
     #[derive(Debug)]
     pub struct AddPlayer {
         pub id: i32 as VarInt,
-        pub uuid: u128 as BigEndian,
+        pub uuid: u128 as Uuid,
         pub x: f64 as BigEndian,
         pub y: f64 as BigEndian,
         pub z: f64 as BigEndian,
@@ -197,6 +197,16 @@ slice_serializable! {
     }
 }
 
+// Block Entity Data
+slice_serializable! {
+    #[derive(Debug)]
+    pub struct BlockEntityData<'a> {
+        pub pos: BlockPosition,
+        pub block_entity_type: i32 as VarInt,
+        pub nbt: Cow<'a, CachedNBT> as NBTBlob
+    }
+}
+
 // Block Update
 slice_serializable! {
     #[derive(Debug)]
@@ -206,6 +216,25 @@ slice_serializable! {
     }
 }
 
+// Command Suggestions
+slice_serializable! {
+    #[derive(Debug)]
+    pub struct SuggestionMatch<'a> {
+        pub text: &'a str as SizedString,
+        pub tooltip: Option<&'a str> as Option<SizedString>
+    }
+}
+
+slice_serializable! {
+    #[derive(Debug)]
+    pub struct CommandSuggestions<'a> {
+        pub id: i32 as VarInt,
+        pub start: i32 as VarInt,
+        pub length: i32 as VarInt,
+        pub suggestions: Vec<SuggestionMatch<'a>> as SizedArray<SuggestionMatch>
+    }
+}
+
 // Commands
 slice_serializable! {
     #[derive(Debug)]
@@ -215,6 +244,25 @@ slice_serializable! {
     }
 }
 
+// Container Close
+slice_serializable! {
+    #[derive(Debug)]
+    pub struct ContainerClose {
+        pub window_id: u8 as Single
+    }
+}
+
+// Container Set Content
+slice_serializable! {
+    #[derive(Debug)]
+    pub struct ContainerSetContent<'a> {
+        pub window_id: u8 as Single,
+        pub state_id: i32 as VarInt,
+        pub items: Vec<Option<ProtocolItemStack<'a>>> as SizedArray<Option<ProtocolItemStack>>,
+        pub carried_item: Option<ProtocolItemStack<'a>>
+    }
+}
+
 // Container Set Slot
 slice_serializable! {
     #[derive(Debug)]
@@ -235,6 +283,14 @@ slice_serializable! {
     }
 }
 
+// Disconnect
+slice_serializable! {
+    #[derive(Debug)]
+    pub struct Disconnect<'a> {
+        pub reason: &'a str as SizedString
+    }
+}
+
 // Forget Level Chunk
 slice_serializable! {
     #[derive(Debug)]
@@ -477,6 +533,34 @@ slice_serializable! {
     }
 }
 
+// Open Screen
+slice_serializable! {
+    #[derive(Debug)]
+    pub struct OpenScreen<'a> {
+        pub window_id: i32 as VarInt,
+        pub window_type: i32 as VarInt,
+        pub title: &'a str as SizedString
+    }
+}
+
+// Open Sign Editor
+slice_serializable! {
+    #[derive(Debug)]
+    pub struct OpenSignEditor {
+        pub pos: BlockPosition
+    }
+}
+
+// Ping
+// Play-state ping, answered by the client with a Pong containing the same id.
+// Not to be confused with the status PingRequest
+slice_serializable! {
+    #[derive(Debug)]
+    pub struct Ping {
+        pub id: i32 as BigEndian
+    }
+}
+
 // Player Abilities
 slice_serializable! {
     #[derive(Debug)]
@@ -505,7 +589,7 @@ slice_serializable! {
 slice_serializable! {
     #[derive(Debug)]
     pub struct PlayerInfoUpdateGamemode {
-        pub uuid: u128 as BigEndian,
+        pub uuid: u128 as Uuid,
         pub gamemode: u8 as Single
     }
 }
@@ -513,7 +597,7 @@ slice_serializable! {
 slice_serializable! {
     #[derive(Debug)]
     pub struct PlayerInfoUpdateLatency {
-        pub uuid: u128 as BigEndian,
+        pub uuid: u128 as Uuid,
         pub ping: i32 as VarInt
     }
 }
@@ -521,7 +605,7 @@ slice_serializable! {
 slice_serializable! {
     #[derive(Debug)]
     pub struct PlayerInfoDisplayName<'a> {
-        pub uuid: u128 as BigEndian,
+        pub uuid: u128 as Uuid,
         pub display_name: Option<&'a str> as Option<SizedString>,
     }
 }
@@ -555,6 +639,33 @@ slice_serializable! {
     }
 }
 
+// Player Look At
+#[derive(Debug, Copy, Clone, PartialEq, Eq, TryFromPrimitive, IntoPrimitive)]
+#[repr(i32)]
+pub enum LookAnchor {
+    Feet,
+    Eyes,
+}
+
+slice_serializable! {
+    #[derive(Debug)]
+    pub struct PlayerLookAtEntity {
+        pub entity_id: i32 as VarInt,
+        pub entity_anchor: LookAnchor as AttemptFrom<VarInt, i32>
+    }
+}
+
+slice_serializable! {
+    #[derive(Debug)]
+    pub struct PlayerLookAt {
+        pub anchor: LookAnchor as AttemptFrom<VarInt, i32>,
+        pub x: f64 as BigEndian,
+        pub y: f64 as BigEndian,
+        pub z: f64 as BigEndian,
+        pub entity: Option<PlayerLookAtEntity>
+    }
+}
+
 // Player Position
 slice_serializable! {
     #[derive(Debug)]
@@ -595,6 +706,41 @@ slice_serializable! {
     }
 }
 
+// Section Blocks Update
+slice_serializable! {
+    #[derive(Debug)]
+    pub struct SectionBlocksUpdate {
+        // x (22 bits), z (22 bits), y (20 bits) of the chunk section
+        pub section_pos: i64 as BigEndian,
+        pub suppress_light_updates: bool as Single,
+        // block state << 12 | local x << 8 | local z << 4 | local y
+        pub blocks: Vec<i64> as SizedArray<VarInt>
+    }
+}
+
+impl SectionBlocksUpdate {
+    pub fn pack_section_pos(section_x: i32, section_y: i32, section_z: i32) -> i64 {
+        ((section_x as i64 & 0x3FFFFF) << 42)
+            | ((section_z as i64 & 0x3FFFFF) << 20)
+            | (section_y as i64 & 0xFFFFF)
+    }
+
+    pub fn pack_block(local_x: u8, local_y: u8, local_z: u8, block_state: u16) -> i64 {
+        ((block_state as i64) << 12)
+            | ((local_x as i64 & 0xF) << 8)
+            | ((local_z as i64 & 0xF) << 4)
+            | (local_y as i64 & 0xF)
+    }
+}
+
+// Set Carried Item
+slice_serializable! {
+    #[derive(Debug)]
+    pub struct SetCarriedItem {
+        pub slot: u8 as Single
+    }
+}
+
 // Set Chunk Cache Center
 slice_serializable! {
     #[derive(Debug)]
@@ -622,6 +768,35 @@ slice_serializable! {
     }
 }
 
+// Set Experience
+slice_serializable! {
+    #[derive(Debug)]
+    pub struct SetExperience {
+        pub experience_progress: f32 as BigEndian,
+        pub level: i32 as VarInt,
+        pub total_experience: i32 as VarInt
+    }
+}
+
+// Set Health
+slice_serializable! {
+    #[derive(Debug)]
+    pub struct SetHealth {
+        pub health: f32 as BigEndian,
+        pub food: i32 as VarInt,
+        pub saturation: f32 as BigEndian
+    }
+}
+
+// Set Time
+slice_serializable! {
+    #[derive(Debug)]
+    pub struct SetTime {
+        pub game_time: i64 as BigEndian,
+        pub day_time: i64 as BigEndian // negative if the client shouldn't advance it
+    }
+}
+
 // System Chat
 slice_serializable! {
     #[derive(Debug)]
@@ -631,6 +806,77 @@ slice_serializable! {
     }
 }
 
+// Set Player Team
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, TryFromPrimitive, IntoPrimitive)]
+#[repr(u8)]
+pub enum ChatFormatting {
+    Black,
+    DarkBlue,
+    DarkGreen,
+    DarkAqua,
+    DarkRed,
+    DarkPurple,
+    Gold,
+    Gray,
+    DarkGray,
+    Blue,
+    Green,
+    Aqua,
+    Red,
+    LightPurple,
+    Yellow,
+    White,
+    Obfuscated,
+    Bold,
+    Strikethrough,
+    Underline,
+    Italic,
+    #[default]
+    Reset,
+}
+
+slice_serializable! {
+    #[derive(Debug)]
+    pub struct SetPlayerTeamParameters<'a> {
+        pub display_name: &'a str as SizedString,
+        pub options: u8 as Single, // 0x1: friendly fire, 0x2: see invisible teammates
+        pub name_tag_visibility: &'a str as SizedString,
+        pub collision_rule: &'a str as SizedString,
+        pub color: ChatFormatting as AttemptFrom<Single, u8>,
+        pub prefix: &'a str as SizedString,
+        pub suffix: &'a str as SizedString
+    }
+}
+
+slice_serializable! {
+    #[derive(Debug)]
+    pub enum SetPlayerTeamAction<'a> {
+        Create {
+            parameters: SetPlayerTeamParameters<'a>,
+            entries: Vec<&'a str> as SizedArray<SizedString>
+        },
+        Remove {
+        },
+        Update {
+            parameters: SetPlayerTeamParameters<'a>
+        },
+        AddEntries {
+            entries: Vec<&'a str> as SizedArray<SizedString>
+        },
+        RemoveEntries {
+            entries: Vec<&'a str> as SizedArray<SizedString>
+        }
+    }
+}
+
+slice_serializable! {
+    #[derive(Debug)]
+    pub struct SetPlayerTeam<'a> {
+        pub name: &'a str as SizedString,
+        pub action: SetPlayerTeamAction<'a>
+    }
+}
+
 // Teleport Entity
 slice_serializable! {
     #[derive(Debug)]
@@ -645,6 +891,41 @@ slice_serializable! {
     }
 }
 
+// Update Attributes
+#[derive(Debug, Copy, Clone, PartialEq, Eq, TryFromPrimitive, IntoPrimitive)]
+#[repr(u8)]
+pub enum AttributeModifierOperation {
+    Addition,
+    MultiplyBase,
+    MultiplyTotal,
+}
+
+slice_serializable! {
+    #[derive(Debug)]
+    pub struct UpdateAttributesModifier {
+        pub uuid: u128 as Uuid,
+        pub amount: f64 as BigEndian,
+        pub operation: AttributeModifierOperation as AttemptFrom<Single, u8>
+    }
+}
+
+slice_serializable! {
+    #[derive(Debug)]
+    pub struct UpdateAttributesProperty<'a> {
+        pub key: &'a str as SizedString,
+        pub value: f64 as BigEndian,
+        pub modifiers: Vec<UpdateAttributesModifier> as SizedArray<UpdateAttributesModifier>
+    }
+}
+
+slice_serializable! {
+    #[derive(Debug)]
+    pub struct UpdateAttributes<'a> {
+        pub entity_id: i32 as VarInt,
+        pub attributes: Vec<UpdateAttributesProperty<'a>> as SizedArray<UpdateAttributesProperty>
+    }
+}
+
 // Update Tags
 slice_serializable! {
     #[derive(Debug)]
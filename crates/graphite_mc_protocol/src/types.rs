@@ -4,7 +4,7 @@ use graphite_binary::{
     nbt::CachedNBT,
     slice_serialization::{
         self, slice_serializable, AttemptFrom, BigEndian, NBTBlob, Single, SizedArray, SizedBlob,
-        SizedString, SliceSerializable, VarInt,
+        SizedString, SliceSerializable, Uuid, VarInt,
     },
 };
 use num_enum::{IntoPrimitive, TryFromPrimitive};
@@ -80,6 +80,18 @@ pub enum MoveAction {
     StartFallFlying,
 }
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq, TryFromPrimitive, IntoPrimitive)]
+#[repr(u8)]
+pub enum ClickType {
+    Pickup,
+    QuickMove,
+    Swap,
+    Clone,
+    Throw,
+    QuickCraft,
+    PickupAll,
+}
+
 #[derive(Debug, Copy, Clone, TryFromPrimitive, IntoPrimitive, Default, PartialEq, Eq)]
 #[repr(u8)]
 pub enum Direction {
@@ -140,7 +152,7 @@ slice_serializable! {
 slice_serializable! {
     #[derive(Debug, Clone)]
     pub struct GameProfile {
-        pub uuid: u128 as BigEndian,
+        pub uuid: u128 as Uuid,
         pub username: String as SizedString<16>,
         pub properties: Vec<GameProfileProperty> as SizedArray<GameProfileProperty>
     }
@@ -745,3 +757,53 @@ where
     }
     bytes
 }
+
+#[cfg(test)]
+mod tests {
+    use graphite_binary::slice_serialization::SliceSerializable;
+
+    use super::{GameProfile, GameProfileProperty};
+
+    #[test]
+    fn game_profile_properties() {
+        let profile = GameProfile {
+            uuid: 0x1234,
+            username: "Moulberry".into(),
+            properties: vec![
+                GameProfileProperty {
+                    id: "textures".into(),
+                    value: "abc".into(),
+                    signature: Some("def".into()),
+                },
+                GameProfileProperty {
+                    id: "other".into(),
+                    value: "".into(),
+                    signature: None,
+                },
+            ],
+        };
+
+        // uuid + username + property count + textures property + other property
+        let expected_size = 16 + 10 + 1 + (9 + 4 + 1 + 4) + (6 + 1 + 1);
+        assert_eq!(GameProfile::get_write_size(&profile), expected_size);
+
+        let mut bytes = vec![0; expected_size];
+        unsafe {
+            let remaining = GameProfile::write(&mut bytes, &profile);
+            assert!(remaining.is_empty());
+        }
+
+        let read = GameProfile::read_fully(&mut bytes.as_slice()).unwrap();
+        assert_eq!(read.uuid, profile.uuid);
+        assert_eq!(read.username, profile.username);
+        assert_eq!(read.properties.len(), 2);
+        assert_eq!(read.properties[0].value, "abc");
+        assert_eq!(read.properties[0].signature.as_deref(), Some("def"));
+        assert_eq!(read.properties[1].id, "other");
+        assert!(read.properties[1].signature.is_none());
+
+        // Length prefix larger than the remaining bytes
+        bytes[26] = 100;
+        assert!(GameProfile::read(&mut bytes.as_slice()).is_err());
+    }
+}
@@ -0,0 +1,181 @@
+use std::io::{Read, Write};
+
+use anyhow::bail;
+use flate2::{read::ZlibDecoder, write::ZlibEncoder, Compression};
+use graphite_binary::varint;
+
+use crate::packet_helper::{self, PacketReadBufferError, PacketReadResult};
+
+// Packet compression, enabled using the Set Compression packet during login
+//
+// Compressed packets are framed as [packet length][data length][data], where data length
+// is the length of the uncompressed packet (id + contents), or 0 if the packet is smaller
+// than the threshold and isn't compressed
+
+/// Largest uncompressed packet that is accepted, same as vanilla
+const MAXIMUM_DATA_LENGTH: usize = 8388608;
+
+/// Converts uncompressed framed packets into compressed framed packets
+///
+/// Packets smaller than `threshold` are sent uncompressed, with a data length of 0.
+/// Returns an error if `bytes` contains anything other than complete packets
+pub fn compress_packets(mut bytes: &[u8], threshold: usize) -> anyhow::Result<Vec<u8>> {
+    let mut output = Vec::with_capacity(bytes.len() + bytes.len() / 64 + 8);
+
+    loop {
+        let packet = match packet_helper::try_read_packet(&mut bytes)? {
+            PacketReadResult::Complete(packet) => packet,
+            PacketReadResult::Partial => bail!("compress_packets: bytes must only contain complete packets"),
+            PacketReadResult::Empty => break,
+        };
+
+        if packet.len() < threshold {
+            varint::encode::extend_i32(&mut output, packet.len() as i32 + 1);
+            output.push(0);
+            output.extend_from_slice(packet);
+        } else {
+            let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(packet).expect("writing to a vec can't fail");
+            let compressed = encoder.finish().expect("writing to a vec can't fail");
+
+            let data_length_bytes = varint::encode::needed_bytes(packet.len() as i32);
+            varint::encode::extend_i32(&mut output, (data_length_bytes + compressed.len()) as i32);
+            varint::encode::extend_i32(&mut output, packet.len() as i32);
+            output.extend_from_slice(&compressed);
+        }
+    }
+
+    Ok(output)
+}
+
+/// Decompresses the complete compressed packets at the start of `bytes`, appending them to
+/// `output` as uncompressed framed packets
+///
+/// Stops once `output` holds at least `limit` bytes, so that many small packets can't
+/// decompress into a huge amount of data at once. `bytes` is advanced past the packets that
+/// were decompressed, leaving the remaining packets and any partial packet
+pub fn decompress_packets(bytes: &mut &[u8], output: &mut Vec<u8>, limit: usize) -> anyhow::Result<()> {
+    while output.len() < limit {
+        let PacketReadResult::Complete(mut packet) = packet_helper::try_read_packet(bytes)? else {
+            break;
+        };
+        let (data_length, data_length_bytes) = varint::decode::i32(packet)?;
+        packet = &packet[data_length_bytes..];
+
+        if data_length == 0 {
+            varint::encode::extend_i32(output, packet.len() as i32);
+            output.extend_from_slice(packet);
+            continue;
+        } else if data_length < 0 || data_length as usize > MAXIMUM_DATA_LENGTH {
+            return Err(PacketReadBufferError::PacketTooBig.into());
+        }
+
+        varint::encode::extend_i32(output, data_length);
+        let start = output.len();
+
+        // Limit the decompressed size to the data length, so that a small packet can't
+        // decompress into a huge amount of data
+        let decoder = ZlibDecoder::new(packet);
+        decoder.take(data_length as u64 + 1).read_to_end(output)?;
+
+        if output.len() - start != data_length as usize {
+            bail!("decompressed packet has length {}, expected {}", output.len() - start, data_length);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use graphite_binary::varint;
+
+    use super::*;
+
+    fn frame(output: &mut Vec<u8>, packet: &[u8]) {
+        varint::encode::extend_i32(output, packet.len() as i32);
+        output.extend_from_slice(packet);
+    }
+
+    #[test]
+    fn compression_round_trip() {
+        let small_packet = [0x21, 1, 2, 3, 4, 5, 6, 7, 8];
+        let large_packet = [0x20; 1000];
+
+        let mut uncompressed = Vec::new();
+        frame(&mut uncompressed, &small_packet);
+        frame(&mut uncompressed, &large_packet);
+
+        let compressed = compress_packets(&uncompressed, 256).unwrap();
+
+        // Below the threshold, the packet is sent as-is with a data length of 0
+        assert_eq!(compressed[..2], [10, 0]);
+        assert_eq!(compressed[2..11], small_packet);
+
+        // Above the threshold, the packet is compressed
+        let mut remaining = &compressed[11..];
+        let (packet_length, packet_length_bytes) = varint::decode::i32(remaining).unwrap();
+        remaining = &remaining[packet_length_bytes..];
+        assert_eq!(remaining.len(), packet_length as usize);
+        let (data_length, _) = varint::decode::i32(remaining).unwrap();
+        assert_eq!(data_length, 1000);
+        assert!(compressed.len() < uncompressed.len());
+
+        let mut output = Vec::new();
+        let mut bytes = compressed.as_slice();
+        decompress_packets(&mut bytes, &mut output, usize::MAX).unwrap();
+        assert!(bytes.is_empty());
+        assert_eq!(output, uncompressed);
+
+        // Partial packets are left in the input
+        let mut output = Vec::new();
+        let mut bytes = &compressed[..compressed.len() - 1];
+        decompress_packets(&mut bytes, &mut output, usize::MAX).unwrap();
+        assert_eq!(bytes.len(), compressed.len() - 12);
+        assert_eq!(output, uncompressed[..10]);
+    }
+
+    #[test]
+    fn decompress_limit() {
+        let mut uncompressed = Vec::new();
+        for _ in 0..3 {
+            frame(&mut uncompressed, &[0; 1000]);
+        }
+        let compressed = compress_packets(&uncompressed, 0).unwrap();
+
+        // Decompression stops once the limit is reached, the rest is left in the input
+        let mut output = Vec::new();
+        let mut bytes = compressed.as_slice();
+        decompress_packets(&mut bytes, &mut output, 1500).unwrap();
+        assert_eq!(output, uncompressed[..2004]);
+        assert_eq!(bytes.len(), compressed.len() / 3);
+
+        decompress_packets(&mut bytes, &mut output, usize::MAX).unwrap();
+        assert!(bytes.is_empty());
+        assert_eq!(output, uncompressed);
+    }
+
+    #[test]
+    fn compress_partial_packet() {
+        let mut uncompressed = Vec::new();
+        frame(&mut uncompressed, &[0x21, 1, 2, 3]);
+        frame(&mut uncompressed, &[0x21, 1, 2, 3]);
+
+        // The second packet is cut off, it mustn't be silently dropped
+        assert!(compress_packets(&uncompressed[..uncompressed.len() - 1], 256).is_err());
+    }
+
+    #[test]
+    fn decompress_wrong_length() {
+        let mut uncompressed = Vec::new();
+        frame(&mut uncompressed, &[0; 1000]);
+        let mut compressed = compress_packets(&uncompressed, 0).unwrap();
+
+        // Claim a data length that doesn't match the compressed data
+        assert_eq!(compressed[1..3], [0xE8, 0x07]);
+        compressed[1] = 0xE9;
+
+        let mut output = Vec::new();
+        assert!(decompress_packets(&mut compressed.as_slice(), &mut output, usize::MAX).is_err());
+    }
+}
@@ -0,0 +1,83 @@
+use aes::{
+    cipher::{generic_array::GenericArray, BlockDecryptMut, BlockEncryptMut, KeyIvInit},
+    Aes128,
+};
+
+// Stream encryption, enabled after the Encryption Response packet during login
+//
+// Vanilla encrypts every byte in both directions using AES/CFB8, with the shared secret
+// as both the key and the initial vector
+
+type Encryptor = cfb8::Encryptor<Aes128>;
+type Decryptor = cfb8::Decryptor<Aes128>;
+
+pub struct ConnectionCipher {
+    encryptor: Encryptor,
+    decryptor: Decryptor,
+}
+
+impl ConnectionCipher {
+    pub fn new(shared_secret: [u8; 16]) -> Self {
+        Self {
+            encryptor: Encryptor::new(&shared_secret.into(), &shared_secret.into()),
+            decryptor: Decryptor::new(&shared_secret.into(), &shared_secret.into()),
+        }
+    }
+
+    /// Encrypts bytes that are about to be sent, in place
+    pub fn encrypt(&mut self, bytes: &mut [u8]) {
+        // CFB8 has a block size of a single byte
+        for byte in bytes.chunks_exact_mut(1) {
+            self.encryptor.encrypt_block_mut(GenericArray::from_mut_slice(byte));
+        }
+    }
+
+    /// Decrypts bytes that have been received, in place
+    pub fn decrypt(&mut self, bytes: &mut [u8]) {
+        for byte in bytes.chunks_exact_mut(1) {
+            self.decryptor.decrypt_block_mut(GenericArray::from_mut_slice(byte));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ConnectionCipher;
+
+    const SHARED_SECRET: [u8; 16] = [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15];
+    const PLAINTEXT: &[u8] = b"Hello, Graphite!";
+    // openssl enc -aes-128-cfb8 -K 000102030405060708090a0b0c0d0e0f -iv 000102030405060708090a0b0c0d0e0f
+    const CIPHERTEXT: [u8; 16] = [
+        0x42, 0xea, 0x5e, 0xd4, 0xda, 0xf8, 0x64, 0xe0, 0x7c, 0x4f, 0x32, 0x70, 0xe4, 0x42, 0xc0, 0x06,
+    ];
+
+    #[test]
+    fn encrypt_known_vector() {
+        let mut cipher = ConnectionCipher::new(SHARED_SECRET);
+
+        let mut bytes = PLAINTEXT.to_vec();
+        cipher.encrypt(&mut bytes);
+        assert_eq!(bytes, CIPHERTEXT);
+
+        let mut bytes = CIPHERTEXT.to_vec();
+        cipher.decrypt(&mut bytes);
+        assert_eq!(bytes, PLAINTEXT);
+    }
+
+    #[test]
+    fn encrypt_stream() {
+        // The cipher state carries over between calls, so splitting the stream doesn't matter
+        let mut cipher = ConnectionCipher::new(SHARED_SECRET);
+
+        let mut bytes = PLAINTEXT.to_vec();
+        let (first, second) = bytes.split_at_mut(5);
+        cipher.encrypt(first);
+        cipher.encrypt(second);
+        assert_eq!(bytes, CIPHERTEXT);
+
+        let (first, second) = bytes.split_at_mut(11);
+        cipher.decrypt(first);
+        cipher.decrypt(second);
+        assert_eq!(bytes, PLAINTEXT);
+    }
+}
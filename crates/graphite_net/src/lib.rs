@@ -1,3 +1,5 @@
+pub mod compression;
+pub mod encryption;
 pub mod network_buffer;
 pub mod network_handler;
 pub mod packet_helper;
@@ -1,15 +1,19 @@
 use std::collections::VecDeque;
 use std::io;
 use std::net::TcpListener;
-use std::os::unix::io::{AsRawFd, RawFd};
+use std::os::unix::io::{AsRawFd, IntoRawFd, RawFd};
 use std::ptr;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
-use anyhow::bail;
+use anyhow::{bail, Context};
 use io_uring::types::Timespec;
 use io_uring::{opcode, squeue, types, IoUring, SubmissionQueue, Submitter};
 use slab::Slab;
 
+use crate::compression;
+use crate::packet_helper::{self, PacketReadResult};
+use crate::encryption::ConnectionCipher;
+
 #[derive(Debug, PartialEq)]
 #[repr(C, u16)]
 enum UserData {
@@ -24,8 +28,10 @@ enum UserData {
     TickTimeout,
     // IOTimeout,
     Write {
+        // same hack as `Read`
         connection_index: u16,
-        write_buffer_index: u16,
+        _2: u16,
+        _3: u16,
     },
 }
 
@@ -38,10 +44,19 @@ impl UserData {
         }
     }
 
+    fn create_write(connection_index: u16) -> UserData {
+        UserData::Write {
+            connection_index,
+            _2: 0,
+            _3: 0,
+        }
+    }
+
     fn is_write(&self) -> bool {
         const COMPARE_TO: UserData = UserData::Write {
             connection_index: 0,
-            write_buffer_index: 0,
+            _2: 0,
+            _3: 0,
         };
         std::mem::discriminant(&COMPARE_TO) == std::mem::discriminant(self)
     }
@@ -128,12 +143,22 @@ impl<N: NetworkManagerService> NewConnectionAccepter<N> {
             connections_waiting_for_close: self.connections_waiting_for_close,
 
             is_processing_read: false,
+            incomplete_since: None,
+            last_received: Instant::now(),
             close_requested: false,
+            write_failed: false,
 
             self_index: connection_index,
+            nodelay: uninitialized_conn.fd.init_nodelay::<N>(uninitialized_conn.nodelay),
             fd: uninitialized_conn.fd,
-            write_buffers: Slab::new(),
+            send_buffer: Vec::new(),
+            send_offset: 0,
+            pending_writes: Vec::new(),
             connection_redirect: None,
+            compression_threshold: uninitialized_conn.compression_threshold,
+            decompressed: uninitialized_conn.decompressed,
+            cipher: uninitialized_conn.cipher,
+            stats: uninitialized_conn.stats,
             rbuff_data_offset: uninitialized_conn.rbuff_data_offset,
             rbuff_write_offset: uninitialized_conn.rbuff_write_offset,
             read_buffer: uninitialized_conn.read_buffer,
@@ -177,13 +202,79 @@ impl Drop for AutoclosingFd {
     }
 }
 
+impl AutoclosingFd {
+    fn set_nodelay(&self, nodelay: bool) -> std::io::Result<()> {
+        let value = nodelay as libc::c_int;
+        let result = unsafe {
+            libc::setsockopt(
+                self.0,
+                libc::IPPROTO_TCP,
+                libc::TCP_NODELAY,
+                &value as *const libc::c_int as *const libc::c_void,
+                std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+            )
+        };
+
+        if result == 0 {
+            Ok(())
+        } else {
+            Err(std::io::Error::last_os_error())
+        }
+    }
+
+    /// Applies the service's default nodelay, unless the socket already has a known value
+    /// Returns `None` if the option couldn't be set, eg. because the socket isn't a TCP socket
+    fn init_nodelay<N: NetworkManagerService>(&self, nodelay: Option<bool>) -> Option<bool> {
+        if nodelay.is_some() {
+            return nodelay;
+        }
+
+        let nodelay = N::ConnectionServiceType::TCP_NODELAY;
+        self.set_nodelay(nodelay).ok().map(|_| nodelay)
+    }
+}
+
+/// Counters for the traffic of a connection, or of every connection of a `NetworkManager`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ConnectionStats {
+    /// Bytes received, including framing, compression and encryption
+    pub bytes_read: u64,
+    /// Bytes sent, including framing, compression and encryption
+    pub bytes_written: u64,
+    /// Packets that have been read by the connection service
+    pub packets_received: u64,
+}
+
 pub struct UninitializedConnection {
     fd: AutoclosingFd,
+    nodelay: Option<bool>,
+    compression_threshold: Option<usize>,
+    decompressed: Vec<u8>,
+    cipher: Option<ConnectionCipher>,
+    stats: ConnectionStats,
     rbuff_data_offset: usize,
     rbuff_write_offset: usize,
     read_buffer: Vec<u8>,
 }
 
+impl UninitializedConnection {
+    /// Wraps an already-connected stream (eg. one half of a `UnixStream::pair`)
+    /// so that it can be given to a network manager using `NewConnectionAccepter`
+    pub fn from_stream(stream: impl IntoRawFd, buffer_size: usize) -> Self {
+        Self {
+            fd: AutoclosingFd(stream.into_raw_fd()),
+            nodelay: None,
+            compression_threshold: None,
+            decompressed: Vec::new(),
+            cipher: None,
+            stats: ConnectionStats::default(),
+            rbuff_data_offset: 0,
+            rbuff_write_offset: 0,
+            read_buffer: vec![0_u8; buffer_size],
+        }
+    }
+}
+
 pub struct Connection<N: NetworkManagerService> {
     network_manager: *const NetworkManager<N>,
     submission_backlog: *mut VecDeque<squeue::Entry>,
@@ -191,12 +282,29 @@ pub struct Connection<N: NetworkManagerService> {
 
     self_index: u16,
     fd: AutoclosingFd,
-    write_buffers: Slab<Vec<u8>>,
+    nodelay: Option<bool>,
+
+    // Bytes that are being sent by io_uring, these must not be modified until the send completes
+    send_buffer: Vec<u8>,
+    send_offset: usize,
+    // Bytes written while a send is in progress, these are sent once it completes
+    pending_writes: Vec<u8>,
 
     close_requested: bool,
+    write_failed: bool,
     connection_redirect: Option<FnConnectionRedirect<N>>,
 
     is_processing_read: bool,
+    incomplete_since: Option<Instant>,
+    last_received: Instant,
+
+    // Packets are compressed when they are at least this size, see `Connection::set_compression`
+    compression_threshold: Option<usize>,
+    // Complete packets that have been decompressed from the read buffer, but not yet read
+    decompressed: Vec<u8>,
+    // Encrypts written bytes and decrypts received bytes, see `Connection::enable_encryption`
+    cipher: Option<ConnectionCipher>,
+    stats: ConnectionStats,
 
     rbuff_data_offset: usize,
     rbuff_write_offset: usize,
@@ -209,18 +317,91 @@ impl<N: NetworkManagerService> Connection<N> {
     }
 
     pub fn read_bytes(&self) -> &[u8] {
-        &self.read_buffer[self.rbuff_data_offset..self.rbuff_write_offset as usize]
+        if self.compression_threshold.is_some() {
+            &self.decompressed
+        } else {
+            &self.read_buffer[self.rbuff_data_offset..self.rbuff_write_offset]
+        }
+    }
+
+    /// The size at which packets start being compressed, `None` if compression is disabled
+    pub fn get_compression(&self) -> Option<usize> {
+        self.compression_threshold
+    }
+
+    /// Enables (Some) or disables (None) packet compression for this connection
+    ///
+    /// This should be called right after writing the Set Compression packet, which is
+    /// itself sent uncompressed. Once enabled, `write` compresses packets that are at least
+    /// `threshold` bytes, and `read_bytes` returns the decompressed packets
+    pub fn set_compression(&mut self, threshold: Option<usize>) {
+        self.compression_threshold = threshold;
+    }
+
+    /// Traffic of this connection so far, including traffic from before it was redirected
+    pub fn get_stats(&self) -> ConnectionStats {
+        self.stats
+    }
+
+    /// Whether the bytes sent and received by this connection are encrypted
+    pub fn is_encrypted(&self) -> bool {
+        self.cipher.is_some()
+    }
+
+    /// Encrypts all further bytes sent and received by this connection using AES/CFB8,
+    /// with the shared secret as both the key and the initial vector
+    ///
+    /// This should be called while handling the Encryption Response packet. Bytes that
+    /// are left in the read buffer after the current read are decrypted, as the client
+    /// encrypts everything it sends after that packet
+    pub fn enable_encryption(&mut self, shared_secret: [u8; 16]) {
+        self.cipher = Some(ConnectionCipher::new(shared_secret));
+    }
+
+    /// Whether Nagle's algorithm is disabled for this connection,
+    /// `None` if the connection isn't a TCP connection
+    pub fn get_nodelay(&self) -> Option<bool> {
+        self.nodelay
+    }
+
+    /// Disables (true) or enables (false) Nagle's algorithm for this connection
+    ///
+    /// Nagle's algorithm combines small writes into larger segments, which can be
+    /// useful for bulk transfers at the cost of latency. The socket option is only
+    /// changed if it differs from the current value
+    pub fn set_nodelay(&mut self, nodelay: bool) -> std::io::Result<()> {
+        if self.nodelay == Some(nodelay) {
+            return Ok(());
+        }
+
+        self.fd.set_nodelay(nodelay)?;
+        self.nodelay = Some(nodelay);
+        Ok(())
     }
 
     pub fn request_redirect(
         &mut self,
         func: impl FnMut(&mut N, UninitializedConnection, N::ConnectionServiceType) + 'static,
     ) {
+        self.connection_redirect = Some(Box::from(func));
+
         if self.is_processing_read {
-            self.connection_redirect = Some(Box::from(func));
+            // handle in read process
         } else {
-            unimplemented!();
-            // put into waiting list somehow??
+            // Cancel the pending read. The redirect will be performed once
+            // the read completes, so that no bytes are lost in the process
+            let cancel_e = opcode::AsyncCancel::new(UserData::create_read(self.self_index).into())
+                .build()
+                .user_data(UserData::CancelRead.into());
+            unsafe {
+                let network_manager = self.network_manager.as_ref().unwrap();
+                let mut ring_squeue = network_manager.ring.submission_shared();
+
+                if ring_squeue.push(&cancel_e).is_err() {
+                    let backlog = self.submission_backlog.as_mut().unwrap();
+                    backlog.push_back(cancel_e);
+                }
+            }
         }
     }
 
@@ -252,28 +433,73 @@ impl<N: NetworkManagerService> Connection<N> {
         }
     }
 
+    /// Queues the bytes to be sent
+    ///
+    /// Only one send is in progress at a time. Bytes written in the meantime are buffered
+    /// and sent once it completes, so that bytes are never reordered, even if the send
+    /// buffer of the socket is full and sends only partially complete
+    ///
+    /// If a previous write failed (eg. the remote reset the connection), the connection
+    /// is being closed and the bytes are discarded instead. Bytes that can't be compressed
+    /// because they aren't complete packets also close the connection
     pub fn write(&mut self, bytes: Vec<u8>) {
-        if bytes.is_empty() {
+        if bytes.is_empty() || self.write_failed {
             return;
         }
 
-        // get length and pointer for ffi
-        let bytes_len = bytes.len();
-        let bytes_pointer = &*bytes as *const _ as *const u8;
+        let mut bytes = if let Some(threshold) = self.compression_threshold {
+            match compression::compress_packets(&bytes, threshold) {
+                Ok(compressed) => compressed,
+                Err(_) => {
+                    // The bytes weren't framed correctly, the client would be sent garbage
+                    self.write_failed = true;
+                    if !self.close_requested {
+                        self.request_close();
+                    }
+                    return;
+                }
+            }
+        } else {
+            bytes
+        };
+        if let Some(cipher) = &mut self.cipher {
+            cipher.encrypt(&mut bytes);
+        }
 
-        // put bytes into output_buffers, get an index
-        let write_index = self.write_buffers.insert(bytes);
+        if self.is_sending() {
+            self.pending_writes.extend_from_slice(&bytes);
+        } else {
+            self.send_buffer = bytes;
+            self.send_offset = 0;
+            self.submit_send();
+        }
+    }
 
-        // create user data for write operation, needed in order to drop the write_buffer once finished
-        let write_user_data = UserData::Write {
-            connection_index: self.self_index,
-            write_buffer_index: write_index as u16,
-        };
+    // Decompresses the complete packets in the read buffer, until `BUFFER_SIZE` bytes are waiting to be read
+    fn decompress_read_buffer(&mut self) -> anyhow::Result<()> {
+        let mut bytes = &self.read_buffer[self.rbuff_data_offset..self.rbuff_write_offset];
+        let available = bytes.len();
+
+        let limit = N::ConnectionServiceType::BUFFER_SIZE as usize;
+        let result = compression::decompress_packets(&mut bytes, &mut self.decompressed, limit);
+        self.rbuff_data_offset += available - bytes.len();
+        result
+    }
+
+    fn is_sending(&self) -> bool {
+        !self.send_buffer.is_empty()
+    }
+
+    fn submit_send(&mut self) {
+        // get length and pointer for ffi
+        let remaining = &self.send_buffer[self.send_offset..];
+        let bytes_len = remaining.len();
+        let bytes_pointer = remaining.as_ptr();
 
         // submit the write operation to io_uring
         let write_e = opcode::Send::new(types::Fd(self.fd.0), bytes_pointer, bytes_len as u32)
             .build()
-            .user_data(write_user_data.into());
+            .user_data(UserData::create_write(self.self_index).into());
         unsafe {
             let network_manager = self.network_manager.as_ref().unwrap();
             let mut ring_squeue = network_manager.ring.submission_shared();
@@ -285,16 +511,54 @@ impl<N: NetworkManagerService> Connection<N> {
         }
     }
 
+    fn on_send_completed(&mut self, result: i32) {
+        if !self.is_sending() {
+            return;
+        }
+
+        if result <= 0 {
+            // The remote is gone, close the connection instead of continuing to write to it
+            self.send_buffer = Vec::new();
+            self.pending_writes = Vec::new();
+            self.write_failed = true;
+            if !self.close_requested {
+                self.request_close();
+            }
+            return;
+        }
+
+        self.stats.bytes_written += result as u64;
+        self.send_offset += result as usize;
+        if self.send_offset < self.send_buffer.len() {
+            // Partial send, send the rest
+            self.submit_send();
+            return;
+        }
+
+        // Send the bytes that were written in the meantime, reusing the allocation of the sent bytes
+        std::mem::swap(&mut self.send_buffer, &mut self.pending_writes);
+        self.pending_writes.clear();
+        self.send_offset = 0;
+        if self.is_sending() {
+            self.submit_send();
+        }
+    }
+
     fn close(self) {
         std::mem::drop(self);
     }
 
     fn redirect(mut self, network_service: &mut N, connection_service: N::ConnectionServiceType) {
         debug_assert!(self.connection_redirect.is_some());
-        debug_assert!(self.write_buffers.is_empty());
+        debug_assert!(!self.is_sending());
 
         let unintialized = UninitializedConnection {
             fd: self.fd,
+            nodelay: self.nodelay,
+            compression_threshold: self.compression_threshold,
+            decompressed: self.decompressed,
+            cipher: self.cipher,
+            stats: self.stats,
             rbuff_data_offset: self.rbuff_data_offset,
             rbuff_write_offset: self.rbuff_write_offset,
             read_buffer: self.read_buffer,
@@ -309,6 +573,16 @@ where
     Self: Sized,
 {
     const BUFFER_SIZE: u32 = 4_194_304;
+    /// Connections that leave a packet incomplete for longer than this are closed,
+    /// preventing clients from holding onto a partially-filled read buffer indefinitely.
+    /// Only checked on tick, so this does nothing without a `TICK_RATE`
+    const INCOMPLETE_PACKET_TIMEOUT: Option<Duration> = Some(Duration::from_secs(30));
+    /// Connections that don't receive any bytes for longer than this are closed, same as
+    /// the read timeout of vanilla. Only checked on tick, so this does nothing without a `TICK_RATE`
+    const IDLE_TIMEOUT: Option<Duration> = Some(Duration::from_secs(30));
+    /// Whether Nagle's algorithm is disabled for new connections. Can be changed
+    /// for individual connections using `Connection::set_nodelay`
+    const TCP_NODELAY: bool = true;
     type NetworkManagerServiceType: NetworkManagerService<ConnectionServiceType = Self>;
 
     fn on_receive(
@@ -329,7 +603,10 @@ where
 
     type ConnectionServiceType: ConnectionService<NetworkManagerServiceType = Self>;
 
-    fn new_connection_service(&mut self) -> Self::ConnectionServiceType;
+    /// Creates the connection service for a newly accepted TCP connection
+    ///
+    /// Returning None declines the connection, closing it without a connection service
+    fn new_connection_service(&mut self) -> Option<Self::ConnectionServiceType>;
     fn tick(
         &mut self,
         connections: &mut ConnectionSlab<Self>, // todo: move this field into accepter
@@ -347,6 +624,8 @@ pub struct NetworkManager<N: NetworkManagerService> {
     connections_waiting_for_redirect: Slab<u16>,
     connections_waiting_for_close: Vec<u16>,
 
+    stats: ConnectionStats,
+
     tv_sec: u64,
     tv_nsec: u32,
     current_timespec: Timespec,
@@ -385,6 +664,8 @@ impl<N: NetworkManagerService> NetworkManager<N> {
             connections_waiting_for_redirect: Default::default(),
             connections_waiting_for_close: Default::default(),
 
+            stats: ConnectionStats::default(),
+
             tv_sec: 0,
             tv_nsec: 0,
             current_timespec: Timespec::new(),
@@ -393,11 +674,19 @@ impl<N: NetworkManagerService> NetworkManager<N> {
         })
     }
 
+    /// Combined traffic of every connection, including connections that have been closed
+    pub fn get_stats(&self) -> ConnectionStats {
+        self.stats
+    }
+
     fn start(&mut self, addr: Option<&str>) -> anyhow::Result<()> {
         // Start listening on the address `addr`
+        // Every address that `addr` resolves to is tried in turn, eg. both `[::1]` and `127.0.0.1`
+        // for `localhost`, and an error is only returned if none of them can be bound
         let mut accept = None;
         if let Some(addr) = addr {
-            accept = Some(AcceptCount::new(TcpListener::bind(addr)?, 16));
+            let listener = TcpListener::bind(addr).with_context(|| format!("unable to listen on {}", addr))?;
+            accept = Some(AcceptCount::new(listener, 16));
         }
 
         // Split the ring into submitter and completion queue
@@ -459,9 +748,7 @@ impl<N: NetworkManagerService> NetworkManager<N> {
             self.connections_waiting_for_redirect
                 .retain(|_, connection_index| {
                     let (connection, _) = self.connections.get(*connection_index as _).unwrap();
-                    let is_empty = connection.write_buffers.is_empty();
-
-                    if is_empty {
+                    if !connection.is_sending() {
                         let (connection, service) = self.connections.remove(*connection_index as _);
                         connection.redirect(&mut self.service, service);
 
@@ -472,15 +759,22 @@ impl<N: NetworkManagerService> NetworkManager<N> {
                 });
 
             // Close connections
+            // Connections that requested to be closed are kept open until their pending writes
+            // have completed, so that the last packets (e.g. a disconnect message) aren't lost
             self.connections_waiting_for_close
-                .iter()
-                .for_each(|connection_index| {
+                .retain(|connection_index| {
+                    if let Some((connection, _)) = self.connections.get(*connection_index as _) {
+                        if connection.close_requested && connection.is_sending() {
+                            return true; // keep in waitlist
+                        }
+                    }
+
                     NetworkManager::<N>::try_close_connection_by_index(
                         &mut self.connections,
                         *connection_index,
                     );
+                    false // remove from waitlist
                 });
-            self.connections_waiting_for_close.clear();
 
             // Read all the entries in the completion queue
             for cqe in &mut ring_cqueue {
@@ -490,8 +784,22 @@ impl<N: NetworkManagerService> NetworkManager<N> {
                 // Handle cqe error
                 if result < 0 {
                     match -result {
-                        libc::EALREADY | libc::ECANCELED => continue,
+                        libc::ECANCELED => {
+                            // Read was cancelled in order to redirect the connection
+                            if let UserData::Read { connection_index, .. } = user_data {
+                                NetworkManager::<N>::try_redirect_connection_by_index(
+                                    &mut self.connections,
+                                    &mut self.connections_waiting_for_redirect,
+                                    &mut self.service,
+                                    connection_index,
+                                );
+                            }
+                            continue;
+                        }
+                        libc::EALREADY => continue,
+                        libc::ENOENT if user_data == UserData::CancelRead => continue,
                         libc::ETIME | libc::ECONNRESET => (),
+                        libc::EPIPE if user_data.is_write() => (),
                         err => {
                             const EBADFD: i32 = 9;
                             if user_data.is_write() && err == EBADFD {
@@ -510,18 +818,15 @@ impl<N: NetworkManagerService> NetworkManager<N> {
                 match user_data {
                     // UserData::IOTimeout => (),
                     UserData::CancelRead => (),
-                    UserData::Write {
-                        connection_index,
-                        write_buffer_index,
-                    } => {
-                        // Write has completed, we can drop the associated buffer
+                    UserData::Write { connection_index, .. } => {
+                        // Send has completed, continue with the remaining bytes (if any)
                         if let Some((connection, _)) =
                             self.connections.get_mut(connection_index as usize)
                         {
-                            connection
-                                .write_buffers
-                                .try_remove(write_buffer_index as usize)
-                                .unwrap();
+                            if result > 0 && connection.is_sending() {
+                                self.stats.bytes_written += result as u64;
+                            }
+                            connection.on_send_completed(result);
                         }
                     }
                     UserData::TickTimeout => {
@@ -559,6 +864,28 @@ impl<N: NetworkManagerService> NetworkManager<N> {
                             &self.current_timespec,
                         );
 
+                        // Close connections that have been sitting on an incomplete packet, or
+                        // haven't received anything, for too long. The pending read must be
+                        // cancelled before the read buffer is freed, so `request_close` is used
+                        let incomplete_timeout = N::ConnectionServiceType::INCOMPLETE_PACKET_TIMEOUT;
+                        let idle_timeout = N::ConnectionServiceType::IDLE_TIMEOUT;
+                        for (_, (connection, _)) in self.connections.iter_mut() {
+                            if connection.close_requested {
+                                continue;
+                            }
+
+                            let incomplete = match (connection.incomplete_since, incomplete_timeout) {
+                                (Some(incomplete_since), Some(timeout)) => incomplete_since.elapsed() > timeout,
+                                _ => false,
+                            };
+                            let idle = idle_timeout
+                                .is_some_and(|timeout| connection.last_received.elapsed() > timeout);
+
+                            if incomplete || idle {
+                                connection.request_close();
+                            }
+                        }
+
                         // Call the service-defined tick method
                         let accepter = NewConnectionAccepter {
                             network_manager: self,
@@ -579,6 +906,20 @@ impl<N: NetworkManagerService> NetworkManager<N> {
                             break;
                         }
 
+                        let connection_service = match self.service.new_connection_service() {
+                            Some(connection_service) => connection_service,
+                            None => {
+                                // Connection was declined by the service
+                                unsafe {
+                                    libc::close(fd);
+                                }
+                                if let Some(ref mut accept) = accept {
+                                    accept.count += 1;
+                                }
+                                continue;
+                            }
+                        };
+
                         // Allocate a new connection struct
                         let mut read_buffer =
                             vec![0_u8; N::ConnectionServiceType::BUFFER_SIZE as usize];
@@ -586,12 +927,17 @@ impl<N: NetworkManagerService> NetworkManager<N> {
 
                         let self_ptr: *const NetworkManager<N> = self;
 
+                        // Apply the default nodelay, the socket option persists for the whole connection
+                        let fd = AutoclosingFd(fd);
+                        let nodelay = fd.init_nodelay::<N>(None);
+
                         let vacant_entry = self.connections.vacant_entry();
                         let connection_index = vacant_entry.key();
 
                         vacant_entry.insert((
                             Connection {
-                                fd: AutoclosingFd(fd),
+                                fd,
+                                nodelay,
 
                                 network_manager: self_ptr,
                                 submission_backlog: &mut self.backlog,
@@ -601,16 +947,25 @@ impl<N: NetworkManagerService> NetworkManager<N> {
                                 // connections_waiting_for_redirect: &mut self.connections_waiting_for_redirect,
                                 self_index: connection_index as u16,
                                 is_processing_read: false,
+                                incomplete_since: None,
+                                last_received: Instant::now(),
 
                                 connection_redirect: None,
                                 close_requested: false,
+                                write_failed: false,
+                                compression_threshold: None,
+                                decompressed: Vec::new(),
+                                cipher: None,
+                                stats: ConnectionStats::default(),
 
                                 rbuff_data_offset: 0,
                                 rbuff_write_offset: 0,
                                 read_buffer,
-                                write_buffers: Slab::new(),
+                                send_buffer: Vec::new(),
+                                send_offset: 0,
+                                pending_writes: Vec::new(),
                             },
-                            self.service.new_connection_service(),
+                            connection_service,
                         ));
 
                         // Increase accept count, so we can accept another connection
@@ -654,69 +1009,180 @@ impl<N: NetworkManagerService> NetworkManager<N> {
                             // Sanity check, this should never be set
                             debug_assert!(!connection.close_requested);
 
+                            // A full buffer is only a problem if it doesn't contain any complete
+                            // packets, which is checked once the service has read from it
+                            let read_start = connection.rbuff_data_offset;
+                            let received_start = connection.rbuff_write_offset;
                             connection.rbuff_write_offset += result as usize;
-                            if connection.rbuff_write_offset
-                                >= N::ConnectionServiceType::BUFFER_SIZE as usize
-                            {
-                                // Exceeded buffer size... crap...
-                                NetworkManager::<N>::close_connection_by_index(
-                                    &mut self.connections,
-                                    connection_index,
+                            connection.last_received = Instant::now();
+                            connection.stats.bytes_read += result as u64;
+                            self.stats.bytes_read += result as u64;
+
+                            // Decrypt the received bytes, before anything else reads them
+                            let encrypted = connection.cipher.is_some();
+                            if let Some(cipher) = &mut connection.cipher {
+                                cipher.decrypt(
+                                    &mut connection.read_buffer[received_start..connection.rbuff_write_offset],
                                 );
-                                continue;
                             }
 
-                            // Call the service-defined receive method
-                            connection.is_processing_read = true;
-                            let receive_result = connection_service.on_receive(connection);
-                            connection.is_processing_read = false;
+                            // Decompress the complete packets, leaving partial packets in the read buffer
+                            let compressed = connection.compression_threshold.is_some();
+                            if compressed {
+                                if let Err(err) = connection.decompress_read_buffer() {
+                                    eprintln!("error: {:?}", err);
+                                    NetworkManager::<N>::close_connection_by_index(
+                                        &mut self.connections,
+                                        connection_index,
+                                    );
+                                    continue;
+                                }
+                            }
 
-                            if let Err(err) = receive_result {
-                                // Error during handling
-                                eprintln!("error: {:?}", err);
-                                NetworkManager::<N>::close_connection_by_index(
+                            if connection.connection_redirect.is_some() {
+                                // Redirect was requested outside of a read, the received
+                                // bytes are kept in the read buffer for the new service
+                                NetworkManager::<N>::try_redirect_connection_by_index(
                                     &mut self.connections,
+                                    &mut self.connections_waiting_for_redirect,
+                                    &mut self.service,
                                     connection_index,
                                 );
                                 continue;
                             }
 
+                            let receive_result = loop {
+                                // Call the service-defined receive method
+                                connection.is_processing_read = true;
+                                let receive_result = connection_service.on_receive(connection);
+                                connection.is_processing_read = false;
+
+                                let Ok(mut remaining_bytes) = receive_result else {
+                                    break receive_result;
+                                };
+
+                                // Count the packets that were read, the read bytes only contain complete packets
+                                let read_bytes = if compressed {
+                                    &connection.decompressed
+                                } else {
+                                    &connection.read_buffer[connection.rbuff_data_offset..connection.rbuff_write_offset]
+                                };
+                                let packets_received =
+                                    count_packets(&read_bytes[..read_bytes.len().saturating_sub(remaining_bytes as usize)]);
+                                connection.stats.packets_received += packets_received;
+                                self.stats.packets_received += packets_received;
+
+                                if compressed {
+                                    // The service read from the decompressed packets, the read buffer
+                                    // now only contains the packets that haven't been decompressed yet
+                                    let read = connection.decompressed.len() - remaining_bytes as usize;
+                                    connection.decompressed.drain(..read);
+                                    remaining_bytes =
+                                        (connection.rbuff_write_offset - connection.rbuff_data_offset) as u32;
+
+                                    // Packets that were left in the read buffer because of the decompression
+                                    // limit are decompressed once the service has read everything else,
+                                    // unless encryption was enabled and the bytes still need to be decrypted
+                                    let can_read_more = connection.decompressed.is_empty()
+                                        && encrypted == connection.cipher.is_some()
+                                        && !connection.close_requested
+                                        && connection.connection_redirect.is_none();
+                                    if can_read_more && remaining_bytes > 0 {
+                                        if let Err(err) = connection.decompress_read_buffer() {
+                                            break Err(err);
+                                        }
+                                        if !connection.decompressed.is_empty() {
+                                            continue;
+                                        }
+                                    }
+                                }
+
+                                break Ok(remaining_bytes);
+                            };
+
+                            let remaining_bytes = match receive_result {
+                                Ok(remaining_bytes) => remaining_bytes,
+                                Err(err) => {
+                                    // Error during handling
+                                    eprintln!("error: {:?}", err);
+                                    NetworkManager::<N>::close_connection_by_index(
+                                        &mut self.connections,
+                                        connection_index,
+                                    );
+                                    continue;
+                                }
+                            };
+
                             // Update read and write offsets
-                            let remaining_bytes = receive_result.unwrap();
+                            if !encrypted {
+                                if let Some(cipher) = &mut connection.cipher {
+                                    // Encryption was enabled during the read, the unread bytes
+                                    // were sent after the client enabled it
+                                    let unread_start =
+                                        connection.rbuff_write_offset - remaining_bytes as usize;
+                                    cipher.decrypt(
+                                        &mut connection.read_buffer[unread_start..connection.rbuff_write_offset],
+                                    );
+                                }
+                            }
                             if remaining_bytes == 0 {
                                 // Fully read
 
                                 // Since we fully read, we can start receving again from the start of the buffer
                                 connection.rbuff_data_offset = 0;
                                 connection.rbuff_write_offset = 0;
+                                connection.incomplete_since = None;
+                            } else if remaining_bytes >= N::ConnectionServiceType::BUFFER_SIZE {
+                                // The whole buffer is a single incomplete packet which can never
+                                // be completed. Exceeded buffer size... crap...
+                                NetworkManager::<N>::close_connection_by_index(
+                                    &mut self.connections,
+                                    connection_index,
+                                );
+                                continue;
                             } else {
                                 // Partial read
 
-                                // Set the `data_offset` to the start of the partially-unread data
-                                connection.rbuff_data_offset =
+                                // Move the partially-unread data to the start of the buffer, so that
+                                // the buffer size limits the size of a single packet rather than
+                                // the total amount of data received while packets are incomplete
+                                let unread_start =
                                     connection.rbuff_write_offset - remaining_bytes as usize;
+                                let made_progress = unread_start > read_start;
+                                connection
+                                    .read_buffer
+                                    .copy_within(unread_start..connection.rbuff_write_offset, 0);
+                                connection.rbuff_data_offset = 0;
+                                connection.rbuff_write_offset = remaining_bytes as usize;
 
-                                // Keep reading from the existing write offset
+                                // Only restart the timer when a packet was completed, a client
+                                // sending a single packet slowly shouldn't be able to keep it alive
+                                if made_progress || connection.incomplete_since.is_none() {
+                                    connection.incomplete_since = Some(Instant::now());
+                                }
+
+                                // Keep reading from the new write offset
                             }
 
                             if connection.close_requested {
                                 // Close requested
-                                NetworkManager::<N>::close_connection_by_index(
+                                if !connection.is_sending() {
+                                    NetworkManager::<N>::close_connection_by_index(
+                                        &mut self.connections,
+                                        connection_index,
+                                    );
+                                } else {
+                                    // Pending write, close once it has completed
+                                    self.connections_waiting_for_close.push(connection_index);
+                                }
+                            } else if connection.connection_redirect.is_some() {
+                                // Redirect requested
+                                NetworkManager::<N>::try_redirect_connection_by_index(
                                     &mut self.connections,
+                                    &mut self.connections_waiting_for_redirect,
+                                    &mut self.service,
                                     connection_index,
                                 );
-                            } else if connection.connection_redirect.is_some() {
-                                // Redirect requested
-                                if !connection.write_buffers.is_empty() {
-                                    // Pending write, add connection to the wait list
-                                    self.connections_waiting_for_redirect
-                                        .insert(connection_index);
-                                } else {
-                                    // No pending writes, we can redirect the connection immediately
-                                    let (connection, service) =
-                                        self.connections.remove(connection_index as _);
-                                    connection.redirect(&mut self.service, service);
-                                }
                             } else {
                                 // Re-queue the recv event, lets read some more data!
                                 let read_buffer_ptr = unsafe {
@@ -791,6 +1257,28 @@ impl<N: NetworkManagerService> NetworkManager<N> {
         }
     }
 
+    fn try_redirect_connection_by_index(
+        connections: &mut ConnectionSlab<N>,
+        connections_waiting_for_redirect: &mut Slab<u16>,
+        service: &mut N,
+        connection_index: u16,
+    ) {
+        if let Some((connection, _)) = connections.get(connection_index as _) {
+            if connection.connection_redirect.is_none() {
+                return;
+            }
+
+            if connection.is_sending() {
+                // Pending write, add connection to the wait list
+                connections_waiting_for_redirect.insert(connection_index);
+            } else {
+                // No pending writes, we can redirect the connection immediately
+                let (connection, connection_service) = connections.remove(connection_index as _);
+                connection.redirect(service, connection_service);
+            }
+        }
+    }
+
     fn push_tick_timeout_event(
         mut sq: SubmissionQueue,
         backlog: &mut VecDeque<squeue::Entry>,
@@ -828,3 +1316,673 @@ impl<N: NetworkManagerService> NetworkManager<N> {
         }
     }
 }
+
+fn count_packets(mut bytes: &[u8]) -> u64 {
+    let mut count = 0;
+    while let Ok(PacketReadResult::Complete(_)) = packet_helper::try_read_packet(&mut bytes) {
+        count += 1;
+    }
+    count
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{Read, Write},
+        net::{TcpListener, TcpStream},
+        os::unix::net::UnixStream,
+        sync::mpsc::{self, Receiver, Sender},
+        thread::JoinHandle,
+        time::Duration,
+    };
+
+    use anyhow::bail;
+    use graphite_mc_protocol::play::server::KeepAlive;
+
+    use super::*;
+    use crate::{network_buffer::WriteBuffer, packet_helper};
+
+    // Loopback harness
+    // The network manager runs on its own thread, with one side of a unix socket pair
+    // given to it as a connection. The test drives the other side as the "client"
+
+    struct EchoService {
+        pending: Receiver<UninitializedConnection>,
+        accepted: bool,
+        options: EchoOptions,
+    }
+
+    #[derive(Clone, Default)]
+    struct EchoOptions {
+        // Applied to the connection before echoing the first packet
+        nodelay: Option<bool>,
+        // If non-zero, every packet is answered with this many numbered keep alives instead
+        flood: u64,
+        // Compression threshold set on the connection once it has been accepted
+        compression: Option<usize>,
+        // Encryption is enabled after echoing the first packet, like an Encryption Response
+        encryption: Option<[u8; 16]>,
+        // Receives the stats of the connection and the network manager on every tick
+        stats: Option<Sender<(ConnectionStats, ConnectionStats)>>,
+    }
+
+    struct EchoConnectionService {
+        nodelay: Option<bool>,
+        flood: u64,
+        encryption: Option<[u8; 16]>,
+    }
+
+    impl ConnectionService for EchoConnectionService {
+        const BUFFER_SIZE: u32 = 1024;
+        const INCOMPLETE_PACKET_TIMEOUT: Option<Duration> = Some(Duration::from_millis(100));
+        const IDLE_TIMEOUT: Option<Duration> = Some(Duration::from_secs(1));
+        type NetworkManagerServiceType = EchoService;
+
+        fn on_receive(&mut self, connection: &mut Connection<EchoService>) -> anyhow::Result<u32> {
+            if let Some(nodelay) = self.nodelay.take() {
+                connection.set_nodelay(nodelay)?;
+            }
+
+            if let Some(shared_secret) = self.encryption.take() {
+                let all_bytes = connection.read_bytes();
+                let mut bytes = all_bytes;
+                let first_packet = packet_helper::try_read_packet(&mut bytes)?;
+                if !matches!(first_packet, packet_helper::PacketReadResult::Complete(_)) {
+                    bail!("expected a complete packet before enabling encryption");
+                }
+
+                let remaining = bytes.len();
+                let echo = all_bytes[..all_bytes.len() - remaining].to_vec();
+                connection.write(echo);
+                connection.enable_encryption(shared_secret);
+                return Ok(remaining as u32);
+            }
+
+            let all_bytes = connection.read_bytes();
+            let mut bytes = all_bytes;
+
+            // Echo every complete packet, leaving partial packets in the buffer
+            while let packet_helper::PacketReadResult::Complete(_) =
+                packet_helper::try_read_packet(&mut bytes)?
+            {}
+
+            let remaining = bytes.len();
+            if self.flood > 0 {
+                for id in 0..self.flood {
+                    connection.write(keep_alive_bytes(id));
+                }
+            } else {
+                let echo = all_bytes[..all_bytes.len() - remaining].to_vec();
+                connection.write(echo);
+            }
+
+            Ok(remaining as u32)
+        }
+    }
+
+    impl NetworkManagerService for EchoService {
+        const TICK_RATE: Option<Duration> = Some(Duration::from_millis(1));
+        type ConnectionServiceType = EchoConnectionService;
+
+        fn new_connection_service(&mut self) -> Option<Self::ConnectionServiceType> {
+            Some(EchoConnectionService { nodelay: None, flood: 0, encryption: None })
+        }
+
+        fn tick(
+            &mut self,
+            connections: &mut ConnectionSlab<Self>,
+            accepter: NewConnectionAccepter<Self>,
+        ) -> anyhow::Result<()> {
+            if let Ok(connection) = self.pending.try_recv() {
+                let connection_service = EchoConnectionService {
+                    nodelay: self.options.nodelay,
+                    flood: self.options.flood,
+                    encryption: self.options.encryption,
+                };
+                let index = accepter.accept_and_get_index(connection, connection_service, connections)?;
+                connections.get_mut(index as _).unwrap().0.set_compression(self.options.compression);
+                self.accepted = true;
+            } else if self.accepted && connections.is_empty() {
+                // Client disconnected, stop the network manager
+                bail!("loopback closed");
+            }
+
+            if let Some(stats) = &self.options.stats {
+                for (_, (connection, _)) in connections.iter() {
+                    let _ = stats.send((connection.get_stats(), connection.get_network_manager().get_stats()));
+                }
+            }
+            Ok(())
+        }
+    }
+
+    fn create_loopback() -> (UnixStream, JoinHandle<()>) {
+        let (client, server) = UnixStream::pair().unwrap();
+        client.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+
+        (client, start_loopback(server, EchoOptions::default()))
+    }
+
+    // Same as `create_loopback`, but over TCP. Also returns a clone of the server side of the
+    // socket, which can be used to inspect the socket options set by the network manager
+    fn create_tcp_loopback(nodelay: Option<bool>) -> (TcpStream, TcpStream, JoinHandle<()>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        client.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        let (server, _) = listener.accept().unwrap();
+        let server_clone = server.try_clone().unwrap();
+
+        (client, server_clone, start_loopback(server, EchoOptions { nodelay, ..Default::default() }))
+    }
+
+    fn start_loopback(server: impl IntoRawFd, options: EchoOptions) -> JoinHandle<()> {
+        start_loopback_listening(server, options, None)
+    }
+
+    // Same as `start_loopback`, but the network manager also listens for TCP connections on `addr`
+    fn start_loopback_listening(server: impl IntoRawFd, options: EchoOptions,
+            addr: Option<&'static str>) -> JoinHandle<()> {
+        start_loopback_many(vec![server], options, addr)
+    }
+
+    // Same as `start_loopback_listening`, but with several connections that are accepted in turn
+    fn start_loopback_many(servers: Vec<impl IntoRawFd>, options: EchoOptions,
+            addr: Option<&'static str>) -> JoinHandle<()> {
+        let (sender, receiver) = mpsc::channel();
+        let buffer_size = EchoConnectionService::BUFFER_SIZE as usize;
+        for server in servers {
+            sender.send(UninitializedConnection::from_stream(server, buffer_size)).unwrap();
+        }
+
+        std::thread::spawn(move || {
+            let service = EchoService {
+                pending: receiver,
+                accepted: false,
+                options,
+            };
+            let result = start(service, addr);
+            assert_eq!(result.unwrap_err().to_string(), "loopback closed");
+        })
+    }
+
+    fn keep_alive_bytes(id: u64) -> Vec<u8> {
+        let mut write_buffer = WriteBuffer::new();
+        packet_helper::write_packet(&mut write_buffer, &KeepAlive { id }).unwrap();
+        write_buffer.get_written().to_vec()
+    }
+
+    #[test]
+    fn listen_unresolvable_address() {
+        let (_, receiver) = mpsc::channel();
+        let service = EchoService {
+            pending: receiver,
+            accepted: false,
+            options: EchoOptions::default(),
+        };
+
+        let result = start(service, Some("graphite.invalid:25565"));
+        let err = result.unwrap_err();
+        assert_eq!(err.to_string(), "unable to listen on graphite.invalid:25565");
+    }
+
+    #[test]
+    fn listen_ipv6() {
+        let (mut client, server) = UnixStream::pair().unwrap();
+        client.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        let handle = start_loopback_listening(server, EchoOptions::default(), Some("[::1]:0"));
+
+        // The network manager only runs, and echoes, if it was able to listen
+        let sent = keep_alive_bytes(1);
+        client.write_all(&sent).unwrap();
+        let mut received = vec![0_u8; sent.len()];
+        client.read_exact(&mut received).unwrap();
+        assert_eq!(received, sent);
+
+        drop(client);
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn loopback_echo() {
+        let (mut client, handle) = create_loopback();
+
+        let sent = keep_alive_bytes(1234);
+        client.write_all(&sent).unwrap();
+
+        let mut received = vec![0_u8; sent.len()];
+        client.read_exact(&mut received).unwrap();
+        assert_eq!(received, sent);
+
+        drop(client);
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn loopback_partial_read() {
+        let (mut client, handle) = create_loopback();
+
+        // Send one and a half packets, then the rest of the second packet
+        let first = keep_alive_bytes(1);
+        let second = keep_alive_bytes(2);
+        let (second_start, second_end) = second.split_at(second.len() / 2);
+
+        client.write_all(&first).unwrap();
+        client.write_all(second_start).unwrap();
+
+        let mut received = vec![0_u8; first.len()];
+        client.read_exact(&mut received).unwrap();
+        assert_eq!(received, first);
+
+        // The partial packet must be carried over to the next read
+        std::thread::sleep(Duration::from_millis(10));
+        client.write_all(second_end).unwrap();
+
+        let mut received = vec![0_u8; second.len()];
+        client.read_exact(&mut received).unwrap();
+        assert_eq!(received, second);
+
+        drop(client);
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn loopback_many_partial_reads() {
+        let (mut client, handle) = create_loopback();
+
+        // Split every packet across two reads, so that there is always a partial packet
+        // The total amount sent is larger than the read buffer
+        let packets: Vec<Vec<u8>> = (0..200).map(keep_alive_bytes).collect();
+        let total: usize = packets.iter().map(Vec::len).sum();
+        assert!(total > EchoConnectionService::BUFFER_SIZE as usize);
+
+        for packet in &packets {
+            let (start, end) = packet.split_at(packet.len() / 2);
+            client.write_all(start).unwrap();
+            std::thread::sleep(Duration::from_millis(1));
+            client.write_all(end).unwrap();
+        }
+
+        let mut received = vec![0_u8; total];
+        client.read_exact(&mut received).unwrap();
+        assert_eq!(received, packets.concat());
+
+        drop(client);
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn loopback_split_packet() {
+        let (mut client, handle) = create_loopback();
+
+        // 5 byte packet, sent as 3 bytes and then 2 bytes
+        let packet = [4_u8, 1, 2, 3, 4];
+        client.write_all(&packet[..3]).unwrap();
+        std::thread::sleep(Duration::from_millis(10));
+        client.write_all(&packet[3..]).unwrap();
+
+        let mut received = [0_u8; 5];
+        client.read_exact(&mut received).unwrap();
+        assert_eq!(received, packet);
+
+        // The packet must only be handled once
+        client.set_read_timeout(Some(Duration::from_millis(50))).unwrap();
+        let mut extra = [0_u8; 1];
+        assert!(client.read(&mut extra).is_err());
+
+        drop(client);
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn loopback_full_read_buffer() {
+        let (mut client, handle) = create_loopback();
+
+        // Complete packets that exactly fill the read buffer
+        let mut sent = Vec::new();
+        for id in 0..8 {
+            sent.push(127);
+            sent.extend_from_slice(&[id; 127]);
+        }
+        assert_eq!(sent.len(), EchoConnectionService::BUFFER_SIZE as usize);
+        client.write_all(&sent).unwrap();
+
+        let mut received = vec![0_u8; sent.len()];
+        client.read_exact(&mut received).unwrap();
+        assert_eq!(received, sent);
+
+        drop(client);
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn loopback_oversized_packet() {
+        let (mut client, handle) = create_loopback();
+
+        // Declare a packet larger than the read buffer and keep sending its bytes
+        let mut header = Vec::new();
+        graphite_binary::varint::encode::extend_i32(&mut header, 2048);
+        let _ = client.write_all(&header);
+        for _ in 0..16 {
+            let _ = client.write_all(&[0_u8; 128]);
+        }
+
+        // The network manager must close the connection
+        let mut received = Vec::new();
+        let _ = client.read_to_end(&mut received);
+        assert!(received.is_empty());
+
+        drop(client);
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn loopback_incomplete_packet_timeout() {
+        let (mut client, handle) = create_loopback();
+
+        // Send half of a packet, and never send the rest
+        let packet = keep_alive_bytes(1);
+        client.write_all(&packet[..packet.len() / 2]).unwrap();
+
+        // The network manager must close the connection
+        let mut received = Vec::new();
+        client.read_to_end(&mut received).unwrap();
+        assert!(received.is_empty());
+
+        drop(client);
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn loopback_write_after_timeout() {
+        // A second connection keeps the network manager running after the first is closed
+        let (mut client, server) = UnixStream::pair().unwrap();
+        let (mut other_client, other_server) = UnixStream::pair().unwrap();
+        client.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        other_client.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        let handle = start_loopback_many(vec![server, other_server], EchoOptions::default(), None);
+
+        let packet = keep_alive_bytes(1);
+        client.write_all(&packet[..packet.len() / 2]).unwrap();
+
+        // The pending read must be cancelled, otherwise the socket stays open
+        let mut received = Vec::new();
+        client.read_to_end(&mut received).unwrap();
+        assert!(received.is_empty());
+
+        // Bytes sent after the timeout must not be received into the freed read buffer
+        let _ = client.write_all(&packet);
+        std::thread::sleep(Duration::from_millis(10));
+
+        other_client.write_all(&packet).unwrap();
+        let mut received = vec![0_u8; packet.len()];
+        other_client.read_exact(&mut received).unwrap();
+        assert_eq!(received, packet);
+
+        drop(other_client);
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn loopback_idle_timeout() {
+        let (mut client, handle) = create_loopback();
+        let start = Instant::now();
+
+        // Never send anything, the network manager must close the connection
+        let mut received = Vec::new();
+        client.read_to_end(&mut received).unwrap();
+        assert!(received.is_empty());
+        assert!(start.elapsed() >= EchoConnectionService::IDLE_TIMEOUT.unwrap());
+
+        drop(client);
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn loopback_write_after_idle_timeout() {
+        let (mut client, server) = UnixStream::pair().unwrap();
+        let (mut other_client, other_server) = UnixStream::pair().unwrap();
+        client.set_read_timeout(Some(Duration::from_millis(100))).unwrap();
+        other_client.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        let handle = start_loopback_many(vec![server, other_server], EchoOptions::default(), None);
+
+        // The second connection keeps sending, so that only the first one is idle
+        let packet = keep_alive_bytes(1);
+        let deadline = Instant::now() + Duration::from_secs(5);
+        loop {
+            other_client.write_all(&packet).unwrap();
+            let mut received = vec![0_u8; packet.len()];
+            other_client.read_exact(&mut received).unwrap();
+
+            // The pending read must be cancelled, otherwise the socket stays open
+            match client.read(&mut [0_u8; 1]) {
+                Ok(0) => break,
+                Ok(_) => panic!("idle connection received bytes"),
+                Err(_) => assert!(Instant::now() < deadline, "idle connection wasn't closed"),
+            }
+        }
+
+        // Bytes sent after the timeout must not be received into the freed read buffer
+        let _ = client.write_all(&packet);
+        std::thread::sleep(Duration::from_millis(10));
+
+        other_client.write_all(&packet).unwrap();
+        let mut received = vec![0_u8; packet.len()];
+        other_client.read_exact(&mut received).unwrap();
+        assert_eq!(received, packet);
+
+        drop(other_client);
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn loopback_nodelay() {
+        // Nagle's algorithm is disabled by default
+        let (mut client, server, handle) = create_tcp_loopback(None);
+        let sent = keep_alive_bytes(1);
+        client.write_all(&sent).unwrap();
+        let mut received = vec![0_u8; sent.len()];
+        client.read_exact(&mut received).unwrap();
+        assert!(server.nodelay().unwrap());
+
+        drop(client);
+        handle.join().unwrap();
+
+        // The connection service can enable Nagle's algorithm mid-connection
+        let (mut client, server, handle) = create_tcp_loopback(Some(false));
+        client.write_all(&sent).unwrap();
+        client.read_exact(&mut received).unwrap();
+        assert!(!server.nodelay().unwrap());
+
+        drop(client);
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn loopback_write_error() {
+        let (mut client, handle) = create_loopback();
+
+        // Sending to a socket that can't be read from fails with EPIPE
+        client.shutdown(std::net::Shutdown::Read).unwrap();
+        client.write_all(&keep_alive_bytes(1)).unwrap();
+
+        // The failed echo closes the connection, even though the client is still open
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !handle.is_finished() {
+            assert!(Instant::now() < deadline, "connection wasn't closed after a failed write");
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        handle.join().unwrap();
+
+        drop(client);
+    }
+
+    #[test]
+    fn loopback_full_send_buffer() {
+        let (mut client, server) = UnixStream::pair().unwrap();
+        client.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+
+        // Use the smallest send buffer possible, so that the writes can't complete immediately
+        let size: libc::c_int = 1;
+        let result = unsafe {
+            libc::setsockopt(server.as_raw_fd(), libc::SOL_SOCKET, libc::SO_SNDBUF,
+                &size as *const _ as *const libc::c_void, std::mem::size_of_val(&size) as _)
+        };
+        assert_eq!(result, 0);
+
+        let count = 10_000;
+        let handle = start_loopback(server, EchoOptions { flood: count, ..Default::default() });
+        client.write_all(&keep_alive_bytes(0)).unwrap();
+
+        // Every byte must arrive, in the order it was written
+        let mut expected = Vec::new();
+        for id in 0..count {
+            expected.extend_from_slice(&keep_alive_bytes(id));
+        }
+        let mut received = vec![0_u8; expected.len()];
+        client.read_exact(&mut received).unwrap();
+        assert!(received == expected, "written bytes were lost or reordered");
+
+        drop(client);
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn loopback_compression() {
+        let (mut client, server) = UnixStream::pair().unwrap();
+        client.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        let handle = start_loopback(server, EchoOptions { compression: Some(256), ..Default::default() });
+
+        // One packet below the threshold and one above it, the second is larger than the read buffer
+        let mut uncompressed = Vec::new();
+        for packet in [vec![0x21; 9], vec![0x20; 2000]] {
+            graphite_binary::varint::encode::extend_i32(&mut uncompressed, packet.len() as i32);
+            uncompressed.extend_from_slice(&packet);
+        }
+
+        let sent = compression::compress_packets(&uncompressed, 256).unwrap();
+        assert!(sent.len() < EchoConnectionService::BUFFER_SIZE as usize);
+        client.write_all(&sent).unwrap();
+
+        // The echo is compressed in the same way
+        let mut received = vec![0_u8; sent.len()];
+        client.read_exact(&mut received).unwrap();
+        let mut received_bytes = received.as_slice();
+        let mut decompressed = Vec::new();
+        compression::decompress_packets(&mut received_bytes, &mut decompressed, usize::MAX).unwrap();
+        assert!(received_bytes.is_empty());
+        assert_eq!(decompressed, uncompressed);
+
+        drop(client);
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn loopback_compression_limit() {
+        let (mut client, server) = UnixStream::pair().unwrap();
+        client.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        let handle = start_loopback(server, EchoOptions { compression: Some(256), ..Default::default() });
+
+        // Many small compressed packets, which decompress to much more than the read buffer
+        let mut uncompressed = Vec::new();
+        for id in 0..20 {
+            graphite_binary::varint::encode::extend_i32(&mut uncompressed, 500);
+            uncompressed.extend_from_slice(&[id; 500]);
+        }
+
+        let sent = compression::compress_packets(&uncompressed, 256).unwrap();
+        assert!(sent.len() < EchoConnectionService::BUFFER_SIZE as usize);
+        assert!(uncompressed.len() > 8 * EchoConnectionService::BUFFER_SIZE as usize);
+        client.write_all(&sent).unwrap();
+
+        // Every packet is echoed, even though they couldn't all be decompressed at once
+        let mut received = vec![0_u8; sent.len()];
+        client.read_exact(&mut received).unwrap();
+        let mut received_bytes = received.as_slice();
+        let mut decompressed = Vec::new();
+        compression::decompress_packets(&mut received_bytes, &mut decompressed, usize::MAX).unwrap();
+        assert!(received_bytes.is_empty());
+        assert_eq!(decompressed, uncompressed);
+
+        drop(client);
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn loopback_encryption() {
+        let (mut client, server) = UnixStream::pair().unwrap();
+        client.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        let shared_secret = [7; 16];
+        let handle = start_loopback(server, EchoOptions { encryption: Some(shared_secret), ..Default::default() });
+        let mut cipher = ConnectionCipher::new(shared_secret);
+
+        // The encrypted packet arrives in the same read as the packet that enables encryption
+        let first = keep_alive_bytes(1);
+        let second = keep_alive_bytes(2);
+        let mut sent = first.clone();
+        let mut encrypted = second.clone();
+        cipher.encrypt(&mut encrypted);
+        sent.extend_from_slice(&encrypted);
+        client.write_all(&sent).unwrap();
+
+        // The first echo is sent before encryption is enabled
+        let mut received = vec![0_u8; first.len()];
+        client.read_exact(&mut received).unwrap();
+        assert_eq!(received, first);
+
+        // Unread bytes are only handled on the next read, along with the newly received bytes
+        let third = keep_alive_bytes(3);
+        let mut encrypted = third.clone();
+        cipher.encrypt(&mut encrypted);
+        client.write_all(&encrypted).unwrap();
+
+        let mut expected = second;
+        expected.extend_from_slice(&third);
+        let mut received = vec![0_u8; expected.len()];
+        client.read_exact(&mut received).unwrap();
+        assert_ne!(received, expected);
+        cipher.decrypt(&mut received);
+        assert_eq!(received, expected);
+
+        drop(client);
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn loopback_stats() {
+        let (mut client, server) = UnixStream::pair().unwrap();
+        client.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        let (stats_sender, stats_receiver) = mpsc::channel();
+        let handle = start_loopback(server, EchoOptions { stats: Some(stats_sender), ..Default::default() });
+
+        // Send three packets, the last of which is split over two reads
+        let mut sent = Vec::new();
+        for id in 0..3 {
+            sent.extend_from_slice(&keep_alive_bytes(id));
+        }
+        let (sent_start, sent_end) = sent.split_at(sent.len() - 2);
+        client.write_all(sent_start).unwrap();
+        std::thread::sleep(Duration::from_millis(10));
+        client.write_all(sent_end).unwrap();
+
+        let mut received = vec![0_u8; sent.len()];
+        client.read_exact(&mut received).unwrap();
+
+        let expected = ConnectionStats {
+            bytes_read: sent.len() as u64,
+            bytes_written: sent.len() as u64,
+            packets_received: 3,
+        };
+        let deadline = Instant::now() + Duration::from_secs(5);
+        loop {
+            let (connection_stats, manager_stats) = stats_receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+            if connection_stats == expected {
+                assert_eq!(manager_stats, expected);
+                break;
+            }
+            assert!(Instant::now() < deadline, "stats weren't updated: {:?}", connection_stats);
+        }
+
+        drop(client);
+        handle.join().unwrap();
+    }
+}
@@ -5,6 +5,7 @@ use graphite_binary::varint;
 use graphite_mc_constants::entity::Metadata;
 use graphite_mc_protocol::IdentifiedPacket;
 use std::fmt::Debug;
+use std::io::Read;
 use thiserror::Error;
 
 pub fn write_slice_serializable<'a, T>(write_buffer: &mut WriteBuffer, serializable: &'a T)
@@ -151,6 +152,17 @@ where
     write_custom_packet(write_buffer, packet.get_packet_id_as_u8(), packet)
 }
 
+/// Serializes a packet including the length and packet id, so that the same bytes
+/// can be written to many connections without serializing the packet again
+pub fn serialize_packet<'a, I: Debug, T>(packet: &'a T) -> anyhow::Result<Vec<u8>>
+where
+    T: SliceSerializable<'a, T> + IdentifiedPacket<I> + 'a,
+{
+    let mut write_buffer = WriteBuffer::new();
+    write_packet(&mut write_buffer, packet)?;
+    Ok(write_buffer.into_written())
+}
+
 pub enum PacketReadResult<'a> {
     Complete(&'a [u8]),
     Partial,
@@ -161,6 +173,8 @@ pub enum PacketReadResult<'a> {
 pub enum PacketReadBufferError {
     #[error("received packet exceeds maximum size of 2097148")]
     PacketTooBig,
+    #[error("stream ended in the middle of a packet")]
+    UnexpectedEof,
 }
 
 const MAXIMUM_PACKET_SIZE: usize = 2097148;
@@ -203,3 +217,130 @@ pub fn try_read_packet<'a>(slice: &mut &'a [u8]) -> anyhow::Result<PacketReadRes
     // Not enough bytes to fully read, emit [varint header + remaining data] as partial read
     Ok(PacketReadResult::Partial)
 }
+
+/// Reads framed packets from any `Read`, such as a file containing a captured packet stream
+///
+/// A single buffer is reused for all packets, growing only when a packet doesn't fit
+pub struct PacketStreamReader<R: Read> {
+    reader: R,
+    buffer: Vec<u8>,
+    read_offset: usize,
+    write_offset: usize,
+}
+
+impl<R: Read> PacketStreamReader<R> {
+    const INITIAL_CAPACITY: usize = 4096;
+
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            buffer: vec![0; Self::INITIAL_CAPACITY],
+            read_offset: 0,
+            write_offset: 0,
+        }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Returns the next complete packet (packet id + data), or `None` if the stream has ended
+    pub fn next_packet(&mut self) -> anyhow::Result<Option<&[u8]>> {
+        loop {
+            let mut bytes = &self.buffer[self.read_offset..self.write_offset];
+            let remaining_before = bytes.len();
+
+            if let PacketReadResult::Complete(packet) = try_read_packet(&mut bytes)? {
+                let packet_end = self.read_offset + remaining_before - bytes.len();
+                let packet_start = packet_end - packet.len();
+                self.read_offset = packet_end;
+                return Ok(Some(&self.buffer[packet_start..packet_end]));
+            }
+
+            // Not enough bytes for a complete packet, move the partial packet to the front
+            if self.read_offset > 0 {
+                self.buffer.copy_within(self.read_offset..self.write_offset, 0);
+                self.write_offset -= self.read_offset;
+                self.read_offset = 0;
+            }
+
+            // Grow the buffer if the partial packet fills it entirely
+            if self.write_offset == self.buffer.len() {
+                if self.buffer.len() >= MAXIMUM_PACKET_SIZE + 3 {
+                    return Err(PacketReadBufferError::PacketTooBig.into());
+                }
+                let new_len = (self.buffer.len() * 2).min(MAXIMUM_PACKET_SIZE + 3);
+                self.buffer.resize(new_len, 0);
+            }
+
+            let read = match self.reader.read(&mut self.buffer[self.write_offset..]) {
+                Ok(read) => read,
+                Err(err) if err.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err.into()),
+            };
+
+            if read == 0 {
+                if self.write_offset == 0 {
+                    return Ok(None);
+                } else {
+                    return Err(PacketReadBufferError::UnexpectedEof.into());
+                }
+            }
+            self.write_offset += read;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use graphite_mc_protocol::play::server::KeepAlive;
+
+    use super::*;
+
+    // Reader that only returns a few bytes at a time, to force partial reads
+    struct SlowReader<'a>(&'a [u8]);
+
+    impl<'a> Read for SlowReader<'a> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let amount = buf.len().min(self.0.len()).min(3);
+            buf[..amount].copy_from_slice(&self.0[..amount]);
+            self.0 = &self.0[amount..];
+            Ok(amount)
+        }
+    }
+
+    #[test]
+    fn read_packet_stream() {
+        let mut write_buffer = WriteBuffer::new();
+        for id in 0..1000 {
+            write_packet(&mut write_buffer, &KeepAlive { id }).unwrap();
+        }
+        // Packet of size 1
+        write_buffer.copy_from(&[1, 0x20]);
+
+        let mut reader = PacketStreamReader::new(SlowReader(write_buffer.get_written()));
+        let mut count = 0;
+        while let Some(packet) = reader.next_packet().unwrap() {
+            if count < 1000 {
+                assert_eq!(packet.len(), 9);
+            } else {
+                assert_eq!(packet, &[0x20]);
+            }
+            count += 1;
+        }
+        assert_eq!(count, 1001);
+    }
+
+    #[test]
+    fn read_packet_stream_eof() {
+        let mut write_buffer = WriteBuffer::new();
+        write_packet(&mut write_buffer, &KeepAlive { id: 0 }).unwrap();
+        write_packet(&mut write_buffer, &KeepAlive { id: 1 }).unwrap();
+
+        let bytes = write_buffer.get_written();
+        let mut reader = PacketStreamReader::new(&bytes[..bytes.len() - 4]);
+
+        assert!(reader.next_packet().unwrap().is_some());
+        assert!(reader.next_packet().is_err());
+    }
+}
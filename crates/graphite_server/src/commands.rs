@@ -0,0 +1,231 @@
+use graphite_command::types::CommandResult;
+use graphite_mc_constants::block;
+use graphite_mc_protocol::types::BlockPosition;
+
+use crate::{
+    entity::position::Position,
+    player::{Player, PlayerService},
+};
+
+// Ready-made implementations of the vanilla world editing commands
+//
+// Brigadier commands need to know the concrete player service, so these are called
+// from a `#[brigadier]` function, eg.
+//
+// #[brigadier("setblock", {block_pos}, {block_state})]
+// fn setblock(player: &mut Player<MyPlayerService>, pos: &str, block: &str) -> CommandResult {
+//     graphite_server::commands::setblock(player, pos, block)
+// }
+//
+// Positions are the three coordinates taken by a `{block_pos}` argument, eg. `~ ~-1 ~`
+//
+// Like vanilla, every command notifies the neighbors of the blocks that it changes,
+// see `World::notify_neighbors`
+
+/// Maximum number of blocks that can be changed by a single `fill` or `clone`, same as vanilla
+pub const MAX_COMMAND_VOLUME: usize = 32768;
+
+/// Sets the block at the position, eg. `setblock ~ ~-1 ~ minecraft:stone`
+pub fn setblock<P: PlayerService>(player: &mut Player<P>, pos: &str, block: &str) -> CommandResult {
+    let pos = parse_block_position(split_coordinates(pos)?, player.position)?;
+    let block = parse_block_state(block)?;
+
+    let world = player.get_world_mut();
+    if world.get_block_i32(pos.x, pos.y, pos.z).is_none() {
+        return Err("That position is out of this world".into());
+    }
+    if world.set_block_i32(pos.x, pos.y, pos.z, block).is_none() {
+        return Err("Could not set the block".into());
+    }
+
+    player.send_message(format!("Changed the block at {}, {}, {}", pos.x, pos.y, pos.z));
+    Ok(())
+}
+
+/// Sets every block in the box between the two corners, eg. `fill ~ ~ ~ ~5 ~5 ~5 stone`
+pub fn fill<P: PlayerService>(player: &mut Player<P>, from: &str, to: &str, block: &str) -> CommandResult {
+    let from = parse_block_position(split_coordinates(from)?, player.position)?;
+    let to = parse_block_position(split_coordinates(to)?, player.position)?;
+    let block = parse_block_state(block)?;
+    check_volume(from, to)?;
+
+    let changed = player
+        .get_world_mut()
+        .fill(from, to, block)
+        .ok_or("That position is out of this world")?;
+    if changed == 0 {
+        return Err("No blocks were filled".into());
+    }
+
+    player.send_message(format!("Successfully filled {} block(s)", changed));
+    Ok(())
+}
+
+/// Copies the blocks in the box between the two corners to the destination,
+/// eg. `clone 0 64 0 10 70 10 ~ ~ ~`
+pub fn clone<P: PlayerService>(player: &mut Player<P>, from: &str, to: &str,
+        destination: &str) -> CommandResult {
+    let from = parse_block_position(split_coordinates(from)?, player.position)?;
+    let to = parse_block_position(split_coordinates(to)?, player.position)?;
+    let destination = parse_block_position(split_coordinates(destination)?, player.position)?;
+    check_volume(from, to)?;
+
+    let changed = player
+        .get_world_mut()
+        .clone_blocks(from, to, destination)
+        .ok_or("That position is out of this world")?;
+    if changed == 0 {
+        return Err("No blocks were cloned".into());
+    }
+
+    player.send_message(format!("Successfully cloned {} block(s)", changed));
+    Ok(())
+}
+
+fn check_volume(from: BlockPosition, to: BlockPosition) -> CommandResult {
+    // Each side is at most 2^32 blocks, so the volume can overflow even a u64
+    let volume = (from.x.abs_diff(to.x) as u128 + 1)
+        * (from.y.abs_diff(to.y) as u128 + 1)
+        * (from.z.abs_diff(to.z) as u128 + 1);
+    if volume > MAX_COMMAND_VOLUME as u128 {
+        return Err(format!("Too many blocks in the specified area (maximum {}, specified {})",
+            MAX_COMMAND_VOLUME, volume));
+    }
+    Ok(())
+}
+
+/// Parses a block state such as `minecraft:oak_stairs[facing=east]`, see `block::blockstate_from_string`
+pub fn parse_block_state(block: &str) -> Result<u16, String> {
+    block::blockstate_from_string(block).ok_or_else(|| format!("Unknown block '{}'", block))
+}
+
+/// Splits a block position such as `~ ~-1 ~` into its three coordinates
+pub fn split_coordinates(pos: &str) -> Result<[&str; 3], String> {
+    let mut coordinates = pos.split_whitespace();
+    match (coordinates.next(), coordinates.next(), coordinates.next(), coordinates.next()) {
+        (Some(x), Some(y), Some(z), None) => Ok([x, y, z]),
+        _ => Err(format!("Expected three coordinates, got '{}'", pos)),
+    }
+}
+
+/// Parses the coordinates of a block position, relative to `origin`
+///
+/// Each coordinate is either absolute (`10`), relative to the origin (`~`, `~-2`)
+/// or local (`^`, `^1`). Local coordinates are left, up and forwards from the rotation
+/// of the origin, and can't be mixed with the other kinds
+pub fn parse_block_position(coordinates: [&str; 3], origin: Position) -> Result<BlockPosition, String> {
+    let local_count = coordinates.iter().filter(|coordinate| coordinate.starts_with('^')).count();
+    match local_count {
+        0 => {
+            let x = parse_world_coordinate(coordinates[0], origin.coord.x)?;
+            let y = parse_world_coordinate(coordinates[1], origin.coord.y)?;
+            let z = parse_world_coordinate(coordinates[2], origin.coord.z)?;
+            Ok(BlockPosition { x: x.floor() as _, y: y.floor() as _, z: z.floor() as _ })
+        }
+        3 => {
+            let left = parse_offset(&coordinates[0][1..])?;
+            let up = parse_offset(&coordinates[1][1..])?;
+            let forwards = parse_offset(&coordinates[2][1..])?;
+
+            let yaw = (origin.rot.yaw + 90.0).to_radians();
+            let pitch = -origin.rot.pitch.to_radians();
+            let pitch_up = (-origin.rot.pitch + 90.0).to_radians();
+
+            let forwards_dir = [yaw.cos() * pitch.cos(), pitch.sin(), yaw.sin() * pitch.cos()];
+            let up_dir = [yaw.cos() * pitch_up.cos(), pitch_up.sin(), yaw.sin() * pitch_up.cos()];
+            // Cross product of forwards and up, negated
+            let left_dir = [
+                -(forwards_dir[1] * up_dir[2] - forwards_dir[2] * up_dir[1]),
+                -(forwards_dir[2] * up_dir[0] - forwards_dir[0] * up_dir[2]),
+                -(forwards_dir[0] * up_dir[1] - forwards_dir[1] * up_dir[0]),
+            ];
+
+            let offset = |axis: usize| forwards_dir[axis] * forwards + up_dir[axis] * up + left_dir[axis] * left;
+            Ok(BlockPosition {
+                x: (origin.coord.x + offset(0)).floor() as _,
+                y: (origin.coord.y + offset(1)).floor() as _,
+                z: (origin.coord.z + offset(2)).floor() as _,
+            })
+        }
+        _ => Err("Cannot mix world & local coordinates (everything must either use ^ or not)".into()),
+    }
+}
+
+fn parse_world_coordinate(coordinate: &str, origin: f32) -> Result<f32, String> {
+    if let Some(offset) = coordinate.strip_prefix('~') {
+        Ok(origin + parse_offset(offset)?)
+    } else {
+        coordinate
+            .parse::<i32>()
+            .map(|coordinate| coordinate as f32)
+            .map_err(|_| format!("Expected a coordinate, got '{}'", coordinate))
+    }
+}
+
+fn parse_offset(offset: &str) -> Result<f32, String> {
+    if offset.is_empty() {
+        Ok(0.0)
+    } else {
+        offset.parse().map_err(|_| format!("Expected a number, got '{}'", offset))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use graphite_mc_protocol::types::BlockPosition;
+
+    use crate::entity::position::{Coordinate, Position, Rotation};
+
+    use super::{check_volume, parse_block_position, split_coordinates};
+
+    fn origin(yaw: f32) -> Position {
+        Position {
+            coord: Coordinate { x: 10.5, y: 64.0, z: -3.5 },
+            rot: Rotation { yaw, pitch: 0.0 },
+        }
+    }
+
+    #[test]
+    fn absolute_and_relative() {
+        let pos = parse_block_position(["1", "~", "~-2"], origin(0.0)).unwrap();
+        assert_eq!(pos, BlockPosition { x: 1, y: 64, z: -6 });
+    }
+
+    #[test]
+    fn local() {
+        // Yaw 0 faces south (+z), so left is east (+x)
+        let pos = parse_block_position(["^", "^", "^2"], origin(0.0)).unwrap();
+        assert_eq!(pos, BlockPosition { x: 10, y: 64, z: -2 });
+        let pos = parse_block_position(["^1", "^", "^"], origin(0.0)).unwrap();
+        assert_eq!(pos, BlockPosition { x: 11, y: 64, z: -4 });
+        let pos = parse_block_position(["^", "^3", "^"], origin(0.0)).unwrap();
+        assert_eq!(pos, BlockPosition { x: 10, y: 67, z: -4 });
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(parse_block_position(["^", "~", "^"], origin(0.0)).is_err());
+        assert!(parse_block_position(["1.5", "0", "0"], origin(0.0)).is_err());
+        assert!(parse_block_position(["~a", "0", "0"], origin(0.0)).is_err());
+    }
+
+    #[test]
+    fn split() {
+        assert_eq!(split_coordinates("~ ~-1  ^2").unwrap(), ["~", "~-1", "^2"]);
+        assert!(split_coordinates("1 2").is_err());
+        assert!(split_coordinates("1 2 3 4").is_err());
+    }
+
+    #[test]
+    fn volume() {
+        let pos = |x, y, z| BlockPosition { x, y, z };
+        assert!(check_volume(pos(0, 0, 0), pos(31, 31, 31)).is_ok());
+        assert!(check_volume(pos(0, 0, 0), pos(32, 31, 31)).is_err());
+
+        // The volume of the largest box doesn't fit in a u64
+        let min = pos(i32::MIN, i32::MIN, i32::MIN);
+        let max = pos(i32::MAX, i32::MAX, i32::MAX);
+        assert!(check_volume(min, max).is_err());
+        assert!(check_volume(pos(i32::MIN, 0, 0), pos(i32::MAX, 0, 0)).is_err());
+    }
+}
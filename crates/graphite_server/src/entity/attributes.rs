@@ -0,0 +1,236 @@
+use bevy_ecs::prelude::Component;
+use graphite_mc_protocol::play::server::{
+    AttributeModifierOperation, UpdateAttributes, UpdateAttributesModifier,
+    UpdateAttributesProperty,
+};
+
+use crate::universe::EntityId;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Attribute {
+    MaxHealth,
+    FollowRange,
+    KnockbackResistance,
+    MovementSpeed,
+    FlyingSpeed,
+    AttackDamage,
+    AttackKnockback,
+    AttackSpeed,
+    Armor,
+    ArmorToughness,
+    Luck,
+}
+
+impl Attribute {
+    pub fn key(self) -> &'static str {
+        match self {
+            Attribute::MaxHealth => "minecraft:generic.max_health",
+            Attribute::FollowRange => "minecraft:generic.follow_range",
+            Attribute::KnockbackResistance => "minecraft:generic.knockback_resistance",
+            Attribute::MovementSpeed => "minecraft:generic.movement_speed",
+            Attribute::FlyingSpeed => "minecraft:generic.flying_speed",
+            Attribute::AttackDamage => "minecraft:generic.attack_damage",
+            Attribute::AttackKnockback => "minecraft:generic.attack_knockback",
+            Attribute::AttackSpeed => "minecraft:generic.attack_speed",
+            Attribute::Armor => "minecraft:generic.armor",
+            Attribute::ArmorToughness => "minecraft:generic.armor_toughness",
+            Attribute::Luck => "minecraft:generic.luck",
+        }
+    }
+
+    /// The base value used by vanilla when an entity doesn't specify one
+    pub fn default_value(self) -> f64 {
+        match self {
+            Attribute::MaxHealth => 20.0,
+            Attribute::FollowRange => 32.0,
+            Attribute::MovementSpeed => 0.7,
+            Attribute::FlyingSpeed => 0.4,
+            Attribute::AttackDamage => 2.0,
+            Attribute::AttackSpeed => 4.0,
+            _ => 0.0,
+        }
+    }
+
+    pub fn range(self) -> (f64, f64) {
+        match self {
+            Attribute::MaxHealth => (1.0, 1024.0),
+            Attribute::FollowRange => (0.0, 2048.0),
+            Attribute::KnockbackResistance => (0.0, 1.0),
+            Attribute::MovementSpeed => (0.0, 1024.0),
+            Attribute::FlyingSpeed => (0.0, 1024.0),
+            Attribute::AttackDamage => (0.0, 2048.0),
+            Attribute::AttackKnockback => (0.0, 5.0),
+            Attribute::AttackSpeed => (0.0, 1024.0),
+            Attribute::Armor => (0.0, 30.0),
+            Attribute::ArmorToughness => (0.0, 20.0),
+            Attribute::Luck => (-1024.0, 1024.0),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct AttributeModifier {
+    pub uuid: u128,
+    pub amount: f64,
+    pub operation: AttributeModifierOperation,
+}
+
+#[derive(Debug, Clone)]
+struct AttributeInstance {
+    attribute: Attribute,
+    base: f64,
+    modifiers: Vec<AttributeModifier>,
+    dirty: bool,
+}
+
+impl AttributeInstance {
+    fn value(&self) -> f64 {
+        let mut base = self.base;
+        for modifier in &self.modifiers {
+            if modifier.operation == AttributeModifierOperation::Addition {
+                base += modifier.amount;
+            }
+        }
+
+        let mut value = base;
+        for modifier in &self.modifiers {
+            if modifier.operation == AttributeModifierOperation::MultiplyBase {
+                value += base * modifier.amount;
+            }
+        }
+        for modifier in &self.modifiers {
+            if modifier.operation == AttributeModifierOperation::MultiplyTotal {
+                value *= 1.0 + modifier.amount;
+            }
+        }
+
+        let (min, max) = self.attribute.range();
+        value.clamp(min, max)
+    }
+}
+
+/// Attribute values and modifiers of an entity. Only attributes that have been set are stored,
+/// the client uses its own defaults for everything else
+///
+/// Changes are sent to the client on the next tick
+#[derive(Debug, Clone, Default)]
+pub struct Attributes {
+    instances: Vec<AttributeInstance>,
+}
+
+impl Attributes {
+    /// Attributes with vanilla's base values for players. These are known by the client
+    /// already, so they won't be sent unless they are changed
+    pub fn new_player() -> Self {
+        let mut attributes = Self::default();
+        attributes.get_or_create(Attribute::MaxHealth).base = 20.0;
+        attributes.get_or_create(Attribute::MovementSpeed).base = 0.1;
+        attributes.get_or_create(Attribute::AttackDamage).base = 1.0;
+        attributes.instances.iter_mut().for_each(|instance| instance.dirty = false);
+        attributes
+    }
+
+    fn get_or_create(&mut self, attribute: Attribute) -> &mut AttributeInstance {
+        let index = match self.instances.iter().position(|i| i.attribute == attribute) {
+            Some(index) => index,
+            None => {
+                self.instances.push(AttributeInstance {
+                    attribute,
+                    base: attribute.default_value(),
+                    modifiers: Vec::new(),
+                    dirty: true,
+                });
+                self.instances.len() - 1
+            }
+        };
+
+        let instance = &mut self.instances[index];
+        instance.dirty = true;
+        instance
+    }
+
+    /// Returns the value of the attribute with all modifiers applied
+    pub fn get(&self, attribute: Attribute) -> f64 {
+        match self.instances.iter().find(|i| i.attribute == attribute) {
+            Some(instance) => instance.value(),
+            None => attribute.default_value(),
+        }
+    }
+
+    pub fn get_base(&self, attribute: Attribute) -> f64 {
+        match self.instances.iter().find(|i| i.attribute == attribute) {
+            Some(instance) => instance.base,
+            None => attribute.default_value(),
+        }
+    }
+
+    pub fn set_base(&mut self, attribute: Attribute, value: f64) {
+        self.get_or_create(attribute).base = value;
+    }
+
+    /// Adds a modifier to the attribute, replacing any existing modifier with the same UUID
+    pub fn add_modifier(&mut self, attribute: Attribute, modifier: AttributeModifier) {
+        let instance = self.get_or_create(attribute);
+        instance.modifiers.retain(|m| m.uuid != modifier.uuid);
+        instance.modifiers.push(modifier);
+    }
+
+    /// Returns false if the attribute didn't have a modifier with the UUID
+    pub fn remove_modifier(&mut self, attribute: Attribute, uuid: u128) -> bool {
+        if let Some(instance) = self.instances.iter_mut().find(|i| i.attribute == attribute) {
+            let len = instance.modifiers.len();
+            instance.modifiers.retain(|m| m.uuid != uuid);
+            if instance.modifiers.len() != len {
+                instance.dirty = true;
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Creates a packet containing the attributes that have changed since the last call
+    pub(crate) fn take_changes(&mut self, entity_id: EntityId) -> Option<UpdateAttributes<'static>> {
+        let mut attributes = Vec::new();
+        for instance in self.instances.iter_mut().filter(|i| i.dirty) {
+            instance.dirty = false;
+            attributes.push(UpdateAttributesProperty {
+                key: instance.attribute.key(),
+                value: instance.base,
+                modifiers: instance
+                    .modifiers
+                    .iter()
+                    .map(|modifier| UpdateAttributesModifier {
+                        uuid: modifier.uuid,
+                        amount: modifier.amount,
+                        operation: modifier.operation,
+                    })
+                    .collect(),
+            });
+        }
+
+        if attributes.is_empty() {
+            None
+        } else {
+            Some(UpdateAttributes {
+                entity_id: entity_id.as_i32(),
+                attributes,
+            })
+        }
+    }
+}
+
+/// Attributes of a non-player entity, see `World::set_entity_attribute`
+#[derive(Component)]
+pub struct EntityAttributes {
+    pub(crate) entity_id: EntityId,
+    pub attributes: Attributes,
+}
+
+impl EntityAttributes {
+    pub fn new(entity_id: EntityId) -> Self {
+        Self {
+            entity_id,
+            attributes: Default::default(),
+        }
+    }
+}
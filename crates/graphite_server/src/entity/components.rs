@@ -3,6 +3,7 @@ use bevy_ecs::{
     world::{EntityMut, EntityRef},
 };
 use graphite_binary::slice_serialization::SliceSerializable;
+use graphite_mc_constants::entity::{Entity, ItemMetadata, MarkerMetadata, Metadata};
 use graphite_net::{network_buffer::WriteBuffer, packet_helper};
 use graphite_mc_protocol::{
     play::server::{self, AddEntity, AddPlayer, PlayerInfo, PlayerInfoAddPlayer, RemoveEntities},
@@ -11,30 +12,34 @@ use graphite_mc_protocol::{
 };
 use rand::Rng;
 
-use crate::universe::EntityId;
+use crate::{inventory::itemstack::ItemStack, universe::EntityId};
 
-use super::position::{Coordinate, Rotation};
+use super::{
+    position::{Coordinate, Position, Rotation},
+    tracking,
+};
 
 type FnPacket = fn(&mut WriteBuffer, EntityRef);
 
 #[derive(Component, Clone)]
 pub struct Viewable {
     pub coord: Coordinate,
+    /// Distance (in blocks) at which players can see the entity, see `tracking::get_default_tracking_range`
+    pub tracking_range: f32,
+    /// Position when players last checked whether to track the entity, see `World::update_viewable_entities`
+    pub(crate) tracking_coord: Coordinate,
     pub(crate) index_in_chunk_entity_slab: usize,
 
     pub(crate) last_chunk_x: i32,
     pub(crate) last_chunk_z: i32,
 
-    pub(crate) buffer: *mut WriteBuffer,
+    /// Packets for this tick, sent only to the players that are tracking the entity
+    pub(crate) buffer: WriteBuffer,
 
     pub(crate) fn_create: FnPacket,
     pub(crate) destroy_buffer: WriteBuffer,
 }
 
-// Don't worry about it
-unsafe impl Send for Viewable {}
-unsafe impl Sync for Viewable {}
-
 impl Viewable {
     pub fn new(
         coord: Coordinate,
@@ -48,7 +53,9 @@ impl Viewable {
             last_chunk_x: chunk_x,
             last_chunk_z: chunk_z,
             coord,
-            buffer: std::ptr::null_mut(),
+            tracking_range: tracking::DEFAULT_TRACKING_RANGE,
+            tracking_coord: coord,
+            buffer: WriteBuffer::new(),
 
             fn_create,
             destroy_buffer,
@@ -56,16 +63,26 @@ impl Viewable {
     }
 
     // Update packets
-    pub fn write_viewable_packet<'a, T>(&mut self, packet: &'a T) -> bool
+    pub fn write_viewable_packet<'a, T>(&mut self, packet: &'a T)
     where
         T: SliceSerializable<'a, T> + IdentifiedPacket<server::PacketId> + 'a,
     {
-        if let Some(buffer) = unsafe { self.buffer.as_mut() } {
-            packet_helper::try_write_packet(buffer, packet);
-            true
-        } else {
-            false
+        packet_helper::try_write_packet(&mut self.buffer, packet);
+    }
+
+    /// Writes the changed values of the metadata, does nothing if there are no changes
+    pub fn write_viewable_metadata<T: Metadata>(&mut self, entity_id: EntityId, metadata: &mut T) -> bool {
+        if metadata.get_write_size() == 0 {
+            return false;
         }
+
+        packet_helper::write_metadata_packet(
+            &mut self.buffer,
+            server::PacketId::SetEntityData as _,
+            entity_id.as_i32(),
+            metadata,
+        )
+        .is_ok()
     }
 }
 
@@ -73,6 +90,10 @@ pub trait EntitySpawnDefinition {
     fn get_spawn_function(&mut self) -> FnPacket;
     fn get_despawn_buffer(&mut self) -> WriteBuffer;
     fn add_components(self, entity: &mut EntityMut);
+
+    fn get_tracking_range(&self) -> f32 {
+        tracking::DEFAULT_TRACKING_RANGE
+    }
 }
 
 pub struct EntityIdHolder(EntityId);
@@ -91,6 +112,44 @@ impl EntityMetadata {
     }
 }*/
 
+/// Name shown above a non-player entity, see `World::set_entity_custom_name`
+#[derive(Component)]
+pub struct EntityCustomName {
+    pub(crate) entity_id: EntityId,
+    // Markers only have the metadata shared by every entity, which includes the custom name
+    pub(crate) metadata: MarkerMetadata,
+}
+
+impl EntityCustomName {
+    pub(crate) fn new(entity_id: EntityId) -> Self {
+        Self {
+            entity_id,
+            metadata: MarkerMetadata::default(),
+        }
+    }
+
+    pub fn get_custom_name(&self) -> Option<&str> {
+        self.metadata.custom_name.as_deref()
+    }
+
+    pub fn is_custom_name_visible(&self) -> bool {
+        self.metadata.custom_name_visible
+    }
+
+    /// Writes the current name to a player that starts viewing the entity
+    pub(crate) fn write_spawn_metadata(&self, write_buffer: &mut WriteBuffer) {
+        let mut metadata = MarkerMetadata::default();
+        metadata.set_custom_name(self.metadata.custom_name.clone());
+        metadata.set_custom_name_visible(self.metadata.custom_name_visible);
+        let _ = packet_helper::write_metadata_packet(
+            write_buffer,
+            server::PacketId::SetEntityData as _,
+            self.entity_id.as_i32(),
+            &mut metadata,
+        );
+    }
+}
+
 #[derive(Component)]
 pub struct PlayerNPC {
     pub entity_id: EntityId,
@@ -104,6 +163,27 @@ pub struct BasicEntity {
     pub entity_type: i32,
 }
 
+/// An item lying on the ground, eg. after being dropped by a player
+#[derive(Component)]
+pub struct DroppedItem {
+    pub entity_id: EntityId,
+    pub itemstack: ItemStack,
+}
+
+// ItemStack contains a CachedNBT, entities are only ever accessed from the world's thread
+unsafe impl Send for DroppedItem {}
+unsafe impl Sync for DroppedItem {}
+
+/// Moves an entity towards a target position over multiple ticks using relative move packets,
+/// allowing the client to smoothly interpolate the movement. See `World::set_interpolated_position`
+#[derive(Component)]
+pub struct InterpolatedMovement {
+    pub(crate) entity_id: EntityId,
+    pub(crate) target: Position,
+    pub(crate) synced_coord: Coordinate, // position as known by the client
+    pub(crate) remaining_ticks: usize,
+}
+
 #[derive(Component)]
 pub struct Spinalla {
     pub direction: (f32, f32),
@@ -115,6 +195,16 @@ impl EntitySpawnDefinition for BasicEntity {
         BasicEntity::write_spawn_packet
     }
 
+    fn get_tracking_range(&self) -> f32 {
+        let entity = u8::try_from(self.entity_type)
+            .ok()
+            .and_then(|id| Entity::try_from(id).ok());
+        match entity {
+            Some(entity) => tracking::get_default_tracking_range(entity),
+            None => tracking::DEFAULT_TRACKING_RANGE,
+        }
+    }
+
     fn get_despawn_buffer(&mut self) -> WriteBuffer {
         let mut write_buffer = WriteBuffer::with_min_capacity(8);
         let remove_packet = RemoveEntities {
@@ -157,11 +247,76 @@ impl BasicEntity {
     }
 }
 
+impl EntitySpawnDefinition for DroppedItem {
+    fn get_spawn_function(&mut self) -> FnPacket {
+        DroppedItem::write_spawn_packet
+    }
+
+    fn get_tracking_range(&self) -> f32 {
+        tracking::get_default_tracking_range(Entity::Item)
+    }
+
+    fn get_despawn_buffer(&mut self) -> WriteBuffer {
+        let mut write_buffer = WriteBuffer::with_min_capacity(8);
+        let remove_packet = RemoveEntities {
+            entities: vec![self.entity_id.as_i32()],
+        };
+        graphite_net::packet_helper::try_write_packet(&mut write_buffer, &remove_packet);
+        write_buffer
+    }
+
+    fn add_components(self, entity: &mut EntityMut) {
+        entity.insert(self);
+    }
+}
+
+impl DroppedItem {
+    fn write_spawn_packet(write_buffer: &mut WriteBuffer, entity: EntityRef) {
+        let viewable = entity
+            .get::<Viewable>()
+            .expect("all entities must have viewable");
+        let dropped_item = entity
+            .get::<DroppedItem>()
+            .expect("should have dropped item!");
+
+        let add_entity_packet = AddEntity {
+            id: dropped_item.entity_id.as_i32(),
+            uuid: rand::thread_rng().gen(), // todo: don't randomize here
+            entity_type: Entity::Item as _,
+            x: viewable.coord.x as _,
+            y: viewable.coord.y as _,
+            z: viewable.coord.z as _,
+            yaw: 0.0,
+            pitch: 0.0,
+            head_yaw: 0.0,
+            data: 0,
+            x_vel: 0.0, // todo: velocity
+            y_vel: 0.0,
+            z_vel: 0.0,
+        };
+        graphite_net::packet_helper::try_write_packet(write_buffer, &add_entity_packet);
+
+        // The client won't render the item until it knows which item it is
+        let mut metadata = ItemMetadata::default();
+        metadata.set_item((&dropped_item.itemstack).into());
+        let _ = packet_helper::write_metadata_packet(
+            write_buffer,
+            server::PacketId::SetEntityData as _,
+            dropped_item.entity_id.as_i32(),
+            &mut metadata,
+        );
+    }
+}
+
 impl EntitySpawnDefinition for PlayerNPC {
     fn get_spawn_function(&mut self) -> FnPacket {
         PlayerNPC::write_spawn_packet
     }
 
+    fn get_tracking_range(&self) -> f32 {
+        tracking::get_default_tracking_range(Entity::Player)
+    }
+
     fn get_despawn_buffer(&mut self) -> WriteBuffer {
         let mut write_buffer = WriteBuffer::with_min_capacity(8);
 
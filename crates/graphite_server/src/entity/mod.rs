@@ -1,2 +1,4 @@
+pub mod attributes;
 pub mod components;
 pub mod position;
+pub mod tracking;
@@ -0,0 +1,70 @@
+use graphite_mc_constants::entity::Entity;
+
+use super::{components::Viewable, position::Coordinate};
+
+/// Tracking range used for entities without a specific range
+pub const DEFAULT_TRACKING_RANGE: f32 = 80.0;
+
+/// Extra distance (in blocks) that an entity has to move past its tracking range before it is
+/// removed for a player, preventing entities on the boundary from repeatedly spawning and despawning
+pub const TRACKING_RANGE_HYSTERESIS: f32 = 4.0;
+
+/// Distance (in blocks) at which players can see entities of the given type
+///
+/// Large entities are visible from further away than small ones. Entities are never visible outside
+/// of the world's `ENTITY_VIEW_DISTANCE`, regardless of their tracking range
+pub fn get_default_tracking_range(entity: Entity) -> f32 {
+    match entity {
+        Entity::Player => 48.0,
+        Entity::Item | Entity::ExperienceOrb => 64.0,
+        Entity::Arrow
+        | Entity::SpectralArrow
+        | Entity::Trident
+        | Entity::Snowball
+        | Entity::Egg
+        | Entity::EnderPearl
+        | Entity::ExperienceBottle
+        | Entity::Potion
+        | Entity::Fireball
+        | Entity::SmallFireball
+        | Entity::DragonFireball
+        | Entity::WitherSkull
+        | Entity::ShulkerBullet
+        | Entity::LlamaSpit
+        | Entity::FireworkRocket
+        | Entity::FishingBobber => 64.0,
+        Entity::EnderDragon | Entity::Ghast | Entity::Giant | Entity::Wither | Entity::Warden => 128.0,
+        _ => DEFAULT_TRACKING_RANGE,
+    }
+}
+
+/// Horizontal distance used for tracking ranges, measured along the furthest axis
+pub(crate) fn get_tracking_distance(from: Coordinate, to: Coordinate) -> f32 {
+    (from.x - to.x).abs().max((from.z - to.z).abs())
+}
+
+/// Returns true if a player at `coord` should be tracking the entity
+///
+/// Entities that are already `tracked` stay tracked until they are `TRACKING_RANGE_HYSTERESIS`
+/// blocks past their tracking range. Entities outside of the `view_distance` (in chunks) around
+/// the player's chunk are never tracked
+pub(crate) fn is_in_tracking_range(
+    coord: Coordinate,
+    chunk_x: i32,
+    chunk_z: i32,
+    viewable: &Viewable,
+    tracked: bool,
+    view_distance: u8,
+) -> bool {
+    let chunk_distance = (viewable.last_chunk_x - chunk_x).abs().max((viewable.last_chunk_z - chunk_z).abs());
+    if chunk_distance > view_distance as i32 {
+        return false;
+    }
+
+    let range = if tracked {
+        viewable.tracking_range + TRACKING_RANGE_HYSTERESIS
+    } else {
+        viewable.tracking_range
+    };
+    get_tracking_distance(coord, viewable.coord) <= range
+}
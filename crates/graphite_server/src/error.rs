@@ -3,3 +3,11 @@ use thiserror::Error;
 #[derive(Error, Debug)]
 #[error("initialize must be called on this prior to use")]
 pub struct UninitializedError;
+
+#[derive(Error, Debug)]
+#[error("error handling packet {packet_id:?}: {error}")]
+pub struct PacketHandleError {
+    /// Id of the packet that caused the error, `None` if the packet was empty
+    pub packet_id: Option<u8>,
+    pub error: anyhow::Error,
+}
@@ -0,0 +1,50 @@
+use graphite_mc_protocol::play::server::{GameEvent, GameEventType};
+
+use crate::gamemode::GameMode;
+
+/// Typed version of the event type and parameter sent in the `GameEvent` packet
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum GameEventKind {
+    /// Shows the "You have no home bed or charged respawn anchor" message
+    NoRespawnBlockAvailable,
+    StartRaining,
+    StopRaining,
+    ChangeGameMode(GameMode),
+    /// Shows the end credits if `show_credits` is true, otherwise the player respawns immediately
+    WinGame { show_credits: bool },
+    ArrowHitPlayer,
+    /// Rain level between 0.0 and 1.0
+    RainLevelChange(f32),
+    /// Thunder level between 0.0 and 1.0
+    ThunderLevelChange(f32),
+    PufferFishSting,
+    GuardianElderEffect,
+    /// Skips the death screen when enabled
+    ImmediateRespawn(bool),
+}
+
+impl From<GameEventKind> for GameEvent {
+    fn from(kind: GameEventKind) -> Self {
+        let (event_type, param) = match kind {
+            GameEventKind::NoRespawnBlockAvailable => (GameEventType::NoRespawnBlockAvailable, 0.0),
+            GameEventKind::StartRaining => (GameEventType::StartRaining, 0.0),
+            GameEventKind::StopRaining => (GameEventType::StopRaining, 0.0),
+            GameEventKind::ChangeGameMode(gamemode) => {
+                (GameEventType::ChangeGameMode, gamemode as u8 as f32)
+            }
+            GameEventKind::WinGame { show_credits } => {
+                (GameEventType::WinGame, show_credits as u8 as f32)
+            }
+            GameEventKind::ArrowHitPlayer => (GameEventType::ArrowHitPlayer, 0.0),
+            GameEventKind::RainLevelChange(level) => (GameEventType::RainLevelChange, level),
+            GameEventKind::ThunderLevelChange(level) => (GameEventType::ThunderLevelChange, level),
+            GameEventKind::PufferFishSting => (GameEventType::PufferFishSting, 0.0),
+            GameEventKind::GuardianElderEffect => (GameEventType::GuardianElderEffect, 0.0),
+            GameEventKind::ImmediateRespawn(enabled) => {
+                (GameEventType::ImmediateRespawn, enabled as u8 as f32)
+            }
+        };
+
+        GameEvent { event_type, param }
+    }
+}
@@ -15,10 +15,13 @@ const CREATIVE: GameMode = GameMode {
 
 use graphite_net::packet_helper;
 use graphite_mc_protocol::play::server::{
-    GameEvent, GameEventType, PlayerAbilities, PlayerInfo, PlayerInfoUpdateGamemode,
+    GameEvent, PlayerAbilities, PlayerInfo, PlayerInfoUpdateGamemode,
 };
 
-use crate::player::{Player, PlayerService};
+use crate::{
+    game_event::GameEventKind,
+    player::{Player, PlayerService},
+};
 
 #[derive(Default, PartialEq, Eq, Clone, Copy, Debug)]
 #[repr(u8)]
@@ -146,10 +149,7 @@ impl Abilities {
     }
 
     pub fn create_set_gamemode_packet(&self) -> GameEvent {
-        GameEvent {
-            event_type: GameEventType::ChangeGameMode,
-            param: self.gamemode as u8 as f32,
-        }
+        GameEventKind::ChangeGameMode(self.gamemode).into()
     }
 
     pub fn sync(&mut self) {
@@ -3,7 +3,7 @@ use std::result;
 
 use graphite_mc_constants::item::NoSuchItemError;
 use graphite_net::{network_buffer::WriteBuffer, packet_helper};
-use graphite_mc_protocol::play::server::ContainerSetSlot;
+use graphite_mc_protocol::play::server::{ContainerSetContent, ContainerSetSlot};
 use graphite_mc_protocol::types::ProtocolItemStack;
 use thiserror::Error;
 
@@ -15,6 +15,11 @@ pub trait InventoryHandler: Default {
         index: usize,
         itemstack: Option<ProtocolItemStack>,
     ) -> anyhow::Result<()>;
+    fn client_set(
+        &mut self,
+        index: usize,
+        itemstack: Option<ProtocolItemStack>,
+    ) -> anyhow::Result<()>;
     fn get(&self, slot: InventorySlot) -> result::Result<&ItemSlot, SlotOutOfBoundsError>;
     fn set(
         &mut self,
@@ -23,12 +28,19 @@ pub trait InventoryHandler: Default {
     ) -> result::Result<(), SlotOutOfBoundsError>;
     fn clear(&mut self, slot: InventorySlot) -> result::Result<(), SlotOutOfBoundsError>;
 
+    // The item being carried on the cursor while the inventory window is open
+    fn client_set_cursor(&mut self, itemstack: Option<ProtocolItemStack>) -> anyhow::Result<()>;
+    fn get_cursor(&self) -> &ItemSlot;
+    fn set_cursor(&mut self, itemslot: ItemSlot);
+
     fn has_changed(&self, slot: InventorySlot) -> result::Result<bool, SlotOutOfBoundsError>;
     fn is_any_changed(&self) -> bool;
     fn write_changes(&mut self, write_buffer: &mut WriteBuffer) -> result::Result<(), ItemTooBig>;
+    /// Writes the entire contents of the inventory, including the cursor, clearing any pending changes
+    fn write_all(&mut self, write_buffer: &mut WriteBuffer) -> result::Result<(), ItemTooBig>;
 }
 
-#[derive(Default, Clone, Debug)]
+#[derive(Default, Clone, Debug, PartialEq)]
 pub enum ItemSlot {
     #[default]
     Empty,
@@ -57,6 +69,8 @@ impl<'a> From<&'a ItemSlot> for Option<ProtocolItemStack<'a>> {
 pub struct VanillaPlayerInventory {
     change_state: ChangeState,
     slots: [ItemSlot; 46],
+    cursor_change_type: ChangeType,
+    cursor: ItemSlot,
 }
 
 impl Default for VanillaPlayerInventory {
@@ -64,6 +78,8 @@ impl Default for VanillaPlayerInventory {
         Self {
             change_state: ChangeState::NoChange,
             slots: [(); 46].map(|_| Default::default()),
+            cursor_change_type: ChangeType::None,
+            cursor: ItemSlot::Empty,
         }
     }
 }
@@ -164,6 +180,14 @@ impl InventoryHandler for VanillaPlayerInventory {
         &mut self,
         index: usize,
         itemstack: Option<ProtocolItemStack>,
+    ) -> anyhow::Result<()> {
+        self.client_set(index, itemstack)
+    }
+
+    fn client_set(
+        &mut self,
+        index: usize,
+        itemstack: Option<ProtocolItemStack>,
     ) -> anyhow::Result<()> {
         if index > 45 {
             return Err(SlotOutOfBoundsError(45, index).into());
@@ -202,6 +226,21 @@ impl InventoryHandler for VanillaPlayerInventory {
         Ok(())
     }
 
+    fn client_set_cursor(&mut self, itemstack: Option<ProtocolItemStack>) -> anyhow::Result<()> {
+        self.cursor = itemstack.try_into()?;
+        self.cursor_change_type = self.cursor_change_type.max(ChangeType::Client);
+        Ok(())
+    }
+
+    fn get_cursor(&self) -> &ItemSlot {
+        &self.cursor
+    }
+
+    fn set_cursor(&mut self, itemslot: ItemSlot) {
+        self.cursor = itemslot;
+        self.cursor_change_type = ChangeType::Server;
+    }
+
     fn has_changed(&self, slot: InventorySlot) -> result::Result<bool, SlotOutOfBoundsError> {
         let check_index = slot.get_index()?;
         match self.change_state {
@@ -214,6 +253,10 @@ impl InventoryHandler for VanillaPlayerInventory {
     }
 
     fn is_any_changed(&self) -> bool {
+        if self.cursor_change_type != ChangeType::None {
+            return true;
+        }
+
         match self.change_state {
             ChangeState::NoChange => false,
             ChangeState::SingleSlot {
@@ -225,6 +268,11 @@ impl InventoryHandler for VanillaPlayerInventory {
     }
 
     fn write_changes(&mut self, write_buffer: &mut WriteBuffer) -> result::Result<(), ItemTooBig> {
+        if self.cursor_change_type == ChangeType::Server {
+            self.send_cursor(write_buffer)?;
+        }
+        self.cursor_change_type = ChangeType::None;
+
         match self.change_state {
             ChangeState::NoChange => return Ok(()),
             ChangeState::SingleSlot { index, change_type } => {
@@ -245,6 +293,24 @@ impl InventoryHandler for VanillaPlayerInventory {
 
         Ok(())
     }
+
+    fn write_all(&mut self, write_buffer: &mut WriteBuffer) -> result::Result<(), ItemTooBig> {
+        let packet = ContainerSetContent {
+            window_id: 0,
+            state_id: 0,
+            items: self.slots.iter().map(Into::into).collect(),
+            carried_item: (&self.cursor).into(),
+        };
+
+        self.cursor_change_type = ChangeType::None;
+        self.change_state = ChangeState::NoChange;
+
+        if packet_helper::write_packet(write_buffer, &packet).is_err() {
+            Err(ItemTooBig)
+        } else {
+            Ok(())
+        }
+    }
 }
 
 impl VanillaPlayerInventory {
@@ -269,6 +335,22 @@ impl VanillaPlayerInventory {
         }
     }
 
+    fn send_cursor(&self, write_buffer: &mut WriteBuffer) -> result::Result<(), ItemTooBig> {
+        // Window -1, slot -1 is used by the client to represent the cursor
+        let packet = ContainerSetSlot {
+            window_id: -1,
+            state_id: 0,
+            slot: -1,
+            item: (&self.cursor).into(),
+        };
+
+        if packet_helper::write_packet(write_buffer, &packet).is_err() {
+            Err(ItemTooBig)
+        } else {
+            Ok(())
+        }
+    }
+
     fn mark_changed(&mut self, index: usize, change_type: ChangeType) {
         match &mut self.change_state {
             ChangeState::NoChange => {
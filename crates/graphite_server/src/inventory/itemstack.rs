@@ -1,8 +1,18 @@
 use std::borrow::Cow;
 
-use graphite_binary::nbt::CachedNBT;
+use graphite_binary::nbt::{CachedNBT, NBTNode, TAG_COMPOUND_ID, TAG_STRING_ID};
 use graphite_mc_constants::item::{Item, ItemProperties, NoSuchItemError};
-use graphite_mc_protocol::types::ProtocolItemStack;
+use graphite_mc_protocol::types::{BlockPosition, ProtocolItemStack};
+use graphite_text_component::TextComponent;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+#[error("invalid count {count} for {item:?}, must be between 1 and {max_stack_size}")]
+pub struct InvalidItemCountError {
+    pub item: Item,
+    pub count: i8,
+    pub max_stack_size: u8,
+}
 
 #[derive(Clone, Debug)]
 pub struct ItemStack {
@@ -12,6 +22,119 @@ pub struct ItemStack {
     pub(crate) nbt: CachedNBT,
 }
 
+impl ItemStack {
+    /// Creates a stack of `count` items without any nbt
+    ///
+    /// Returns an error if `count` isn't between 1 and the max stack size of the item
+    pub fn of(item: Item, count: i8) -> Result<Self, InvalidItemCountError> {
+        let properties = item.get_properties();
+        if count < 1 || count as u8 > properties.max_stack_size {
+            return Err(InvalidItemCountError {
+                item,
+                count,
+                max_stack_size: properties.max_stack_size,
+            });
+        }
+
+        Ok(ItemStack {
+            item,
+            count,
+            properties,
+            nbt: CachedNBT::new(),
+        })
+    }
+
+    /// Sets the custom name of the item, replacing the default name shown by the client
+    pub fn with_name(mut self, name: impl Into<TextComponent>) -> Self {
+        let name = NBTNode::String(name.into().to_json().into());
+        if let Some(mut display) = self.nbt.root_mut().get_or_create_compound("display") {
+            display.insert("Name", name);
+        }
+        self
+    }
+
+    /// Sets the lore of the item, shown as extra lines below the name
+    pub fn with_lore<T: Into<TextComponent>>(mut self, lines: impl IntoIterator<Item = T>) -> Self {
+        let mut lore = NBTNode::List { type_id: TAG_STRING_ID, children: Vec::new() };
+        for line in lines {
+            let line = NBTNode::String(line.into().to_json().into());
+            self.nbt.append(&mut lore, line);
+        }
+
+        if let Some(mut display) = self.nbt.root_mut().get_or_create_compound("display") {
+            display.insert("Lore", lore);
+        }
+        self
+    }
+
+    /// Adds an enchantment to the item, eg. `with_enchant("minecraft:sharpness", 5)`
+    ///
+    /// Replaces the level if the item already has the enchantment
+    pub fn with_enchant(mut self, id: &str, level: i16) -> Self {
+        // Collect the existing enchantments, lists can't be modified in place
+        let mut enchantments: Vec<(String, i16)> = Vec::new();
+        if let Some(existing) = self.nbt.find_root("Enchantments") {
+            for enchantment in self.nbt.iter(existing).into_iter().flatten() {
+                let existing_id = self.nbt.find(enchantment, "id").and_then(NBTNode::as_string);
+                let existing_level = self.nbt.find(enchantment, "lvl").and_then(NBTNode::as_short);
+                if let (Some(existing_id), Some(existing_level)) = (existing_id, existing_level) {
+                    if existing_id != id {
+                        enchantments.push((existing_id.clone(), existing_level));
+                    }
+                }
+            }
+        }
+        enchantments.push((id.into(), level));
+
+        let mut list = NBTNode::List { type_id: TAG_COMPOUND_ID, children: Vec::new() };
+        for (id, level) in enchantments {
+            let mut enchantment = NBTNode::Compound(Default::default());
+            self.nbt.insert(&mut enchantment, "id", NBTNode::String(id));
+            self.nbt.insert(&mut enchantment, "lvl", NBTNode::Short(level));
+            self.nbt.append(&mut list, enchantment);
+        }
+        self.nbt.insert_root("Enchantments", list);
+        self
+    }
+
+    /// Makes a compass point at `pos`, like a compass that has been used on a lodestone
+    ///
+    /// The client only points at the position while the player is in `dimension`, otherwise the
+    /// needle spins randomly. Players are in `universe::DEFAULT_DIMENSION_NAME` unless changed.
+    ///
+    /// `tracked` should be true if `pos` is an actual lodestone. Vanilla servers remove the target
+    /// from tracked compasses when the lodestone is broken, untracked compasses keep their target
+    pub fn set_lodestone_target(&mut self, dimension: &str, pos: BlockPosition, tracked: bool) {
+        let mut root = self.nbt.root_mut();
+        root.insert("LodestoneTracked", NBTNode::Byte(tracked as i8));
+        root.insert("LodestoneDimension", NBTNode::String(dimension.into()));
+
+        // Replace any existing position, which may not be a compound
+        root.insert("LodestonePos", NBTNode::Compound(Default::default()));
+        let mut lodestone_pos = root
+            .get_or_create_compound("LodestonePos")
+            .expect("LodestonePos was just inserted as a compound");
+        lodestone_pos.insert("X", NBTNode::Int(pos.x));
+        lodestone_pos.insert("Y", NBTNode::Int(pos.y));
+        lodestone_pos.insert("Z", NBTNode::Int(pos.z));
+    }
+
+    /// Returns the dimension and position that the compass points at, see `set_lodestone_target`
+    pub fn get_lodestone_target(&self) -> Option<(&str, BlockPosition)> {
+        let dimension = self.nbt.find_root("LodestoneDimension")?.as_string()?;
+        let lodestone_pos = self.nbt.find_root("LodestonePos")?;
+
+        let get = |key| self.nbt.find(lodestone_pos, key).and_then(NBTNode::as_int);
+        let pos = BlockPosition {
+            x: get("X")?,
+            y: get("Y")?,
+            z: get("Z")?,
+        };
+
+        Some((dimension, pos))
+    }
+}
+
 impl PartialEq for ItemStack {
     fn eq(&self, other: &Self) -> bool {
         self.item == other.item && self.count == other.count
@@ -42,3 +165,92 @@ impl<'a> From<&'a ItemStack> for ProtocolItemStack<'a> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use graphite_binary::{
+        nbt::{decode, stringified, CachedNBT, NBTNode, NBT, TAG_COMPOUND_ID, TAG_STRING_ID},
+        slice_serialization::SliceSerializable,
+        varint,
+    };
+    use graphite_mc_constants::item::Item;
+    use graphite_mc_protocol::types::{BlockPosition, ProtocolItemStack};
+
+    use crate::universe::DEFAULT_DIMENSION_NAME;
+
+    use super::ItemStack;
+
+    #[test]
+    fn lodestone_compass() {
+        let mut compass: ItemStack = ProtocolItemStack {
+            item: Item::Compass as _,
+            count: 1,
+            ..Default::default()
+        }
+        .try_into()
+        .unwrap();
+        assert!(compass.get_lodestone_target().is_none());
+
+        // An invalid position is replaced
+        compass.nbt.insert_root("LodestonePos", NBTNode::Int(0));
+
+        let pos = BlockPosition { x: 10, y: -20, z: 30 };
+        compass.set_lodestone_target(DEFAULT_DIMENSION_NAME, pos, false);
+        assert_eq!(compass.get_lodestone_target(), Some((DEFAULT_DIMENSION_NAME, pos)));
+
+        // Check the serialized nbt sent to the client
+        let protocol_itemstack: ProtocolItemStack = (&compass).into();
+        let nbt = decode::read(&mut protocol_itemstack.nbt.to_bytes()).unwrap();
+        let expected = stringified::from_snbt(
+            r#"{LodestoneTracked:0b,LodestoneDimension:"graphite:default_dimension",LodestonePos:{X:10,Y:-20,Z:30}}"#,
+        )
+        .unwrap();
+        assert!(nbt.structurally_eq(&expected, Default::default()));
+    }
+
+    #[test]
+    fn builder() {
+        assert!(ItemStack::of(Item::DiamondSword, 2).is_err());
+        assert!(ItemStack::of(Item::Stone, 0).is_err());
+        assert!(ItemStack::of(Item::Stone, 64).is_ok());
+
+        let sword = ItemStack::of(Item::DiamondSword, 1)
+            .unwrap()
+            .with_name("Excalibur")
+            .with_lore(["Forged in fire"])
+            .with_enchant("minecraft:sharpness", 4)
+            .with_enchant("minecraft:unbreaking", 3)
+            .with_enchant("minecraft:sharpness", 5);
+
+        // Write the slot
+        let slot = Some(ProtocolItemStack::from(&sword));
+        let mut bytes = vec![0; <Option<ProtocolItemStack>>::get_write_size(&slot)];
+        unsafe { <Option<ProtocolItemStack>>::write(&mut bytes, &slot) };
+
+        // Present, item id, count, nbt
+        let mut expected = vec![1];
+        varint::encode::extend_i32(&mut expected, Item::DiamondSword as i32);
+        expected.push(1);
+        let mut nbt = NBT::new();
+        let mut display = NBTNode::Compound(Default::default());
+        nbt.insert(&mut display, "Name", NBTNode::String(r#"{"text": "Excalibur"}"#.into()));
+        let mut lore = NBTNode::List { type_id: TAG_STRING_ID, children: Vec::new() };
+        nbt.append(&mut lore, NBTNode::String(r#"{"text": "Forged in fire"}"#.into()));
+        nbt.insert(&mut display, "Lore", lore);
+        nbt.insert_root("display", display);
+
+        let mut enchantments = NBTNode::List { type_id: TAG_COMPOUND_ID, children: Vec::new() };
+        for (id, level) in [("minecraft:unbreaking", 3), ("minecraft:sharpness", 5)] {
+            let mut enchantment = NBTNode::Compound(Default::default());
+            nbt.insert(&mut enchantment, "id", NBTNode::String(id.into()));
+            nbt.insert(&mut enchantment, "lvl", NBTNode::Short(level));
+            nbt.append(&mut enchantments, enchantment);
+        }
+        nbt.insert_root("Enchantments", enchantments);
+
+        let nbt: CachedNBT = nbt.into();
+        expected.extend_from_slice(nbt.to_bytes());
+
+        assert_eq!(bytes, expected);
+    }
+}
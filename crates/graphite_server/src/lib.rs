@@ -1,5 +1,7 @@
+pub mod commands;
 pub mod entity;
 pub mod error;
+pub mod game_event;
 pub mod gamemode;
 pub mod inventory;
 pub mod player;
@@ -0,0 +1,178 @@
+use graphite_mc_protocol::{
+    play::server::{ContainerClose, ContainerSetContent, ContainerSetSlot, OpenScreen},
+    types::{BlockPosition, ProtocolItemStack},
+};
+use graphite_text_component::Component;
+
+use crate::{
+    inventory::inventory_handler::{InventoryHandler, InventorySlot, ItemSlot},
+    world::chest::CHEST_SLOTS,
+};
+
+use super::{Player, PlayerService};
+
+/// Window types of chests, `minecraft:generic_9x3` and `minecraft:generic_9x6`
+const SINGLE_CHEST_WINDOW_TYPE: i32 = 2;
+const DOUBLE_CHEST_WINDOW_TYPE: i32 = 5;
+
+/// A chest window that the player has open
+///
+/// The window shows the items of the whole container, regardless of which half was opened.
+/// The player's main inventory and hotbar follow the container slots
+pub(crate) struct OpenContainer {
+    pub(crate) window_id: u8,
+    /// Positions of the chest halves, in slot order
+    halves: Vec<BlockPosition>,
+    /// The container items as last sent to the client
+    client_items: Vec<ItemSlot>,
+}
+
+impl OpenContainer {
+    pub(crate) fn size(&self) -> usize {
+        self.client_items.len()
+    }
+
+    /// Converts a window slot after the container slots into an index of the player inventory
+    pub(crate) fn to_inventory_index(&self, slot: usize) -> usize {
+        slot - self.size() + InventorySlot::MainAndHotbar(0).get_index().expect("slot in bounds")
+    }
+}
+
+impl<P: PlayerService> Player<P> {
+    /// Opens the chest at `pos`, closing any container the player already has open
+    ///
+    /// Either half of a double chest opens the whole 54 slot container. Changes made by
+    /// the player are stored in the block entities of the chest halves, and changes made
+    /// by other players or the server are sent to the player while the chest is open.
+    /// Returns false if there is no chest at the position
+    pub fn open_chest(&mut self, pos: BlockPosition) -> bool {
+        let world = self.get_world();
+        let (Some(halves), Some(items)) = (world.get_chest_container(pos), world.get_chest_items(pos)) else {
+            return false;
+        };
+
+        self.close_container();
+
+        // Window ids cycle between 1 and 100 like vanilla, 0 is the player inventory
+        self.container_counter = self.container_counter % 100 + 1;
+        let window_id = self.container_counter;
+
+        let (window_type, title) = if halves.len() > 1 {
+            (DOUBLE_CHEST_WINDOW_TYPE, Component::new().translate("container.chestDouble"))
+        } else {
+            (SINGLE_CHEST_WINDOW_TYPE, Component::new().translate("container.chest"))
+        };
+        self.packets.write_packet(&OpenScreen {
+            window_id: window_id as _,
+            window_type,
+            title: &title.to_json(),
+        });
+
+        self.open_container = Some(OpenContainer {
+            window_id,
+            halves,
+            client_items: items,
+        });
+        self.write_container_contents();
+        true
+    }
+
+    /// Sends the entire contents of the open container window, including the cursor
+    pub(crate) fn write_container_contents(&mut self) {
+        let Some(container) = &self.open_container else {
+            return;
+        };
+
+        // The window contains the container followed by the main inventory and hotbar
+        let mut window_items: Vec<Option<ProtocolItemStack>> =
+            container.client_items.iter().map(Into::into).collect();
+        for index in 0..36 {
+            let itemslot = self.inventory.get(InventorySlot::MainAndHotbar(index)).expect("slot in bounds");
+            window_items.push(itemslot.into());
+        }
+        self.packets.write_packet(&ContainerSetContent {
+            window_id: container.window_id,
+            state_id: 0,
+            items: window_items,
+            carried_item: self.inventory.get_cursor().into(),
+        });
+    }
+
+    /// Closes the container that the player has open, if any. Items on the cursor are
+    /// returned to the inventory
+    pub fn close_container(&mut self) {
+        if let Some(container) = self.open_container.take() {
+            self.packets.write_packet(&ContainerClose {
+                window_id: container.window_id,
+            });
+            self.return_cursor_to_inventory();
+        }
+    }
+
+    /// Called when the client closed the container window
+    pub(crate) fn handle_close_container(&mut self) {
+        if self.open_container.take().is_some() {
+            self.return_cursor_to_inventory();
+        }
+    }
+
+    /// Stores a container slot changed by the client, it isn't sent back to the client
+    pub(crate) fn client_set_container_slot(&mut self, slot: usize, itemslot: ItemSlot) {
+        let Some(container) = &mut self.open_container else {
+            return;
+        };
+
+        let pos = container.halves[0];
+        container.client_items[slot] = itemslot.clone();
+        self.get_world_mut().set_chest_item(pos, slot, itemslot);
+    }
+
+    /// Returns the items of the open container as they are stored in the world, or None if
+    /// the chest was broken or joined another chest
+    pub(crate) fn get_container_items(&self) -> Option<Vec<ItemSlot>> {
+        let container = self.open_container.as_ref()?;
+        let world = self.get_world();
+
+        let pos = container.halves[0];
+        match world.get_chest_container(pos) {
+            Some(halves) if halves == container.halves => world.get_chest_items(pos),
+            _ => None,
+        }
+    }
+
+    /// Returns the item in a slot of the open container
+    pub(crate) fn get_container_slot(&self, slot: usize) -> ItemSlot {
+        let Some(container) = &self.open_container else {
+            return ItemSlot::Empty;
+        };
+        container.client_items.get(slot).cloned().unwrap_or_default()
+    }
+
+    /// Sends the container slots that were changed by other players or the server, and
+    /// closes the window if the chest was broken or joined another chest
+    pub(crate) fn update_open_container(&mut self) {
+        if self.open_container.is_none() {
+            return;
+        }
+        let Some(items) = self.get_container_items() else {
+            self.close_container();
+            return;
+        };
+        let Some(container) = &mut self.open_container else {
+            return;
+        };
+        debug_assert_eq!(items.len(), container.halves.len() * CHEST_SLOTS);
+
+        for (slot, (itemslot, client_itemslot)) in items.into_iter().zip(&mut container.client_items).enumerate() {
+            if itemslot != *client_itemslot {
+                self.packets.write_packet(&ContainerSetSlot {
+                    window_id: container.window_id as _,
+                    state_id: 0,
+                    slot: slot as _,
+                    item: (&itemslot).into(),
+                });
+                *client_itemslot = itemslot;
+            }
+        }
+    }
+}
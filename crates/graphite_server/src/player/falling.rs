@@ -0,0 +1,73 @@
+use graphite_mc_constants::block::{Block, BlockAttributes};
+
+use crate::{
+    entity::position::Coordinate,
+    world::{World, WorldService},
+};
+
+/// Distance a player can fall without taking damage
+pub const SAFE_FALL_DISTANCE: f32 = 3.0;
+
+/// Half the width of the player's bounding box
+const HALF_WIDTH: f32 = 0.3;
+
+/// Returns true if there is a block below `coord` that a player could be standing on
+///
+/// Used to validate the on-ground flag sent by the client. The corners of the bounding box are
+/// checked so that players standing on the edge of a block aren't rejected. Blocks taller than a
+/// full block (fences, walls) are covered by also checking half a block down
+pub(crate) fn has_support<W: WorldService>(world: &World<W>, coord: Coordinate) -> bool {
+    let corners = [
+        (-HALF_WIDTH, -HALF_WIDTH),
+        (-HALF_WIDTH, HALF_WIDTH),
+        (HALF_WIDTH, -HALF_WIDTH),
+        (HALF_WIDTH, HALF_WIDTH),
+    ];
+
+    [0.001, 0.501].iter().any(|offset| {
+        let y = (coord.y - offset).floor() as i32;
+        corners.iter().any(|(dx, dz)| {
+            let x = (coord.x + dx).floor() as i32;
+            let z = (coord.z + dz).floor() as i32;
+            is_solid(world.get_block_i32(x, y, z))
+        })
+    })
+}
+
+/// Returns true if the block at the feet of the player stops fall damage from accumulating,
+/// eg. water or ladders
+pub(crate) fn negates_fall_damage<W: WorldService>(world: &World<W>, coord: Coordinate) -> bool {
+    let x = coord.x.floor() as i32;
+    let y = coord.y.floor() as i32;
+    let z = coord.z.floor() as i32;
+
+    let block: Option<&Block> = world.get_block_i32(x, y, z).and_then(|id| id.try_into().ok());
+    matches!(
+        block,
+        Some(
+            Block::Water { .. }
+                | Block::BubbleColumn { .. }
+                | Block::Cobweb
+                | Block::PowderSnow
+                | Block::Ladder { .. }
+                | Block::Vine { .. }
+                | Block::Scaffolding { .. }
+                | Block::WeepingVines { .. }
+                | Block::WeepingVinesPlant
+                | Block::TwistingVines { .. }
+                | Block::TwistingVinesPlant
+                | Block::CaveVines { .. }
+                | Block::CaveVinesPlant { .. }
+        )
+    )
+}
+
+/// Damage taken when landing after falling `fall_distance` blocks
+pub fn fall_damage(fall_distance: f32) -> f32 {
+    (fall_distance - SAFE_FALL_DISTANCE).ceil().max(0.0)
+}
+
+pub(crate) fn is_solid(block: Option<u16>) -> bool {
+    let attributes: Option<&BlockAttributes> = block.and_then(|id| id.try_into().ok());
+    attributes.is_some_and(|attributes| !attributes.air && !attributes.replaceable)
+}
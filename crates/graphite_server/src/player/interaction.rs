@@ -94,6 +94,8 @@ pub(crate) struct InteractionState {
     pub(crate) break_time: usize,
     // Used in order to reset breaking_block if the player stops breaking for long enough
     pub(crate) breaking_block_timer: usize,
+    // Last destroy stage sent to viewers, used to avoid resending the same stage
+    pub(crate) destroy_stage: Option<i8>,
 }
 
 impl InteractionState {
@@ -107,6 +109,7 @@ impl InteractionState {
         self.breaking_block = None;
         self.break_time = 0;
         self.breaking_block_timer = 0;
+        self.destroy_stage = None;
     }
 
     pub(crate) fn start_breaking(&mut self, position: BlockPosition) -> Option<Interaction> {
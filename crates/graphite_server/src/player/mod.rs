@@ -1,12 +1,17 @@
+pub mod falling;
 pub mod interaction;
 pub mod metadata;
 pub mod packet_buffer;
 pub mod player_connection;
 pub mod player_settings;
 pub mod player_vec;
+pub mod pose;
 pub mod proto_player;
+pub mod spawn_point;
 
+mod container;
 mod player_packet_handler;
+mod window_click;
 
 #[allow(clippy::module_inception)]
 // Justification: we re-export player, moving all the `player.rs` code to here would be messy
@@ -15,6 +15,8 @@ impl PacketBuffer {
         }
     }
 
+    /// Writes packets that have already been serialized, including the length and packet id,
+    /// eg. using `packet_helper::serialize_packet` to send the same packet to many players
     pub fn write_raw_packets(&mut self, packet_bytes: &[u8]) {
         self.write_buffer.copy_from(packet_bytes);
     }
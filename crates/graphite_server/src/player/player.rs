@@ -1,6 +1,7 @@
-use std::{mem::ManuallyDrop, ops::Range};
+use std::{collections::HashSet, mem::ManuallyDrop, ops::Range, sync::mpsc::Sender};
 
 use anyhow::bail;
+use bevy_ecs::entity::Entity;
 use graphite_mc_constants::{
     block::Block,
     entity::{Metadata, PlayerMetadata},
@@ -8,6 +9,7 @@ use graphite_mc_constants::{
 };
 use graphite_net::{
     network_buffer::WriteBuffer,
+    network_handler::UninitializedConnection,
     packet_helper::{self, PacketReadResult},
 };
 use parry3d::{
@@ -19,36 +21,58 @@ use graphite_mc_protocol::{
     play::{
         client::PacketHandler,
         server::{
-            self, AddPlayer, BlockChangedAck, BlockDestruction, LevelEvent, LevelEventType,
-            MoveEntityPosRot, PlayerInfo, PlayerInfoAddPlayer, RemoveEntities, RotateHead,
-            SetEquipment, TeleportEntity,
+            self, AddPlayer, BlockChangedAck, BlockDestruction, GameEvent,
+            LevelEvent, LevelEventType, LookAnchor,
+            MoveEntityPosRot, OpenSignEditor, Ping, PlayerLookAt, PlayerLookAtEntity, PlayerInfo, PlayerInfoAddPlayer, PlayerPosition, RemoveEntities,
+            RotateHead, SetCarriedItem, SetEquipment, SetExperience, SetHealth, TeleportEntity,
         },
     },
     types::{BlockPosition, EquipmentSlot, GameProfile, Hand, Pose},
 };
-use queues::Buffer;
+use queues::{Buffer, IsQueue};
 use rand::RngCore;
 use graphite_sticky::Unsticky;
-use graphite_text_component::TextComponent;
+use graphite_text_component::{Component, TextComponent};
 
 use crate::{
-    entity::position::{Coordinate, Position, Vec3f},
-    gamemode::Abilities,
-    inventory::inventory_handler::{InventoryHandler, InventorySlot, ItemSlot},
-    universe::{EntityId, UniverseService, Universe},
+    error::PacketHandleError,
+    entity::{
+        attributes::{Attribute, Attributes},
+        components::{DroppedItem, EntityCustomName},
+        components::Viewable,
+        position::{Coordinate, Position, Rotation, Vec3f},
+        tracking,
+    },
+    game_event::GameEventKind,
+    gamemode::{Abilities, GameMode},
+    inventory::{
+        inventory_handler::{InventoryHandler, InventorySlot, ItemSlot},
+        itemstack::ItemStack,
+    },
+    universe::{EntityId, OnlinePlayer, UniverseService, MAX_PERMISSION_LEVEL},
     world::{
-        ChunkViewPosition, TickPhase, TickPhaseInner, World, WorldService, block_update,
+        ChunkViewPosition, TickPhase, TickPhaseInner, World, WorldService, chest, sign,
     },
 };
 
 use super::{
+    container::OpenContainer,
     interaction::{Interaction, InteractionState},
     packet_buffer::PacketBuffer,
     player_connection::AbstractConnectionReference,
+    falling,
+    pose,
     player_settings::PlayerSettings,
+    player_vec::PlayerVec,
     proto_player::ProtoPlayer,
+    spawn_point::{self, SpawnPoint},
 };
 
+/// Ticks to wait for the client to confirm a teleport before sending it again
+const TELEPORT_RESEND_TICKS: u8 = 20;
+/// Unconfirmed resends after which the teleport is dropped and client movement is accepted again
+const MAX_TELEPORT_RESENDS: u8 = 3;
+
 // User defined player service trait
 
 pub trait PlayerService
@@ -62,10 +86,31 @@ where
     /// Drawback: more write operations which could potentially strain the server
     const FAST_PACKET_RESPONSE: bool = true;
 
+    /// Ticks between keep alive packets sent to the client (20 ticks = 1 second)
+    const KEEP_ALIVE_INTERVAL_TICKS: u32 = 256;
+    /// Ticks the client has to respond to a keep alive before being disconnected
+    ///
+    /// A new keep alive isn't sent until the previous one has been answered
+    const KEEP_ALIVE_TIMEOUT_TICKS: u32 = 256;
+
     type UniverseServiceType: UniverseService;
     type WorldServiceType: WorldService<UniverseServiceType = Self::UniverseServiceType>;
 
     type InventoryHandlerType: InventoryHandler;
+
+    /// Called when a packet sent by the player can't be parsed or handled
+    ///
+    /// The default disconnects the player. Returning `HandleAction::Continue` skips the packet
+    /// and handles the remaining packets as normal
+    fn on_packet_error(_player: &mut Player<Self>, _error: &PacketHandleError) -> HandleAction {
+        HandleAction::Disconnect
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HandleAction {
+    Continue,
+    Disconnect,
 }
 
 #[allow(type_alias_bounds)] // Justification: used as a shortcut to avoid monsterous type
@@ -74,9 +119,20 @@ type ConnectionReferenceType<P: PlayerService> =
 #[allow(type_alias_bounds)] // Justification: used as a shortcut to avoid monsterous type
 type TransferFn<P: PlayerService> = Box<dyn FnOnce(&mut World<P::WorldServiceType>, P, ProtoPlayer<P::UniverseServiceType>)>;
 
+// Experience
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Experience {
+    /// Progress towards the next level, between 0 and 1
+    pub progress: f32,
+    pub level: i32,
+    pub total: i32,
+}
+
 // graphite player
 pub struct Player<P: PlayerService> {
     pub(crate) world: *mut World<P::WorldServiceType>,
+    pub(crate) player_vec: *mut PlayerVec<P>,
 
     pub packets: PacketBuffer,
     pub(crate) disconnected: bool,
@@ -84,34 +140,54 @@ pub struct Player<P: PlayerService> {
     pub entity_id: EntityId,
     pub abilities: Abilities,
     pub metadata: PlayerMetadata,
+    pub attributes: Attributes,
     pub inventory: P::InventoryHandlerType,
     pub settings: PlayerSettings,
     pub profile: GameProfile,
+    pub spawn_point: Option<SpawnPoint>,
 
     last_position: Position,  // used to check for changes
     synced_coord: Coordinate, // used to calculate correct quantized movement
     pub(crate) client_position: Position,
     pub position: Position,
     pub on_ground: bool,
+    /// Blocks fallen since the player was last on the ground
+    pub fall_distance: f32,
+    health: f32,
+    food: i32,
+    saturation: f32,
+    experience: Experience,
 
     pub selected_hotbar_slot: u8,
     last_selected_hotbar_slot: u8,
+    permission_level: u8,
 
     viewable_exclusion_range: Range<usize>,
+    tracked_entities: HashSet<Entity>,
+    entity_tracking_coord: Option<Coordinate>,
     pub(crate) chunk_view_position: ChunkViewPosition,
     pub(crate) new_chunk_view_position: ChunkViewPosition,
     pub(crate) chunk_ref: usize,
     pub(crate) teleport_id_timer: u8,
     pub(crate) waiting_teleportation_id: Buffer<i32>,
+    pub(crate) teleport_resend_timer: u8,
+    pub(crate) teleport_resend_count: u8,
+    teleport_id_counter: i32,
     pub(crate) ack_sequence_up_to: Option<i32>,
     pub(crate) interaction_state: InteractionState,
+    pub(crate) editing_sign: Option<BlockPosition>,
+    pub(crate) open_container: Option<OpenContainer>,
+    pub(crate) container_counter: u8,
 
     pub(crate) current_keep_alive: u64,
-    keep_alive_timer: u8,
+    /// Ticks since the last keep alive was sent
+    keep_alive_timer: u32,
+    pub(crate) last_pong_id: Option<i32>,
 
     moved_into_proto: bool,
     connection: ManuallyDrop<ConnectionReferenceType<P>>,
     pub(crate) transfer_fn: Option<TransferFn<P>>,
+    transferring_universe: bool,
     pub service: ManuallyDrop<P>,
 }
 
@@ -125,39 +201,65 @@ impl<P: PlayerService> Player<P> {
         view_position: ChunkViewPosition,
         proto_player: ProtoPlayer<P::UniverseServiceType>,
     ) -> Self {
+        let mut inventory = P::InventoryHandlerType::default();
+        for (index, itemslot) in proto_player.inventory.into_iter().enumerate() {
+            if let ItemSlot::Filled(itemstack) = itemslot {
+                let _ = inventory.set(InventorySlot::All(index), itemstack);
+            }
+        }
+
         Self {
             world,
+            player_vec: std::ptr::null_mut(),
 
             packets: PacketBuffer::new(),
             disconnected: false,
 
             entity_id: proto_player.entity_id,
             abilities: proto_player.abilities,
-            inventory: Default::default(),
+            inventory,
             settings: PlayerSettings::new(),
             profile: proto_player.profile,
+            spawn_point: proto_player.spawn_point,
             metadata: Default::default(),
+            attributes: Attributes::new_player(),
 
             last_position: position,
             synced_coord: position.coord,
             client_position: position,
             position,
             on_ground: false,
+            fall_distance: 0.0,
+            health: proto_player.health,
+            food: proto_player.food,
+            saturation: proto_player.saturation,
+            experience: proto_player.experience,
 
-            selected_hotbar_slot: 0,
-            last_selected_hotbar_slot: 0,
+            selected_hotbar_slot: proto_player.selected_hotbar_slot,
+            last_selected_hotbar_slot: proto_player.selected_hotbar_slot,
+            permission_level: proto_player.permission_level,
 
             viewable_exclusion_range: 0..0,
+            tracked_entities: HashSet::new(),
+            entity_tracking_coord: None,
             chunk_view_position: view_position,
             new_chunk_view_position: view_position,
             chunk_ref: usize::MAX,
             teleport_id_timer: 0,
             waiting_teleportation_id: Buffer::new(20),
+            teleport_resend_timer: 0,
+            teleport_resend_count: 0,
+            teleport_id_counter: 0,
             ack_sequence_up_to: None,
             interaction_state: Default::default(),
+            editing_sign: None,
+            open_container: None,
+            container_counter: 0,
 
             current_keep_alive: 0,
+            last_pong_id: None,
             transfer_fn: None,
+            transferring_universe: false,
             keep_alive_timer: 0,
 
             moved_into_proto: false,
@@ -174,8 +276,92 @@ impl<P: PlayerService> Player<P> {
         unsafe { self.world.as_mut().unwrap() }
     }
 
+    /// Spawns a dropped item entity at the eye position of the player
+    pub fn drop_item(&mut self, itemstack: ItemStack) {
+        let mut coord = self.position.coord;
+        coord.y += 1.62 - 0.3;
+
+        let world = self.get_world_mut();
+        let entity_id = world.get_universe().new_entity_id();
+        let dropped_item = DroppedItem {
+            entity_id,
+            itemstack,
+        };
+        world.push_entity((), coord, dropped_item, entity_id);
+    }
+
+    /// Moves the item being carried on the cursor into the first empty slot of the inventory,
+    /// dropping it if the inventory is full
+    pub fn return_cursor_to_inventory(&mut self) {
+        let itemstack = match self.inventory.get_cursor() {
+            ItemSlot::Filled(itemstack) => itemstack.clone(),
+            ItemSlot::Empty => return,
+        };
+        self.inventory.set_cursor(ItemSlot::Empty);
+        self.return_to_inventory(itemstack);
+    }
+
+    /// Closes the inventory window, moving the items in the crafting grid and on the cursor
+    /// into the inventory. Items that don't fit are dropped
+    pub fn close_inventory(&mut self) {
+        for index in 0..4 {
+            let slot = InventorySlot::CraftingInput(index);
+            if let ItemSlot::Filled(itemstack) = self.inventory.get(slot).unwrap() {
+                let itemstack = itemstack.clone();
+                self.inventory.clear(slot).unwrap();
+                self.return_to_inventory(itemstack);
+            }
+        }
+        if let ItemSlot::Filled(_) = self.inventory.get(InventorySlot::CraftingResult).unwrap() {
+            self.inventory.clear(InventorySlot::CraftingResult).unwrap();
+        }
+
+        self.return_cursor_to_inventory();
+    }
+
+    fn return_to_inventory(&mut self, itemstack: ItemStack) {
+        // todo: merge with existing stacks of the same item
+        let slots = (0..9)
+            .map(InventorySlot::Hotbar)
+            .chain((0..27).map(InventorySlot::Main));
+        for slot in slots {
+            if let ItemSlot::Empty = self.inventory.get(slot).unwrap() {
+                self.inventory.set(slot, itemstack).unwrap();
+                return;
+            }
+        }
+
+        self.drop_item(itemstack);
+    }
+
+    /// Closes the inventory of a player that has disconnected, dropping the whole inventory
+    /// unless the `keep_inventory_on_disconnect` game rule is true
+    fn handle_disconnected_inventory(&mut self) {
+        self.close_inventory();
+
+        if self.get_world().game_rules.keep_inventory_on_disconnect {
+            return;
+        }
+        for index in 0..46 {
+            let slot = InventorySlot::All(index);
+            if let ItemSlot::Filled(itemstack) = self.inventory.get(slot).unwrap() {
+                let itemstack = itemstack.clone();
+                self.inventory.clear(slot).unwrap();
+                self.drop_item(itemstack);
+            }
+        }
+    }
+
+    /// Cleans up a player that has disconnected, before it is removed from the world
+    pub(crate) fn handle_disconnected(&mut self) {
+        if !self.transferring_universe {
+            self.handle_disconnected_inventory();
+        }
+    }
+
     pub(crate) fn tick(&mut self, tick_phase: TickPhase) -> anyhow::Result<()> {
         if self.disconnected {
+            self.handle_disconnected();
             bail!("player has been disconnected");
         }
 
@@ -187,9 +373,21 @@ impl<P: PlayerService> Player<P> {
             // Global viewable buffer
             self.packets.write_raw_packets(self.get_world().global_write_buffer.get_written());
             
-            let chunks = &self.get_world().chunks;
+            // Spawn/despawn entities before copying their packets, so
+            // that updates for tracked entities arrive after the spawn
+            self.update_tracked_entities();
+
+            // Entity packets, only for entities tracked by this player
+            let world = self.get_world();
+            for id in &self.tracked_entities {
+                if let Some(viewable) = world.entities.get::<Viewable>(*id) {
+                    self.packets.write_raw_packets(viewable.buffer.get_written());
+                }
+            }
 
-            // Entity viewable buffers
+            let chunks = &world.chunks;
+
+            // Player viewable buffers
             let view_distance = P::WorldServiceType::ENTITY_VIEW_DISTANCE as i32;
             for x in (chunk_x - view_distance).max(0)
                 ..(chunk_x + view_distance + 1).min(chunks.size_x() as _)
@@ -267,6 +465,9 @@ impl<P: PlayerService> Player<P> {
         // Update equipment (held items and armor)
         self.update_equipment(selected_hotbar_slot_changed);
 
+        // Write container packets, after the inventory so that the client's clicks were applied
+        self.update_open_container();
+
         // Write inventory packets (note: after equipment changes)
         self.inventory
             .write_changes(&mut self.packets.write_buffer)?;
@@ -277,6 +478,12 @@ impl<P: PlayerService> Player<P> {
         // Write metadata packets
         self.update_metadata()?;
 
+        // Write attribute packets
+        if let Some(packet) = self.attributes.take_changes(self.entity_id) {
+            self.packets.write_packet(&packet);
+            self.packets.write_self_excluded_viewable_packet(&packet);
+        }
+
         // Update position
         if self.position != self.last_position {
             self.position.rot.fix();
@@ -284,6 +491,7 @@ impl<P: PlayerService> Player<P> {
         } else {
             // todo: check for moving too fast
             self.client_position.rot.fix();
+            self.update_fall_distance(self.client_position.coord.y - self.last_position.coord.y);
             self.handle_movement(self.client_position, false)?;
         }
 
@@ -315,6 +523,33 @@ impl<P: PlayerService> Player<P> {
         Ok(())
     }
 
+    fn update_fall_distance(&mut self, delta_y: f32) {
+        let world = self.get_world();
+        let coord = self.client_position.coord;
+
+        // Don't trust the client claiming to be on the ground while in mid-air
+        if self.on_ground && !falling::has_support(world, coord) {
+            self.on_ground = false;
+        }
+
+        if self.abilities.is_flying || falling::negates_fall_damage(world, coord) {
+            self.fall_distance = 0.0;
+            return;
+        }
+
+        if delta_y < 0.0 {
+            self.fall_distance -= delta_y;
+        }
+
+        if self.on_ground {
+            let damage = falling::fall_damage(self.fall_distance);
+            self.fall_distance = 0.0;
+            if damage > 0.0 && world.game_rules.fall_damage {
+                self.damage(damage);
+            }
+        }
+    }
+
     fn update_client_synchronization(&mut self) -> anyhow::Result<()> {
         // Check teleport timer
         if self.teleport_id_timer > 0 {
@@ -325,12 +560,31 @@ impl<P: PlayerService> Player<P> {
             }
         }
 
+        // Resend unconfirmed teleports
+        if self.waiting_teleportation_id.size() > 0 {
+            self.teleport_resend_timer += 1;
+
+            if self.teleport_resend_timer >= TELEPORT_RESEND_TICKS {
+                if self.teleport_resend_count >= MAX_TELEPORT_RESENDS {
+                    // The client is never going to confirm, stop ignoring its movement
+                    self.waiting_teleportation_id = Buffer::new(20);
+                    self.teleport_resend_timer = 0;
+                    self.teleport_resend_count = 0;
+                } else {
+                    self.teleport_resend_count += 1;
+                    self.send_teleport()?;
+                }
+            }
+        }
+
         // Send keep alive timer
-        self.keep_alive_timer = self.keep_alive_timer.wrapping_add(1);
-        if self.keep_alive_timer == 0 {
-            if self.current_keep_alive != 0 {
+        self.keep_alive_timer += 1;
+        if self.current_keep_alive != 0 {
+            if self.keep_alive_timer >= P::KEEP_ALIVE_TIMEOUT_TICKS {
                 bail!("client hasn't responded with keep alive")
             }
+        } else if self.keep_alive_timer >= P::KEEP_ALIVE_INTERVAL_TICKS {
+            self.keep_alive_timer = 0;
             self.current_keep_alive = rand::thread_rng().next_u64();
 
             self.packets.write_packet(&server::KeepAlive {
@@ -434,7 +688,113 @@ impl<P: PlayerService> Player<P> {
         }
     }
 
+    /// Spawns entities that have come within their tracking range and despawns entities
+    /// that have moved too far away
+    ///
+    /// All entities in view are only checked when the player moves, otherwise only
+    /// the entities that were spawned or moved this tick are checked
+    fn update_tracked_entities(&mut self) {
+        let world = self.get_world();
+        let coord = self.position.coord;
+
+        let chunk_x = self.new_chunk_view_position.x as i32;
+        let chunk_z = self.new_chunk_view_position.z as i32;
+        let view_distance = P::WorldServiceType::ENTITY_VIEW_DISTANCE;
+
+        let mut spawned = Vec::new();
+        let mut despawned = Vec::new();
+
+        if self.entity_tracking_coord != Some(coord) {
+            self.entity_tracking_coord = Some(coord);
+
+            let chunks = &world.chunks;
+            let mut in_view = HashSet::new();
+
+            let view_distance_i32 = view_distance as i32;
+            for x in (chunk_x - view_distance_i32).max(0)
+                ..(chunk_x + view_distance_i32 + 1).min(chunks.size_x() as _)
+            {
+                for z in (chunk_z - view_distance_i32).max(0)
+                    ..(chunk_z + view_distance_i32 + 1).min(chunks.size_z() as _)
+                {
+                    let chunk = chunks.get(x as usize, z as usize).expect("chunk coords in bounds");
+
+                    for (_, id) in chunk.entities.iter() {
+                        let viewable = world.entities.get::<Viewable>(*id)
+                            .expect("entity in chunk-list must be viewable");
+                        let tracked = self.tracked_entities.contains(id);
+                        if tracking::is_in_tracking_range(coord, chunk_x, chunk_z, viewable, tracked, view_distance) {
+                            in_view.insert(*id);
+                        }
+                    }
+                }
+            }
+
+            spawned.extend(in_view.difference(&self.tracked_entities).copied());
+            despawned.extend(self.tracked_entities.difference(&in_view).copied());
+            self.tracked_entities = in_view;
+        } else {
+            for id in &world.tracking_updates {
+                let Some(viewable) = world.entities.get::<Viewable>(*id) else {
+                    continue;
+                };
+
+                let tracked = self.tracked_entities.contains(id);
+                if tracking::is_in_tracking_range(coord, chunk_x, chunk_z, viewable, tracked, view_distance) {
+                    if self.tracked_entities.insert(*id) {
+                        spawned.push(*id);
+                    }
+                } else if self.tracked_entities.remove(id) {
+                    despawned.push(*id);
+                }
+            }
+        }
+
+        // Despawn entities that are out of range or no longer in view distance
+        for id in despawned {
+            if let Some(viewable) = world.entities.get::<Viewable>(id) {
+                self.packets.write_buffer.copy_from(viewable.destroy_buffer.get_written());
+            }
+        }
+
+        // Spawn entities that came into range
+        for id in spawned {
+            let entity = world.entities.entity(id);
+            let viewable = entity.get::<Viewable>().expect("entity must be viewable");
+            let custom_name = entity.get::<EntityCustomName>();
+            (viewable.fn_create)(&mut self.packets.write_buffer, entity);
+            if let Some(custom_name) = custom_name {
+                custom_name.write_spawn_metadata(&mut self.packets.write_buffer);
+            }
+        }
+    }
+
+    fn update_swimming(&mut self) {
+        let world = self.get_world();
+        let coord = self.client_position.coord;
+
+        // Start swimming once the eyes are underwater, but only stop once
+        // the feet leave the water so that surfacing doesn't cancel swimming
+        let swimming = if self.abilities.is_flying || !self.is_sprinting() {
+            false
+        } else if self.is_swimming() {
+            pose::is_in_water(world, coord)
+        } else {
+            let eyes = Coordinate {
+                y: coord.y + self.get_eye_height(),
+                ..coord
+            };
+            pose::is_in_water(world, eyes) && pose::is_in_water(world, coord)
+        };
+
+        if self.is_swimming() != swimming {
+            self.set_swimming(swimming);
+        }
+    }
+
     fn update_pose(&mut self) -> anyhow::Result<()> {
+        self.update_swimming();
+
         let mut new_pose;
 
         // if passenger of another entity, pose should always be standing
@@ -468,8 +828,15 @@ impl<P: PlayerService> Player<P> {
         Ok(())
     }
 
-    pub fn can_enter_pose(&self, _pose: Pose) -> bool {
-        true // todo: actually check
+    /// Returns true if the bounding box of the pose wouldn't intersect any blocks,
+    /// eg. standing up isn't possible while crawling under a 1-block gap
+    pub fn can_enter_pose(&self, pose: Pose) -> bool {
+        if self.abilities.gamemode == GameMode::Spectator {
+            return true;
+        }
+
+        let height = pose::get_pose_height(pose);
+        pose::has_room(self.get_world(), self.client_position.coord, height)
     }
 
     fn update_interaction_state(&mut self) -> anyhow::Result<()> {
@@ -623,10 +990,263 @@ impl<P: PlayerService> Player<P> {
         Ok(())
     }
 
+    /// Moves the player to `position`, forcing the client to accept the new position
+    ///
+    /// Movement from the client is ignored until it confirms the teleport. If it doesn't
+    /// confirm, the teleport is resent a few times before its movement is accepted again
+    pub fn teleport(&mut self, mut position: Position) -> anyhow::Result<()> {
+        position.rot.fix();
+        self.handle_movement(position, false)?;
+
+        self.teleport_resend_count = 0;
+        self.send_teleport()
+    }
+
+    fn send_teleport(&mut self) -> anyhow::Result<()> {
+        self.teleport_id_counter = self.teleport_id_counter.wrapping_add(1);
+        if self.waiting_teleportation_id.add(self.teleport_id_counter).is_err() {
+            bail!("player has too many unconfirmed teleports");
+        }
+        self.teleport_resend_timer = 0;
+
+        self.packets.write_packet(&PlayerPosition {
+            x: self.position.coord.x as _,
+            y: self.position.coord.y as _,
+            z: self.position.coord.z as _,
+            yaw: self.position.rot.yaw,
+            pitch: self.position.rot.pitch,
+            relative_arguments: 0,
+            id: self.teleport_id_counter,
+            dismount_vehicle: false,
+        });
+
+        Ok(())
+    }
+
+    /// Rotates the player so that their `anchor` (eyes or feet) faces `target`
+    pub fn look_at(&mut self, target: Coordinate, anchor: LookAnchor) -> anyhow::Result<()> {
+        self.rotate_towards(target, anchor)?;
+
+        self.packets.write_packet(&PlayerLookAt {
+            anchor,
+            x: target.x as _,
+            y: target.y as _,
+            z: target.z as _,
+            entity: None,
+        });
+
+        Ok(())
+    }
+
+    /// Rotates the player so that their `anchor` faces the `entity_anchor` of an entity
+    ///
+    /// Returns false if the entity doesn't exist
+    pub fn look_at_entity(
+        &mut self,
+        entity_id: EntityId,
+        anchor: LookAnchor,
+        entity_anchor: LookAnchor,
+    ) -> anyhow::Result<bool> {
+        let target = match self.get_world_mut().get_entity_mut(entity_id) {
+            Some(entity) => entity.get::<Viewable>()
+                .expect("all entities must have viewable")
+                .coord,
+            None => return Ok(false),
+        };
+
+        // Entities don't have an eye height yet, so the server-side
+        // rotation always faces the feet of the entity
+        self.rotate_towards(target, anchor)?;
+
+        self.packets.write_packet(&PlayerLookAt {
+            anchor,
+            x: target.x as _,
+            y: target.y as _,
+            z: target.z as _,
+            entity: Some(PlayerLookAtEntity {
+                entity_id: entity_id.as_i32(),
+                entity_anchor,
+            }),
+        });
+
+        Ok(true)
+    }
+
+    fn rotate_towards(&mut self, target: Coordinate, anchor: LookAnchor) -> anyhow::Result<()> {
+        let mut from = self.position.coord;
+        if anchor == LookAnchor::Eyes {
+            from.y += self.get_eye_height();
+        }
+
+        let delta_x = target.x - from.x;
+        let delta_y = target.y - from.y;
+        let delta_z = target.z - from.z;
+        let horizontal = (delta_x * delta_x + delta_z * delta_z).sqrt();
+
+        let mut rot = Rotation {
+            yaw: -delta_x.atan2(delta_z).to_degrees(),
+            pitch: -delta_y.atan2(horizontal).to_degrees(),
+        };
+        rot.fix();
+
+        self.handle_movement(Position { coord: self.position.coord, rot }, false)
+    }
+
+    /// Moves the player to their spawn point, or to `world_spawn` if they don't have one
+    ///
+    /// If the bed or respawn anchor of the spawn point is missing or obstructed, the spawn point
+    /// is cleared and the player is told that it couldn't be used
+    pub fn respawn(&mut self, world_spawn: Position) -> anyhow::Result<()> {
+        let mut position = world_spawn;
+
+        if let Some(spawn_point) = self.spawn_point {
+            if spawn_point.is_for_world::<P::WorldServiceType>() {
+                match spawn_point.find_respawn_coordinate(self.get_world()) {
+                    Some(coord) => position.coord = coord,
+                    None => {
+                        self.spawn_point = None;
+                        self.send_game_event(GameEventKind::NoRespawnBlockAvailable);
+                    }
+                }
+            }
+        }
+
+        self.teleport(position)?;
+        self.resync_hud()
+    }
+
+    /// Sends the health, food, experience, abilities, selected slot and inventory to the client again
+    ///
+    /// The client resets these when respawning or changing worlds. None of the values are
+    /// changed, so this can be called any number of times
+    pub fn resync_hud(&mut self) -> anyhow::Result<()> {
+        self.write_health();
+        self.write_experience();
+        self.abilities.sync();
+        self.packets.write_packet(&SetCarriedItem {
+            slot: self.selected_hotbar_slot,
+        });
+        self.inventory.write_all(&mut self.packets.write_buffer)?;
+        Ok(())
+    }
+
+    /// Sends a play-state ping, the client will respond with a pong containing the same id
+    pub fn send_ping(&mut self, id: i32) {
+        self.packets.write_packet(&Ping { id });
+    }
+
+    /// Id of the most recent pong received from the client, see `send_ping`
+    pub fn get_last_pong_id(&self) -> Option<i32> {
+        self.last_pong_id
+    }
+
+    pub fn send_game_event(&mut self, kind: GameEventKind) {
+        self.packets.write_packet(&GameEvent::from(kind));
+    }
+
+    pub fn get_health(&self) -> f32 {
+        self.health
+    }
+
+    /// Sets the health of the player, clamped between 0 and the max health attribute
+    pub fn set_health(&mut self, health: f32) {
+        let max_health = self.attributes.get(Attribute::MaxHealth) as f32;
+        self.health = health.clamp(0.0, max_health);
+        self.write_health();
+    }
+
+    pub fn get_food(&self) -> i32 {
+        self.food
+    }
+
+    pub fn get_saturation(&self) -> f32 {
+        self.saturation
+    }
+
+    /// Sets the food level, clamped between 0 and 20, and the saturation, clamped between 0 and the food level
+    pub fn set_food(&mut self, food: i32, saturation: f32) {
+        self.food = food.clamp(0, 20);
+        self.saturation = saturation.clamp(0.0, self.food as f32);
+        self.write_health();
+    }
+
+    fn write_health(&mut self) {
+        self.packets.write_packet(&SetHealth {
+            health: self.health,
+            food: self.food,
+            saturation: self.saturation,
+        });
+    }
+
+    pub fn get_experience(&self) -> Experience {
+        self.experience
+    }
+
+    pub fn set_experience(&mut self, experience: Experience) {
+        self.experience = experience;
+        self.write_experience();
+    }
+
+    fn write_experience(&mut self) {
+        self.packets.write_packet(&SetExperience {
+            experience_progress: self.experience.progress,
+            level: self.experience.level,
+            total_experience: self.experience.total,
+        });
+    }
+
+    pub fn get_permission_level(&self) -> u8 {
+        self.permission_level
+    }
+
+    /// Sets the permission level used for commands, clamped to `MAX_PERMISSION_LEVEL`
+    ///
+    /// The player is sent the commands that they are now allowed to use
+    pub fn set_permission_level(&mut self, permission_level: u8) {
+        self.permission_level = permission_level.min(MAX_PERMISSION_LEVEL);
+
+        let universe = self.get_world_mut().get_universe();
+        if let Some(command_packet) = universe.command_packet(self.permission_level) {
+            self.packets.write_packet(command_packet);
+        }
+    }
+
+    /// Reduces the health of the player by `amount`, unless they are invulnerable
+    pub fn damage(&mut self, amount: f32) {
+        if self.abilities.invulnerable {
+            return;
+        }
+        self.set_health(self.health - amount);
+    }
+
+    /// Opens the sign editor for the sign at `pos`. The text entered by the player
+    /// is set using `World::set_sign_text`
+    pub fn open_sign_editor(&mut self, pos: BlockPosition) {
+        self.editing_sign = Some(pos);
+        self.packets.write_packet(&OpenSignEditor { pos });
+    }
+
+    /// Sets the base value of an attribute, see `Player::attributes` for modifiers
+    pub fn set_attribute(&mut self, attribute: Attribute, value: f64) {
+        self.attributes.set_base(attribute, value);
+    }
+
     pub fn transfer(&mut self, func: TransferFn<P>) {
         self.transfer_fn = Some(func);
     }
 
+    /// Transfers the player to another universe using the sender returned by
+    /// `Universe::create_and_start`. The player will be removed from this world on the next tick
+    pub fn transfer_universe(&mut self, sender: Sender<(UninitializedConnection, GameProfile)>) {
+        if self.disconnected {
+            return;
+        }
+
+        self.connection.redirect_to_universe(sender, self.profile.clone());
+        self.transferring_universe = true;
+        self.disconnect();
+    }
+
     pub fn clip_block_position(&self, position: BlockPosition) -> Option<(f32, f32)> {
         let aabb = AABB::new(
             Point::new(position.x as f32, position.y as f32, position.z as f32),
@@ -651,6 +1271,21 @@ impl<P: PlayerService> Player<P> {
         )
     }
 
+    pub fn get_bounding_box(&self) -> AABB {
+        let coord = self.client_position.coord;
+        let half_width = pose::PLAYER_WIDTH / 2.0;
+        let height = pose::get_pose_height(self.metadata.pose);
+
+        AABB::new(
+            Point::new(coord.x - half_width, coord.y, coord.z - half_width),
+            Point::new(
+                coord.x + half_width,
+                coord.y + height,
+                coord.z + half_width,
+            ),
+        )
+    }
+
     pub fn get_eye_height(&self) -> f32 {
         match self.metadata.pose {
             Pose::Sleeping => 0.2,
@@ -681,22 +1316,6 @@ impl<P: PlayerService> Player<P> {
                     global: false,
                 },
             );
-
-            // Update neighbors
-            for offset in [(1, 0, 0), (0, 1, 0), (0, 0, 1), (-1, 0, 0), (0, -1, 0), (0, 0, -1)] {
-                let x = pos.x + offset.0;
-                let y = pos.y + offset.1;
-                let z = pos.z + offset.2;
-                
-                let block_state_id = self.get_world().get_block_i32(x, y, z);
-                if let Some(block_state_id) = block_state_id {
-                    let block: &Block = block_state_id.try_into().unwrap();
-                    let mut block = block.clone();
-                    if block_update::update(block_state_id, &mut block, x, y, z, self.get_world_mut()) {
-                        self.get_world_mut().set_block_i32(x, y, z, (&block).into());
-                    }
-                }
-            }
         }
     }
 
@@ -721,6 +1340,24 @@ impl<P: PlayerService> Player<P> {
                 face,
                 offset,
             } => {
+                // Using a bed or a charged respawn anchor sets the spawn point
+                if !self.is_shift_key_down() {
+                    let clicked = self
+                        .get_world()
+                        .get_block_i32(position.x, position.y, position.z)
+                        .and_then(|id| <&Block>::try_from(id).ok());
+                    if clicked.is_some_and(spawn_point::can_set_spawn) {
+                        self.spawn_point = Some(SpawnPoint::new::<P::WorldServiceType>(position));
+                        self.send_message("Respawn point set");
+                        return;
+                    }
+
+                    if clicked.is_some_and(chest::is_chest) {
+                        self.open_chest(position);
+                        return;
+                    }
+                }
+
                 let slot = InventorySlot::Hotbar(self.selected_hotbar_slot as _);
                 let held_item = self
                     .inventory
@@ -749,20 +1386,9 @@ impl<P: PlayerService> Player<P> {
                                 let block_id: u16 = block.to_id();
                                 world.set_block_i32(place_position.x, place_position.y, place_position.z, block_id);
 
-                                // Update neighbors
-                                for offset in [(1, 0, 0), (0, 1, 0), (0, 0, 1), (-1, 0, 0), (0, -1, 0), (0, 0, -1)] {
-                                    let x = place_position.x + offset.0;
-                                    let y = place_position.y + offset.1;
-                                    let z = place_position.z + offset.2;
-                                    
-                                    let block_state_id = self.get_world().get_block_i32(x, y, z);
-                                    if let Some(block_state_id) = block_state_id {
-                                        let block: &Block = block_state_id.try_into().unwrap();
-                                        let mut block = block.clone();
-                                        if block_update::update(block_state_id, &mut block, x, y, z, self.get_world_mut()) {
-                                            self.get_world_mut().set_block_i32(x, y, z, (&block).into());
-                                        }
-                                    }
+                                if sign::is_sign(&block) {
+                                    world.set_sign_text(place_position, ["".into(), "".into(), "".into(), "".into()]);
+                                    self.open_sign_editor(place_position);
                                 }
                             }
                         }
@@ -799,23 +1425,33 @@ impl<P: PlayerService> Player<P> {
                     break_time,
                     self.get_break_speed_multiplier(),
                 ) {
-                    // todo: check if the stage changed, only send packet then
-                    self.packets.write_self_excluded_viewable_packet(
-                        &BlockDestruction {
-                            entity_id: self.entity_id.as_i32(),
-                            location: position,
-                            destroy_stage,
-                        }
-                    );
+                    // Only send the destroy stage to viewers if it has changed
+                    if self.interaction_state.destroy_stage != Some(destroy_stage) {
+                        self.interaction_state.destroy_stage = Some(destroy_stage);
+                        self.packets.write_self_excluded_viewable_packet(
+                            &BlockDestruction {
+                                entity_id: self.entity_id.as_i32(),
+                                location: position,
+                                destroy_stage,
+                            }
+                        );
+                    }
                 }
-
-                // update destruction
             }
             Interaction::FinishBreak {
                 position,
                 break_time: _,
                 distance: _,
             } => {
+                // Clear the destroy stage for viewers
+                self.packets.write_self_excluded_viewable_packet(
+                    &BlockDestruction {
+                        entity_id: self.entity_id.as_i32(),
+                        location: position,
+                        destroy_stage: -1,
+                    },
+                );
+
                 self.break_block(position);
             }
             Interaction::AbortBreak {
@@ -868,9 +1504,15 @@ impl<P: PlayerService> Player<P> {
     }
 
     pub fn send_message<T: Into<TextComponent>>(&mut self, message: T) {
+        self.send_system_message(message, false);
+    }
+
+    /// Sends a system message to the player. If `overlay` is true, the message is shown
+    /// in the action bar instead of the chat box
+    pub fn send_system_message<T: Into<TextComponent>>(&mut self, message: T, overlay: bool) {
         self.packets.write_packet(&server::SystemChat {
             message: message.into().to_json(),
-            overlay: false,
+            overlay,
         })
     }
 
@@ -878,6 +1520,41 @@ impl<P: PlayerService> Player<P> {
         self.disconnected = true;
     }
 
+    /// Kicks the player because a player with the same UUID joined the universe,
+    /// see `DuplicateLoginPolicy`
+    ///
+    /// The player is removed from its world immediately, so that it has been despawned
+    /// before the new player spawns, even if the new player joins another world
+    fn kick_duplicate_login(player: *mut ()) {
+        // Safety: the pointer is kept up to date by `update_pointer`
+        let player = unsafe { &mut *(player as *mut Self) };
+        player.kick(Component::new().translate("multiplayer.disconnect.duplicate_login"));
+
+        let uuid = player.profile.uuid;
+        // Safety: the pointer is kept up to date by `PlayerVec`
+        if let Some(player_vec) = unsafe { player.player_vec.as_mut() } {
+            player_vec.remove_duplicate_login(uuid);
+        }
+    }
+
+    /// Disconnects the player, showing the reason on the disconnection screen
+    pub fn kick<T: Into<TextComponent>>(&mut self, reason: T) {
+        if self.disconnected {
+            return;
+        }
+
+        self.packets.write_packet(&server::Disconnect {
+            reason: reason.into().to_json(),
+        });
+
+        // The player won't be ticked again, so the packets are written immediately
+        let to_write = self.packets.write_buffer.pop_written();
+        self.connection.write_bytes(to_write);
+        self.packets.write_buffer.clear();
+
+        self.disconnect();
+    }
+
     pub(crate) fn fire_interaction(&mut self, interaction: Interaction) {
         // todo: send to service
         self.do_default_interaction(interaction);
@@ -941,7 +1618,16 @@ impl<P: PlayerService> Player<P> {
             let packet_read_result = graphite_net::packet_helper::try_read_packet(&mut bytes)?;
             match packet_read_result {
                 PacketReadResult::Complete(bytes) => {
-                    self.parse_and_handle(bytes)?;
+                    if let Err(error) = self.parse_and_handle(bytes) {
+                        let error = PacketHandleError {
+                            packet_id: bytes.first().copied(),
+                            error,
+                        };
+                        match P::on_packet_error(self, &error) {
+                            HandleAction::Continue => {}
+                            HandleAction::Disconnect => return Err(error.into()),
+                        }
+                    }
                 }
                 PacketReadResult::Partial => break,
                 PacketReadResult::Empty => break,
@@ -949,7 +1635,7 @@ impl<P: PlayerService> Player<P> {
         }
 
         // Send contents of write buffer if FAST_PACKET_RESPONSE is enabled
-        if P::FAST_PACKET_RESPONSE {
+        if P::FAST_PACKET_RESPONSE && !self.disconnected {
             let to_write = self.packets.write_buffer.pop_written();
             self.connection.write_bytes(to_write);
             self.packets.write_buffer.clear();
@@ -971,6 +1657,8 @@ impl<P: PlayerService> Drop for Player<P> {
     fn drop(&mut self) {
         if !std::thread::panicking() {
             self.get_world_mut().remove_player(self, self.new_chunk_view_position);
+            let (uuid, entity_id) = (self.profile.uuid, self.entity_id);
+            self.get_world_mut().get_universe().remove_online_player(uuid, entity_id);
 
             if !self.moved_into_proto {
                 unsafe {
@@ -998,6 +1686,14 @@ impl<P: PlayerService> Unsticky for Player<P> {
         } else {
             chunk.update_player_pointer(self);
         }
+
+        let online_player = OnlinePlayer {
+            entity_id: self.entity_id,
+            player: ptr as *mut (),
+            fn_kick_duplicate: Self::kick_duplicate_login,
+        };
+        let uuid = self.profile.uuid;
+        self.get_world_mut().get_universe().add_online_player(uuid, online_player);
     }
 
     fn unstick(mut self) -> Self::UnstuckType {
@@ -1014,9 +1710,23 @@ impl<P: PlayerService> Unsticky for Player<P> {
             let service = unsafe { ManuallyDrop::take(&mut self.service) };
             let transfer_fn = self.transfer_fn.take();
     
+            let mut proto_player = ProtoPlayer::new(connection, self.profile.clone(), self.entity_id);
+            proto_player.spawn_point = self.spawn_point;
+            proto_player.abilities = std::mem::take(&mut self.abilities);
+            proto_player.health = self.health;
+            proto_player.food = self.food;
+            proto_player.saturation = self.saturation;
+            proto_player.experience = self.experience;
+            proto_player.selected_hotbar_slot = self.selected_hotbar_slot;
+            proto_player.permission_level = self.permission_level;
+            proto_player.inventory = (0..46)
+                .map(|index| self.inventory.get(InventorySlot::All(index)).cloned().unwrap_or_default())
+                .collect();
+            proto_player.resync_hud = true;
+
             // Return the ProtoPlayer and Service as a tuple
             Some((
-                ProtoPlayer::new(connection, self.profile.clone(), self.entity_id),
+                proto_player,
                 service,
                 transfer_fn
             ))
@@ -1,6 +1,9 @@
-use std::marker::PhantomData;
+use std::{marker::PhantomData, sync::mpsc::Sender};
 
-use graphite_net::network_handler::{Connection, ConnectionService, ConnectionSlab};
+use graphite_mc_protocol::types::GameProfile;
+use graphite_net::network_handler::{
+    Connection, ConnectionService, ConnectionSlab, UninitializedConnection,
+};
 
 use crate::universe::{Universe, UniverseService};
 
@@ -20,6 +23,16 @@ pub trait AbstractConnectionReference<U: UniverseService> {
         connection_index: u16,
     ) -> Self;
 
+    /// Moves the underlying connection to another universe (which may be running on another thread)
+    ///
+    /// Any bytes that have been received but not yet handled are sent along with the connection.
+    /// After calling this method, the reference no longer points to a valid connection
+    fn redirect_to_universe(
+        &mut self,
+        sender: Sender<(UninitializedConnection, GameProfile)>,
+        profile: GameProfile,
+    );
+
     /// # Safety
     /// This method should only be called if it is known that
     /// the connection pointed to has been closed as well
@@ -81,6 +94,26 @@ impl<U: UniverseService> AbstractConnectionReference<U> for ConnectionReference<
         self.closed = true;
     }
 
+    fn redirect_to_universe(
+        &mut self,
+        sender: Sender<(UninitializedConnection, GameProfile)>,
+        profile: GameProfile,
+    ) {
+        let (connection, player_connection) = self.get_connection_mut();
+        player_connection.clear_player_pointer();
+
+        let mut to_send = Some((sender, profile));
+        connection.request_redirect(move |_, connection, _| {
+            if let Some((sender, profile)) = to_send.take() {
+                // If the receiver is gone, the connection is closed when dropped
+                let _ = sender.send((connection, profile));
+            }
+        });
+
+        // The connection now belongs to the other universe, don't close it on drop
+        self.closed = true;
+    }
+
     fn read_bytes(&self) -> &[u8] {
         self.get_connection().0.read_bytes()
     }
@@ -3,23 +3,29 @@ use graphite_command::types::ParseState;
 use graphite_mc_protocol::{
     play::{
         client::{
-            self, AcceptTeleportation, ClientInformation, CustomPayload, InteractEntity,
-            MovePlayerOnGround, MovePlayerPos, MovePlayerPosRot, MovePlayerRot, PlayerAbilities,
-            PlayerHandAction, PlayerMoveAction, UseItem, UseItemOn,
+            self, AcceptTeleportation, ClientInformation, ContainerClick, ContainerClose,
+            CustomPayload, InteractEntity, MovePlayerOnGround, MovePlayerPos, MovePlayerPosRot,
+            MovePlayerRot, PlayerAbilities, PlayerHandAction, PlayerMoveAction, UpdateSign, UseItem,
+            UseItemOn,
+        },
+        server::{
+            AnimateEntity, CommandSuggestions, ContainerSetSlot, EntityAnimation, SuggestionMatch,
         },
-        server::{AnimateEntity, ContainerSetSlot, EntityAnimation},
     },
-    types::{Hand, HandAction, MoveAction},
+    types::{ClickType, Hand, HandAction, MoveAction},
 };
 use queues::IsQueue;
 
 use crate::{
     gamemode::GameMode,
-    inventory::inventory_handler::{InventoryHandler, InventorySlot},
+    inventory::inventory_handler::{InventoryHandler, InventorySlot, ItemSlot},
     player::interaction::Interaction,
 };
 
-use super::{Player, PlayerService};
+use super::{
+    window_click::{is_same_slot, ClickWindow},
+    Player, PlayerService,
+};
 
 impl<P: PlayerService> client::PacketHandler for Player<P> {
     const DEBUG: bool = false;
@@ -82,8 +88,12 @@ impl<P: PlayerService> client::PacketHandler for Player<P> {
                 // Pop the teleport ID from the queue
                 self.waiting_teleportation_id.remove().unwrap();
 
-                // Reset the timer, the player has confirmed the teleport
+                // Reset the timers, the player has confirmed the teleport
                 self.teleport_id_timer = 0;
+                self.teleport_resend_timer = 0;
+                if self.waiting_teleportation_id.size() == 0 {
+                    self.teleport_resend_count = 0;
+                }
             } else {
                 // Wrong teleport ID! But lets not kick the player just yet...
                 // Start a timer, if they don't send the correct ID within 20 ticks,
@@ -162,9 +172,13 @@ impl<P: PlayerService> client::PacketHandler for Player<P> {
             }
             HandAction::DropAllItems => {
                 self.interaction_state.ignore_swing_ticks = 1;
+                let slot = InventorySlot::Hotbar(self.selected_hotbar_slot as _);
+                self.drop_from_slot(slot, true)?;
             }
             HandAction::DropItem => {
                 self.interaction_state.ignore_swing_ticks = 1;
+                let slot = InventorySlot::Hotbar(self.selected_hotbar_slot as _);
+                self.drop_from_slot(slot, false)?;
             }
             HandAction::ReleaseUseItem => {
                 if let Some(interaction) = self.interaction_state.try_abort_use(true) {
@@ -306,6 +320,20 @@ impl<P: PlayerService> client::PacketHandler for Player<P> {
         Ok(())
     }
 
+    fn handle_update_sign(&mut self, packet: UpdateSign) -> anyhow::Result<()> {
+        // Only accept text for the sign that the player was asked to edit
+        if self.editing_sign.take() != Some(packet.pos) {
+            return Ok(());
+        }
+
+        // Lines are limited to 384 characters by the packet, formatting codes are removed
+        let lines = [packet.line1, packet.line2, packet.line3, packet.line4]
+            .map(|line| line.replace('\u{a7}', "").into());
+        self.get_world_mut().set_sign_text(packet.pos, lines);
+
+        Ok(())
+    }
+
     fn handle_player_abilities(&mut self, packet: PlayerAbilities) -> anyhow::Result<()> {
         if packet.flags == 0 {
             // Client can always stop flying
@@ -364,26 +392,58 @@ impl<P: PlayerService> client::PacketHandler for Player<P> {
         Ok(())
     }
 
+    fn handle_pong(&mut self, packet: client::Pong) -> anyhow::Result<()> {
+        self.last_pong_id = Some(packet.id);
+        Ok(())
+    }
+
     fn handle_chat_command(&mut self, packet: client::ChatCommand) -> anyhow::Result<()> {
         // todo: finalize this functionality, add comments
 
         if let Some(dispatch) = &mut self.get_world_mut().get_universe().root_dispatch_node {
             let mut parse_state = ParseState::new(packet.command);
-            parse_state.push_ref(self, parse_state.full_span);
-            parse_state.push_arg(
-                unsafe {
-                    std::mem::transmute::<std::any::TypeId, u64>(std::any::TypeId::of::<P>())
-                },
-                parse_state.full_span,
-            );
+            parse_state.set_permission_level(self.get_permission_level());
+            parse_state.push_context(self);
             let result = dispatch.dispatch_with(parse_state);
-            
+
             self.send_message(format!("{:?}", result));
         }
 
         Ok(())
     }
 
+    fn handle_command_suggestion(
+        &mut self,
+        packet: client::CommandSuggestion,
+    ) -> anyhow::Result<()> {
+        // Commands are sent with the leading slash, which the dispatcher doesn't expect
+        let command = packet.command.strip_prefix('/').unwrap_or(packet.command);
+
+        let suggestions = match &self.get_world_mut().get_universe().root_dispatch_node {
+            Some(dispatch) => dispatch.suggest(command),
+            None => None,
+        };
+
+        if let Some(suggestions) = suggestions {
+            let offset = packet.command.len() - command.len();
+            self.packets.write_packet(&CommandSuggestions {
+                id: packet.id,
+                start: (suggestions.start + offset) as _,
+                length: suggestions.length as _,
+                suggestions: suggestions
+                    .suggestions
+                    .iter()
+                    .map(|text| SuggestionMatch {
+                        text,
+                        tooltip: None,
+                    })
+                    .collect(),
+            });
+        }
+
+        Ok(())
+    }
+
     fn handle_set_carried_item(&mut self, packet: client::SetCarriedItem) -> anyhow::Result<()> {
         if packet.slot > 8 {
             bail!("invalid slot")
@@ -446,9 +506,204 @@ impl<P: PlayerService> client::PacketHandler for Player<P> {
 
         Ok(())
     }
+
+    fn handle_container_click(&mut self, packet: ContainerClick) -> anyhow::Result<()> {
+        // Slots of the container come first, followed by the main inventory and hotbar
+        let container_size = match &self.open_container {
+            _ if packet.window_id == 0 => 0,
+            Some(container) if container.window_id == packet.window_id => container.size(),
+            _ => return Ok(()), // the window was closed by the server
+        };
+        let window_size = if container_size == 0 { 46 } else { container_size + 36 };
+
+        match (packet.click_type, packet.slot) {
+            // Clicking outside of the window drops the cursor item
+            (ClickType::Pickup, -999) => {
+                let mut itemstack = match self.inventory.get_cursor() {
+                    ItemSlot::Filled(itemstack) => itemstack.clone(),
+                    ItemSlot::Empty => return Ok(()),
+                };
+
+                if packet.button == 0 || itemstack.count <= 1 {
+                    self.inventory.set_cursor(ItemSlot::Empty);
+                } else {
+                    let mut remaining = itemstack.clone();
+                    remaining.count -= 1;
+                    itemstack.count = 1;
+                    self.inventory.set_cursor(ItemSlot::Filled(remaining));
+                }
+
+                self.drop_item(itemstack);
+            }
+            (ClickType::Throw, -999) => (),
+            (ClickType::Throw, slot) => {
+                if !(0..window_size as i16).contains(&slot) {
+                    bail!("invalid slot");
+                }
+                let slot = slot as usize;
+                if slot < container_size {
+                    self.drop_from_container_slot(slot, packet.button == 1);
+                } else {
+                    let index = self.to_inventory_index(slot, container_size);
+                    self.drop_from_slot(InventorySlot::All(index), packet.button == 1)?;
+                }
+            }
+            // Creative mode players can create any item, so their changes are trusted
+            _ if self.abilities.gamemode == GameMode::Creative => {
+                for changed_slot in packet.changed_slots {
+                    if !(0..window_size as i16).contains(&changed_slot.slot) {
+                        bail!("invalid slot");
+                    }
+                    let slot = changed_slot.slot as usize;
+                    if slot < container_size {
+                        self.client_set_container_slot(slot, changed_slot.item.try_into()?);
+                    } else {
+                        let index = self.to_inventory_index(slot, container_size);
+                        self.inventory.client_set(index, changed_slot.item)?;
+                    }
+                }
+                self.inventory.client_set_cursor(packet.carried_item)?;
+            }
+            (click_type, slot) => {
+                // Apply the click to the server-side inventory and container, the changes of
+                // the client are only used to check whether it agrees with the result
+                let Some(mut window) = self.create_click_window(container_size) else {
+                    return Ok(()); // the container was removed, the window is closed next tick
+                };
+                let before = window.slots.clone();
+                let before_cursor = window.cursor.clone();
+                window.click(click_type, slot, packet.button);
+
+                let mut agrees = is_same_slot(&packet.carried_item.try_into()?, &window.cursor);
+                let mut reported = vec![false; window.slots.len()];
+                for changed_slot in packet.changed_slots {
+                    if !(0..window_size as i16).contains(&changed_slot.slot) {
+                        bail!("invalid slot");
+                    }
+                    let slot = changed_slot.slot as usize;
+                    reported[slot] = true;
+                    agrees &= is_same_slot(&changed_slot.item.try_into()?, &window.slots[slot]);
+                }
+                for (index, (before, after)) in before.iter().zip(&window.slots).enumerate() {
+                    agrees &= reported[index] || is_same_slot(before, after);
+                }
+
+                // Changes the client didn't predict are sent back to it
+                for (index, (before, after)) in before.into_iter().zip(window.slots).enumerate() {
+                    if is_same_slot(&before, &after) {
+                        continue;
+                    }
+                    if index < container_size {
+                        self.client_set_container_slot(index, after);
+                        continue;
+                    }
+
+                    // The offhand follows the slots of the window
+                    let index = if index == window_size {
+                        InventorySlot::OffHand.get_index()?
+                    } else {
+                        self.to_inventory_index(index, container_size)
+                    };
+                    match after {
+                        _ if agrees => self.inventory.client_set(index, (&after).into())?,
+                        ItemSlot::Filled(itemstack) => self.inventory.set(InventorySlot::All(index), itemstack)?,
+                        ItemSlot::Empty => self.inventory.clear(InventorySlot::All(index))?,
+                    }
+                }
+                if !is_same_slot(&before_cursor, &window.cursor) {
+                    self.inventory.client_set_cursor((&window.cursor).into())?;
+                }
+
+                if !agrees {
+                    self.resync_window(container_size)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn handle_container_close(&mut self, packet: ContainerClose) -> anyhow::Result<()> {
+        if packet.window_id == 0 {
+            self.close_inventory();
+        } else if self.open_container.as_ref().is_some_and(|container| container.window_id == packet.window_id) {
+            self.handle_close_container();
+        }
+
+        Ok(())
+    }
 }
 
 impl<P: PlayerService> Player<P> {
+    // Copies the slots of the open window, used to apply clicks
+    fn create_click_window(&self, container_size: usize) -> Option<ClickWindow> {
+        let get = |slot| self.inventory.get(slot).expect("slot in bounds").clone();
+        let slots = if container_size > 0 {
+            let mut slots = self.get_container_items()?;
+            slots.extend((0..36).map(|index| get(InventorySlot::MainAndHotbar(index))));
+            slots.push(get(InventorySlot::OffHand));
+            slots
+        } else {
+            (0..46).map(|index| get(InventorySlot::All(index))).collect()
+        };
+        Some(ClickWindow::new(slots, self.inventory.get_cursor().clone(), container_size))
+    }
+
+    // Sends the entire contents of the open window, undoing the changes that the client predicted
+    fn resync_window(&mut self, container_size: usize) -> anyhow::Result<()> {
+        if container_size > 0 {
+            self.write_container_contents();
+        } else {
+            self.inventory.write_all(&mut self.packets.write_buffer)?;
+        }
+        Ok(())
+    }
+
+    // Converts a slot of the open window into an index of the player inventory
+    fn to_inventory_index(&self, slot: usize, container_size: usize) -> usize {
+        match &self.open_container {
+            Some(container) if container_size > 0 => container.to_inventory_index(slot),
+            _ => slot,
+        }
+    }
+
+    fn drop_from_container_slot(&mut self, slot: usize, whole_stack: bool) {
+        let mut itemstack = match self.get_container_slot(slot) {
+            ItemSlot::Filled(itemstack) => itemstack,
+            ItemSlot::Empty => return,
+        };
+
+        if whole_stack || itemstack.count <= 1 {
+            self.client_set_container_slot(slot, ItemSlot::Empty);
+        } else {
+            let mut remaining = itemstack.clone();
+            remaining.count -= 1;
+            itemstack.count = 1;
+            self.client_set_container_slot(slot, ItemSlot::Filled(remaining));
+        }
+
+        self.drop_item(itemstack);
+    }
+
+    fn drop_from_slot(&mut self, slot: InventorySlot, whole_stack: bool) -> anyhow::Result<()> {
+        let mut itemstack = match self.inventory.get(slot)? {
+            ItemSlot::Filled(itemstack) => itemstack.clone(),
+            ItemSlot::Empty => return Ok(()),
+        };
+
+        if whole_stack || itemstack.count <= 1 {
+            self.inventory.clear(slot)?;
+        } else {
+            let mut remaining = itemstack.clone();
+            remaining.count -= 1;
+            itemstack.count = 1;
+            self.inventory.set(slot, remaining)?;
+        }
+
+        self.drop_item(itemstack);
+        Ok(())
+    }
+
     fn ack_block_sequence(&mut self, sequence: i32) {
         match self.ack_sequence_up_to {
             Some(old) => {
@@ -471,11 +726,8 @@ impl<P: PlayerService> Player<P> {
                     .try_abort_break()
                     .expect("break must be active");
                 self.fire_interaction(interaction);
-            }
-
-            // Make sure player is looking at block, get distance
-            if let Some((first, _)) = self.clip_block_position(position) {
-                // Finish the block break
+            } else if let Some((first, _)) = self.clip_block_position(position) {
+                // Player is looking at the block, finish the break
                 let interaction = self
                     .interaction_state
                     .try_finish_break(first)
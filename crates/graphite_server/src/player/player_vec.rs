@@ -36,8 +36,10 @@ impl<P: PlayerService> PlayerVec<P> {
         self.world = world;
 
         // Update all the world refs of the players inside this player vec
+        let player_vec: *mut Self = self;
         for player in self.players.iter_mut() {
             player.world = self.world;
+            player.player_vec = player_vec;
         }
     }
 
@@ -49,6 +51,10 @@ impl<P: PlayerService> PlayerVec<P> {
         self.players.get(index)
     }
 
+    pub fn get_mut_by_index(&mut self, index: usize) -> Option<&mut Player<P>> {
+        self.players.get_mut(index)
+    }
+
     pub fn add(
         &mut self,
         proto_player: ProtoPlayer<P::UniverseServiceType>,
@@ -64,13 +70,35 @@ impl<P: PlayerService> PlayerVec<P> {
             return Ok(());
         }
 
+        self.remove_duplicate_login(proto_player.profile.uuid);
+
         let world = self.get_world()?;
-        let player = proto_player.create_player(service, world, position)?;
+        let mut player = proto_player.create_player(service, world, position)?;
+        player.player_vec = self;
         self.players.push(player);
 
         Ok(())
     }
 
+    /// Removes a player with the same UUID that was kicked by the `DuplicateLoginPolicy` of the
+    /// universe, so that it is cleaned up before the new player is spawned
+    ///
+    /// Does nothing while the players are being ticked, the kicked player is removed by the tick instead
+    pub(crate) fn remove_duplicate_login(&mut self, uuid: u128) {
+        if self.locked {
+            return;
+        }
+
+        self.players.retain_mut(|player| {
+            if player.profile.uuid != uuid || !player.disconnected {
+                return true;
+            }
+
+            player.handle_disconnected();
+            false
+        });
+    }
+
     pub fn len(&self) -> usize {
         self.players.len()
     }
@@ -95,8 +123,10 @@ impl<P: PlayerService> PlayerVec<P> {
 
                     // It is important that new players are added after View,
                     // otherwise a player may see themselves despawn
-                    for (proto_player, service, position) in self.delayed_add.drain(..) {
-                        if let Ok(player) = proto_player.create_player(service, world, position) {
+                    for (proto_player, service, position) in std::mem::take(&mut self.delayed_add) {
+                        self.remove_duplicate_login(proto_player.profile.uuid);
+                        if let Ok(mut player) = proto_player.create_player(service, world, position) {
+                            player.player_vec = self;
                             self.players.push(player);
                         }
                     }
@@ -0,0 +1,60 @@
+use graphite_mc_constants::block::Block;
+use graphite_mc_protocol::types::Pose;
+
+use crate::{
+    entity::position::Coordinate,
+    world::{World, WorldService},
+};
+
+use super::falling;
+
+/// Width of the player's bounding box, which is the same for every pose
+pub const PLAYER_WIDTH: f32 = 0.6;
+
+/// Height of the player's bounding box in the given pose
+///
+/// Swimming (and crawling, which uses the same pose) makes the bounding box horizontal,
+/// leaving a 0.6x0.6x0.6 cube
+pub fn get_pose_height(pose: Pose) -> f32 {
+    match pose {
+        Pose::Sleeping => 0.2,
+        Pose::Swimming | Pose::FallFlying | Pose::SpinAttack => 0.6,
+        Pose::Sneaking => 1.5,
+        _ => 1.8,
+    }
+}
+
+/// Returns true if a bounding box of `height` placed at `coord` doesn't intersect any solid blocks
+pub(crate) fn has_room<W: WorldService>(world: &World<W>, coord: Coordinate, height: f32) -> bool {
+    let half_width = PLAYER_WIDTH / 2.0;
+
+    // Shrink slightly so that a box touching the face of a block doesn't count as intersecting
+    let min_x = (coord.x - half_width + 0.001).floor() as i32;
+    let max_x = (coord.x + half_width - 0.001).floor() as i32;
+    let min_y = (coord.y + 0.001).floor() as i32;
+    let max_y = (coord.y + height - 0.001).floor() as i32;
+    let min_z = (coord.z - half_width + 0.001).floor() as i32;
+    let max_z = (coord.z + half_width - 0.001).floor() as i32;
+
+    for x in min_x..=max_x {
+        for y in min_y..=max_y {
+            for z in min_z..=max_z {
+                if falling::is_solid(world.get_block_i32(x, y, z)) {
+                    return false;
+                }
+            }
+        }
+    }
+
+    true
+}
+
+/// Returns true if the block containing `coord` is water
+pub(crate) fn is_in_water<W: WorldService>(world: &World<W>, coord: Coordinate) -> bool {
+    let x = coord.x.floor() as i32;
+    let y = coord.y.floor() as i32;
+    let z = coord.z.floor() as i32;
+
+    let block: Option<&Block> = world.get_block_i32(x, y, z).and_then(|id| id.try_into().ok());
+    matches!(block, Some(Block::Water { .. } | Block::BubbleColumn { .. }))
+}
@@ -1,18 +1,21 @@
 use crate::{
     entity::position::Position,
     gamemode::Abilities,
+    inventory::inventory_handler::ItemSlot,
     universe::{EntityId, UniverseService},
     world::World,
 };
 use graphite_net::{network_buffer::WriteBuffer, packet_helper};
+use graphite_text_component::TextComponent;
 use graphite_mc_protocol::{
-    play::server::{PlayerInfo, PlayerInfoAddPlayer, Respawn},
+    play::server::{Disconnect, PlayerInfo, PlayerInfoAddPlayer},
     types::GameProfile,
 };
 
 use super::{
-    player::{Player, PlayerService},
+    player::{Experience, Player, PlayerService},
     player_connection::AbstractConnectionReference,
+    spawn_point::SpawnPoint,
 };
 
 // Proto player
@@ -22,7 +25,20 @@ pub struct ProtoPlayer<U: UniverseService> {
     pub hardcore: bool,
     pub abilities: Abilities,
     pub profile: GameProfile,
-
+    pub spawn_point: Option<SpawnPoint>,
+
+    pub health: f32,
+    pub food: i32,
+    pub saturation: f32,
+    pub experience: Experience,
+    pub selected_hotbar_slot: u8,
+    /// Permission level used for commands, between 0 and `universe::MAX_PERMISSION_LEVEL`
+    pub permission_level: u8,
+    /// Contents of the inventory by slot index, empty slots are used for missing entries
+    pub inventory: Vec<ItemSlot>,
+
+    /// Set when the proto player comes from an existing player, eg. when transferring worlds
+    pub(crate) resync_hud: bool,
     pub(crate) write_buffer: WriteBuffer,
     pub(crate) entity_id: EntityId,
 }
@@ -37,7 +53,17 @@ impl<U: UniverseService> ProtoPlayer<U> {
             hardcore: false,
             abilities: Default::default(),
             profile,
+            spawn_point: None,
+
+            health: 20.0,
+            food: 20,
+            saturation: 5.0,
+            experience: Default::default(),
+            selected_hotbar_slot: 0,
+            permission_level: 0,
+            inventory: Vec::new(),
 
+            resync_hud: false,
             write_buffer: WriteBuffer::new(),
             entity_id,
 
@@ -45,6 +71,16 @@ impl<U: UniverseService> ProtoPlayer<U> {
         }
     }
 
+    /// Disconnects the player before it has been spawned, showing the reason on the disconnection screen
+    pub fn kick<T: Into<TextComponent>>(mut self, reason: T) {
+        packet_helper::try_write_packet(&mut self.write_buffer, &Disconnect {
+            reason: reason.into().to_json(),
+        });
+        self.connection.write_bytes(self.write_buffer.pop_written());
+
+        // Dropping the connection closes it
+    }
+
     pub(crate) fn create_player<P: PlayerService<UniverseServiceType = U>>(
         mut self,
         service: P,
@@ -69,6 +105,11 @@ impl<U: UniverseService> ProtoPlayer<U> {
         };
         packet_helper::try_write_packet(&mut self.write_buffer, &add_player_info);
 
+        // Send the commands that the player is allowed to use
+        if let Some(command_packet) = world.get_universe().command_packet(self.permission_level) {
+            packet_helper::try_write_packet(&mut self.write_buffer, command_packet);
+        }
+
         /*let respawn = Respawn {
             dimension_type: "graphite:default_dimension",
             dimension_name: "graphite:default_dimension",
@@ -105,7 +146,13 @@ impl<U: UniverseService> ProtoPlayer<U> {
         // Write the necessary packets to the TCP stream
         self.connection.write_bytes(self.write_buffer.pop_written());
 
-        let player = Player::new(service, world, position, view_position, self);
+        let resync_hud = self.resync_hud;
+        let mut player = Player::new(service, world, position, view_position, self);
+
+        // The client resets its HUD when changing worlds, so the existing state needs to be sent again
+        if resync_hud {
+            player.resync_hud()?;
+        }
 
         Ok(player)
     }
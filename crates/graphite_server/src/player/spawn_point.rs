@@ -0,0 +1,85 @@
+use std::any::TypeId;
+
+use graphite_mc_constants::block::{Block, BlockAttributes};
+use graphite_mc_protocol::types::BlockPosition;
+
+use crate::{
+    entity::position::Coordinate,
+    world::{World, WorldService},
+};
+
+/// A personal spawn point of a player, set by using a bed or a charged respawn anchor
+///
+/// There are no dimension ids yet, so the dimension is the type of the world's service.
+/// The spawn point is only used when respawning in a world with the same service type
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SpawnPoint {
+    pub position: BlockPosition,
+    pub dimension: TypeId,
+}
+
+impl SpawnPoint {
+    pub fn new<W: WorldService>(position: BlockPosition) -> Self {
+        Self {
+            position,
+            dimension: TypeId::of::<W>(),
+        }
+    }
+
+    pub fn is_for_world<W: WorldService>(&self) -> bool {
+        self.dimension == TypeId::of::<W>()
+    }
+
+    /// Returns the coordinate to respawn at, or None if the bed/anchor is missing or obstructed
+    pub fn find_respawn_coordinate<W: WorldService>(&self, world: &World<W>) -> Option<Coordinate> {
+        let pos = self.position;
+        let block: &Block = world.get_block_i32(pos.x, pos.y, pos.z)?.try_into().ok()?;
+        let height = match block {
+            Block::RespawnAnchor { charges } if *charges > 0 => 1.0,
+            block if is_bed(block) => 0.5625,
+            _ => return None,
+        };
+
+        // The block above needs to be free to stand in
+        let above = world.get_block_i32(pos.x, pos.y + 1, pos.z)?;
+        let above: &BlockAttributes = above.try_into().ok()?;
+        if !above.air && !above.replaceable {
+            return None;
+        }
+
+        Some(Coordinate {
+            x: pos.x as f32 + 0.5,
+            y: pos.y as f32 + height,
+            z: pos.z as f32 + 0.5,
+        })
+    }
+}
+
+pub(crate) fn can_set_spawn(block: &Block) -> bool {
+    match block {
+        Block::RespawnAnchor { charges } => *charges > 0,
+        block => is_bed(block),
+    }
+}
+
+fn is_bed(block: &Block) -> bool {
+    matches!(
+        block,
+        Block::WhiteBed { .. }
+            | Block::OrangeBed { .. }
+            | Block::MagentaBed { .. }
+            | Block::LightBlueBed { .. }
+            | Block::YellowBed { .. }
+            | Block::LimeBed { .. }
+            | Block::PinkBed { .. }
+            | Block::GrayBed { .. }
+            | Block::LightGrayBed { .. }
+            | Block::CyanBed { .. }
+            | Block::PurpleBed { .. }
+            | Block::BlueBed { .. }
+            | Block::BrownBed { .. }
+            | Block::GreenBed { .. }
+            | Block::RedBed { .. }
+            | Block::BlackBed { .. }
+    )
+}
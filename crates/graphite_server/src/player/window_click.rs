@@ -0,0 +1,278 @@
+use graphite_mc_protocol::types::ClickType;
+
+use crate::inventory::{inventory_handler::ItemSlot, itemstack::ItemStack};
+
+/// The server-side state of a window that the player clicked in
+///
+/// The slots are laid out like the window sent to the client. The player inventory window
+/// contains the 46 slots of the inventory, other windows contain the container slots followed
+/// by the main inventory, the hotbar and the offhand. The offhand isn't part of these windows,
+/// it is only included so that it can be swapped with
+pub(crate) struct ClickWindow {
+    pub(crate) slots: Vec<ItemSlot>,
+    pub(crate) cursor: ItemSlot,
+    container_size: usize,
+}
+
+impl ClickWindow {
+    pub(crate) fn new(slots: Vec<ItemSlot>, cursor: ItemSlot, container_size: usize) -> Self {
+        debug_assert_eq!(slots.len(), if container_size == 0 { 46 } else { container_size + 37 });
+        Self {
+            slots,
+            cursor,
+            container_size,
+        }
+    }
+
+    /// The number of slots that the client knows about
+    pub(crate) fn window_size(&self) -> usize {
+        if self.container_size == 0 {
+            46
+        } else {
+            self.container_size + 36
+        }
+    }
+
+    fn hotbar_start(&self) -> usize {
+        self.window_size() - 9 - (self.container_size == 0) as usize
+    }
+
+    fn offhand(&self) -> usize {
+        self.slots.len() - 1
+    }
+
+    // The crafting result of the player inventory can only be taken from
+    fn is_result_slot(&self, slot: usize) -> bool {
+        self.container_size == 0 && slot == 0
+    }
+
+    /// Applies a click like the vanilla server would
+    ///
+    /// Returns false if the click isn't supported, in which case nothing is changed
+    pub(crate) fn click(&mut self, click_type: ClickType, slot: i16, button: i8) -> bool {
+        if !(0..self.window_size() as i16).contains(&slot) {
+            return false;
+        }
+        let slot = slot as usize;
+
+        match click_type {
+            ClickType::Pickup if button == 0 || button == 1 => {
+                self.pickup(slot, button == 1);
+                true
+            }
+            ClickType::QuickMove => {
+                self.quick_move(slot);
+                true
+            }
+            ClickType::Swap => {
+                let other = match button {
+                    0..=8 => self.hotbar_start() + button as usize,
+                    40 => self.offhand(),
+                    _ => return false,
+                };
+                if !self.is_result_slot(slot) {
+                    self.slots.swap(slot, other);
+                }
+                true
+            }
+            // Cloning is only possible in creative mode
+            ClickType::Clone => true,
+            _ => false,
+        }
+    }
+
+    fn pickup(&mut self, slot: usize, single: bool) {
+        let cursor = std::mem::take(&mut self.cursor);
+        let slot_item = std::mem::take(&mut self.slots[slot]);
+
+        let (slot_item, cursor) = match (slot_item, cursor) {
+            (ItemSlot::Empty, ItemSlot::Empty) => (ItemSlot::Empty, ItemSlot::Empty),
+            (ItemSlot::Filled(mut itemstack), ItemSlot::Empty) => {
+                if single && !self.is_result_slot(slot) {
+                    // Take half of the stack, rounding up
+                    let taken = itemstack.count - itemstack.count / 2;
+                    let mut taken_stack = itemstack.clone();
+                    taken_stack.count = taken;
+                    itemstack.count -= taken;
+                    (to_slot(itemstack), ItemSlot::Filled(taken_stack))
+                } else {
+                    (ItemSlot::Empty, ItemSlot::Filled(itemstack))
+                }
+            }
+            (slot_item, cursor) if self.is_result_slot(slot) => (slot_item, cursor),
+            (ItemSlot::Empty, ItemSlot::Filled(mut itemstack)) => {
+                if single && itemstack.count > 1 {
+                    let mut placed = itemstack.clone();
+                    placed.count = 1;
+                    itemstack.count -= 1;
+                    (ItemSlot::Filled(placed), ItemSlot::Filled(itemstack))
+                } else {
+                    (ItemSlot::Filled(itemstack), ItemSlot::Empty)
+                }
+            }
+            (ItemSlot::Filled(mut existing), ItemSlot::Filled(mut itemstack)) => {
+                if can_stack(&existing, &itemstack) {
+                    let room = max_stack_size(&existing) - existing.count;
+                    let moved = if single { 1 } else { itemstack.count }.min(room);
+                    existing.count += moved;
+                    itemstack.count -= moved;
+                    (ItemSlot::Filled(existing), to_slot(itemstack))
+                } else {
+                    (ItemSlot::Filled(itemstack), ItemSlot::Filled(existing))
+                }
+            }
+        };
+
+        self.slots[slot] = slot_item;
+        self.cursor = cursor;
+    }
+
+    fn quick_move(&mut self, slot: usize) {
+        let container_size = self.container_size;
+        let hotbar_start = self.hotbar_start();
+        let window_size = self.window_size();
+
+        if container_size > 0 {
+            if slot < container_size {
+                self.move_to(slot, container_size..window_size, true);
+            } else {
+                self.move_to(slot, 0..container_size, false);
+            }
+        } else {
+            match slot {
+                0 => self.move_to(slot, 9..45, true),
+                9..=35 => self.move_to(slot, hotbar_start..45, false),
+                36..=44 => self.move_to(slot, 9..hotbar_start, false),
+                _ => self.move_to(slot, 9..45, false),
+            }
+        }
+    }
+
+    // Moves the stack in `slot` into the slots of `range`, merging with existing stacks first
+    fn move_to(&mut self, slot: usize, range: std::ops::Range<usize>, reverse: bool) {
+        let ItemSlot::Filled(mut itemstack) = std::mem::take(&mut self.slots[slot]) else {
+            return;
+        };
+
+        let order: Vec<usize> = if reverse { range.rev().collect() } else { range.collect() };
+        for &index in &order {
+            if itemstack.count == 0 {
+                break;
+            }
+            if let ItemSlot::Filled(existing) = &mut self.slots[index] {
+                if can_stack(existing, &itemstack) {
+                    let moved = itemstack.count.min(max_stack_size(existing) - existing.count);
+                    existing.count += moved;
+                    itemstack.count -= moved;
+                }
+            }
+        }
+        if itemstack.count > 0 {
+            if let Some(&index) = order.iter().find(|index| self.slots[**index] == ItemSlot::Empty) {
+                self.slots[index] = ItemSlot::Filled(itemstack);
+                return;
+            }
+        }
+
+        self.slots[slot] = to_slot(itemstack);
+    }
+}
+
+/// Compares the item, count and nbt of two slots
+pub(crate) fn is_same_slot(first: &ItemSlot, second: &ItemSlot) -> bool {
+    match (first, second) {
+        (ItemSlot::Empty, ItemSlot::Empty) => true,
+        (ItemSlot::Filled(first), ItemSlot::Filled(second)) => {
+            first.count == second.count && can_stack(first, second)
+        }
+        _ => false,
+    }
+}
+
+fn can_stack(first: &ItemStack, second: &ItemStack) -> bool {
+    first.item == second.item && first.nbt.to_bytes() == second.nbt.to_bytes()
+}
+
+fn max_stack_size(itemstack: &ItemStack) -> i8 {
+    itemstack.properties.max_stack_size as i8
+}
+
+fn to_slot(itemstack: ItemStack) -> ItemSlot {
+    if itemstack.count > 0 {
+        ItemSlot::Filled(itemstack)
+    } else {
+        ItemSlot::Empty
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use graphite_mc_constants::item::Item;
+    use graphite_mc_protocol::types::ClickType;
+
+    use crate::inventory::{inventory_handler::ItemSlot, itemstack::ItemStack};
+
+    use super::{is_same_slot, ClickWindow};
+
+    fn stack(item: Item, count: i8) -> ItemSlot {
+        ItemSlot::Filled(ItemStack::of(item, count).unwrap())
+    }
+
+    fn inventory_window() -> ClickWindow {
+        ClickWindow::new(vec![ItemSlot::Empty; 46], ItemSlot::Empty, 0)
+    }
+
+    #[test]
+    fn pickup() {
+        let mut window = inventory_window();
+        window.slots[36] = stack(Item::Stone, 5);
+        window.slots[37] = stack(Item::Stone, 62);
+
+        // Right clicking takes half of the stack, rounding up
+        assert!(window.click(ClickType::Pickup, 36, 1));
+        assert_eq!(window.cursor, stack(Item::Stone, 3));
+        assert_eq!(window.slots[36], stack(Item::Stone, 2));
+
+        // Left clicking merges as much as fits
+        assert!(window.click(ClickType::Pickup, 37, 0));
+        assert_eq!(window.cursor, stack(Item::Stone, 1));
+        assert_eq!(window.slots[37], stack(Item::Stone, 64));
+
+        // Different items are swapped
+        window.slots[38] = stack(Item::DiamondSword, 1);
+        assert!(window.click(ClickType::Pickup, 38, 0));
+        assert_eq!(window.cursor, stack(Item::DiamondSword, 1));
+        assert_eq!(window.slots[38], stack(Item::Stone, 1));
+
+        // Nothing can be placed into the crafting result
+        assert!(window.click(ClickType::Pickup, 0, 0));
+        assert_eq!(window.slots[0], ItemSlot::Empty);
+        assert_eq!(window.cursor, stack(Item::DiamondSword, 1));
+
+        // Unsupported clicks and slots outside the window don't change anything
+        assert!(!window.click(ClickType::PickupAll, 38, 0));
+        assert!(!window.click(ClickType::Pickup, 46, 0));
+        assert!(is_same_slot(&window.cursor, &stack(Item::DiamondSword, 1)));
+    }
+
+    #[test]
+    fn quick_move_and_swap() {
+        let mut window = ClickWindow::new(vec![ItemSlot::Empty; 27 + 37], ItemSlot::Empty, 27);
+        window.slots[0] = stack(Item::Stone, 40);
+        window.slots[62] = stack(Item::Stone, 60);
+
+        // Shift clicking the container moves into the inventory, filling the hotbar from the end
+        assert!(window.click(ClickType::QuickMove, 0, 0));
+        assert_eq!(window.slots[0], ItemSlot::Empty);
+        assert_eq!(window.slots[62], stack(Item::Stone, 64));
+        assert_eq!(window.slots[61], stack(Item::Stone, 36));
+
+        // Swapping with the first hotbar slot and the offhand
+        assert!(window.click(ClickType::Swap, 61, 0));
+        assert_eq!(window.slots[54], stack(Item::Stone, 36));
+        assert!(window.click(ClickType::Swap, 54, 40));
+        assert_eq!(window.slots[54], ItemSlot::Empty);
+        assert_eq!(window.slots[63], stack(Item::Stone, 36));
+        assert!(!window.click(ClickType::Swap, 0, 9));
+    }
+}
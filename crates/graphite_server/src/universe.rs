@@ -1,12 +1,15 @@
 use anyhow::bail;
-use graphite_binary::nbt::NBTNode;
+use graphite_binary::nbt::{CachedNBT, NBTNode};
 use graphite_command::dispatcher::RootDispatchNode;
+use graphite_command::minecraft::MinecraftRootDispatchNode;
 use graphite_mc_constants::tags::block::BlockTags;
 use graphite_net::network_handler::{
     ConnectionSlab, NetworkManagerService, NewConnectionAccepter, UninitializedConnection,
 };
 use graphite_mc_protocol::types::GameProfile;
+use graphite_text_component::Component;
 use std::borrow::Cow;
+use std::collections::HashMap;
 use std::sync::mpsc::{self, Receiver, TryRecvError};
 use std::{sync::mpsc::Sender, time::Duration};
 
@@ -15,6 +18,12 @@ use graphite_mc_protocol::play::server::{Commands, CustomPayload, Tag, TagRegist
 use crate::player::player_connection::{AbstractConnectionReference, PlayerConnection};
 use crate::player::proto_player::ProtoPlayer;
 use crate::ticker::UniverseTicker;
+use crate::world::chunk::Chunk;
+
+/// Name of the dimension that players are placed in
+pub const DEFAULT_DIMENSION_NAME: &str = "graphite:default_dimension";
+/// Highest permission level that players can have, players with this level can use every command
+pub const MAX_PERMISSION_LEVEL: u8 = 4;
 
 // user defined universe service trait
 
@@ -30,6 +39,24 @@ where
 
 // graphite universe
 
+/// What happens when a player joins while a player with the same UUID is already in the universe,
+/// eg. when reconnecting before the old connection timed out
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum DuplicateLoginPolicy {
+    /// Kicks the existing player, then spawns the new player. This is what vanilla does
+    #[default]
+    KickOld,
+    /// Kicks the new player, keeping the existing player
+    RejectNew,
+}
+
+/// A player in one of the worlds of the universe, used to apply the `DuplicateLoginPolicy`
+pub(crate) struct OnlinePlayer {
+    pub(crate) entity_id: EntityId,
+    pub(crate) player: *mut (),
+    pub(crate) fn_kick_duplicate: fn(*mut ()),
+}
+
 #[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
 #[repr(transparent)]
 pub struct EntityId(i32);
@@ -45,7 +72,12 @@ pub struct Universe<U: UniverseService> {
     player_receiver: Receiver<(UninitializedConnection, GameProfile)>,
     entity_id_counter: i32,
     pub(crate) root_dispatch_node: Option<RootDispatchNode>,
-    pub(crate) command_packet: Option<Commands>,
+    /// Brigadier packets indexed by permission level, empty if the universe has no commands
+    command_packets: Vec<Commands>,
+    registry_codec: CachedNBT,
+    /// Players in any world of the universe, by UUID
+    online_players: HashMap<u128, OnlinePlayer>,
+    duplicate_login_policy: DuplicateLoginPolicy,
 }
 
 // graphite universe impl
@@ -56,101 +88,40 @@ impl<U: UniverseService> Universe<U> {
         connection_ref: U::ConnectionReferenceType,
         profile: GameProfile,
     ) {
+        let uuid = profile.uuid;
         let mut proto_player = ProtoPlayer::new(connection_ref, profile, self.new_entity_id());
 
+        if let Some(existing) = self.online_players.get(&uuid) {
+            match self.duplicate_login_policy {
+                // The kicked player is removed from its world before the new player is added,
+                // see `Player::kick_duplicate_login`
+                DuplicateLoginPolicy::KickOld => {
+                    // Removing the kicked player also removes it from `online_players`
+                    let (fn_kick_duplicate, player) = (existing.fn_kick_duplicate, existing.player);
+                    fn_kick_duplicate(player);
+                }
+                DuplicateLoginPolicy::RejectNew => {
+                    proto_player.kick(Component::new().translate("multiplayer.disconnect.duplicate_login"));
+                    return;
+                }
+            }
+        }
+
         self.write_login_packets(&mut proto_player);
 
         U::handle_player_join(self, proto_player);
     }
 
     fn write_login_packets(&mut self, proto_player: &mut ProtoPlayer<U>) {
-        let mut nbt = graphite_binary::nbt::NBT::new();
-
-        // Write minecraft:chat_type (empty)
-        let chat_type_values = NBTNode::List { type_id: graphite_binary::nbt::TAG_COMPOUND_ID, children: Vec::new() };
-        let mut chat_type = NBTNode::Compound(Default::default());
-        nbt.insert(&mut chat_type, "type", NBTNode::String("minecraft:chat_type".into()));
-        nbt.insert(&mut chat_type, "value", chat_type_values);
-        nbt.insert_root("minecraft:chat_type", chat_type);
-
-        // Write minecraft:dimension_type
-        let mut my_dimension = NBTNode::Compound(Default::default());
-        nbt.insert(&mut my_dimension, "ambient_light", NBTNode::Float(1.0));
-        nbt.insert(&mut my_dimension, "fixed_time", NBTNode::Long(6000));
-        nbt.insert(&mut my_dimension, "natural", NBTNode::Byte(1));
-        nbt.insert(&mut my_dimension, "min_y", NBTNode::Int(0));
-        nbt.insert(&mut my_dimension, "height", NBTNode::Int(384));
-        // nbt.insert(&mut my_dimension, "effects", NBTNode::Byte(0));
-
-        // These values don't affect the client, only the server. The values are meaningless
-        nbt.insert(&mut my_dimension, "piglin_safe", NBTNode::Byte(0));
-        nbt.insert(&mut my_dimension, "has_raids", NBTNode::Byte(0));
-        nbt.insert(&mut my_dimension, "monster_spawn_light_level", NBTNode::Int(0));
-        nbt.insert(&mut my_dimension, "monster_spawn_block_light_limit", NBTNode::Int(0));
-        nbt.insert(&mut my_dimension, "infiniburn", NBTNode::String("#minecraft:infiniburn_overworld".into()));
-        nbt.insert(&mut my_dimension, "respawn_anchor_works", NBTNode::Byte(0));
-        nbt.insert(&mut my_dimension, "has_skylight", NBTNode::Byte(0));
-        nbt.insert(&mut my_dimension, "bed_works", NBTNode::Byte(0));
-        nbt.insert(&mut my_dimension, "logical_height", NBTNode::Int(384));
-        nbt.insert(&mut my_dimension, "coordinate_scale", NBTNode::Double(1.0));
-        nbt.insert(&mut my_dimension, "ultrawarm", NBTNode::Byte(0));
-        nbt.insert(&mut my_dimension, "has_ceiling", NBTNode::Byte(0));
-
-        let mut my_dimension_entry = NBTNode::Compound(Default::default());
-        nbt.insert(&mut my_dimension_entry, "name", NBTNode::String("graphite:default_dimension".into()));
-        nbt.insert(&mut my_dimension_entry, "id", NBTNode::Int(0));
-        nbt.insert(&mut my_dimension_entry, "element", my_dimension.clone());
-
-        // todo: remove this
-        let mut my_dimension_entry_2 = NBTNode::Compound(Default::default());
-        nbt.insert(&mut my_dimension_entry_2, "name", NBTNode::String("graphite:default_dimension2".into()));
-        nbt.insert(&mut my_dimension_entry_2, "id", NBTNode::Int(0));
-        nbt.insert(&mut my_dimension_entry_2, "element", my_dimension);
-
-        let mut dimension_type_values = NBTNode::List { type_id: graphite_binary::nbt::TAG_COMPOUND_ID, children: Vec::new() };
-        nbt.append(&mut dimension_type_values, my_dimension_entry);
-        nbt.append(&mut dimension_type_values, my_dimension_entry_2);
-
-        let mut dimension_type = NBTNode::Compound(Default::default());
-        nbt.insert(&mut dimension_type, "type", NBTNode::String("minecraft:dimension_type".into()));
-        nbt.insert(&mut dimension_type, "value", dimension_type_values);
-        nbt.insert_root("minecraft:dimension_type", dimension_type);
-
-        // Write minecraft:worldgen/biome
-        let mut my_biome_effects = NBTNode::Compound(Default::default());
-        nbt.insert(&mut my_biome_effects, "sky_color", NBTNode::Int(0x78a7ff));
-        nbt.insert(&mut my_biome_effects, "water_fog_color", NBTNode::Int(0x050533));
-        nbt.insert(&mut my_biome_effects, "water_color", NBTNode::Int(0x3f76e4));
-        nbt.insert(&mut my_biome_effects, "fog_color", NBTNode::Int(0xc0d8ff));
-
-        let mut my_biome = NBTNode::Compound(Default::default());
-        nbt.insert(&mut my_biome, "precipitation", NBTNode::String("rain".into()));
-        nbt.insert(&mut my_biome, "temperature", NBTNode::Float(0.8));
-        nbt.insert(&mut my_biome, "downfall", NBTNode::Float(0.4));
-        nbt.insert(&mut my_biome, "effects", my_biome_effects);
-
-        let mut my_biome_entry = NBTNode::Compound(Default::default());
-        nbt.insert(&mut my_biome_entry, "name", NBTNode::String("minecraft:plains".into()));
-        nbt.insert(&mut my_biome_entry, "id", NBTNode::Int(0));
-        nbt.insert(&mut my_biome_entry, "element", my_biome);
-
-        let mut biome_type_values = NBTNode::List { type_id: graphite_binary::nbt::TAG_COMPOUND_ID, children: Vec::new() };
-        nbt.append(&mut biome_type_values, my_biome_entry);
-
-        let mut biome_type = NBTNode::Compound(Default::default());
-        nbt.insert(&mut biome_type, "type", NBTNode::String("minecraft:worldgen/biome".into()));
-        nbt.insert(&mut biome_type, "value", biome_type_values);
-        nbt.insert_root("minecraft:worldgen/biome", biome_type);
-
         let join_game_packet = Login {
             entity_id: proto_player.entity_id.as_i32(),
             is_hardcore: proto_player.hardcore,
             gamemode: proto_player.abilities.gamemode as u8,
             previous_gamemode: -1,
-            dimension_names: vec!["graphite:default_dimension"],
-            registry_codec: Cow::Owned(nbt.into()),
-            dimension_type: "graphite:default_dimension",
-            dimension_name: "graphite:default_dimension",
+            dimension_names: vec![DEFAULT_DIMENSION_NAME],
+            registry_codec: Cow::Borrowed(&self.registry_codec),
+            dimension_type: DEFAULT_DIMENSION_NAME,
+            dimension_name: DEFAULT_DIMENSION_NAME,
             hashed_seed: 0, // affects biome noise
             max_players: 0, // unused
             view_distance: 8, //W::CHUNK_VIEW_DISTANCE as _,
@@ -164,10 +135,6 @@ impl<U: UniverseService> Universe<U> {
 
         graphite_net::packet_helper::try_write_packet(&mut proto_player.write_buffer, &join_game_packet);
 
-        if let Some(command_packet) = &self.command_packet {
-            graphite_net::packet_helper::try_write_packet(&mut proto_player.write_buffer, command_packet);
-        }
-
         let mut block_registry: Vec<Tag> = Vec::new();
         for block_tag in BlockTags::iter() {
             let tag_name = block_tag.to_namespace();
@@ -204,6 +171,53 @@ impl<U: UniverseService> Universe<U> {
         graphite_net::packet_helper::try_write_packet(&mut proto_player.write_buffer, &brand_packet);
     }
 
+    /// Registry codec sent to every joining player in the `Login` packet
+    ///
+    /// The codec is only encoded once and the bytes are reused for every player
+    pub fn get_registry_codec(&self) -> &CachedNBT {
+        &self.registry_codec
+    }
+
+    /// Allows adding custom registries (eg. dimension types or biomes)
+    ///
+    /// Players that have already joined aren't affected. The codec is re-encoded
+    /// once when the next player joins
+    pub fn get_registry_codec_mut(&mut self) -> &mut CachedNBT {
+        &mut self.registry_codec
+    }
+
+    /// Returns the brigadier packet for players with `permission_level`, if there are any commands
+    pub(crate) fn command_packet(&self, permission_level: u8) -> Option<&Commands> {
+        let level = permission_level.min(MAX_PERMISSION_LEVEL) as usize;
+        self.command_packets.get(level)
+    }
+
+    pub fn get_duplicate_login_policy(&self) -> DuplicateLoginPolicy {
+        self.duplicate_login_policy
+    }
+
+    /// Sets what happens when a player joins with the same UUID as a player that is already
+    /// in one of the worlds, see `DuplicateLoginPolicy`
+    pub fn set_duplicate_login_policy(&mut self, policy: DuplicateLoginPolicy) {
+        self.duplicate_login_policy = policy;
+    }
+
+    /// Returns true if a player with the UUID is in one of the worlds
+    pub fn is_online(&self, uuid: u128) -> bool {
+        self.online_players.contains_key(&uuid)
+    }
+
+    pub(crate) fn add_online_player(&mut self, uuid: u128, online_player: OnlinePlayer) {
+        self.online_players.insert(uuid, online_player);
+    }
+
+    /// Removes the player, unless the UUID now belongs to a player that logged in after it
+    pub(crate) fn remove_online_player(&mut self, uuid: u128, entity_id: EntityId) {
+        if self.online_players.get(&uuid).is_some_and(|online| online.entity_id == entity_id) {
+            self.online_players.remove(&uuid);
+        }
+    }
+
     pub fn new_entity_id(&mut self) -> EntityId {
         self.entity_id_counter = self.entity_id_counter.wrapping_add(1);
         EntityId(self.entity_id_counter)
@@ -216,7 +230,7 @@ impl<U: UniverseService> NetworkManagerService for Universe<U> {
     const TICK_RATE: Option<std::time::Duration> = Some(Duration::from_millis(50));
     type ConnectionServiceType = PlayerConnection<U>;
 
-    fn new_connection_service(&mut self) -> PlayerConnection<U> {
+    fn new_connection_service(&mut self) -> Option<PlayerConnection<U>> {
         unimplemented!();
     }
 
@@ -264,30 +278,48 @@ impl<U: UniverseService> Universe<U> {
             player_receiver: rx,
             entity_id_counter: 0,
             root_dispatch_node: None,
-            command_packet: None,
+            command_packets: Vec::new(),
+            registry_codec: create_registry_codec(),
+            online_players: HashMap::new(),
+            duplicate_login_policy: Default::default(),
         }
     }
 
+    /// Starts the universe on a new thread, with its own network manager
+    ///
+    /// Players are sent to the universe using the returned sender. This is used
+    /// both by the concierge and by `Player::transfer_universe`, allowing multiple
+    /// universes to run on separate threads without sharing any mutable state
     pub fn create_and_start<F: FnOnce() -> U + std::marker::Send + 'static>(
         service_func: F,
-        commands: Option<(RootDispatchNode, Commands)>,
+        commands: Option<MinecraftRootDispatchNode>,
     ) -> Sender<(UninitializedConnection, GameProfile)> {
         let (tx, rx) = mpsc::channel::<(UninitializedConnection, GameProfile)>();
 
-        std::thread::spawn(|| {
-            let (root_dispatch_node, command_packet) = if let Some(commands) = commands {
-                (Some(commands.0), Some(commands.1))
-            } else {
-                (None, None)
-            };
+        // Each permission level is sent a packet containing only the commands it can use
+        let (root_dispatch_node, command_packets) = if let Some(root) = commands {
+            let mut command_packets: Vec<Commands> = (1..=MAX_PERMISSION_LEVEL)
+                .map(|level| graphite_command::minecraft::create_brigadier_packet(&root, level))
+                .collect();
+            let (root_dispatch_node, command_packet) =
+                graphite_command::minecraft::create_dispatcher_and_brigadier_packet(root);
+            command_packets.insert(0, command_packet);
+            (Some(root_dispatch_node), command_packets)
+        } else {
+            (None, Vec::new())
+        };
 
+        std::thread::spawn(|| {
             let service = service_func();
             let universe = Universe {
                 service,
                 player_receiver: rx,
                 entity_id_counter: 0,
                 root_dispatch_node,
-                command_packet,
+                command_packets,
+                registry_codec: create_registry_codec(),
+                online_players: HashMap::new(),
+                duplicate_login_policy: Default::default(),
             };
 
             graphite_net::network_handler::start_with_init(universe, None, |network_manager| {
@@ -300,3 +332,85 @@ impl<U: UniverseService> Universe<U> {
         tx
     }
 }
+
+fn create_registry_codec() -> CachedNBT {
+    let mut nbt = graphite_binary::nbt::NBT::new();
+
+    // Write minecraft:chat_type (empty)
+    let chat_type_values = NBTNode::List { type_id: graphite_binary::nbt::TAG_COMPOUND_ID, children: Vec::new() };
+    let mut chat_type = NBTNode::Compound(Default::default());
+    nbt.insert(&mut chat_type, "type", NBTNode::String("minecraft:chat_type".into()));
+    nbt.insert(&mut chat_type, "value", chat_type_values);
+    nbt.insert_root("minecraft:chat_type", chat_type);
+
+    // Write minecraft:dimension_type
+    let mut my_dimension = NBTNode::Compound(Default::default());
+    nbt.insert(&mut my_dimension, "ambient_light", NBTNode::Float(1.0));
+    nbt.insert(&mut my_dimension, "fixed_time", NBTNode::Long(6000));
+    nbt.insert(&mut my_dimension, "natural", NBTNode::Byte(1));
+    nbt.insert(&mut my_dimension, "min_y", NBTNode::Int(Chunk::MIN_Y));
+    nbt.insert(&mut my_dimension, "height", NBTNode::Int(384));
+    // nbt.insert(&mut my_dimension, "effects", NBTNode::Byte(0));
+
+    // These values don't affect the client, only the server. The values are meaningless
+    nbt.insert(&mut my_dimension, "piglin_safe", NBTNode::Byte(0));
+    nbt.insert(&mut my_dimension, "has_raids", NBTNode::Byte(0));
+    nbt.insert(&mut my_dimension, "monster_spawn_light_level", NBTNode::Int(0));
+    nbt.insert(&mut my_dimension, "monster_spawn_block_light_limit", NBTNode::Int(0));
+    nbt.insert(&mut my_dimension, "infiniburn", NBTNode::String("#minecraft:infiniburn_overworld".into()));
+    nbt.insert(&mut my_dimension, "respawn_anchor_works", NBTNode::Byte(0));
+    nbt.insert(&mut my_dimension, "has_skylight", NBTNode::Byte(0));
+    nbt.insert(&mut my_dimension, "bed_works", NBTNode::Byte(0));
+    nbt.insert(&mut my_dimension, "logical_height", NBTNode::Int(384));
+    nbt.insert(&mut my_dimension, "coordinate_scale", NBTNode::Double(1.0));
+    nbt.insert(&mut my_dimension, "ultrawarm", NBTNode::Byte(0));
+    nbt.insert(&mut my_dimension, "has_ceiling", NBTNode::Byte(0));
+
+    let mut my_dimension_entry = NBTNode::Compound(Default::default());
+    nbt.insert(&mut my_dimension_entry, "name", NBTNode::String("graphite:default_dimension".into()));
+    nbt.insert(&mut my_dimension_entry, "id", NBTNode::Int(0));
+    nbt.insert(&mut my_dimension_entry, "element", my_dimension.clone());
+
+    // todo: remove this
+    let mut my_dimension_entry_2 = NBTNode::Compound(Default::default());
+    nbt.insert(&mut my_dimension_entry_2, "name", NBTNode::String("graphite:default_dimension2".into()));
+    nbt.insert(&mut my_dimension_entry_2, "id", NBTNode::Int(0));
+    nbt.insert(&mut my_dimension_entry_2, "element", my_dimension);
+
+    let mut dimension_type_values = NBTNode::List { type_id: graphite_binary::nbt::TAG_COMPOUND_ID, children: Vec::new() };
+    nbt.append(&mut dimension_type_values, my_dimension_entry);
+    nbt.append(&mut dimension_type_values, my_dimension_entry_2);
+
+    let mut dimension_type = NBTNode::Compound(Default::default());
+    nbt.insert(&mut dimension_type, "type", NBTNode::String("minecraft:dimension_type".into()));
+    nbt.insert(&mut dimension_type, "value", dimension_type_values);
+    nbt.insert_root("minecraft:dimension_type", dimension_type);
+
+    // Write minecraft:worldgen/biome
+    let mut my_biome_effects = NBTNode::Compound(Default::default());
+    nbt.insert(&mut my_biome_effects, "sky_color", NBTNode::Int(0x78a7ff));
+    nbt.insert(&mut my_biome_effects, "water_fog_color", NBTNode::Int(0x050533));
+    nbt.insert(&mut my_biome_effects, "water_color", NBTNode::Int(0x3f76e4));
+    nbt.insert(&mut my_biome_effects, "fog_color", NBTNode::Int(0xc0d8ff));
+
+    let mut my_biome = NBTNode::Compound(Default::default());
+    nbt.insert(&mut my_biome, "precipitation", NBTNode::String("rain".into()));
+    nbt.insert(&mut my_biome, "temperature", NBTNode::Float(0.8));
+    nbt.insert(&mut my_biome, "downfall", NBTNode::Float(0.4));
+    nbt.insert(&mut my_biome, "effects", my_biome_effects);
+
+    let mut my_biome_entry = NBTNode::Compound(Default::default());
+    nbt.insert(&mut my_biome_entry, "name", NBTNode::String("minecraft:plains".into()));
+    nbt.insert(&mut my_biome_entry, "id", NBTNode::Int(0));
+    nbt.insert(&mut my_biome_entry, "element", my_biome);
+
+    let mut biome_type_values = NBTNode::List { type_id: graphite_binary::nbt::TAG_COMPOUND_ID, children: Vec::new() };
+    nbt.append(&mut biome_type_values, my_biome_entry);
+
+    let mut biome_type = NBTNode::Compound(Default::default());
+    nbt.insert(&mut biome_type, "type", NBTNode::String("minecraft:worldgen/biome".into()));
+    nbt.insert(&mut biome_type, "value", biome_type_values);
+    nbt.insert_root("minecraft:worldgen/biome", biome_type);
+
+    nbt.into()
+}
@@ -0,0 +1,103 @@
+use std::{
+    sync::mpsc::{self, Sender},
+    thread::JoinHandle,
+};
+
+use super::{
+    chunk::Chunk,
+    paletted_container::{BiomePalettedContainer, BlockPalettedContainer},
+};
+
+/// Copy of a chunk section, taken at the end of the tick that the chunk was saved in
+#[derive(Debug, Clone)]
+pub struct ChunkSectionSnapshot {
+    pub non_air_blocks: u16,
+    pub block_palette: BlockPalettedContainer,
+    pub biome_palette: BiomePalettedContainer,
+}
+
+/// Copy of a chunk that changed since the last autosave, see `World::enable_autosave`
+#[derive(Debug, Clone)]
+pub struct ChunkSnapshot {
+    pub chunk_x: usize,
+    pub chunk_z: usize,
+    /// Sections from bottom to top, see `Chunk::sections_with_y`
+    pub sections: Vec<ChunkSectionSnapshot>,
+}
+
+impl ChunkSnapshot {
+    pub(crate) fn new(chunk_x: usize, chunk_z: usize, chunk: &Chunk) -> Self {
+        let sections = chunk
+            .get_block_sections()
+            .iter()
+            .map(|section| ChunkSectionSnapshot {
+                non_air_blocks: section.get_non_air_count(),
+                block_palette: section.get_block_palette().clone(),
+                biome_palette: section.get_biome_palette().clone(),
+            })
+            .collect();
+
+        Self {
+            chunk_x,
+            chunk_z,
+            sections,
+        }
+    }
+}
+
+pub(crate) struct Autosave {
+    interval: u64,
+    ticks_until_save: u64,
+    sender: Option<Sender<Vec<ChunkSnapshot>>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Autosave {
+    pub(crate) fn new<F>(interval: u64, mut save: F) -> Self
+    where
+        F: FnMut(Vec<ChunkSnapshot>) + Send + 'static,
+    {
+        let (sender, receiver) = mpsc::channel::<Vec<ChunkSnapshot>>();
+        let thread = std::thread::spawn(move || {
+            // Stops once the sender is dropped and every pending save has been written
+            while let Ok(snapshots) = receiver.recv() {
+                save(snapshots);
+            }
+        });
+
+        Self {
+            interval: interval.max(1),
+            ticks_until_save: interval.max(1),
+            sender: Some(sender),
+            thread: Some(thread),
+        }
+    }
+
+    /// Returns true if the chunks should be saved this tick
+    pub(crate) fn tick(&mut self) -> bool {
+        self.ticks_until_save -= 1;
+        if self.ticks_until_save == 0 {
+            self.ticks_until_save = self.interval;
+            true
+        } else {
+            false
+        }
+    }
+
+    pub(crate) fn save(&mut self, snapshots: Vec<ChunkSnapshot>) {
+        if let Some(sender) = &self.sender {
+            // The thread only stops if `save` panicked, there is nothing left to save to
+            let _ = sender.send(snapshots);
+        }
+    }
+}
+
+impl Drop for Autosave {
+    fn drop(&mut self) {
+        // Wait for pending saves to finish
+        self.sender = None;
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
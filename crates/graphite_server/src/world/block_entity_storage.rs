@@ -15,13 +15,13 @@ pub(crate) struct BlockEntity {
     x: usize,
     y: usize,
     z: usize,
-    block_entity_type: u8,
+    pub block_entity_type: u8,
     pub nbt: CachedNBT,
 }
 
 impl BlockEntity {
     fn calculate_key(x: usize, y: usize, z: usize) -> usize {
-        x + y * 16 + z * 16 * 16
+        x + z * 16 + y * 16 * 16
     }
 
     fn get_key(&self) -> usize {
@@ -94,6 +94,20 @@ impl BlockEntityStorage {
         }
     }
 
+    /// Returns false if there was no block entity at the position
+    pub fn remove(&mut self, x: usize, y: usize, z: usize) -> bool {
+        let key = BlockEntity::calculate_key(x, y, z);
+
+        match self.inner.binary_search_by_key(&key, BlockEntity::get_key) {
+            Ok(index) => {
+                self.inner.remove(index);
+                self.dirty = true;
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
     fn update(&mut self) {
         debug_assert!(self.dirty);
         self.dirty = false;
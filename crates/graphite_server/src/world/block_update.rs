@@ -1,7 +1,7 @@
-use graphite_mc_constants::{block::{Block, BlockAttributes, NoSuchBlockError, self}, block_parameter::{RailShape, StraightRailShape, self, Half, StairShape}, tags::block::BlockTags};
+use graphite_mc_constants::{block::{Block, BlockAttributes, NoSuchBlockError, self}, block_parameter::{RailShape, StraightRailShape, self, Half, StairShape, WallConnection, WireConnection, Facing}, tags::block::BlockTags};
 
 
-use super::{World, WorldService};
+use super::{World, WorldService, chest};
 
 pub fn update<W: WorldService>(block_state_id: u16, block: &mut Block, x: i32, y: i32, z: i32, world: &mut World<W>) -> bool {
     match block {
@@ -99,7 +99,34 @@ pub fn update<W: WorldService>(block_state_id: u16, block: &mut Block, x: i32, y
             return false;
         }
         
-        Block::RedstoneWire { east: _, north: _, power: _, south: _, west: _ } => todo!(),
+        Block::RedstoneWire { east, north, power: _, south, west } => {
+            let mut new_east = get_wire_connection(x, y, z, block_parameter::Direction::East, world);
+            let mut new_north = get_wire_connection(x, y, z, block_parameter::Direction::North, world);
+            let mut new_south = get_wire_connection(x, y, z, block_parameter::Direction::South, world);
+            let mut new_west = get_wire_connection(x, y, z, block_parameter::Direction::West, world);
+
+            // Dust that only connects to one side is drawn as a line through the block
+            let connected = [new_east, new_north, new_south, new_west].iter()
+                .filter(|connection| **connection != WireConnection::None).count();
+            if connected == 1 {
+                if new_east != WireConnection::None {
+                    new_west = WireConnection::Side;
+                } else if new_west != WireConnection::None {
+                    new_east = WireConnection::Side;
+                } else if new_north != WireConnection::None {
+                    new_south = WireConnection::Side;
+                } else {
+                    new_north = WireConnection::Side;
+                }
+            }
+
+            let changed = *east != new_east || *north != new_north || *south != new_south || *west != new_west;
+            *east = new_east;
+            *north = new_north;
+            *south = new_south;
+            *west = new_west;
+            changed
+        }
         
         Block::OakFence { east, north, south, waterlogged: _, west } |
         Block::NetherBrickFence { east, north, south, waterlogged: _, west } |
@@ -256,40 +283,61 @@ pub fn update<W: WorldService>(block_state_id: u16, block: &mut Block, x: i32, y
             return changed;
         }
         
-        Block::CobblestoneWall { east: _, north: _, south: _, up: _, waterlogged: _, west: _ } => todo!(),
-        Block::MossyCobblestoneWall { east: _, north: _, south: _, up: _, waterlogged: _, west: _ } => todo!(),
-        Block::BrickWall { east: _, north: _, south: _, up: _, waterlogged: _, west: _ } => todo!(),
-        Block::PrismarineWall { east: _, north: _, south: _, up: _, waterlogged: _, west: _ } => todo!(),
-        Block::RedSandstoneWall { east: _, north: _, south: _, up: _, waterlogged: _, west: _ } => todo!(),
-        Block::MossyStoneBrickWall { east: _, north: _, south: _, up: _, waterlogged: _, west: _ } => todo!(),
-        Block::GraniteWall { east: _, north: _, south: _, up: _, waterlogged: _, west: _ } => todo!(),
-        Block::StoneBrickWall { east: _, north: _, south: _, up: _, waterlogged: _, west: _ } => todo!(),
-        Block::MudBrickWall { east: _, north: _, south: _, up: _, waterlogged: _, west: _ } => todo!(),
-        Block::NetherBrickWall { east: _, north: _, south: _, up: _, waterlogged: _, west: _ } => todo!(),
-        Block::AndesiteWall { east: _, north: _, south: _, up: _, waterlogged: _, west: _ } => todo!(),
-        Block::RedNetherBrickWall { east: _, north: _, south: _, up: _, waterlogged: _, west: _ } => todo!(),
-        Block::SandstoneWall { east: _, north: _, south: _, up: _, waterlogged: _, west: _ } => todo!(),
-        Block::EndStoneBrickWall { east: _, north: _, south: _, up: _, waterlogged: _, west: _ } => todo!(),
-        Block::DioriteWall { east: _, north: _, south: _, up: _, waterlogged: _, west: _ } => todo!(),
-        Block::BlackstoneWall { east: _, north: _, south: _, up: _, waterlogged: _, west: _ } => todo!(),
-        Block::PolishedBlackstoneBrickWall { east: _, north: _, south: _, up: _, waterlogged: _, west: _ } => todo!(),
-        Block::PolishedBlackstoneWall { east: _, north: _, south: _, up: _, waterlogged: _, west: _ } => todo!(),
-        Block::CobbledDeepslateWall { east: _, north: _, south: _, up: _, waterlogged: _, west: _ } => todo!(),
-        Block::PolishedDeepslateWall { east: _, north: _, south: _, up: _, waterlogged: _, west: _ } => todo!(),
-        Block::DeepslateTileWall { east: _, north: _, south: _, up: _, waterlogged: _, west: _ } => todo!(),
-        Block::DeepslateBrickWall { east: _, north: _, south: _, up: _, waterlogged: _, west: _ } => todo!(),
-
-        Block::Tripwire { attached: _, disarmed: _, east: _, north: _, powered: _, south: _, west: _ } => todo!(),
-        
-        Block::PointedDripstone { thickness: _, vertical_direction: _, waterlogged: _ } => todo!(),
+        Block::CobblestoneWall { east, north, south, up, waterlogged: _, west } |
+        Block::MossyCobblestoneWall { east, north, south, up, waterlogged: _, west } |
+        Block::BrickWall { east, north, south, up, waterlogged: _, west } |
+        Block::PrismarineWall { east, north, south, up, waterlogged: _, west } |
+        Block::RedSandstoneWall { east, north, south, up, waterlogged: _, west } |
+        Block::MossyStoneBrickWall { east, north, south, up, waterlogged: _, west } |
+        Block::GraniteWall { east, north, south, up, waterlogged: _, west } |
+        Block::StoneBrickWall { east, north, south, up, waterlogged: _, west } |
+        Block::MudBrickWall { east, north, south, up, waterlogged: _, west } |
+        Block::NetherBrickWall { east, north, south, up, waterlogged: _, west } |
+        Block::AndesiteWall { east, north, south, up, waterlogged: _, west } |
+        Block::RedNetherBrickWall { east, north, south, up, waterlogged: _, west } |
+        Block::SandstoneWall { east, north, south, up, waterlogged: _, west } |
+        Block::EndStoneBrickWall { east, north, south, up, waterlogged: _, west } |
+        Block::DioriteWall { east, north, south, up, waterlogged: _, west } |
+        Block::BlackstoneWall { east, north, south, up, waterlogged: _, west } |
+        Block::PolishedBlackstoneBrickWall { east, north, south, up, waterlogged: _, west } |
+        Block::PolishedBlackstoneWall { east, north, south, up, waterlogged: _, west } |
+        Block::CobbledDeepslateWall { east, north, south, up, waterlogged: _, west } |
+        Block::PolishedDeepslateWall { east, north, south, up, waterlogged: _, west } |
+        Block::DeepslateTileWall { east, north, south, up, waterlogged: _, west } |
+        Block::DeepslateBrickWall { east, north, south, up, waterlogged: _, west } => {
+            let new_east = get_wall_connection(x, y, z, block_parameter::Direction::East, world);
+            let new_north = get_wall_connection(x, y, z, block_parameter::Direction::North, world);
+            let new_south = get_wall_connection(x, y, z, block_parameter::Direction::South, world);
+            let new_west = get_wall_connection(x, y, z, block_parameter::Direction::West, world);
+
+            // Walls running straight through the block don't have a post
+            let has_east = new_east != WallConnection::None;
+            let has_north = new_north != WallConnection::None;
+            let has_south = new_south != WallConnection::None;
+            let has_west = new_west != WallConnection::None;
+            let new_up = !((has_north && has_south && !has_east && !has_west) ||
+                (has_east && has_west && !has_north && !has_south));
+
+            let changed = *east != new_east || *north != new_north || *south != new_south ||
+                *west != new_west || *up != new_up;
+            *east = new_east;
+            *north = new_north;
+            *south = new_south;
+            *west = new_west;
+            *up = new_up;
+            changed
+        }
 
-        Block::CaveVines { age: _, berries: _ } => todo!(),
-        Block::CaveVinesPlant { berries: _ } => todo!(),
-        
-        Block::BigDripleaf { facing: _, tilt: _, waterlogged: _ } => todo!(),
-        Block::BigDripleafStem { facing: _, waterlogged: _ } => todo!(),
+        // todo: these blocks keep their current state until their updates are implemented
+        Block::Tripwire { .. } |
+        Block::PointedDripstone { .. } |
+        Block::CaveVines { .. } |
+        Block::CaveVinesPlant { .. } |
+        Block::BigDripleaf { .. } |
+        Block::BigDripleafStem { .. } |
+        Block::Scaffolding { .. } => false,
 
-        Block::Scaffolding { bottom: _, distance: _, waterlogged: _ } => todo!(),
+        Block::Chest { .. } | Block::TrappedChest { .. } => chest::update(block, x, y, z, world),
 
         _ => return false,
 
@@ -327,8 +375,6 @@ pub fn update<W: WorldService>(block_state_id: u16, block: &mut Block, x: i32, y
         // Block::Peony { half } => todo!(),
         // Block::TallGrass { half } => todo!(),
         // Block::LargeFern { half } => todo!(),
-        // Block::Chest { facing, block_type, waterlogged } => todo!(),
-        // Block::TrappedChest { facing, block_type, waterlogged } => todo!(),
         // Block::OakDoor { facing, half, hinge, open, powered } => todo!(),
         // Block::IronDoor { facing, half, hinge, open, powered } => todo!(),
         // Block::SpruceDoor { facing, half, hinge, open, powered } => todo!(),
@@ -591,29 +637,7 @@ pub(crate) fn should_iron_bars_connect<W: WorldService>(x: i32, y: i32, z: i32,
         }
         
         if let Ok(block) = <&Block>::try_from(id) {
-            match block {
-                Block::IronBars { east: _, north: _, south: _, waterlogged: _, west: _ } |
-                Block::GlassPane { east: _, north: _, south: _, waterlogged: _, west: _ } |
-                Block::WhiteStainedGlassPane { east: _, north: _, south: _, waterlogged: _, west: _} |
-                Block::OrangeStainedGlassPane { east: _, north: _, south: _, waterlogged: _, west: _} |
-                Block::MagentaStainedGlassPane { east: _, north: _, south: _, waterlogged: _, west: _} |
-                Block::LightBlueStainedGlassPane { east: _, north: _, south: _, waterlogged: _, west: _} |
-                Block::YellowStainedGlassPane { east: _, north: _, south: _, waterlogged: _, west: _} |
-                Block::LimeStainedGlassPane { east: _, north: _, south: _, waterlogged: _, west: _} |
-                Block::PinkStainedGlassPane { east: _, north: _, south: _, waterlogged: _, west: _} |
-                Block::GrayStainedGlassPane { east: _, north: _, south: _, waterlogged: _, west: _} |
-                Block::LightGrayStainedGlassPane { east: _, north: _, south: _, waterlogged: _, west: _} |
-                Block::CyanStainedGlassPane { east: _, north: _, south: _, waterlogged: _, west: _} |
-                Block::PurpleStainedGlassPane { east: _, north: _, south: _, waterlogged: _, west: _} |
-                Block::BlueStainedGlassPane { east: _, north: _, south: _, waterlogged: _, west: _} |
-                Block::BrownStainedGlassPane { east: _, north: _, south: _, waterlogged: _, west: _} |
-                Block::GreenStainedGlassPane { east: _, north: _, south: _, waterlogged: _, west: _} |
-                Block::RedStainedGlassPane { east: _, north: _, south: _, waterlogged: _, west: _} |
-                Block::BlackStainedGlassPane { east: _, north: _, south: _, waterlogged: _, west: _} => {
-                    return true
-                }
-                _ => return false
-            }
+            return is_iron_bars_or_pane(block);
         }
     }
     false
@@ -728,4 +752,139 @@ pub(crate) fn get_straight_rail_shape<W: WorldService>(x: i32, y: i32, z: i32, w
             return None;
         }
     }
-}
\ No newline at end of file
+}
+fn is_iron_bars_or_pane(block: &Block) -> bool {
+    matches!(block,
+        Block::IronBars { east: _, north: _, south: _, waterlogged: _, west: _ } |
+        Block::GlassPane { east: _, north: _, south: _, waterlogged: _, west: _ } |
+        Block::WhiteStainedGlassPane { east: _, north: _, south: _, waterlogged: _, west: _} |
+        Block::OrangeStainedGlassPane { east: _, north: _, south: _, waterlogged: _, west: _} |
+        Block::MagentaStainedGlassPane { east: _, north: _, south: _, waterlogged: _, west: _} |
+        Block::LightBlueStainedGlassPane { east: _, north: _, south: _, waterlogged: _, west: _} |
+        Block::YellowStainedGlassPane { east: _, north: _, south: _, waterlogged: _, west: _} |
+        Block::LimeStainedGlassPane { east: _, north: _, south: _, waterlogged: _, west: _} |
+        Block::PinkStainedGlassPane { east: _, north: _, south: _, waterlogged: _, west: _} |
+        Block::GrayStainedGlassPane { east: _, north: _, south: _, waterlogged: _, west: _} |
+        Block::LightGrayStainedGlassPane { east: _, north: _, south: _, waterlogged: _, west: _} |
+        Block::CyanStainedGlassPane { east: _, north: _, south: _, waterlogged: _, west: _} |
+        Block::PurpleStainedGlassPane { east: _, north: _, south: _, waterlogged: _, west: _} |
+        Block::BlueStainedGlassPane { east: _, north: _, south: _, waterlogged: _, west: _} |
+        Block::BrownStainedGlassPane { east: _, north: _, south: _, waterlogged: _, west: _} |
+        Block::GreenStainedGlassPane { east: _, north: _, south: _, waterlogged: _, west: _} |
+        Block::RedStainedGlassPane { east: _, north: _, south: _, waterlogged: _, west: _} |
+        Block::BlackStainedGlassPane { east: _, north: _, south: _, waterlogged: _, west: _}
+    )
+}
+
+fn get_offset(x: i32, z: i32, direction: block_parameter::Direction) -> (i32, i32) {
+    match direction {
+        block_parameter::Direction::North => (x, z - 1),
+        block_parameter::Direction::South => (x, z + 1),
+        block_parameter::Direction::West => (x - 1, z),
+        block_parameter::Direction::East => (x + 1, z),
+    }
+}
+
+fn is_full_block<W: WorldService>(x: i32, y: i32, z: i32, world: &World<W>) -> bool {
+    let properties = world.get_block_i32(x, y, z).and_then(|id| <&BlockAttributes>::try_from(id).ok());
+    if let Some(properties) = properties {
+        properties.is_north_face_sturdy && properties.is_east_face_sturdy && properties.is_south_face_sturdy &&
+            properties.is_west_face_sturdy && properties.is_up_face_sturdy
+    } else {
+        false
+    }
+}
+
+pub(crate) fn get_wall_connection<W: WorldService>(x: i32, y: i32, z: i32, direction: block_parameter::Direction, world: &World<W>) -> WallConnection {
+    let (other_x, other_z) = get_offset(x, z, direction);
+    let Some(id) = world.get_block_i32(other_x, y, other_z) else {
+        return WallConnection::None;
+    };
+
+    let mut connects = BlockTags::Walls.contains(id) || BlockTags::FenceGates.contains(id);
+    if !connects {
+        if let Ok(properties) = <&BlockAttributes>::try_from(id) {
+            connects = match direction {
+                block_parameter::Direction::North => properties.is_south_face_sturdy,
+                block_parameter::Direction::South => properties.is_north_face_sturdy,
+                block_parameter::Direction::West => properties.is_east_face_sturdy,
+                block_parameter::Direction::East => properties.is_west_face_sturdy,
+            };
+        }
+    }
+    if !connects {
+        connects = <&Block>::try_from(id).map(is_iron_bars_or_pane).unwrap_or(false);
+    }
+
+    if !connects {
+        WallConnection::None
+    } else if is_full_block(x, y + 1, z, world) {
+        // The side is raised to meet the block above
+        WallConnection::Tall
+    } else {
+        WallConnection::Low
+    }
+}
+
+fn is_redstone_wire<W: WorldService>(x: i32, y: i32, z: i32, world: &World<W>) -> bool {
+    let block = world.get_block_i32(x, y, z).and_then(|id| <&Block>::try_from(id).ok());
+    matches!(block, Some(Block::RedstoneWire { .. }))
+}
+
+// Whether redstone dust next to the block in `direction` points towards it
+fn connects_to_redstone(id: u16, direction: block_parameter::Direction) -> bool {
+    if BlockTags::Buttons.contains(id) || BlockTags::PressurePlates.contains(id) {
+        return true;
+    }
+
+    let Ok(block) = <&Block>::try_from(id) else {
+        return false;
+    };
+    match block {
+        Block::RedstoneWire { .. } |
+        Block::RedstoneTorch { .. } |
+        Block::RedstoneWallTorch { .. } |
+        Block::RedstoneBlock |
+        Block::Lever { .. } |
+        Block::Comparator { .. } |
+        Block::DaylightDetector { .. } |
+        Block::Target { .. } => true,
+        // Repeaters only connect to dust at their input or output
+        Block::Repeater { facing, .. } => {
+            let is_north_south = |direction| matches!(direction,
+                block_parameter::Direction::North | block_parameter::Direction::South);
+            is_north_south(*facing) == is_north_south(direction)
+        }
+        // Observers only connect to dust at their output
+        Block::Observer { facing, .. } => matches!((facing, direction),
+            (Facing::North, block_parameter::Direction::North) |
+            (Facing::South, block_parameter::Direction::South) |
+            (Facing::West, block_parameter::Direction::West) |
+            (Facing::East, block_parameter::Direction::East)),
+        _ => false,
+    }
+}
+
+pub(crate) fn get_wire_connection<W: WorldService>(x: i32, y: i32, z: i32, direction: block_parameter::Direction, world: &World<W>) -> WireConnection {
+    let (other_x, other_z) = get_offset(x, z, direction);
+    let Some(id) = world.get_block_i32(other_x, y, other_z) else {
+        return WireConnection::None;
+    };
+    let other_is_full = is_full_block(other_x, y, other_z, world);
+
+    // Dust climbs up the side of a block, unless a block above the dust cuts it off
+    if other_is_full && !is_full_block(x, y + 1, z, world) && is_redstone_wire(other_x, y + 1, other_z, world) {
+        return WireConnection::Up;
+    }
+
+    if connects_to_redstone(id, direction) {
+        return WireConnection::Side;
+    }
+
+    // Dust also connects to dust one block lower, unless a block is in the way
+    if !other_is_full && is_redstone_wire(other_x, y - 1, other_z, world) {
+        return WireConnection::Side;
+    }
+
+    WireConnection::None
+}
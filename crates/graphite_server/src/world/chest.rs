@@ -0,0 +1,286 @@
+use graphite_binary::nbt::{CachedNBT, NBTNode, NBT, TAG_COMPOUND_ID};
+use graphite_mc_constants::{
+    block::Block,
+    block_parameter::{ChestType, Direction},
+    item::Item,
+};
+
+use crate::inventory::{inventory_handler::ItemSlot, itemstack::ItemStack};
+
+use super::{World, WorldService};
+
+/// Number of slots in a single chest, a double chest has twice as many
+pub const CHEST_SLOTS: usize = 27;
+
+/// Block entity type ids of chests and trapped chests
+pub(crate) const CHEST_BLOCK_ENTITY_TYPE: u8 = 1;
+pub(crate) const TRAPPED_CHEST_BLOCK_ENTITY_TYPE: u8 = 2;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct ChestProperties {
+    trapped: bool,
+    facing: Direction,
+    chest_type: ChestType,
+}
+
+fn get_chest_properties(block: &Block) -> Option<ChestProperties> {
+    match block {
+        Block::Chest { facing, block_type, waterlogged: _ } => Some(ChestProperties {
+            trapped: false,
+            facing: *facing,
+            chest_type: *block_type,
+        }),
+        Block::TrappedChest { facing, block_type, waterlogged: _ } => Some(ChestProperties {
+            trapped: true,
+            facing: *facing,
+            chest_type: *block_type,
+        }),
+        _ => None,
+    }
+}
+
+pub fn is_chest(block: &Block) -> bool {
+    get_chest_properties(block).is_some()
+}
+
+pub(crate) fn get_block_entity_type(block: &Block) -> Option<u8> {
+    let properties = get_chest_properties(block)?;
+    if properties.trapped {
+        Some(TRAPPED_CHEST_BLOCK_ENTITY_TYPE)
+    } else {
+        Some(CHEST_BLOCK_ENTITY_TYPE)
+    }
+}
+
+fn clockwise(direction: Direction) -> Direction {
+    match direction {
+        Direction::North => Direction::East,
+        Direction::East => Direction::South,
+        Direction::South => Direction::West,
+        Direction::West => Direction::North,
+    }
+}
+
+fn counter_clockwise(direction: Direction) -> Direction {
+    match direction {
+        Direction::North => Direction::West,
+        Direction::West => Direction::South,
+        Direction::South => Direction::East,
+        Direction::East => Direction::North,
+    }
+}
+
+fn opposite(chest_type: ChestType) -> ChestType {
+    match chest_type {
+        ChestType::Single => ChestType::Single,
+        ChestType::Left => ChestType::Right,
+        ChestType::Right => ChestType::Left,
+    }
+}
+
+/// Direction of the other half of a double chest, or None for a single chest
+///
+/// The left half of a chest connects clockwise from the direction the chest is facing,
+/// the right half connects counter-clockwise
+fn get_connected_direction(facing: Direction, chest_type: ChestType) -> Option<Direction> {
+    match chest_type {
+        ChestType::Single => None,
+        ChestType::Left => Some(clockwise(facing)),
+        ChestType::Right => Some(counter_clockwise(facing)),
+    }
+}
+
+fn offset(x: i32, z: i32, direction: Direction) -> (i32, i32) {
+    match direction {
+        Direction::North => (x, z - 1),
+        Direction::South => (x, z + 1),
+        Direction::West => (x - 1, z),
+        Direction::East => (x + 1, z),
+    }
+}
+
+fn get_chest_properties_at<W: WorldService>(x: i32, y: i32, z: i32, world: &World<W>) -> Option<ChestProperties> {
+    let block = world.get_block_i32(x, y, z).and_then(|id| <&Block>::try_from(id).ok())?;
+    get_chest_properties(block)
+}
+
+/// Returns the chest type for a newly placed chest
+///
+/// The chest joins a single chest of the same kind and facing to either side of it,
+/// preferring the side that would make the new chest the left half
+pub(crate) fn get_placement_chest_type<W: WorldService>(x: i32, y: i32, z: i32, trapped: bool,
+        facing: Direction, world: &World<W>) -> ChestType {
+    for (direction, chest_type) in [(clockwise(facing), ChestType::Left), (counter_clockwise(facing), ChestType::Right)] {
+        let (neighbor_x, neighbor_z) = offset(x, z, direction);
+        let neighbor = get_chest_properties_at(neighbor_x, y, neighbor_z, world);
+
+        if neighbor == Some(ChestProperties { trapped, facing, chest_type: ChestType::Single }) {
+            return chest_type;
+        }
+    }
+    ChestType::Single
+}
+
+/// Returns the chest type that the chest at the position should have after a neighbor changed
+///
+/// A half whose partner is gone becomes a single chest again, and a single chest
+/// joins a neighboring half that connects to it
+fn get_updated_chest_type<W: WorldService>(x: i32, y: i32, z: i32, trapped: bool,
+        facing: Direction, chest_type: ChestType, world: &World<W>) -> ChestType {
+    if let Some(direction) = get_connected_direction(facing, chest_type) {
+        let (partner_x, partner_z) = offset(x, z, direction);
+        let partner = get_chest_properties_at(partner_x, y, partner_z, world);
+
+        if partner == Some(ChestProperties { trapped, facing, chest_type: opposite(chest_type) }) {
+            chest_type
+        } else {
+            ChestType::Single
+        }
+    } else {
+        for direction in [clockwise(facing), counter_clockwise(facing)] {
+            let (neighbor_x, neighbor_z) = offset(x, z, direction);
+            let Some(neighbor) = get_chest_properties_at(neighbor_x, y, neighbor_z, world) else {
+                continue;
+            };
+
+            let connects_back = get_connected_direction(neighbor.facing, neighbor.chest_type)
+                .is_some_and(|neighbor_direction| offset(neighbor_x, neighbor_z, neighbor_direction) == (x, z));
+            if neighbor.trapped == trapped && neighbor.facing == facing && connects_back {
+                return opposite(neighbor.chest_type);
+            }
+        }
+        ChestType::Single
+    }
+}
+
+/// Merges or splits the chest at the position after a neighbor changed, returns true if the block changed
+pub(crate) fn update<W: WorldService>(block: &mut Block, x: i32, y: i32, z: i32, world: &World<W>) -> bool {
+    let Some(properties) = get_chest_properties(block) else {
+        return false;
+    };
+
+    let new_type = get_updated_chest_type(x, y, z, properties.trapped, properties.facing, properties.chest_type, world);
+    match block {
+        Block::Chest { facing: _, block_type, waterlogged: _ } |
+        Block::TrappedChest { facing: _, block_type, waterlogged: _ } if *block_type != new_type => {
+            *block_type = new_type;
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Returns the positions of the chest halves that make up the container at the position, in slot order
+///
+/// A double chest lists the right half first, it holds the first `CHEST_SLOTS` slots of the container
+///
+/// A half without a matching partner, eg. one that was placed using `/setblock`, is treated as a single chest
+pub(crate) fn get_chest_halves<W: WorldService>(x: i32, y: i32, z: i32, world: &World<W>) -> Option<Vec<(i32, i32, i32)>> {
+    let properties = get_chest_properties_at(x, y, z, world)?;
+    let Some(direction) = get_connected_direction(properties.facing, properties.chest_type) else {
+        return Some(vec![(x, y, z)]);
+    };
+
+    let (partner_x, partner_z) = offset(x, z, direction);
+    let partner = get_chest_properties_at(partner_x, y, partner_z, world);
+    if partner != Some(ChestProperties { chest_type: opposite(properties.chest_type), ..properties }) {
+        return Some(vec![(x, y, z)]);
+    }
+
+    match properties.chest_type {
+        ChestType::Left => Some(vec![(partner_x, y, partner_z), (x, y, z)]),
+        _ => Some(vec![(x, y, z), (partner_x, y, partner_z)]),
+    }
+}
+
+/// Writes the items of a chest half into the `Items` list of its block entity
+///
+/// Items are stored using their protocol id, empty slots are left out
+pub(crate) fn write_items(items: &[ItemSlot]) -> CachedNBT {
+    let mut nbt = NBT::new();
+    let mut list = NBTNode::List { type_id: TAG_COMPOUND_ID, children: Vec::new() };
+    for (slot, itemslot) in items.iter().enumerate() {
+        let ItemSlot::Filled(itemstack) = itemslot else {
+            continue;
+        };
+
+        let mut item = NBTNode::Compound(Default::default());
+        nbt.insert(&mut item, "Slot", NBTNode::Byte(slot as _));
+        nbt.insert(&mut item, "id", NBTNode::Int(itemstack.item as _));
+        nbt.insert(&mut item, "Count", NBTNode::Byte(itemstack.count));
+
+        if itemstack.nbt.iter_root().next().is_some() {
+            let mut tag = NBTNode::Compound(Default::default());
+            for (key, value) in itemstack.nbt.iter_root() {
+                let value = copy_node(&itemstack.nbt, value, &mut nbt);
+                nbt.insert(&mut tag, key, value);
+            }
+            nbt.insert(&mut item, "tag", tag);
+        }
+
+        nbt.append(&mut list, item);
+    }
+    nbt.insert_root("Items", list);
+    nbt.into()
+}
+
+/// Reads the items of a chest half from its block entity, returning `CHEST_SLOTS` slots
+///
+/// Invalid items are skipped, leaving their slot empty
+pub(crate) fn read_items(nbt: &NBT) -> Vec<ItemSlot> {
+    let mut items = vec![ItemSlot::Empty; CHEST_SLOTS];
+    let Some(list) = nbt.find_root("Items") else {
+        return items;
+    };
+
+    for item in nbt.iter(list).into_iter().flatten() {
+        let slot = nbt.find(item, "Slot").and_then(NBTNode::as_byte);
+        let id = nbt.find(item, "id").and_then(NBTNode::as_int);
+        let count = nbt.find(item, "Count").and_then(NBTNode::as_byte);
+        let (Some(slot), Some(id), Some(count)) = (slot, id, count) else {
+            continue;
+        };
+        if !(0..CHEST_SLOTS as i8).contains(&slot) {
+            continue;
+        }
+        let Ok(item_type) = Item::try_from(id as u16) else {
+            continue;
+        };
+        let Ok(mut itemstack) = ItemStack::of(item_type, count) else {
+            continue;
+        };
+
+        if let Some(tag) = nbt.find(item, "tag") {
+            for (key, value) in nbt.iter_compound(tag).into_iter().flatten() {
+                let value = copy_node(nbt, value, &mut itemstack.nbt);
+                itemstack.nbt.insert_root(key, value);
+            }
+        }
+
+        items[slot as usize] = ItemSlot::Filled(itemstack);
+    }
+    items
+}
+
+// Copies the node and its children from one nbt into another, the returned node must be inserted into `to`
+fn copy_node(from: &NBT, node: &NBTNode, to: &mut NBT) -> NBTNode {
+    match node {
+        NBTNode::Compound(_) => {
+            let mut compound = NBTNode::Compound(Default::default());
+            for (key, child) in from.iter_compound(node).into_iter().flatten() {
+                let child = copy_node(from, child, to);
+                to.insert(&mut compound, key, child);
+            }
+            compound
+        }
+        NBTNode::List { type_id, children: _ } => {
+            let mut list = NBTNode::List { type_id: type_id.clone(), children: Vec::new() };
+            for child in from.iter(node).into_iter().flatten() {
+                let child = copy_node(from, child, to);
+                to.append(&mut list, child);
+            }
+            list
+        }
+        _ => node.clone(),
+    }
+}
@@ -1,6 +1,7 @@
-use std::borrow::Cow;
+use std::{borrow::Cow, rc::Rc};
 
 use bevy_ecs::entity::Entity;
+use graphite_mc_constants::block::Block;
 use graphite_binary::{
     nbt::{CachedNBT},
     slice_serialization::{slice_serializable, BigEndian, GreedyBlob},
@@ -8,7 +9,9 @@ use graphite_binary::{
 
 use graphite_net::{network_buffer::WriteBuffer, packet_helper};
 use graphite_mc_protocol::{
-    play::server::{self, BlockUpdate, ChunkBlockData, ChunkLightData},
+    play::server::{
+        self, BlockEntityData, BlockUpdate, ChunkBlockData, ChunkLightData, SectionBlocksUpdate,
+    },
     types::BlockPosition,
 };
 use slab::Slab;
@@ -16,8 +19,9 @@ use slab::Slab;
 use crate::player::{Player, PlayerService};
 
 use super::{
+    chunk_cache::{ChunkPayload, SharedChunkPayloadCache},
     chunk_section::ChunkSection,
-    paletted_container::{BiomePalettedContainer, BlockPalettedContainer}, block_entity_storage::BlockEntityStorage,
+    paletted_container::{BiomePalettedContainer, BlockPalettedContainer}, block_entity_storage::{BlockEntity, BlockEntityStorage},
 };
 pub(crate) struct PlayerReference {
     uuid: u128,
@@ -31,14 +35,16 @@ pub struct Chunk {
     block_sections: Vec<ChunkSection>,
     pub(crate) block_entities: BlockEntityStorage,
 
-    valid_cache: bool,
-    cached_block_data: WriteBuffer,
-    cached_light_data: WriteBuffer,
+    cached_payload: Option<Rc<ChunkPayload>>,
+    pub(crate) payload_cache: Option<SharedChunkPayloadCache>,
 
     pub(crate) block_viewable_buffer: WriteBuffer,
     pub(crate) entity_viewable_buffer: WriteBuffer,
     pub(crate) entities: Slab<Entity>,
     player_refs: Slab<PlayerReference>,
+
+    // True if the blocks changed since the last autosave
+    dirty: bool,
 }
 
 impl Clone for Chunk {
@@ -46,13 +52,13 @@ impl Clone for Chunk {
         Self {
             block_sections: self.block_sections.clone(),
             block_entities: self.block_entities.clone(),
-            valid_cache: false,
-            cached_block_data: WriteBuffer::with_min_capacity(0),
-            cached_light_data: WriteBuffer::with_min_capacity(0),
+            cached_payload: None,
+            payload_cache: self.payload_cache.clone(),
             block_viewable_buffer: WriteBuffer::with_min_capacity(0),
             entity_viewable_buffer: WriteBuffer::with_min_capacity(0),
             entities: Slab::new(),
             player_refs: Slab::new(),
+            dirty: self.dirty,
         }
     }
 }
@@ -61,6 +67,9 @@ impl Chunk {
     pub const SECTION_BLOCK_WIDTH_F: f32 = 16.0;
     pub const SECTION_BLOCK_WIDTH_I: usize = 16;
 
+    /// Y coordinate of the bottom of the lowest section, this is the `min_y` of the dimension sent to players
+    pub const MIN_Y: i32 = 0;
+
     const INVALID_NO_ENTRY: &'static str = "player's chunk_ref is invalid - no entry for reference";
     const INVALID_OTHER_PLAYER: &'static str =
         "player's chunk_ref is invalid - entry was for another player";
@@ -74,12 +83,73 @@ impl Chunk {
         self.block_sections.as_slice()
     }
 
+    /// Iterates over the block sections from bottom to top, in the same order as `get_block_sections`,
+    /// along with the world y coordinate of the bottom of each section
+    pub fn sections_with_y(&self) -> impl Iterator<Item = (i32, &ChunkSection)> {
+        self.block_sections.iter().enumerate().map(|(index, section)| {
+            let section_bottom_y = Self::MIN_Y + (index * Self::SECTION_BLOCK_WIDTH_I) as i32;
+            (section_bottom_y, section)
+        })
+    }
+
+    pub(crate) fn get_block_entity(&self, x: usize, y: usize, z: usize) -> Option<&BlockEntity> {
+        self.block_entities.get(x % Self::SECTION_BLOCK_WIDTH_I, y, z % Self::SECTION_BLOCK_WIDTH_I)
+    }
+
+    /// Replaces the block entity at the position, sending it to players that can see the chunk
+    pub(crate) fn set_block_entity(&mut self, x: usize, y: usize, z: usize, block_entity_type: u8, nbt: CachedNBT) {
+        self.mark_changed();
+
+        let block_entity = self.block_entities.get_or_create_mut(
+            x % Self::SECTION_BLOCK_WIDTH_I,
+            y,
+            z % Self::SECTION_BLOCK_WIDTH_I,
+            block_entity_type,
+        );
+        block_entity.nbt = nbt;
+
+        packet_helper::try_write_packet(
+            &mut self.block_viewable_buffer,
+            &BlockEntityData {
+                pos: BlockPosition {
+                    x: x as _,
+                    y: y as _,
+                    z: z as _,
+                },
+                block_entity_type: block_entity_type as _,
+                nbt: Cow::Borrowed(&block_entity.nbt),
+            },
+        );
+    }
+
+    /// Replaces the block entity at the position without sending it to players, for block entities
+    /// whose data the client doesn't need, eg. the items in a chest
+    pub(crate) fn set_block_entity_silently(&mut self, x: usize, y: usize, z: usize, block_entity_type: u8, nbt: CachedNBT) {
+        self.mark_changed();
+
+        let block_entity = self.block_entities.get_or_create_mut(
+            x % Self::SECTION_BLOCK_WIDTH_I,
+            y,
+            z % Self::SECTION_BLOCK_WIDTH_I,
+            block_entity_type,
+        );
+        block_entity.nbt = nbt;
+    }
+
+    /// Removes the block entity at the position without sending it to players, the client removes
+    /// block entities by itself when their block is replaced
+    pub(crate) fn remove_block_entity(&mut self, x: usize, y: usize, z: usize) {
+        if self.block_entities.remove(x % Self::SECTION_BLOCK_WIDTH_I, y, z % Self::SECTION_BLOCK_WIDTH_I) {
+            self.mark_changed();
+        }
+    }
+
     pub(crate) fn expand(&mut self, increase_y: isize) {
         if increase_y == 0 {
             return;
         }
 
-        self.invalidate_cache();
+        self.mark_changed();
 
         let abs_increase_y = increase_y.abs() as usize;
         self.block_sections.reserve_exact(abs_increase_y);
@@ -221,13 +291,13 @@ impl Chunk {
         Self {
             block_sections,
             block_entities: BlockEntityStorage::new(),
-            valid_cache: false,
-            cached_block_data: WriteBuffer::with_min_capacity(0),
-            cached_light_data: WriteBuffer::with_min_capacity(0),
+            cached_payload: None,
+            payload_cache: None,
             block_viewable_buffer: WriteBuffer::with_min_capacity(0),
             entity_viewable_buffer: WriteBuffer::with_min_capacity(0),
             entities: Slab::new(),
             player_refs: Slab::new(),
+            dirty: false,
         }
     }
 
@@ -275,14 +345,27 @@ impl Chunk {
         Self::new(block_sections)
     }
 
-    fn invalidate_cache(&mut self) {
-        // todo: maybe have more fine-grained invalidation here, not sure if its worth it
-        self.valid_cache = false;
+    /// Returns true if the blocks changed since the last autosave, see `World::enable_autosave`
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
     }
 
-    fn compute_cache(&mut self) {
-        self.valid_cache = true;
+    pub(crate) fn clear_dirty(&mut self) {
+        self.dirty = false;
+    }
 
+    // Called whenever the contents of the chunk change
+    fn mark_changed(&mut self) {
+        self.dirty = true;
+        self.invalidate_cache();
+    }
+
+    pub(crate) fn invalidate_cache(&mut self) {
+        // todo: maybe have more fine-grained invalidation here, not sure if its worth it
+        self.cached_payload = None;
+    }
+
+    fn compute_cache(&mut self) -> Rc<ChunkPayload> {
         // Write chunk data
         let mut chunk_data = WriteBuffer::new();
         for block_section in &mut self.block_sections {
@@ -305,29 +388,116 @@ impl Chunk {
             block_light_entries: vec![],
         };
 
-        self.cached_block_data.clear();
+        let mut payload = ChunkPayload {
+            block_data: WriteBuffer::new(),
+            light_data: WriteBuffer::new(),
+        };
         graphite_net::packet_helper::write_slice_serializable(
-            &mut self.cached_block_data,
+            &mut payload.block_data,
             &chunk_block_data,
         );
-
-        self.cached_light_data.clear();
         graphite_net::packet_helper::write_slice_serializable(
-            &mut self.cached_light_data,
+            &mut payload.light_data,
             &chunk_light_data,
         );
+
+        // Share the payload with identical chunks if caching is enabled
+        let payload = match &self.payload_cache {
+            Some(cache) => cache.borrow_mut().get_or_insert(payload),
+            None => Rc::new(payload),
+        };
+        self.cached_payload = Some(payload.clone());
+        payload
     }
 
-    pub fn write_into_self(&mut self, chunk_x: i32, chunk_z: i32) -> anyhow::Result<()> {
-        if !self.valid_cache {
-            self.compute_cache();
+    /// Sets the block without sending a `BlockUpdate` to players, the change must
+    /// be sent afterwards using `write_block_changes`
+    pub(crate) fn set_block_silently(&mut self, x: usize, y: usize, z: usize, block: u16) -> Option<u16> {
+        let section_x = x % Self::SECTION_BLOCK_WIDTH_I;
+        let section_y = y % Self::SECTION_BLOCK_WIDTH_I;
+        let section_z = z % Self::SECTION_BLOCK_WIDTH_I;
+
+        let chunk_y = y / Self::SECTION_BLOCK_WIDTH_I;
+        if chunk_y >= self.block_sections.len() {
+            return None; // out of bounds
         }
 
+        let section = &mut self.block_sections[chunk_y];
+        let old = section.set_block(section_x as _, section_y as _, section_z as _, block)?;
+
+        // The client removes the block entity when the block is replaced by a different block,
+        // so do the same. Changing a property, eg. a chest joining a double chest, keeps it
+        let old_block = <&Block>::try_from(old).ok().map(std::mem::discriminant);
+        let new_block = <&Block>::try_from(block).ok().map(std::mem::discriminant);
+        if old_block.is_none() || old_block != new_block {
+            self.block_entities.remove(section_x, y, section_z);
+        }
+
+        self.mark_changed();
+
+        Some(old)
+    }
+
+    /// Sends the current state of the blocks to players that can see the chunk
+    ///
+    /// All positions must be inside the same chunk section. A single block is sent
+    /// using `BlockUpdate`, multiple blocks are batched into a `SectionBlocksUpdate`
+    pub(crate) fn write_block_changes(&mut self, positions: &[BlockPosition]) {
+        let Some(first) = positions.first() else {
+            return;
+        };
+
+        if positions.len() == 1 {
+            let block_state = self.get_block(first.x as _, first.y as _, first.z as _).unwrap_or(0);
+            packet_helper::try_write_packet(
+                &mut self.block_viewable_buffer,
+                &BlockUpdate {
+                    pos: *first,
+                    block_state: block_state as _,
+                },
+            );
+            return;
+        }
+
+        let width = Self::SECTION_BLOCK_WIDTH_I as i32;
+        let mut blocks = Vec::with_capacity(positions.len());
+        for pos in positions {
+            debug_assert_eq!(pos.y / width, first.y / width, "positions must be in the same section");
+
+            let block_state = self.get_block(pos.x as _, pos.y as _, pos.z as _).unwrap_or(0);
+            blocks.push(SectionBlocksUpdate::pack_block(
+                (pos.x % width) as _,
+                (pos.y % width) as _,
+                (pos.z % width) as _,
+                block_state,
+            ));
+        }
+
+        packet_helper::try_write_packet(
+            &mut self.block_viewable_buffer,
+            &SectionBlocksUpdate {
+                section_pos: SectionBlocksUpdate::pack_section_pos(
+                    first.x / width,
+                    first.y / width,
+                    first.z / width,
+                ),
+                suppress_light_updates: false,
+                blocks,
+            },
+        );
+    }
+
+    pub fn write_into_self(&mut self, chunk_x: i32, chunk_z: i32) -> anyhow::Result<()> {
+        let payload = match &self.cached_payload {
+            Some(payload) => payload.clone(),
+            None => self.compute_cache(),
+        };
+
         let composite = DirectLevelChunkWithLight {
             chunk_x,
             chunk_z,
-            chunk_block_data: self.cached_block_data.get_written(),
-            chunk_light_data: self.cached_light_data.get_written(),
+            chunk_block_data: payload.block_data.get_written(),
+            chunk_light_data: payload.light_data.get_written(),
         };
 
         let packet_id = server::PacketId::LevelChunkWithLight as u8;
@@ -344,15 +514,16 @@ impl Chunk {
         chunk_x: i32,
         chunk_z: i32,
     ) -> anyhow::Result<()> {
-        if !self.valid_cache {
-            self.compute_cache();
-        }
+        let payload = match &self.cached_payload {
+            Some(payload) => payload.clone(),
+            None => self.compute_cache(),
+        };
 
         let composite = DirectLevelChunkWithLight {
             chunk_x,
             chunk_z,
-            chunk_block_data: self.cached_block_data.get_written(),
-            chunk_light_data: self.cached_light_data.get_written(),
+            chunk_block_data: payload.block_data.get_written(),
+            chunk_light_data: payload.light_data.get_written(),
         };
 
         let packet_id = server::PacketId::LevelChunkWithLight as u8;
@@ -373,7 +544,7 @@ impl BlockStorage for Chunk {
         }
 
         if self.block_sections[y].fill_blocks(block) {
-            self.invalidate_cache();
+            self.mark_changed();
         }
     }
 
@@ -392,49 +563,21 @@ impl BlockStorage for Chunk {
     }
 
     fn set_block(&mut self, x: usize, y: usize, z: usize, block: u16) -> Option<u16> {
-        let section_x = x % Self::SECTION_BLOCK_WIDTH_I;
-        let section_y = y % Self::SECTION_BLOCK_WIDTH_I;
-        let section_z = z % Self::SECTION_BLOCK_WIDTH_I;
-
-        let chunk_y = y / Self::SECTION_BLOCK_WIDTH_I;
-        if chunk_y >= self.block_sections.len() {
-            return None; // out of bounds
-        }
+        let old = self.set_block_silently(x, y, z, block)?;
 
-        let section = &mut self.block_sections[chunk_y];
-        if let Some(old) = section.set_block(section_x as _, section_y as _, section_z as _, block)
-        {
-            /*let the_block: &Block = block.try_into().unwrap();
-            match the_block {
-                Block::SkeletonSkull { rotation } => {
-                    section.block_entities.get_or_create_mut(section_x as _, section_y as _, section_z as _, 15);
-                }
-                Block::OakSign { rotation, waterlogged } => {
-                    let block_entity = section.block_entities.get_or_create_mut(section_x as _,
-                            section_y as _, section_z as _, 7);
-                    block_entity.nbt.insert_root("Text1", NBTNode::String("{\"text\": \"Hello World!\"}".into()))
-                }
-                _ => ()
-            }*/
-
-            self.invalidate_cache();
-
-            packet_helper::try_write_packet(
-                &mut self.block_viewable_buffer,
-                &BlockUpdate {
-                    pos: BlockPosition {
-                        x: x as _,
-                        y: y as _,
-                        z: z as _,
-                    },
-                    block_state: block as _,
+        packet_helper::try_write_packet(
+            &mut self.block_viewable_buffer,
+            &BlockUpdate {
+                pos: BlockPosition {
+                    x: x as _,
+                    y: y as _,
+                    z: z as _,
                 },
-            );
+                block_state: block as _,
+            },
+        );
 
-            Some(old)
-        } else {
-            None
-        }
+        Some(old)
     }
 }
 
@@ -446,3 +589,39 @@ slice_serializable! {
         pub chunk_light_data: &'a [u8] as GreedyBlob,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::world::chunk_section::ChunkSection;
+    use crate::world::paletted_container::{BiomePalettedContainer, BlockPalettedContainer};
+
+    use super::Chunk;
+
+    #[test]
+    fn sections_with_y() {
+        let sections = (0..4)
+            .map(|index| {
+                ChunkSection::new(
+                    16 * 16 * 16,
+                    BlockPalettedContainer::filled(index + 1),
+                    BiomePalettedContainer::filled(0),
+                )
+            })
+            .collect();
+        let chunk = Chunk::new(sections);
+
+        let sections: Vec<(i32, u16)> = chunk
+            .sections_with_y()
+            .map(|(y, section)| (y, section.get_block(0, 0, 0)))
+            .collect();
+        assert_eq!(
+            sections,
+            vec![
+                (Chunk::MIN_Y, 1),
+                (Chunk::MIN_Y + 16, 2),
+                (Chunk::MIN_Y + 32, 3),
+                (Chunk::MIN_Y + 48, 4)
+            ]
+        );
+    }
+}
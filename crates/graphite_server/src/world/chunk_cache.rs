@@ -0,0 +1,71 @@
+use std::{
+    cell::RefCell,
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::Hasher,
+    rc::{Rc, Weak},
+};
+
+use graphite_net::network_buffer::WriteBuffer;
+
+/// Serialized block and light data of a chunk, ie. everything in the chunk packet
+/// except for the chunk position
+pub(crate) struct ChunkPayload {
+    pub(crate) block_data: WriteBuffer,
+    pub(crate) light_data: WriteBuffer,
+}
+
+impl ChunkPayload {
+    fn hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        hasher.write(self.block_data.get_written());
+        hasher.write(self.light_data.get_written());
+        hasher.finish()
+    }
+
+    fn bytes_eq(&self, other: &ChunkPayload) -> bool {
+        self.block_data.get_written() == other.block_data.get_written()
+            && self.light_data.get_written() == other.light_data.get_written()
+    }
+}
+
+pub(crate) type SharedChunkPayloadCache = Rc<RefCell<ChunkPayloadCache>>;
+
+/// Content-addressed cache of chunk payloads, allowing chunks with identical contents
+/// (eg. the chunks of a flat world) to share a single serialization
+///
+/// Entries are removed once no chunk uses them, so a modified chunk stops sharing
+/// the old entry and gets its own
+#[derive(Default)]
+pub struct ChunkPayloadCache {
+    entries: HashMap<u64, Weak<ChunkPayload>>,
+}
+
+impl ChunkPayloadCache {
+    /// Number of distinct payloads currently in use
+    pub fn len(&self) -> usize {
+        self.entries.values().filter(|entry| entry.strong_count() > 0).count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub(crate) fn get_or_insert(&mut self, payload: ChunkPayload) -> Rc<ChunkPayload> {
+        let hash = payload.hash();
+
+        if let Some(existing) = self.entries.get(&hash).and_then(Weak::upgrade) {
+            if existing.bytes_eq(&payload) {
+                return existing;
+            }
+
+            // Hash collision, don't replace the entry that is still in use
+            return Rc::new(payload);
+        }
+
+        self.entries.retain(|_, entry| entry.strong_count() > 0);
+
+        let payload = Rc::new(payload);
+        self.entries.insert(hash, Rc::downgrade(&payload));
+        payload
+    }
+}
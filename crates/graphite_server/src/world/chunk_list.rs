@@ -1,11 +1,12 @@
-use std::slice;
-use super::chunk::Chunk;
+use std::{cell::{Ref, RefCell}, rc::Rc, slice};
+use super::{chunk::Chunk, chunk_cache::{ChunkPayloadCache, SharedChunkPayloadCache}};
 
 pub struct ChunkGrid {
     size_x: usize,
     size_y: usize,
     size_z: usize,
-    chunks: Vec<Chunk>
+    chunks: Vec<Chunk>,
+    payload_cache: Option<SharedChunkPayloadCache>,
 }
 
 impl ChunkGrid {
@@ -14,7 +15,8 @@ impl ChunkGrid {
             size_x,
             size_y,
             size_z,
-            chunks
+            chunks,
+            payload_cache: None,
         }
     }
 
@@ -30,7 +32,8 @@ impl ChunkGrid {
             size_x,
             size_y: 24,
             size_z,
-            chunks
+            chunks,
+            payload_cache: None,
         }
     }
 
@@ -46,7 +49,8 @@ impl ChunkGrid {
             size_x,
             size_y: 24,
             size_z,
-            chunks
+            chunks,
+            payload_cache: None,
         }
     }
 
@@ -60,7 +64,8 @@ impl ChunkGrid {
             self.size_y = new_size_y;
         }
 
-        let empty = Chunk::new_empty(new_size_y);
+        let mut empty = Chunk::new_empty(new_size_y);
+        empty.payload_cache = self.payload_cache.clone();
 
         if increase_x != 0 {
             // X = New value
@@ -160,6 +165,25 @@ impl ChunkGrid {
         }
     }
 
+    /// Enables sharing the serialized data of chunks with identical contents,
+    /// see `ChunkPayloadCache`
+    pub fn enable_payload_cache(&mut self) {
+        if self.payload_cache.is_some() {
+            return;
+        }
+
+        let cache = Rc::new(RefCell::new(ChunkPayloadCache::default()));
+        for chunk in self.iter_mut() {
+            chunk.payload_cache = Some(cache.clone());
+            chunk.invalidate_cache();
+        }
+        self.payload_cache = Some(cache);
+    }
+
+    pub fn get_payload_cache(&self) -> Option<Ref<'_, ChunkPayloadCache>> {
+        self.payload_cache.as_ref().map(|cache| cache.borrow())
+    }
+
     #[inline]
     pub fn size_x(&self) -> usize {
         self.size_x
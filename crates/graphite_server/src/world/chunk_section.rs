@@ -91,6 +91,10 @@ impl ChunkSection {
         unsafe { &*self.block_palette }
     }
 
+    pub fn get_biome_palette(&self) -> &BiomePalettedContainer {
+        unsafe { &*self.biome_palette }
+    }
+
     fn get_block_palette_mut(&mut self) -> &mut BlockPalettedContainer {
         if self.copy_on_write {
             self.perform_copy();
@@ -0,0 +1,86 @@
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum GameRuleError {
+    #[error("unknown game rule: {0}")]
+    UnknownRule(String),
+    #[error("invalid value for game rule {rule}: {value}")]
+    InvalidValue { rule: &'static str, value: String },
+}
+
+macro_rules! game_rules {
+    ($($(#[$doc:meta])* $field:ident: $ty:ty = $default:expr, $name:literal;)*) => {
+        /// Rules that change the behaviour of a world, see `World::game_rules`
+        ///
+        /// Rules can be changed directly using the fields, or by name using `GameRules::set`
+        /// in the same way as the `/gamerule` command
+        #[derive(Clone, Debug, PartialEq, Eq)]
+        pub struct GameRules {
+            $(
+                $(#[$doc])*
+                pub $field: $ty,
+            )*
+        }
+
+        impl Default for GameRules {
+            fn default() -> Self {
+                Self {
+                    $($field: $default,)*
+                }
+            }
+        }
+
+        impl GameRules {
+            /// Names of every game rule, eg. `doDaylightCycle`
+            pub const NAMES: &'static [&'static str] = &[$($name,)*];
+
+            /// Returns the value of the game rule with the given name, formatted as a string
+            pub fn get(&self, name: &str) -> Result<String, GameRuleError> {
+                match name {
+                    $($name => Ok(self.$field.to_string()),)*
+                    _ => Err(GameRuleError::UnknownRule(name.into())),
+                }
+            }
+
+            /// Parses and sets the value of the game rule with the given name
+            pub fn set(&mut self, name: &str, value: &str) -> Result<(), GameRuleError> {
+                match name {
+                    $($name => {
+                        self.$field = value.parse().map_err(|_| GameRuleError::InvalidValue {
+                            rule: $name,
+                            value: value.into(),
+                        })?;
+                        Ok(())
+                    })*
+                    _ => Err(GameRuleError::UnknownRule(name.into())),
+                }
+            }
+        }
+    };
+}
+
+game_rules! {
+    /// Whether the time of day advances, see `World::get_day_time`
+    do_daylight_cycle: bool = true, "doDaylightCycle";
+    /// Whether mobs spawn naturally
+    do_mob_spawning: bool = true, "doMobSpawning";
+    /// Whether players take damage when landing after a fall
+    fall_damage: bool = true, "fallDamage";
+    /// Whether players take damage from fire and lava
+    fire_damage: bool = true, "fireDamage";
+    /// Whether players keep their inventory after dying
+    keep_inventory: bool = false, "keepInventory";
+    /// Whether players keep their inventory when they disconnect. If false, the whole inventory
+    /// is dropped at the position of the player
+    ///
+    /// Items in the crafting grid and on the cursor are always returned to the inventory first.
+    /// Players being transferred to another world or universe never drop their inventory.
+    /// This isn't a vanilla rule
+    keep_inventory_on_disconnect: bool = true, "keepInventoryOnDisconnect";
+    /// Whether mobs can change blocks, eg. creepers destroying blocks when exploding
+    mob_griefing: bool = true, "mobGriefing";
+    /// Whether players regenerate health when their food level is high enough
+    natural_regeneration: bool = true, "naturalRegeneration";
+    /// Number of blocks per chunk section that are randomly ticked every tick
+    random_tick_speed: u32 = 3, "randomTickSpeed";
+}
@@ -1,9 +1,15 @@
+pub mod autosave;
 pub mod block_entity_storage;
+pub mod chest;
 pub mod chunk;
+pub mod chunk_cache;
 pub mod chunk_section;
 pub mod chunk_view_diff;
+pub mod game_rules;
 pub mod paletted_container;
 pub mod placement_context;
+pub mod sign;
+pub mod team;
 pub mod block_update;
 pub mod chunk_list;
 pub mod world_map;
@@ -147,6 +147,60 @@ where
     }
 }
 
+impl BlockPalettedContainer {
+    /// Creates a container from a flat array of block ids (indexed by `y*256 + z*16 + x`),
+    /// picking the smallest representation that can hold all the distinct ids
+    ///
+    /// Returns the number of non-air blocks along with the container
+    pub fn from_block_ids(block_ids: &[u16; BLOCK_CAPACITY]) -> (u16, Self) {
+        let non_air_blocks = block_ids.iter().filter(|id| **id != 0).count() as u16;
+
+        // Collect distinct ids in order of first appearance, giving up after 16
+        let mut indices: heapless::Vec<(u16, usize), 16> = heapless::Vec::new();
+        let mut fits_in_array = true;
+        for block_id in block_ids {
+            if let Some((_, count)) = indices.iter_mut().find(|(value, _)| value == block_id) {
+                *count += 1;
+            } else if indices.push((*block_id, 1)).is_err() {
+                fits_in_array = false;
+                break;
+            }
+        }
+
+        let container = if indices.len() == 1 {
+            Self::filled(indices[0].0)
+        } else if fits_in_array {
+            let mut contents = [0_u8; BLOCK_CAPACITY / 2];
+            for (index, block_id) in block_ids.iter().enumerate() {
+                let palette_index = indices
+                    .iter()
+                    .position(|(value, _)| value == block_id)
+                    .expect("all values are in the palette");
+
+                // Convert from xzy index to the big-endian array index
+                let x = index % BLOCK_SIDE_LEN;
+                let array_index = index - x + (BLOCK_SIDE_LEN - 1 - x);
+                ArrayContainer::<u16, { BLOCK_CAPACITY / 2 }>::set_to_palette(
+                    &mut contents,
+                    array_index,
+                    palette_index,
+                );
+            }
+            Self::Array(Box::from(ArrayContainer { indices, contents }))
+        } else {
+            let mut direct = DirectContainer {
+                contents: [0_u64; BLOCK_DIRECT_LEN],
+            };
+            for (index, block_id) in block_ids.iter().enumerate() {
+                direct.set(index, *block_id as usize);
+            }
+            Self::Direct(Box::from(direct))
+        };
+
+        (non_air_blocks, container)
+    }
+}
+
 enum ArraySetResult<T> {
     Changed(T),
     Unchanged,
@@ -396,4 +450,74 @@ where
             Self::Direct(_) => 5 /*array header*/ + DIRECT_LEN*8 /*contents*/,
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use graphite_binary::slice_serialization::SliceSerializable;
+
+    use super::{BlockPalettedContainer, PalettedContainer};
+
+    fn serialize(container: &BlockPalettedContainer) -> Vec<u8> {
+        let mut bytes = vec![0; BlockPalettedContainer::get_write_size(container)];
+        let remaining = unsafe { BlockPalettedContainer::write(&mut bytes, container) }.len();
+        bytes.truncate(bytes.len() - remaining);
+        bytes
+    }
+
+    fn hand_built(block_ids: &[u16; 4096]) -> BlockPalettedContainer {
+        let mut container = BlockPalettedContainer::filled(block_ids[0]);
+        for (index, block_id) in block_ids.iter().enumerate() {
+            let (x, z, y) = (index % 16, (index / 16) % 16, index / 256);
+            container.set(x as _, y as _, z as _, *block_id);
+        }
+        container
+    }
+
+    fn assert_same_blocks(block_ids: &[u16; 4096], container: &BlockPalettedContainer) {
+        for (index, block_id) in block_ids.iter().enumerate() {
+            let (x, z, y) = (index % 16, (index / 16) % 16, index / 256);
+            assert_eq!(container.get(x as _, y as _, z as _), *block_id);
+        }
+    }
+
+    #[test]
+    fn from_block_ids_single() {
+        let block_ids = [1_u16; 4096];
+        let (non_air_blocks, container) = BlockPalettedContainer::from_block_ids(&block_ids);
+
+        assert_eq!(non_air_blocks, 4096);
+        assert!(matches!(container, PalettedContainer::Single(1)));
+        assert_eq!(serialize(&container), serialize(&hand_built(&block_ids)));
+    }
+
+    #[test]
+    fn from_block_ids_array() {
+        // Mostly stone, with a few air blocks
+        let mut block_ids = [1_u16; 4096];
+        block_ids[0] = 0;
+        block_ids[17] = 0;
+        block_ids[4095] = 0;
+
+        let (non_air_blocks, container) = BlockPalettedContainer::from_block_ids(&block_ids);
+
+        assert_eq!(non_air_blocks, 4093);
+        assert!(matches!(container, PalettedContainer::Array(_)));
+        assert_same_blocks(&block_ids, &container);
+        assert_eq!(serialize(&container), serialize(&hand_built(&block_ids)));
+    }
+
+    #[test]
+    fn from_block_ids_direct() {
+        let mut block_ids = [0_u16; 4096];
+        for (index, block_id) in block_ids.iter_mut().enumerate() {
+            *block_id = (index % 20) as u16;
+        }
+
+        let (non_air_blocks, container) = BlockPalettedContainer::from_block_ids(&block_ids);
+
+        assert_eq!(non_air_blocks, 4096 - 205);
+        assert!(matches!(container, PalettedContainer::Direct(_)));
+        assert_same_blocks(&block_ids, &container);
+        assert_eq!(serialize(&container), serialize(&hand_built(&block_ids)));
+    }
+}
@@ -1,11 +1,11 @@
 use graphite_mc_constants::{
     block::{Block, BlockAttributes, NoSuchBlockError, self},
-    block_parameter::{self, Axis3D, DirectionOrDown, Facing, Half, Instrument, RailShape, StraightRailShape, StairShape},
+    block_parameter::{self, Axis3D, ChestType, DirectionOrDown, Facing, Half, Instrument, RailShape, StraightRailShape, StairShape},
     placement::PlacementContext, item::Item,
 };
 use graphite_mc_protocol::types::{BlockPosition, Direction};
 
-use super::{World, WorldService, block_update};
+use super::{World, WorldService, block_update, chest};
 
 pub struct ServerPlacementContext<'a, W: WorldService> {
     pub(crate) interacted_pos: BlockPosition,
@@ -158,6 +158,13 @@ impl<'a, W: WorldService> ServerPlacementContext<'a, W> {
 }
 
 impl<'a, W: WorldService> PlacementContext for ServerPlacementContext<'a, W> {
+    fn get_chest_type(&mut self) -> ChestType {
+        let trapped = self.placed_item == Item::TrappedChest;
+        let facing = self.get_facing_look_horizontal_opposite();
+
+        chest::get_placement_chest_type(self.offset_pos.x, self.offset_pos.y, self.offset_pos.z, trapped, facing, self.world)
+    }
+
     fn get_clicked_axis(&mut self) -> Axis3D {
         match self.face {
             Direction::Down => Axis3D::Y,
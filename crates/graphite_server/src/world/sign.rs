@@ -0,0 +1,49 @@
+use graphite_binary::nbt::{CachedNBT, NBTNode, NBT};
+use graphite_mc_constants::block::Block;
+use graphite_text_component::TextComponent;
+
+/// Block entity type id used by all sign blocks
+pub(crate) const SIGN_BLOCK_ENTITY_TYPE: u8 = 7;
+
+const LINE_KEYS: [&str; 4] = ["Text1", "Text2", "Text3", "Text4"];
+
+pub fn is_sign(block: &Block) -> bool {
+    matches!(
+        block,
+        Block::OakSign { .. }
+            | Block::SpruceSign { .. }
+            | Block::BirchSign { .. }
+            | Block::AcaciaSign { .. }
+            | Block::JungleSign { .. }
+            | Block::DarkOakSign { .. }
+            | Block::MangroveSign { .. }
+            | Block::CrimsonSign { .. }
+            | Block::WarpedSign { .. }
+            | Block::OakWallSign { .. }
+            | Block::SpruceWallSign { .. }
+            | Block::BirchWallSign { .. }
+            | Block::AcaciaWallSign { .. }
+            | Block::JungleWallSign { .. }
+            | Block::DarkOakWallSign { .. }
+            | Block::MangroveWallSign { .. }
+            | Block::CrimsonWallSign { .. }
+            | Block::WarpedWallSign { .. }
+    )
+}
+
+pub(crate) fn write_lines(lines: &[TextComponent; 4]) -> CachedNBT {
+    let mut nbt = NBT::new();
+    for (key, line) in LINE_KEYS.iter().zip(lines) {
+        nbt.insert_root(key, NBTNode::String(line.to_json().into()));
+    }
+    nbt.into()
+}
+
+pub(crate) fn read_lines(nbt: &NBT) -> Option<[TextComponent; 4]> {
+    let mut lines = LINE_KEYS.map(|_| TextComponent::Static(""));
+    for (key, line) in LINE_KEYS.iter().zip(&mut lines) {
+        let json = nbt.find_root(key)?.as_string()?;
+        *line = TextComponent::Owned(json.clone());
+    }
+    Some(lines)
+}
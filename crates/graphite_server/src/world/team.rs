@@ -0,0 +1,169 @@
+use graphite_mc_protocol::play::server::{
+    ChatFormatting, SetPlayerTeam, SetPlayerTeamAction, SetPlayerTeamParameters,
+};
+use graphite_net::network_buffer::WriteBuffer;
+use graphite_text_component::TextComponent;
+
+#[derive(Default, PartialEq, Eq, Clone, Copy, Debug)]
+pub enum NameTagVisibility {
+    #[default]
+    Always,
+    HideForOtherTeams,
+    HideForOwnTeam,
+    Never,
+}
+
+impl NameTagVisibility {
+    fn as_str(self) -> &'static str {
+        match self {
+            NameTagVisibility::Always => "always",
+            NameTagVisibility::HideForOtherTeams => "hideForOtherTeams",
+            NameTagVisibility::HideForOwnTeam => "hideForOwnTeam",
+            NameTagVisibility::Never => "never",
+        }
+    }
+}
+
+#[derive(Default, PartialEq, Eq, Clone, Copy, Debug)]
+pub enum CollisionRule {
+    #[default]
+    Always,
+    PushOtherTeams,
+    PushOwnTeam,
+    Never,
+}
+
+impl CollisionRule {
+    fn as_str(self) -> &'static str {
+        match self {
+            CollisionRule::Always => "always",
+            CollisionRule::PushOtherTeams => "pushOtherTeams",
+            CollisionRule::PushOwnTeam => "pushOwnTeam",
+            CollisionRule::Never => "never",
+        }
+    }
+}
+
+/// A scoreboard team. Entries are player usernames or entity UUIDs
+///
+/// The color of the team is used for the name tags of its entries,
+/// as well as the outline of glowing entries
+pub struct Team {
+    name: String,
+    entries: Vec<String>,
+
+    pub display_name: TextComponent,
+    pub color: ChatFormatting,
+    pub prefix: TextComponent,
+    pub suffix: TextComponent,
+    pub name_tag_visibility: NameTagVisibility,
+    pub collision_rule: CollisionRule,
+    pub friendly_fire: bool,
+    pub see_friendly_invisibles: bool,
+}
+
+impl Team {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.into(),
+            entries: Vec::new(),
+
+            display_name: name.into(),
+            color: ChatFormatting::Reset,
+            prefix: "".into(),
+            suffix: "".into(),
+            name_tag_visibility: NameTagVisibility::Always,
+            collision_rule: CollisionRule::Always,
+            friendly_fire: true,
+            see_friendly_invisibles: true,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn entries(&self) -> &[String] {
+        &self.entries
+    }
+
+    pub fn has_entry(&self, entry: &str) -> bool {
+        self.entries.iter().any(|e| e == entry)
+    }
+
+    pub(crate) fn add_entry(&mut self, entry: &str) -> bool {
+        if self.has_entry(entry) {
+            false
+        } else {
+            self.entries.push(entry.into());
+            true
+        }
+    }
+
+    pub(crate) fn remove_entry(&mut self, entry: &str) -> bool {
+        let len = self.entries.len();
+        self.entries.retain(|e| e != entry);
+        self.entries.len() != len
+    }
+
+    fn parameters(&self) -> SetPlayerTeamParameters<'_> {
+        let mut options = 0;
+        if self.friendly_fire {
+            options |= 0x1;
+        }
+        if self.see_friendly_invisibles {
+            options |= 0x2;
+        }
+
+        SetPlayerTeamParameters {
+            display_name: self.display_name.to_json(),
+            options,
+            name_tag_visibility: self.name_tag_visibility.as_str(),
+            collision_rule: self.collision_rule.as_str(),
+            color: self.color,
+            prefix: self.prefix.to_json(),
+            suffix: self.suffix.to_json(),
+        }
+    }
+
+    fn write_action(&self, write_buffer: &mut WriteBuffer, action: SetPlayerTeamAction) {
+        let packet = SetPlayerTeam {
+            name: &self.name,
+            action,
+        };
+        graphite_net::packet_helper::try_write_packet(write_buffer, &packet);
+    }
+
+    pub(crate) fn write_create_packet(&self, write_buffer: &mut WriteBuffer) {
+        let action = SetPlayerTeamAction::Create {
+            parameters: self.parameters(),
+            entries: self.entries.iter().map(String::as_str).collect(),
+        };
+        self.write_action(write_buffer, action);
+    }
+
+    pub(crate) fn write_remove_packet(&self, write_buffer: &mut WriteBuffer) {
+        self.write_action(write_buffer, SetPlayerTeamAction::Remove {});
+    }
+
+    pub(crate) fn write_update_packet(&self, write_buffer: &mut WriteBuffer) {
+        let action = SetPlayerTeamAction::Update {
+            parameters: self.parameters(),
+        };
+        self.write_action(write_buffer, action);
+    }
+
+    pub(crate) fn write_entries_packet(
+        &self,
+        write_buffer: &mut WriteBuffer,
+        entries: Vec<&str>,
+        add: bool,
+    ) {
+        let action = if add {
+            SetPlayerTeamAction::AddEntries { entries }
+        } else {
+            SetPlayerTeamAction::RemoveEntries { entries }
+        };
+        self.write_action(write_buffer, action);
+    }
+}
@@ -1,28 +1,42 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 
 use bevy_ecs::{prelude::*, world::EntityMut};
-use graphite_mc_constants::{block::BlockAttributes, item::Item};
+use graphite_mc_constants::{block::{Block, BlockAttributes}, entity::MarkerMetadata, item::Item};
 use graphite_net::network_buffer::WriteBuffer;
 use graphite_mc_protocol::{
-    play::server::{PlayerPosition, RotateHead, SetChunkCacheCenter, TeleportEntity, InitializeBorder, ForgetLevelChunk},
+    play::server::{PlayerPosition, RotateHead, SetChunkCacheCenter, TeleportEntity, InitializeBorder, ForgetLevelChunk, MoveEntityPosRot, LevelEvent, LevelEventType, SetTime, SystemChat},
     types::{BlockPosition, Direction},
 };
 use graphite_sticky::Unsticky;
+use graphite_text_component::TextComponent;
 
 use crate::{
     entity::{
-        components::{BasicEntity, EntitySpawnDefinition, Spinalla, Viewable},
+        attributes::{Attribute, EntityAttributes},
+        components::{BasicEntity, DroppedItem, EntityCustomName, EntitySpawnDefinition, InterpolatedMovement, Spinalla, Viewable},
         position::{Coordinate, Position, Rotation},
     },
+    inventory::inventory_handler::ItemSlot,
     player::{proto_player::ProtoPlayer, Player, PlayerService},
     universe::{EntityId, Universe, UniverseService}, ticker::WorldTicker,
 };
 
 use super::{
+    autosave::{Autosave, ChunkSnapshot},
+    block_entity_storage::BlockEntity,
+    block_update, chest,
     chunk::{BlockStorage, Chunk},
     placement_context::ServerPlacementContext, chunk_list::ChunkGrid,
+    game_rules::GameRules,
+    sign,
+    team::Team,
 };
 
+/// Maximum distance (in blocks) that a chain of neighbor updates can travel, see `World::notify_neighbors`
+pub const MAX_NEIGHBOR_UPDATE_DEPTH: usize = 16;
+
+const NEIGHBOR_OFFSETS: [(i32, i32, i32); 6] = [(1, 0, 0), (0, 1, 0), (0, 0, 1), (-1, 0, 0), (0, -1, 0), (0, 0, -1)];
+
 // user defined world service trait
 
 #[derive(Clone, Copy, PartialEq, Eq)]
@@ -48,14 +62,27 @@ where
     type UniverseServiceType: UniverseService;
     type ParentWorldServiceType: WorldService;
 
+    /// Radius (in chunks) around the player in which chunks and block changes are sent
     const CHUNK_VIEW_DISTANCE: u8 = 8;
-    const ENTITY_VIEW_DISTANCE: u8 = 8;
+    /// Radius (in chunks) around the player in which entities and other players are shown
+    /// This can be smaller than the chunk view distance to reduce the cost of entity updates
+    ///
+    /// Defaults to the chunk view distance
+    const ENTITY_VIEW_DISTANCE: u8 = Self::CHUNK_VIEW_DISTANCE;
     const SHOW_DEFAULT_WORLD_BORDER: bool = false;
 
     fn handle_player_join(
         world: &mut World<Self>,
         proto_player: ProtoPlayer<Self::UniverseServiceType>,
     );
+
+    /// Creates the chunk at the grid position `(chunk_x, chunk_z)`. This is used for every chunk
+    /// of `World::new_with_generated_chunks` and for the new chunks when the world is expanded
+    ///
+    /// Defaults to an empty chunk
+    fn generate_chunk(&mut self, _chunk_x: usize, _chunk_z: usize, size_y: usize) -> Chunk {
+        Chunk::new_empty(size_y)
+    }
 }
 
 // graphite world
@@ -67,14 +94,25 @@ pub struct World<W: WorldService + ?Sized> {
     pub service: W,
     pub(crate) entities: bevy_ecs::world::World,
     pub(crate) entity_map: HashMap<EntityId, bevy_ecs::entity::Entity>,
+    /// Entities that were spawned or moved since the last tick,
+    /// players check whether they should start or stop tracking them
+    pub(crate) tracking_updates: Vec<bevy_ecs::entity::Entity>,
     pub(crate) global_write_buffer: WriteBuffer,
+    teams: Vec<Team>,
+    game_time: u64,
+    frozen: bool,
+    remaining_steps: usize,
+    day_time: u64,
+    synced_daylight_cycle: bool,
+    pub game_rules: GameRules,
+    autosave: Option<Autosave>,
 
     // Don't move -- chunks must be dropped last
     pub(crate) chunks: ChunkGrid,
     empty_chunk: Chunk,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct ChunkViewPosition {
     pub(crate) x: usize, //todo: make private
     pub(crate) z: usize,
@@ -95,7 +133,16 @@ impl<W: WorldService> World<W> {
             service,
             entities: Default::default(),
             entity_map: Default::default(),
+            tracking_updates: Vec::new(),
             global_write_buffer: Default::default(),
+            teams: Vec::new(),
+            game_time: 0,
+            frozen: false,
+            remaining_steps: 0,
+            day_time: 0,
+            synced_daylight_cycle: true,
+            game_rules: GameRules::default(),
+            autosave: None,
 
             empty_chunk: Chunk::new_empty(chunks.size_y()),
             chunks,
@@ -110,6 +157,18 @@ impl<W: WorldService> World<W> {
         Self::new(service, ChunkGrid::new_with_default_chunks(size_x, size_y, size_z))
     }
 
+    /// Creates a world using `WorldService::generate_chunk` for every chunk
+    pub fn new_with_generated_chunks(mut service: W, size_x: usize, size_y: usize, size_z: usize) -> Self {
+        let mut chunks = Vec::with_capacity(size_x * size_z);
+        for z in 0..size_z {
+            for x in 0..size_x {
+                chunks.push(service.generate_chunk(x, z, size_y));
+            }
+        }
+
+        Self::new(service, ChunkGrid::new(chunks, size_x, size_y, size_z))
+    }
+
     pub fn update_universe_ptr(&mut self, universe: *mut Universe<W::UniverseServiceType>) {
         self.universe = universe;
         self.service.update_universe_ptr(universe);
@@ -131,6 +190,30 @@ impl<W: WorldService> World<W> {
         self.empty_chunk.expand(increase_y);
         self.chunks.expand(increase_x, increase_y, increase_z);
 
+        // Generate the chunks that were added
+        let (size_x, size_y, size_z) = self.chunks.dimensions();
+        let new_x = if increase_x > 0 {
+            size_x - increase_x as usize..size_x
+        } else {
+            0..increase_x.unsigned_abs()
+        };
+        let new_z = if increase_z > 0 {
+            size_z - increase_z as usize..size_z
+        } else {
+            0..increase_z.unsigned_abs()
+        };
+        for (x, z, chunk) in self.chunks.enumerate_mut() {
+            if new_x.contains(&x) || new_z.contains(&z) {
+                let mut generated = self.service.generate_chunk(x, z, size_y);
+
+                // Keep any references that were moved into the placeholder chunk
+                generated.payload_cache = chunk.payload_cache.take();
+                generated.push_all_entities(chunk.pop_all_entities());
+                generated.push_all_player_refs(chunk.pop_all_player_refs());
+                *chunk = generated;
+            }
+        }
+
         if increase_x < 0 || increase_y < 0 || increase_z < 0 {
             for (x, z, chunk) in self.chunks.enumerate_mut() {
                 chunk.write_into_self(x as _, z as _).unwrap();
@@ -162,6 +245,7 @@ impl<W: WorldService> World<W> {
     ) {
         let fn_create = spawn_def.get_spawn_function();
         let destroy_buf = spawn_def.get_despawn_buffer();
+        let tracking_range = spawn_def.get_tracking_range();
 
         // Compute chunk coordinates, clamped to valid coordinates
         let chunk_x = Chunk::to_chunk_coordinate(position.x).max(0).min(self.chunks.size_x() as i32 - 1);
@@ -180,9 +264,9 @@ impl<W: WorldService> World<W> {
         // Initialize viewable
         let mut viewable = Viewable::new(position, chunk_x, chunk_z, fn_create, destroy_buf);
         viewable.index_in_chunk_entity_slab = chunk.entities.insert(id);
-        viewable.buffer = &mut chunk.entity_viewable_buffer as *mut WriteBuffer;
         viewable.last_chunk_x = chunk_x;
         viewable.last_chunk_z = chunk_z;
+        viewable.tracking_range = tracking_range;
 
         // Construct entity using components
         entity.insert_bundle(components).insert(viewable);
@@ -190,13 +274,71 @@ impl<W: WorldService> World<W> {
         // Allow the spawn definition to add components
         spawn_def.add_components(&mut entity);
 
-        // todo: why do we have to do this... can't we just convert EntityMut into EntityRef...
-        // bevy.. please... im begging you
-        // https://github.com/bevyengine/bevy/issues/5459
-        let entity_ref = self.entities.entity(id);
+        // The entity is spawned for players within its tracking range, see `Player::update_tracked_entities`
+        self.tracking_updates.push(id);
+    }
 
-        // Spawn the entity for players in the view distance of the chunk
-        (fn_create)(&mut chunk.entity_viewable_buffer, entity_ref);
+    /// Number of ticks the world has advanced, not counting ticks spent frozen
+    pub fn get_game_time(&self) -> u64 {
+        self.game_time
+    }
+
+    pub fn is_frozen(&self) -> bool {
+        self.frozen
+    }
+
+    /// Freezes or unfreezes the world. Frozen worlds don't update entities or advance the
+    /// game time, but players are still ticked and packets are still sent
+    ///
+    /// Unfreezing the world discards any remaining steps
+    pub fn set_frozen(&mut self, frozen: bool) {
+        self.frozen = frozen;
+        if !frozen {
+            self.remaining_steps = 0;
+        }
+    }
+
+    /// Time of day in ticks, where 0 is sunrise and 24000 is the next sunrise
+    ///
+    /// Advances every tick while the world is running, unless the `do_daylight_cycle` game rule is false
+    pub fn get_day_time(&self) -> u64 {
+        self.day_time
+    }
+
+    /// Sets the time of day, sending it to every player in the world
+    pub fn set_day_time(&mut self, day_time: u64) {
+        self.day_time = day_time;
+        let packet = self.create_time_packet();
+        graphite_net::packet_helper::try_write_packet(&mut self.global_write_buffer, &packet);
+    }
+
+    fn create_time_packet(&self) -> SetTime {
+        // A negative time of day stops the client from advancing it
+        let day_time = if self.game_rules.do_daylight_cycle {
+            self.day_time as i64
+        } else {
+            -(self.day_time as i64).max(1)
+        };
+
+        SetTime {
+            game_time: self.game_time as _,
+            day_time,
+        }
+    }
+
+    /// Advances a frozen world by `ticks` ticks, one per universe tick, before it freezes again
+    ///
+    /// Does nothing if the world isn't frozen
+    pub fn step(&mut self, ticks: usize) {
+        if self.frozen {
+            self.remaining_steps += ticks;
+        }
+    }
+
+    /// Returns true if the world is advancing during the current tick,
+    /// ie. it isn't frozen or is being stepped
+    pub fn is_running(&self) -> bool {
+        !self.frozen || self.remaining_steps > 0
     }
 
     pub fn tick(&mut self) {
@@ -205,6 +347,55 @@ impl<W: WorldService> World<W> {
         // Update viewable state for entities
         self.update_viewable_entities();
 
+        let running = self.is_running();
+        if running {
+            self.tick_entities();
+            self.game_time += 1;
+            if self.game_rules.do_daylight_cycle {
+                self.day_time += 1;
+            }
+        }
+
+        // The client advances the time of day by itself, it only needs to be told when that stops or starts
+        if self.game_rules.do_daylight_cycle != self.synced_daylight_cycle {
+            self.synced_daylight_cycle = self.game_rules.do_daylight_cycle;
+            let packet = self.create_time_packet();
+            graphite_net::packet_helper::try_write_packet(&mut self.global_write_buffer, &packet);
+        }
+
+        // Send attribute and custom name changes
+        self.update_entity_attributes();
+        self.update_entity_custom_names();
+
+        // Tick service (ticks players as well)
+        self.service.tick(TickPhase(TickPhaseInner::Update));
+        self.service.tick(TickPhase(TickPhaseInner::View));
+
+        if running && self.frozen {
+            self.remaining_steps = self.remaining_steps.saturating_sub(1);
+        }
+
+        if self.autosave.as_mut().is_some_and(Autosave::tick) {
+            self.autosave_chunks();
+        }
+
+        // Clear viewable buffers
+        for chunk in self.chunks.iter_mut() {
+            chunk.entity_viewable_buffer.clear();
+            chunk.block_viewable_buffer.clear();
+        }
+        self.entities
+            .query::<&mut Viewable>()
+            .for_each_mut(&mut self.entities, |mut viewable| viewable.buffer.clear());
+        self.tracking_updates.clear();
+        self.global_write_buffer.clear();
+
+        // let end = Instant::now();
+        // let took = end.duration_since(start);
+        // println!("Took: {:?}", took);
+    }
+
+    fn tick_entities(&mut self) {
         // Update entities
         // todo: call system::tick
 
@@ -245,28 +436,182 @@ impl<W: WorldService> World<W> {
                 },
             );
 
-        // Tick service (ticks players as well)
-        self.service.tick(TickPhase(TickPhaseInner::Update));
-        self.service.tick(TickPhase(TickPhaseInner::View));
+        // Interpolate movement
+        self.update_interpolated_movement();
+    }
 
-        // Clear viewable buffers
-        for chunk in self.chunks.iter_mut() {
-            chunk.entity_viewable_buffer.clear();
-            chunk.block_viewable_buffer.clear();
+    /// Moves an entity to `target` over `ticks` ticks. Relative move packets are sent
+    /// every tick, allowing the client to smoothly render the movement
+    ///
+    /// Returns false if the entity doesn't exist
+    pub fn set_interpolated_position(&mut self, entity_id: EntityId, target: Position, ticks: usize) -> bool {
+        if let Some(mut entity) = self.get_entity_mut(entity_id) {
+            let coord = entity.get::<Viewable>()
+                .expect("all entities must have viewable")
+                .coord;
+
+            entity.insert(InterpolatedMovement {
+                entity_id,
+                target,
+                synced_coord: coord,
+                remaining_ticks: ticks.max(1),
+            });
+            true
+        } else {
+            false
         }
-        self.global_write_buffer.clear();
+    }
 
-        // let end = Instant::now();
-        // let took = end.duration_since(start);
-        // println!("Took: {:?}", took);
+    /// Sets the base value of an attribute of the entity, sending it to viewers on the next tick
+    ///
+    /// Returns false if the entity doesn't exist
+    pub fn set_entity_attribute(&mut self, entity_id: EntityId, attribute: Attribute, value: f64) -> bool {
+        if let Some(mut entity) = self.get_entity_mut(entity_id) {
+            if let Some(mut attributes) = entity.get_mut::<EntityAttributes>() {
+                attributes.attributes.set_base(attribute, value);
+            } else {
+                let mut attributes = EntityAttributes::new(entity_id);
+                attributes.attributes.set_base(attribute, value);
+                entity.insert(attributes);
+            }
+            true
+        } else {
+            false
+        }
     }
 
-    fn update_viewable_entities(&mut self) {
-        let size_x = self.chunks.size_x();
-        let size_z = self.chunks.size_z();
+    /// Sets the name shown above the entity, sending it to viewers on the next tick.
+    /// Clearing the name shows the default name of the entity again
+    ///
+    /// The name is only shown while looking at the entity, unless `set_entity_custom_name_visible` is used
+    ///
+    /// Returns false if the entity doesn't exist
+    pub fn set_entity_custom_name(&mut self, entity_id: EntityId, name: Option<TextComponent>) -> bool {
+        let name = name.map(|name| name.to_json().into());
+        self.update_entity_custom_name(entity_id, |metadata| metadata.set_custom_name(name))
+    }
 
-        let mut temp_write_buffer = WriteBuffer::with_min_capacity(64);
+    /// Sets whether the custom name is always shown above the entity, see `set_entity_custom_name`
+    ///
+    /// Returns false if the entity doesn't exist
+    pub fn set_entity_custom_name_visible(&mut self, entity_id: EntityId, visible: bool) -> bool {
+        self.update_entity_custom_name(entity_id, |metadata| metadata.set_custom_name_visible(visible))
+    }
 
+    fn update_entity_custom_name(&mut self, entity_id: EntityId, update: impl FnOnce(&mut MarkerMetadata)) -> bool {
+        if let Some(mut entity) = self.get_entity_mut(entity_id) {
+            if let Some(mut custom_name) = entity.get_mut::<EntityCustomName>() {
+                update(&mut custom_name.metadata);
+            } else {
+                let mut custom_name = EntityCustomName::new(entity_id);
+                update(&mut custom_name.metadata);
+                entity.insert(custom_name);
+            }
+            true
+        } else {
+            false
+        }
+    }
+
+    fn update_entity_custom_names(&mut self) {
+        self.entities
+            .query::<(&mut Viewable, &mut EntityCustomName)>()
+            .for_each_mut(
+                &mut self.entities,
+                |(mut viewable, mut custom_name)| {
+                    let entity_id = custom_name.entity_id;
+                    viewable.write_viewable_metadata(entity_id, &mut custom_name.metadata);
+                },
+            );
+    }
+
+    fn update_entity_attributes(&mut self) {
+        self.entities
+            .query::<(&mut Viewable, &mut EntityAttributes)>()
+            .for_each_mut(
+                &mut self.entities,
+                |(mut viewable, mut attributes)| {
+                    let entity_id = attributes.entity_id;
+                    if let Some(packet) = attributes.attributes.take_changes(entity_id) {
+                        viewable.write_viewable_packet(&packet);
+                    }
+                },
+            );
+    }
+
+    fn update_interpolated_movement(&mut self) {
+        let mut finished = Vec::new();
+
+        self.entities
+            .query::<(Entity, &mut Viewable, &mut InterpolatedMovement)>()
+            .for_each_mut(
+                &mut self.entities,
+                |(id, mut viewable, mut movement)| {
+                    let target = movement.target.coord;
+                    let remaining_ticks = movement.remaining_ticks as f32;
+
+                    // Delta is always computed from the synced coordinate, so the
+                    // error from quantizing the movement doesn't accumulate
+                    let delta_x = (target.x - movement.synced_coord.x) / remaining_ticks;
+                    let delta_y = (target.y - movement.synced_coord.y) / remaining_ticks;
+                    let delta_z = (target.z - movement.synced_coord.z) / remaining_ticks;
+
+                    let quantized_x = (delta_x * 4096.0).round();
+                    let quantized_y = (delta_y * 4096.0).round();
+                    let quantized_z = (delta_z * 4096.0).round();
+
+                    let in_range = |v: f32| v >= i16::MIN as f32 && v <= i16::MAX as f32;
+                    if in_range(quantized_x) && in_range(quantized_y) && in_range(quantized_z) {
+                        movement.synced_coord.x += quantized_x / 4096.0;
+                        movement.synced_coord.y += quantized_y / 4096.0;
+                        movement.synced_coord.z += quantized_z / 4096.0;
+                        movement.remaining_ticks -= 1;
+
+                        // Relative Move & Rotate
+                        let move_packet = MoveEntityPosRot {
+                            entity_id: movement.entity_id.as_i32(),
+                            delta_x: quantized_x as i16,
+                            delta_y: quantized_y as i16,
+                            delta_z: quantized_z as i16,
+                            yaw: movement.target.rot.yaw,
+                            pitch: movement.target.rot.pitch,
+                            on_ground: true,
+                        };
+                        viewable.write_viewable_packet(&move_packet);
+                        viewable.coord = movement.synced_coord;
+                    } else {
+                        // Moving too fast for a relative move, teleport instead
+                        movement.synced_coord = target;
+                        movement.remaining_ticks = 0;
+                    }
+
+                    if movement.remaining_ticks == 0 {
+                        // Correct any remaining rounding error using a teleport
+                        if movement.synced_coord != target || viewable.coord != target {
+                            let teleport = TeleportEntity {
+                                entity_id: movement.entity_id.as_i32(),
+                                x: target.x as _,
+                                y: target.y as _,
+                                z: target.z as _,
+                                yaw: movement.target.rot.yaw,
+                                pitch: movement.target.rot.pitch,
+                                on_ground: true,
+                            };
+                            viewable.write_viewable_packet(&teleport);
+                        }
+
+                        viewable.coord = target;
+                        finished.push(id);
+                    }
+                },
+            );
+
+        for id in finished {
+            self.entities.entity_mut(id).remove::<InterpolatedMovement>();
+        }
+    }
+
+    fn update_viewable_entities(&mut self) {
         // todo: this might have shit performance because we iterate over every entity
         // and then have to do a second map lookup, as opposed to just being able to iterate
         // over the EntityRefs. If this is actually how you're supposed to write this using
@@ -283,15 +628,19 @@ impl<W: WorldService> World<W> {
                 let chunk_z = Chunk::to_chunk_coordinate(viewable.coord.z);
 
                 if let Some(chunk) = self.chunks.get_mut(chunk_x as usize, chunk_z as usize) {
+                    // Players spawn/despawn the entity themselves based on
+                    // its tracking range, see `Player::update_tracked_entities`
+                    if viewable.coord != viewable.tracking_coord {
+                        viewable.tracking_coord = viewable.coord;
+                        self.tracking_updates.push(id);
+                    }
+
                     if viewable.last_chunk_x == chunk_x && viewable.last_chunk_z == chunk_z {
                         return;
                     }
 
                     // Update chunk entity list
-                    viewable.index_in_chunk_entity_slab = chunk.entities.insert(id);
-
-                    // Update viewable entity's buffer ptr
-                    viewable.buffer = &mut chunk.entity_viewable_buffer as *mut WriteBuffer;
+                    let index_in_new_chunk = chunk.entities.insert(id);
 
                     // Remove from old entity list
                     if let Some(old_chunk) = self.chunks.get_mut_i32(viewable.last_chunk_x, viewable.last_chunk_z) {
@@ -299,27 +648,7 @@ impl<W: WorldService> World<W> {
                             .entities.remove(viewable.index_in_chunk_entity_slab);
                         debug_assert_eq!(id_in_list, id);
                     }
-
-                    temp_write_buffer.clear();
-                    (viewable.fn_create)(&mut temp_write_buffer, entity_ref);
-                    let create_bytes = temp_write_buffer.get_written();
-                    let destroy_bytes = viewable.destroy_buffer.get_written();
-
-                    // Find chunk differences and write create/destroy packets
-                    super::chunk_view_diff::for_each_diff_chunks(
-                        (viewable.last_chunk_x, viewable.last_chunk_z),
-                        (chunk_x, chunk_z),
-                        W::ENTITY_VIEW_DISTANCE,
-                        &mut self.chunks,
-                        |chunk, _, _| {
-                            chunk.write_to_players_in_chunk(create_bytes);
-                        },
-                        |chunk, _, _| {
-                            chunk.write_to_players_in_chunk(destroy_bytes);
-                        },
-                        size_x,
-                        size_z
-                    );
+                    viewable.index_in_chunk_entity_slab = index_in_new_chunk;
 
                     viewable.last_chunk_x = chunk_x;
                     viewable.last_chunk_z = chunk_z;
@@ -455,26 +784,14 @@ impl<W: WorldService> World<W> {
         player.write_destroy_packet(&mut destroy_buffer);
         let destroy_bytes = destroy_buffer.get_written();
 
-        // Write create packets for now-visible entities and
-        // destroy packets for no-longer-visible entities
+        // Write create packets for now-visible players and destroy packets for no-longer-visible
+        // players. Entities are handled separately, see `Player::update_tracked_entities`
         super::chunk_view_diff::for_each_diff_chunks(
             (old_chunk_x, old_chunk_z),
             (chunk_x, chunk_z),
             W::ENTITY_VIEW_DISTANCE,
             &mut self.chunks,
             |chunk, _, _| {
-                // Get all entities in chunk
-                chunk.entities.iter().for_each(|(_, id)| {
-                    // Get viewable component
-                    let entity = self.entities.entity(*id);
-                    let viewable = entity
-                        .get::<Viewable>()
-                        .expect("entity in chunk-list must be viewable");
-
-                    // Write create into player's buffer
-                    (viewable.fn_create)(&mut player.packets.write_buffer, entity);
-                });
-
                 // Create players
                 chunk.write_create_for_players_in_chunk(&mut player.packets.write_buffer);
                 chunk.write_to_players_in_chunk(create_bytes);
@@ -483,18 +800,6 @@ impl<W: WorldService> World<W> {
                 // Access the write_buffer from the ptr
                 let write_buffer = unsafe { &mut *player_write_buffer_ptr };
 
-                // Get all entities in chunk
-                chunk.entities.iter().for_each(|(_, id)| {
-                    // Get viewable component
-                    let entity = self.entities.entity(*id);
-                    let viewable = entity
-                        .get::<Viewable>()
-                        .expect("entity in chunk-list must be viewable");
-
-                    // Write destroy into player's buffer
-                    write_buffer.copy_from(viewable.destroy_buffer.get_written());
-                });
-
                 // Destroy players
                 chunk.write_destroy_for_players_in_chunk(write_buffer);
                 chunk.write_to_players_in_chunk(destroy_bytes);
@@ -521,6 +826,13 @@ impl<W: WorldService> World<W> {
             old_chunk.destroy_player(player);
         }
 
+        // Remove the player from their team
+        let username = &player.profile.username;
+        if let Some(team) = self.teams.iter_mut().find(|team| team.has_entry(username)) {
+            team.remove_entry(username);
+            team.write_entries_packet(&mut self.global_write_buffer, vec![username], false);
+        }
+
         let view_distance = W::CHUNK_VIEW_DISTANCE as i32;
         for x in -view_distance..view_distance + 1 {
             let chunk_x = x + view_position.x as i32;
@@ -591,7 +903,7 @@ impl<W: WorldService> World<W> {
         };
         graphite_net::packet_helper::try_write_packet(&mut proto_player.write_buffer, &position_packet);
 
-        // Entities
+        // Players, entities are spawned on the first tick, see `Player::update_tracked_entities`
         let view_distance = W::ENTITY_VIEW_DISTANCE as i32;
         for x in -view_distance..view_distance + 1 {
             let chunk_x = x + chunk_view_position.x as i32;
@@ -599,24 +911,20 @@ impl<W: WorldService> World<W> {
                 let chunk_z = z + chunk_view_position.z as i32;
 
                 if let Some(chunk) = self.chunks.get_mut_i32(chunk_x, chunk_z) {
-                    // Write entities
-                    chunk.entities.iter().for_each(|(_, id)| {
-                        // Get viewable component
-                        let entity = self.entities.entity(*id);
-                        let viewable = entity
-                            .get::<Viewable>()
-                            .expect("entity in chunk-list must be viewable");
-
-                        // Use viewable to write create packet into player's buffer
-                        (viewable.fn_create)(&mut proto_player.write_buffer, entity);
-                    });
-
-                    // Write players
                     chunk.write_create_for_players_in_chunk(&mut proto_player.write_buffer);
                 }
             }
         }
 
+        // Teams
+        for team in &self.teams {
+            team.write_create_packet(&mut proto_player.write_buffer);
+        }
+
+        // Time
+        let time_packet = self.create_time_packet();
+        graphite_net::packet_helper::try_write_packet(&mut proto_player.write_buffer, &time_packet);
+
         // World Border
         if W::SHOW_DEFAULT_WORLD_BORDER {
             let border_packet = self.make_default_world_border(chunk_z, chunk_x);
@@ -729,11 +1037,8 @@ impl<W: WorldService> World<W> {
             time as f32 / destroy_ticks
         };
 
-        let destroy_stage = if break_progress > 1.0 {
-            8
-        } else {
-            (break_progress * 10.0 - 1.0).floor() as i8
-        };
+        // Stages go from 0 (just started) to 9 (almost broken)
+        let destroy_stage = (break_progress * 10.0).floor().clamp(0.0, 9.0) as i8;
 
         Some(destroy_stage)
     }
@@ -747,11 +1052,258 @@ impl<W: WorldService> World<W> {
         &self.chunks
     }
 
+    /// Saves the chunks whose blocks changed every `interval` ticks
+    ///
+    /// The changed chunks are copied at the end of the tick and passed to `save` on a background
+    /// thread, so writing them (eg. to a magma file) doesn't block the tick loop. Disabling autosave
+    /// or dropping the world waits for pending saves to finish
+    pub fn enable_autosave<F>(&mut self, interval: u64, save: F)
+    where
+        F: FnMut(Vec<ChunkSnapshot>) + Send + 'static,
+    {
+        self.autosave = Some(Autosave::new(interval, save));
+    }
+
+    pub fn disable_autosave(&mut self) {
+        self.autosave = None;
+    }
+
+    fn autosave_chunks(&mut self) {
+        let Some(autosave) = &mut self.autosave else {
+            return;
+        };
+
+        let mut snapshots = Vec::new();
+        for (chunk_x, chunk_z, chunk) in self.chunks.enumerate_mut() {
+            if chunk.is_dirty() {
+                snapshots.push(ChunkSnapshot::new(chunk_x, chunk_z, chunk));
+                chunk.clear_dirty();
+            }
+        }
+
+        if !snapshots.is_empty() {
+            autosave.save(snapshots);
+        }
+    }
+
+    /// Enables sharing the serialized data of chunks with identical contents,
+    /// see `ChunkPayloadCache`
+    pub fn enable_chunk_payload_cache(&mut self) {
+        self.chunks.enable_payload_cache();
+    }
+
+    /// Sets the block and notifies its neighbors, see `notify_neighbors`
+    ///
+    /// Returns the previous block, or `None` if the position is out of bounds or the block didn't change
     pub fn set_block_i32(&mut self, x: i32, y: i32, z: i32, block: u16) -> Option<u16> {
         if x < 0 || y < 0 || z < 0 {
             return None;
         }
-        self.set_block(x as _, y as _, z as _, block)
+        let old = self.set_block(x as _, y as _, z as _, block)?;
+        self.notify_neighbors(BlockPosition { x, y, z });
+        Some(old)
+    }
+
+    /// Updates the shape of the blocks surrounding `pos`, eg. fences connecting to a newly placed block
+    ///
+    /// Blocks that change notify their own neighbors in turn, up to `MAX_NEIGHBOR_UPDATE_DEPTH`
+    /// blocks away from `pos`. The changes are sent to players batched per chunk section
+    pub fn notify_neighbors(&mut self, pos: BlockPosition) {
+        self.notify_neighbors_of_all(&[pos]);
+    }
+
+    /// Same as `notify_neighbors`, but for many positions at once, eg. every block changed by `fill`
+    fn notify_neighbors_of_all(&mut self, positions: &[BlockPosition]) {
+        let mut changed: Vec<(i32, i32, i32)> = Vec::new();
+        let mut visited: HashSet<(i32, i32, i32)> = HashSet::new();
+        let mut queue: VecDeque<_> = positions.iter().map(|pos| (pos.x, pos.y, pos.z, 0)).collect();
+
+        while let Some((x, y, z, depth)) = queue.pop_front() {
+            for (offset_x, offset_y, offset_z) in NEIGHBOR_OFFSETS {
+                let x = x + offset_x;
+                let y = y + offset_y;
+                let z = z + offset_z;
+
+                let Some(block_state_id) = self.get_block_i32(x, y, z) else {
+                    continue;
+                };
+                let Ok(block) = <&Block>::try_from(block_state_id) else {
+                    continue;
+                };
+
+                let mut block = block.clone();
+                if !block_update::update(block_state_id, &mut block, x, y, z, self) {
+                    continue;
+                }
+
+                let chunk_x = x as usize / Chunk::SECTION_BLOCK_WIDTH_I;
+                let chunk_z = z as usize / Chunk::SECTION_BLOCK_WIDTH_I;
+                let Some(chunk) = self.chunks.get_mut(chunk_x, chunk_z) else {
+                    continue;
+                };
+                if chunk.set_block_silently(x as _, y as _, z as _, block.to_id()).is_none() {
+                    continue;
+                }
+
+                // Positions are queued in order of depth, so the first change is the closest to the source
+                if visited.insert((x, y, z)) {
+                    changed.push((x, y, z));
+                    if depth + 1 < MAX_NEIGHBOR_UPDATE_DEPTH {
+                        queue.push_back((x, y, z, depth + 1));
+                    }
+                }
+            }
+        }
+
+        // Group the changes by chunk section so that they can be sent using a single packet
+        let mut sections: BTreeMap<(usize, usize, usize), Vec<BlockPosition>> = BTreeMap::new();
+        for (x, y, z) in changed {
+            let section = (
+                x as usize / Chunk::SECTION_BLOCK_WIDTH_I,
+                y as usize / Chunk::SECTION_BLOCK_WIDTH_I,
+                z as usize / Chunk::SECTION_BLOCK_WIDTH_I,
+            );
+            sections.entry(section).or_default().push(BlockPosition { x, y, z });
+        }
+
+        for ((chunk_x, _, chunk_z), positions) in sections {
+            if let Some(chunk) = self.chunks.get_mut(chunk_x, chunk_z) {
+                chunk.write_block_changes(&positions);
+            }
+        }
+    }
+
+    /// Sets every block in the box between the two corners (inclusive)
+    ///
+    /// Neighbors of the changed blocks are notified, same as `set_block_i32`
+    ///
+    /// Returns the number of blocks that changed, or `None` if part of the box is out of bounds
+    pub fn fill(&mut self, from: BlockPosition, to: BlockPosition, block: u16) -> Option<usize> {
+        let (min, max) = Self::get_box_corners(from, to);
+        self.get_block_i32(min.x, min.y, min.z)?;
+        self.get_block_i32(max.x, max.y, max.z)?;
+
+        let mut blocks = Vec::new();
+        for x in min.x..=max.x {
+            for y in min.y..=max.y {
+                for z in min.z..=max.z {
+                    blocks.push((BlockPosition { x, y, z }, block));
+                }
+            }
+        }
+        Some(self.set_blocks_batched(blocks))
+    }
+
+    /// Copies the blocks in the box between the two corners (inclusive), so that the lowest corner
+    /// of the box ends up at `destination`. The source and destination are allowed to overlap
+    ///
+    /// Neighbors of the changed blocks are notified, same as `set_block_i32`. Block entities, eg.
+    /// the items in a chest, are copied along with their blocks
+    ///
+    /// Returns the number of blocks that changed, or `None` if part of either box is out of bounds
+    pub fn clone_blocks(&mut self, from: BlockPosition, to: BlockPosition, destination: BlockPosition) -> Option<usize> {
+        let (min, max) = Self::get_box_corners(from, to);
+        self.get_block_i32(min.x, min.y, min.z)?;
+        self.get_block_i32(max.x, max.y, max.z)?;
+        self.get_block_i32(destination.x, destination.y, destination.z)?;
+        self.get_block_i32(
+            destination.x + max.x - min.x,
+            destination.y + max.y - min.y,
+            destination.z + max.z - min.z,
+        )?;
+
+        // Every block is read before any are written, in case the boxes overlap
+        let mut blocks = Vec::new();
+        let mut block_entities = Vec::new();
+        for x in min.x..=max.x {
+            for y in min.y..=max.y {
+                for z in min.z..=max.z {
+                    let block = self.get_block_i32(x, y, z)?;
+                    let pos = BlockPosition {
+                        x: destination.x + x - min.x,
+                        y: destination.y + y - min.y,
+                        z: destination.z + z - min.z,
+                    };
+                    blocks.push((pos, block));
+
+                    let block_entity = self
+                        .get_block_entity(BlockPosition { x, y, z })
+                        .map(|block_entity| (block_entity.block_entity_type, block_entity.nbt.clone()));
+                    block_entities.push((pos, block, block_entity));
+                }
+            }
+        }
+        let changed = self.set_blocks_batched(blocks);
+
+        // Block entities are copied after the blocks have changed, otherwise replacing the block
+        // would remove them again
+        for (pos, block, block_entity) in block_entities {
+            let chunk_x = Chunk::to_chunk_coordinate(pos.x as _);
+            let chunk_z = Chunk::to_chunk_coordinate(pos.z as _);
+            let chunk = self.chunks.get_mut(chunk_x as _, chunk_z as _).expect("block exists");
+
+            let is_chest = <&Block>::try_from(block).is_ok_and(chest::is_chest);
+            match block_entity {
+                Some((block_entity_type, nbt)) if is_chest => {
+                    chunk.set_block_entity_silently(pos.x as _, pos.y as _, pos.z as _, block_entity_type, nbt);
+                }
+                Some((block_entity_type, nbt)) => {
+                    chunk.set_block_entity(pos.x as _, pos.y as _, pos.z as _, block_entity_type, nbt);
+                }
+                None => chunk.remove_block_entity(pos.x as _, pos.y as _, pos.z as _),
+            }
+        }
+
+        Some(changed)
+    }
+
+    fn get_box_corners(from: BlockPosition, to: BlockPosition) -> (BlockPosition, BlockPosition) {
+        let min = BlockPosition {
+            x: from.x.min(to.x),
+            y: from.y.min(to.y),
+            z: from.z.min(to.z),
+        };
+        let max = BlockPosition {
+            x: from.x.max(to.x),
+            y: from.y.max(to.y),
+            z: from.z.max(to.z),
+        };
+        (min, max)
+    }
+
+    /// Sets the blocks and notifies their neighbors, sending the changes batched per chunk section
+    fn set_blocks_batched(&mut self, blocks: Vec<(BlockPosition, u16)>) -> usize {
+        let mut sections: BTreeMap<(usize, usize, usize), Vec<BlockPosition>> = BTreeMap::new();
+        for (pos, block) in blocks {
+            if pos.x < 0 || pos.y < 0 || pos.z < 0 {
+                continue;
+            }
+
+            let chunk_x = pos.x as usize / Chunk::SECTION_BLOCK_WIDTH_I;
+            let chunk_z = pos.z as usize / Chunk::SECTION_BLOCK_WIDTH_I;
+            let Some(chunk) = self.chunks.get_mut(chunk_x, chunk_z) else {
+                continue;
+            };
+            if chunk.set_block_silently(pos.x as _, pos.y as _, pos.z as _, block).is_none() {
+                continue;
+            }
+
+            let section = (chunk_x, pos.y as usize / Chunk::SECTION_BLOCK_WIDTH_I, chunk_z);
+            sections.entry(section).or_default().push(pos);
+        }
+
+        let mut changed = Vec::new();
+        for ((chunk_x, _, chunk_z), positions) in sections {
+            if let Some(chunk) = self.chunks.get_mut(chunk_x, chunk_z) {
+                chunk.write_block_changes(&positions);
+            }
+            changed.extend(positions);
+        }
+
+        // Neighbors are notified after every block has been set, so that blocks inside the box
+        // are updated using their final neighbors
+        self.notify_neighbors_of_all(&changed);
+        changed.len()
     }
 
     pub fn get_block_i32(&self, x: i32, y: i32, z: i32) -> Option<u16> {
@@ -761,6 +1313,260 @@ impl<W: WorldService> World<W> {
         self.get_block(x as _, y as _, z as _)
     }
 
+    /// Sets the block to air, playing the block break particles and sound if a block was removed
+    ///
+    /// Returns the block that was broken
+    pub fn break_block(&mut self, pos: BlockPosition) -> Option<u16> {
+        // A chest drops the items stored in its own half of the container
+        let is_chest = self
+            .get_block_i32(pos.x, pos.y, pos.z)
+            .and_then(|id| <&Block>::try_from(id).ok())
+            .is_some_and(chest::is_chest);
+        let dropped_items = match self.get_block_entity(pos) {
+            Some(block_entity) if is_chest => chest::read_items(&block_entity.nbt),
+            _ => Vec::new(),
+        };
+
+        let old = self.set_block_i32(pos.x, pos.y as _, pos.z, 0)?;
+        self.play_level_event(LevelEventType::ParticlesDestroyBlock, pos, old as _, false);
+
+        let coord = Coordinate {
+            x: pos.x as f32 + 0.5,
+            y: pos.y as f32 + 0.5,
+            z: pos.z as f32 + 0.5,
+        };
+        for itemslot in dropped_items {
+            if let ItemSlot::Filled(itemstack) = itemslot {
+                let entity_id = self.get_universe().new_entity_id();
+                self.push_entity((), coord, DroppedItem { entity_id, itemstack }, entity_id);
+            }
+        }
+
+        Some(old)
+    }
+
+    /// Plays an effect/sound at the position for players in view distance
+    ///
+    /// If `global` is true, the event is sent to every player in the world regardless of distance.
+    /// Vanilla only uses this for the wither spawn, dragon death and end portal opening sounds
+    pub fn play_level_event(&mut self, event_type: LevelEventType, pos: BlockPosition, data: i32, global: bool) {
+        let packet = LevelEvent {
+            event_type,
+            pos,
+            data,
+            global,
+        };
+
+        if global {
+            graphite_net::packet_helper::try_write_packet(&mut self.global_write_buffer, &packet);
+            return;
+        }
+
+        let chunk_x = Chunk::to_chunk_coordinate(pos.x as _);
+        let chunk_z = Chunk::to_chunk_coordinate(pos.z as _);
+        if !self.chunk_coords_in_bounds(chunk_x, chunk_z) {
+            return;
+        }
+
+        let chunk = self.chunks.get_mut(chunk_x as _, chunk_z as _).expect("chunk coords in bounds");
+        graphite_net::packet_helper::try_write_packet(&mut chunk.entity_viewable_buffer, &packet);
+    }
+
+    /// Sends a system message to the chat box of every player in the world
+    pub fn broadcast_system_message<T: Into<TextComponent>>(&mut self, message: T) {
+        let message = message.into();
+        let packet = SystemChat {
+            message: message.to_json(),
+            overlay: false,
+        };
+        graphite_net::packet_helper::try_write_packet(&mut self.global_write_buffer, &packet);
+    }
+
+    /// Sets the text on the sign at `pos`, sending it to players in view distance
+    ///
+    /// Returns false if there is no sign at the position
+    pub fn set_sign_text(&mut self, pos: BlockPosition, lines: [TextComponent; 4]) -> bool {
+        let is_sign = self
+            .get_block_i32(pos.x, pos.y, pos.z)
+            .and_then(|id| <&Block>::try_from(id).ok())
+            .is_some_and(sign::is_sign);
+        if !is_sign {
+            return false;
+        }
+
+        let chunk_x = Chunk::to_chunk_coordinate(pos.x as _);
+        let chunk_z = Chunk::to_chunk_coordinate(pos.z as _);
+        let chunk = self.chunks.get_mut(chunk_x as _, chunk_z as _).expect("block exists");
+        chunk.set_block_entity(
+            pos.x as _,
+            pos.y as _,
+            pos.z as _,
+            sign::SIGN_BLOCK_ENTITY_TYPE,
+            sign::write_lines(&lines),
+        );
+        true
+    }
+
+    /// Returns the text on the sign at `pos`, or None if the sign has no text
+    pub fn get_sign_text(&self, pos: BlockPosition) -> Option<[TextComponent; 4]> {
+        let block_entity = self.get_block_entity(pos)?;
+        sign::read_lines(&block_entity.nbt)
+    }
+
+    fn get_block_entity(&self, pos: BlockPosition) -> Option<&BlockEntity> {
+        if pos.x < 0 || pos.y < 0 || pos.z < 0 {
+            return None;
+        }
+
+        let chunk_x = Chunk::to_chunk_coordinate(pos.x as _);
+        let chunk_z = Chunk::to_chunk_coordinate(pos.z as _);
+        let chunk = self.chunks.get(chunk_x as _, chunk_z as _)?;
+        chunk.get_block_entity(pos.x as _, pos.y as _, pos.z as _)
+    }
+
+    /// Returns the positions of the block entities backing the chest at `pos`, in slot order
+    ///
+    /// A double chest is backed by the block entities of both halves, regardless of which half
+    /// was opened. Returns None if there is no chest at the position
+    pub fn get_chest_container(&self, pos: BlockPosition) -> Option<Vec<BlockPosition>> {
+        let halves = chest::get_chest_halves(pos.x, pos.y, pos.z, self)?;
+        Some(halves.into_iter().map(|(x, y, z)| BlockPosition { x, y, z }).collect())
+    }
+
+    /// Returns the number of slots in the chest at `pos`, 54 for a double chest
+    pub fn get_chest_size(&self, pos: BlockPosition) -> Option<usize> {
+        self.get_chest_container(pos).map(|halves| halves.len() * chest::CHEST_SLOTS)
+    }
+
+    /// Returns the items in the chest at `pos`, in slot order
+    ///
+    /// A double chest returns the items of both halves, regardless of which half is at `pos`.
+    /// Returns None if there is no chest at the position
+    pub fn get_chest_items(&self, pos: BlockPosition) -> Option<Vec<ItemSlot>> {
+        let halves = self.get_chest_container(pos)?;
+
+        let mut items = Vec::with_capacity(halves.len() * chest::CHEST_SLOTS);
+        for half in halves {
+            match self.get_block_entity(half) {
+                Some(block_entity) => items.extend(chest::read_items(&block_entity.nbt)),
+                None => items.resize(items.len() + chest::CHEST_SLOTS, ItemSlot::Empty),
+            }
+        }
+        Some(items)
+    }
+
+    /// Sets a slot of the chest at `pos`, see `get_chest_items` for the slot order
+    ///
+    /// The item is stored in the block entity of the half that holds the slot. Returns false
+    /// if there is no chest at the position or the slot is out of bounds
+    pub fn set_chest_item(&mut self, pos: BlockPosition, slot: usize, itemslot: ItemSlot) -> bool {
+        let Some(halves) = self.get_chest_container(pos) else {
+            return false;
+        };
+        let Some(half) = halves.get(slot / chest::CHEST_SLOTS).copied() else {
+            return false;
+        };
+
+        let Some(block_entity_type) = self
+            .get_block_i32(half.x, half.y, half.z)
+            .and_then(|id| <&Block>::try_from(id).ok())
+            .and_then(chest::get_block_entity_type) else {
+            return false;
+        };
+
+        let mut items = match self.get_block_entity(half) {
+            Some(block_entity) => chest::read_items(&block_entity.nbt),
+            None => vec![ItemSlot::Empty; chest::CHEST_SLOTS],
+        };
+        items[slot % chest::CHEST_SLOTS] = itemslot;
+
+        let chunk_x = Chunk::to_chunk_coordinate(half.x as _);
+        let chunk_z = Chunk::to_chunk_coordinate(half.z as _);
+        let chunk = self.chunks.get_mut(chunk_x as _, chunk_z as _).expect("block exists");
+        chunk.set_block_entity_silently(
+            half.x as _,
+            half.y as _,
+            half.z as _,
+            block_entity_type,
+            chest::write_items(&items),
+        );
+        true
+    }
+
+    pub fn get_team(&self, name: &str) -> Option<&Team> {
+        self.teams.iter().find(|team| team.name() == name)
+    }
+
+    /// Returns the team that `entry` (a player username or entity UUID) is on
+    pub fn get_team_of(&self, entry: &str) -> Option<&Team> {
+        self.teams.iter().find(|team| team.has_entry(entry))
+    }
+
+    /// Adds the team to the world, sending it to every player
+    ///
+    /// Returns false if a team with the same name already exists
+    pub fn add_team(&mut self, team: Team) -> bool {
+        if self.get_team(team.name()).is_some() {
+            return false;
+        }
+
+        team.write_create_packet(&mut self.global_write_buffer);
+        self.teams.push(team);
+        true
+    }
+
+    pub fn remove_team(&mut self, name: &str) -> Option<Team> {
+        let index = self.teams.iter().position(|team| team.name() == name)?;
+        let team = self.teams.remove(index);
+        team.write_remove_packet(&mut self.global_write_buffer);
+        Some(team)
+    }
+
+    /// Modifies the display properties (color, prefix, collision rule, etc.) of a team
+    ///
+    /// Returns false if the team doesn't exist
+    pub fn update_team(&mut self, name: &str, func: impl FnOnce(&mut Team)) -> bool {
+        if let Some(team) = self.teams.iter_mut().find(|team| team.name() == name) {
+            func(team);
+            team.write_update_packet(&mut self.global_write_buffer);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Adds `entry` (a player username or entity UUID) to the team, removing it from its previous team
+    ///
+    /// Returns false if the team doesn't exist
+    pub fn add_team_entry(&mut self, name: &str, entry: &str) -> bool {
+        if self.get_team(name).is_none() {
+            return false;
+        }
+
+        // An entry can only be on one team, the client removes it from the old team automatically
+        for team in &mut self.teams {
+            if team.name() != name {
+                team.remove_entry(entry);
+            } else if team.add_entry(entry) {
+                team.write_entries_packet(&mut self.global_write_buffer, vec![entry], true);
+            }
+        }
+        true
+    }
+
+    /// Removes `entry` from the team
+    ///
+    /// Returns false if the entry wasn't on the team
+    pub fn remove_team_entry(&mut self, name: &str, entry: &str) -> bool {
+        if let Some(team) = self.teams.iter_mut().find(|team| team.name() == name) {
+            if team.remove_entry(entry) {
+                team.write_entries_packet(&mut self.global_write_buffer, vec![entry], false);
+                return true;
+            }
+        }
+        false
+    }
+
     pub fn initialize(&mut self) {
         self.update_pointer();
     }
@@ -1,15 +1,18 @@
 use graphite_binary::slice_serialization::SliceSerializable;
 use graphite_net::{
     network_buffer::WriteBuffer,
-    network_handler::ConnectionSlab,
+    network_handler::{ConnectionService, ConnectionSlab, UninitializedConnection},
     packet_helper::{self, PacketReadResult},
 };
-use graphite_mc_protocol::{play, IdentifiedPacket};
+use graphite_mc_protocol::{play, types::GameProfile, IdentifiedPacket};
 use graphite_server::{
-    player::{player_connection::AbstractConnectionReference, Player, PlayerService},
+    player::{
+        player_connection::{AbstractConnectionReference, PlayerConnection},
+        Player, PlayerService,
+    },
     universe::Universe,
 };
-use std::fmt::Debug;
+use std::{fmt::Debug, io::Write, os::unix::net::UnixStream, sync::mpsc::Sender};
 
 use crate::log;
 
@@ -20,6 +23,8 @@ pub struct FakePlayerConnection {
     handle_disconnect: fn(*mut ()),
     incoming_bytes: WriteBuffer,
     pub outgoing_bytes: WriteBuffer,
+    /// Client side of the socket given to another universe, see `redirect_to_universe`
+    pub redirected: Option<UnixStream>,
 }
 
 impl FakePlayerConnection {
@@ -29,6 +34,7 @@ impl FakePlayerConnection {
             handle_disconnect: unsafe { std::mem::transmute(std::ptr::null_mut() as *mut ()) },
             incoming_bytes: WriteBuffer::new(),
             outgoing_bytes: WriteBuffer::new(),
+            redirected: None,
         }
     }
 
@@ -81,6 +87,18 @@ impl FakePlayerConnection {
         }
     }
 
+    pub fn skip_outgoing_until(&mut self, packet_id: u8) {
+        loop {
+            let mut bytes = self.outgoing_bytes.get_written();
+            match packet_helper::try_read_packet(&mut bytes).expect("invalid packet was sent to player") {
+                PacketReadResult::Complete(packet_bytes) if packet_bytes[0] == packet_id => return,
+                PacketReadResult::Complete(packet_bytes) => self.skip_outgoing(packet_bytes[0]),
+                PacketReadResult::Partial => panic!("packet was only partially written"),
+                PacketReadResult::Empty => panic!("expected a packet, but there was none"),
+            }
+        }
+    }
+
     pub fn assert_outgoing_as<'a, T, F>(&mut self, func: F)
     where
         T: Debug + SliceSerializable<'a, T> + IdentifiedPacket<play::server::PacketId> + 'a,
@@ -148,8 +166,22 @@ impl FakePlayerConnection {
         T: SliceSerializable<'a, T> + IdentifiedPacket<play::client::PacketId> + 'a,
     {
         packet_helper::write_packet(&mut self.incoming_bytes, packet).unwrap();
+        self.handle_incoming()
+    }
+
+    /// Writes the bytes of a packet (starting with the packet id) without serializing them,
+    /// allowing malformed packets to be sent
+    pub fn write_packet_bytes(&mut self, packet_bytes: &[u8]) -> anyhow::Result<()> {
+        assert!(packet_bytes.len() < 128, "packet length must fit in a single byte varint");
+        self.incoming_bytes.copy_from(&[packet_bytes.len() as u8]);
+        self.incoming_bytes.copy_from(packet_bytes);
+        self.handle_incoming()
+    }
 
-        let bytes_remaining = Player::handle_packets(unsafe { &mut *self.player })?;
+    fn handle_incoming(&mut self) -> anyhow::Result<()> {
+        let result = Player::handle_packets(unsafe { &mut *self.player });
+        self.incoming_bytes.clear();
+        let bytes_remaining = result?;
         assert_eq!(bytes_remaining, 0); // Player must have handled the entire packet
         Ok(())
     }
@@ -183,6 +215,25 @@ impl AbstractConnectionReference<DummyUniverseService> for *mut FakePlayerConnec
         panic!("Cannot create a fake player from a real connection")
     }
 
+    fn redirect_to_universe(
+        &mut self,
+        sender: Sender<(UninitializedConnection, GameProfile)>,
+        profile: GameProfile,
+    ) {
+        let conn = unsafe { &mut **self };
+        let (mut client, server) = UnixStream::pair().expect("unable to create socket pair");
+
+        // Bytes that haven't been handled yet are received by the other universe instead
+        client.write_all(conn.incoming_bytes.get_written()).unwrap();
+        conn.incoming_bytes.clear();
+
+        let buffer_size = PlayerConnection::<DummyUniverseService>::BUFFER_SIZE as usize;
+        let _ = sender.send((UninitializedConnection::from_stream(server, buffer_size), profile));
+
+        conn.player = std::ptr::null_mut();
+        conn.redirected = Some(client);
+    }
+
     unsafe fn forget(&mut self) {
         // noop
     }
@@ -2,12 +2,16 @@ use super::FakePlayerConnection;
 use graphite_mc_protocol::types::GameProfile;
 use graphite_server::{
     entity::position::{Coordinate, Position, Rotation},
+    error::PacketHandleError,
     inventory::inventory_handler::VanillaPlayerInventory,
-    player::{player_vec::PlayerVec, PlayerService},
+    player::{
+        player_connection::ConnectionReference, player_vec::PlayerVec, HandleAction, Player,
+        PlayerService,
+    },
     universe::{Universe, UniverseService},
-    world::{TickPhase, World, WorldService}, UniverseTicker, ticker::*, WorldTicker,
+    world::{chunk::{BlockStorage, Chunk}, TickPhase, World, WorldService}, UniverseTicker, ticker::*, WorldTicker,
 };
-use std::pin::Pin;
+use std::{pin::Pin, sync::mpsc::Sender};
 
 pub fn create_game_profile() -> GameProfile {
     GameProfile {
@@ -28,9 +32,27 @@ pub fn create_universe_and_player() -> (
 
 pub fn create_player(
     universe: &mut Universe<DummyUniverseService>,
+) -> Pin<Box<FakePlayerConnection>> {
+    create_player_with_profile(universe, create_game_profile())
+}
+
+/// Creates a player with a different UUID, players with the same UUID replace each other
+pub fn create_other_player(
+    universe: &mut Universe<DummyUniverseService>,
+    uuid: u128,
+) -> Pin<Box<FakePlayerConnection>> {
+    let mut profile = create_game_profile();
+    profile.username = format!("Other{}", uuid);
+    profile.uuid = uuid;
+    create_player_with_profile(universe, profile)
+}
+
+pub fn create_player_with_profile(
+    universe: &mut Universe<DummyUniverseService>,
+    profile: GameProfile,
 ) -> Pin<Box<FakePlayerConnection>> {
     let mut conn = Box::from(FakePlayerConnection::new());
-    universe.handle_player_connect(conn.as_mut(), create_game_profile());
+    universe.handle_player_connect(conn.as_mut(), profile);
     Pin::from(conn)
 }
 
@@ -39,6 +61,12 @@ pub fn create_universe() -> Pin<Box<Universe<DummyUniverseService>>> {
         the_world: World::new_with_default_chunks(DummyWorldService {
             players: PlayerVec::new(),
         }, 5, 24, 5),
+        other_world: World::new_with_default_chunks(DummyWorldService {
+            players: PlayerVec::new(),
+        }, 5, 24, 5),
+        wide_world: World::new_with_default_chunks(WideWorldService {
+            players: PlayerVec::new(),
+        }, 13, 24, 13),
     };
 
     let mut pinned = Box::pin(Universe::create_dummy(service));
@@ -50,6 +78,10 @@ pub fn create_universe() -> Pin<Box<Universe<DummyUniverseService>>> {
 #[derive(UniverseTicker)]
 pub struct DummyUniverseService {
     pub the_world: World<DummyWorldService>,
+    /// Players only join `the_world`, they have to be transferred into this world
+    pub other_world: World<DummyWorldService>,
+    /// Entities are visible from further away in this world, players have to be transferred into it
+    pub wide_world: World<WideWorldService>,
 }
 
 impl UniverseService for DummyUniverseService {
@@ -63,6 +95,24 @@ impl UniverseService for DummyUniverseService {
     }
 }
 
+// Started on another thread using `Universe::create_and_start`, the profile of each player that
+// joins is sent back to the test
+#[derive(UniverseTicker)]
+pub struct ThreadedUniverseService {
+    pub joined: Sender<GameProfile>,
+}
+
+impl UniverseService for ThreadedUniverseService {
+    type ConnectionReferenceType = ConnectionReference<Self>;
+
+    fn handle_player_join(
+        universe: &mut Universe<Self>,
+        proto_player: graphite_server::player::proto_player::ProtoPlayer<Self>,
+    ) {
+        let _ = universe.service.joined.send(proto_player.profile.clone());
+    }
+}
+
 #[derive(WorldTicker)]
 pub struct DummyWorldService {
     pub players: PlayerVec<DummyPlayerService>,
@@ -84,7 +134,7 @@ impl WorldService for DummyWorldService {
             .players
             .add(
                 proto_player,
-                DummyPlayerService,
+                DummyPlayerService::default(),
                 Position {
                     coord: Coordinate {
                         x: 40.0,
@@ -98,10 +148,95 @@ impl WorldService for DummyWorldService {
     }
 }
 
-pub struct DummyPlayerService;
+// Shows entities in the whole world, so that their tracking ranges aren't limited by the view distance
+#[derive(WorldTicker)]
+pub struct WideWorldService {
+    pub players: PlayerVec<WidePlayerService>,
+}
+
+impl WorldService for WideWorldService {
+    type UniverseServiceType = DummyUniverseService;
+    type ParentWorldServiceType = Self;
+
+    const CHUNK_VIEW_DISTANCE: u8 = 8;
+    const ENTITY_VIEW_DISTANCE: u8 = 8;
+
+    fn handle_player_join(
+        _world: &mut World<Self>,
+        _proto_player: graphite_server::player::proto_player::ProtoPlayer<Self::UniverseServiceType>,
+    ) {
+        unimplemented!()
+    }
+}
+
+// Generates chunks with a bedrock floor, dirt and a layer of grass on top
+#[derive(WorldTicker)]
+pub struct FlatWorldService;
+
+impl FlatWorldService {
+    pub const BEDROCK: u16 = 79;
+    pub const DIRT: u16 = 10;
+    pub const GRASS_BLOCK: u16 = 9;
+}
+
+impl WorldService for FlatWorldService {
+    type UniverseServiceType = DummyUniverseService;
+    type ParentWorldServiceType = Self;
+
+    const CHUNK_VIEW_DISTANCE: u8 = 8;
+    const ENTITY_VIEW_DISTANCE: u8 = 1;
+
+    fn handle_player_join(
+        _world: &mut World<Self>,
+        _proto_player: graphite_server::player::proto_player::ProtoPlayer<Self::UniverseServiceType>,
+    ) {
+        unimplemented!()
+    }
+
+    fn generate_chunk(&mut self, _chunk_x: usize, _chunk_z: usize, size_y: usize) -> Chunk {
+        let mut chunk = Chunk::new_empty(size_y);
+        for x in 0..16 {
+            for z in 0..16 {
+                chunk.set_block(x, 0, z, Self::BEDROCK);
+                chunk.set_block(x, 1, z, Self::DIRT);
+                chunk.set_block(x, 2, z, Self::DIRT);
+                chunk.set_block(x, 3, z, Self::GRASS_BLOCK);
+            }
+        }
+        chunk
+    }
+}
+
+#[derive(Default)]
+pub struct DummyPlayerService {
+    pub ignore_packet_errors: bool,
+}
+
 impl PlayerService for DummyPlayerService {
     const FAST_PACKET_RESPONSE: bool = true;
+    const KEEP_ALIVE_INTERVAL_TICKS: u32 = 100;
+    const KEEP_ALIVE_TIMEOUT_TICKS: u32 = 150;
     type UniverseServiceType = DummyUniverseService;
     type WorldServiceType = DummyWorldService;
     type InventoryHandlerType = VanillaPlayerInventory;
+
+    fn on_packet_error(player: &mut Player<Self>, _error: &PacketHandleError) -> HandleAction {
+        if player.service.ignore_packet_errors {
+            HandleAction::Continue
+        } else {
+            HandleAction::Disconnect
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct WidePlayerService;
+
+impl PlayerService for WidePlayerService {
+    const FAST_PACKET_RESPONSE: bool = true;
+    const KEEP_ALIVE_INTERVAL_TICKS: u32 = 100;
+    const KEEP_ALIVE_TIMEOUT_TICKS: u32 = 150;
+    type UniverseServiceType = DummyUniverseService;
+    type WorldServiceType = WideWorldService;
+    type InventoryHandlerType = VanillaPlayerInventory;
 }
@@ -1,11 +1,47 @@
-use common::DummyUniverseService;
-use graphite_mc_protocol::play::server::*;
+use common::{DummyUniverseService, FlatWorldService, ThreadedUniverseService};
+use graphite_mc_constants::{
+    block::Block,
+    entity::Entity as EntityType,
+    block_parameter::{ChestType, Direction, Part, WallConnection, WireConnection},
+    item::Item,
+};
+use graphite_mc_protocol::{
+    play::{client, server::*},
+    types::{
+        BlockHitResult, BlockPosition, ClickType, Direction as ProtocolDirection, Hand, HandAction,
+        MoveAction, Pose, ProtocolItemStack,
+    },
+};
+use graphite_binary::nbt::NBTNode;
+use graphite_text_component::TextComponent;
+use graphite_net::{
+    network_buffer::WriteBuffer,
+    packet_helper::{self, PacketReadResult},
+};
+use graphite_command::{brigadier, types::{CommandDispatchResult, CommandResult}};
 use graphite_server::{
+    commands,
     entity::{
+        attributes::{Attribute, AttributeModifier},
         components::{BasicEntity, Viewable},
-        position::Coordinate,
+        position::{Coordinate, Position, Rotation},
+    },
+    error::PacketHandleError,
+    game_event::GameEventKind,
+    gamemode::GameMode,
+    inventory::{
+        inventory_handler::{InventoryHandler, InventorySlot, ItemSlot},
+        itemstack::ItemStack,
+    },
+    player::{Experience, Player, PlayerService},
+    universe::{DuplicateLoginPolicy, EntityId, Universe}, ticker::UniverseTicker,
+    world::{
+        chunk::BlockStorage,
+        chunk_list::ChunkGrid,
+        game_rules::GameRuleError,
+        team::{CollisionRule, Team},
+        World, WorldService,
     },
-    universe::{EntityId, Universe, UniverseService}, ticker::UniverseTicker,
 };
 
 mod common;
@@ -22,6 +58,10 @@ fn player_join() {
     log!("Skipping login packet...");
     conn.skip_outgoing(PacketId::Login as u8);
 
+    // UpdateTags -- Contains every block tag
+    log!("Skipping tags packet...");
+    conn.skip_outgoing(PacketId::UpdateTags as u8);
+
     // Custom Payload
     log!("Checking custom payload packet...");
     conn.assert_outgoing(&CustomPayload {
@@ -34,7 +74,7 @@ fn player_join() {
     conn.assert_outgoing(&PlayerInfo::AddPlayer {
         values: vec![PlayerInfoAddPlayer {
             profile: common::create_game_profile(),
-            gamemode: 1,
+            gamemode: 0,
             ping: 0,
             display_name: None,
             signature_data: None,
@@ -43,7 +83,8 @@ fn player_join() {
 
     // LevelChunkWithLight -- Can't read NBT yet
     log!("Checking chunk packets...");
-    for _ in 0..(17 * 17) {
+    // Every chunk of the 5x5 world is within view distance, as well as the empty chunks around it
+    for _ in 0..(5 * 5 + 4 * 5) {
         conn.skip_outgoing(PacketId::LevelChunkWithLight as u8);
     }
 
@@ -67,6 +108,10 @@ fn player_join() {
         dismount_vehicle: false,
     });
 
+    // SetTime
+    log!("Skipping SetTime packet...");
+    conn.skip_outgoing(PacketId::SetTime as u8);
+
     // No more packets
     log!("Checking for no more packets...");
     conn.assert_none_outgoing();
@@ -165,6 +210,41 @@ fn spawn_entity_far() {
     conn.assert_none_outgoing(); // No more packets
 }
 
+// Does the following:
+//  a. Spawn an entity inside the chunk view distance, but outside the entity view distance
+//  b. Change a block in the same chunk as the entity
+// Checks to see that:
+//  1. Player receives the BlockUpdate
+//  2. Player *DOES NOT* receive AddEntity
+#[test]
+fn entity_view_distance_smaller_than_chunk() {
+    let (mut universe, mut conn) = common::create_universe_and_player();
+
+    // Skip joining packets
+    conn.skip_all_outgoing();
+
+    // (a) Spawn an entity inside the chunk view distance, but outside the entity view distance
+    let entity_position = Coordinate {
+        x: 65.0, // 2 chunks away
+        y: 224.0,
+        z: 31.0,
+    };
+    spawn_entity_at(&mut universe, entity_position);
+
+    // (b) Change a block in the same chunk as the entity
+    universe.service.the_world.set_block_i32(65, 0, 31, 0);
+
+    // (1) Player receives the BlockUpdate
+    universe.service.tick();
+    conn.assert_outgoing(&BlockUpdate {
+        pos: BlockPosition { x: 65, y: 0, z: 31 },
+        block_state: 0,
+    });
+
+    // (2) Player *DOES NOT* receive AddEntity
+    conn.assert_none_outgoing();
+}
+
 // Does the following:
 //  a. Spawn an entity
 //  b. Connect a player at a nearby location
@@ -191,12 +271,19 @@ fn spawn_entity_before() {
 
     // Skip login packets
     conn.skip_outgoing(PacketId::Login as u8);
+    conn.skip_outgoing(PacketId::UpdateTags as u8);
     conn.skip_outgoing(PacketId::CustomPayload as u8);
     conn.skip_outgoing(PacketId::PlayerInfo as u8);
-    for _ in 0..(17 * 17) {
+    // Every chunk of the 5x5 world is within view distance, as well as the empty chunks around it
+    for _ in 0..(5 * 5 + 4 * 5) {
         conn.skip_outgoing(PacketId::LevelChunkWithLight as u8);
     }
+    conn.skip_outgoing(PacketId::SetChunkCacheCenter as u8);
+    conn.skip_outgoing(PacketId::PlayerPosition as u8);
+    conn.skip_outgoing(PacketId::SetTime as u8);
 
+    // (1) Player receives AddEntity on the 1st login, once the entity is tracked
+    universe.service.tick();
     conn.assert_outgoing_as::<AddEntity, _>(|packet| {
         assert_eq!(packet.id, entity_id.as_i32());
         assert_eq!(packet.entity_type, 6);
@@ -222,9 +309,11 @@ fn spawn_entity_before() {
 
     // Skip initial login packets
     conn.skip_outgoing(PacketId::Login as u8);
+    conn.skip_outgoing(PacketId::UpdateTags as u8);
     conn.skip_outgoing(PacketId::CustomPayload as u8);
     conn.skip_outgoing(PacketId::PlayerInfo as u8);
-    for _ in 0..(17 * 17) {
+    // Every chunk of the 5x5 world is within view distance, as well as the empty chunks around it
+    for _ in 0..(5 * 5 + 4 * 5) {
         conn.skip_outgoing(PacketId::LevelChunkWithLight as u8);
     }
 
@@ -233,41 +322,2520 @@ fn spawn_entity_before() {
     // Skip remaining login packets
     conn.skip_outgoing(PacketId::SetChunkCacheCenter as u8);
     conn.skip_outgoing(PacketId::PlayerPosition as u8);
+    conn.skip_outgoing(PacketId::SetTime as u8);
     conn.assert_none_outgoing();
+
+    universe.service.tick();
+    assert!(!contains_packet(&conn, PacketId::AddEntity as u8));
 }
 
-// Helper functions
+// Does the following:
+//  a. Spawn an entity within viewing distance of the Player
+//  b. Move the entity by 1 block over 3 ticks using interpolation
+// Checks to see that:
+//  1. Player receives a relative move every tick
+//  2. The relative moves add up to exactly 1 block, without a teleport
+#[test]
+fn interpolate_entity() {
+    let (mut universe, mut conn) = common::create_universe_and_player();
 
-fn spawn_entity_at(
-    universe: &mut Universe<DummyUniverseService>,
-    position: Coordinate,
-) -> EntityId {
-    let entity_id = universe.new_entity_id();
-    let test_entity = BasicEntity {
-        entity_id,
-        entity_type: 6,
+    // Skip joining packets
+    conn.skip_all_outgoing();
+
+    // (a) Spawn an entity within viewing distance of the Player
+    let entity_position = Coordinate {
+        x: 49.0,
+        y: 224.0,
+        z: 31.0,
     };
-    universe
+    let entity_id = spawn_entity_at(&mut universe, entity_position);
+    universe.service.tick();
+    conn.skip_outgoing(PacketId::AddEntity as u8);
+
+    // (b) Move the entity by 1 block over 3 ticks using interpolation
+    let target = Position {
+        coord: Coordinate {
+            x: 50.0,
+            y: 224.0,
+            z: 31.0,
+        },
+        rot: Default::default(),
+    };
+    assert!(universe
         .service
         .the_world
-        .push_entity((), position, test_entity, entity_id);
-    entity_id
+        .set_interpolated_position(entity_id, target, 3));
+
+    // (1) Player receives a relative move every tick
+    let mut total_delta_x = 0;
+    for _ in 0..3 {
+        universe.service.tick();
+        conn.assert_outgoing_as::<MoveEntityPosRot, _>(|packet| {
+            assert_eq!(packet.entity_id, entity_id.as_i32());
+            assert!((1365..=1366).contains(&packet.delta_x));
+            assert_eq!(packet.delta_y, 0);
+            assert_eq!(packet.delta_z, 0);
+            total_delta_x += packet.delta_x as i32;
+        });
+        conn.assert_none_outgoing();
+    }
+
+    // (2) The relative moves add up to exactly 1 block, without a teleport
+    assert_eq!(total_delta_x, 4096);
+    universe.service.tick();
+    conn.assert_none_outgoing();
 }
 
-fn move_entity_to(
-    universe: &mut Universe<DummyUniverseService>,
-    entity_id: EntityId,
-    coordinate: Coordinate,
-) {
-    let mut entity = universe
-        .service
-        .the_world
-        .get_entity_mut(entity_id)
-        .expect("entity must exist");
-    let mut viewable = entity
-        .get_mut::<Viewable>()
-        .expect("entity must have viewable component");
-    viewable.coord.x = coordinate.x;
-    viewable.coord.y = coordinate.y;
-    viewable.coord.z = coordinate.z;
+// Checks to see that the destroy stage increases with the time spent
+// breaking a block, and stays within the valid 0..=9 range
+#[test]
+fn block_destroy_stage() {
+    let universe = common::create_universe();
+    let world = &universe.service.the_world;
+
+    // Stone, 150 ticks to break with a speed of 100
+    let destroy_ticks = world.get_required_destroy_ticks(40, 0, 40, 100.0).unwrap();
+    assert_eq!(destroy_ticks, 150.0);
+
+    let mut last_stage = 0;
+    for time in 0..150 {
+        let stage = world.get_destroy_stage(40, 0, 40, time, 100.0).unwrap();
+        assert!(stage >= last_stage);
+        assert_eq!(stage, (time / 15) as i8);
+        last_stage = stage;
+    }
+    assert_eq!(last_stage, 9);
+
+    // Breaking for longer than required doesn't exceed the last stage
+    assert_eq!(world.get_destroy_stage(40, 0, 40, 300, 100.0), Some(9));
+
+    // Air has no destroy stage
+    assert_eq!(world.get_destroy_stage(40, 224, 40, 10, 100.0), None);
+}
+
+// Does the following:
+//  a. Player starts destroying a block in front of them, swinging every tick
+//  b. Player finishes destroying the block
+//  c. Player starts destroying the block behind it, swinging every tick
+//  d. Player switches to destroying another block
+// Checks to see that:
+//  1. The other Player receives BlockDestruction only when the stage rises
+//  2. The other Player receives BlockDestruction clearing the stage of the destroyed block
+//  3. The other Player receives BlockDestruction only when the stage rises
+//  4. The other Player receives BlockDestruction clearing the stage of the previous block
+#[test]
+fn block_destruction() {
+    let (mut universe, mut conn) = common::create_universe_and_player();
+    let mut conn2 = common::create_other_player(&mut universe, 1);
+    universe.service.tick();
+
+    // Skip joining packets
+    conn.skip_all_outgoing();
+    conn2.skip_all_outgoing();
+
+    let entity_id = universe.service.the_world.service.players.get_by_index(0).unwrap().entity_id;
+    let first = BlockPosition { x: 40, y: 225, z: 42 };
+    let second = BlockPosition { x: 40, y: 225, z: 43 };
+    let third = BlockPosition { x: 40, y: 225, z: 44 };
+    for pos in [first, second, third] {
+        universe.service.the_world.set_block_i32(pos.x, pos.y, pos.z, 1);
+    }
+
+    let hand_action = |conn: &mut common::FakePlayerConnection, action: HandAction, block_pos: BlockPosition| {
+        conn.write_packet(&client::PlayerHandAction {
+            action,
+            block_pos,
+            direction: ProtocolDirection::North,
+            sequence: 0,
+        })
+        .unwrap();
+    };
+    let swing = |universe: &mut Universe<DummyUniverseService>, conn: &mut common::FakePlayerConnection, ticks| {
+        for _ in 0..ticks {
+            conn.write_packet(&client::Swing { hand: Hand::Main }).unwrap();
+            universe.service.tick();
+        }
+    };
+
+    // (a) Player starts destroying a block in front of them, swinging every tick
+    hand_action(&mut conn, HandAction::StartDestroyBlock, first);
+    let mut stages = Vec::new();
+    for _ in 0..40 {
+        swing(&mut universe, &mut conn, 1);
+        stages.extend(take_destroy_stages(&mut conn2, entity_id));
+    }
+
+    // (1) The other Player receives BlockDestruction only when the stage rises
+    assert_eq!(stages, vec![(first, 0), (first, 1), (first, 2)]);
+
+    // (b) Player finishes destroying the block
+    hand_action(&mut conn, HandAction::StopDestroyBlock, first);
+    universe.service.tick();
+
+    // (2) The other Player receives BlockDestruction clearing the stage of the destroyed block
+    assert_eq!(take_destroy_stages(&mut conn2, entity_id), vec![(first, -1)]);
+    assert_eq!(universe.service.the_world.get_block_i32(first.x, first.y, first.z), Some(0));
+
+    // (c) Player starts destroying the block behind it, swinging every tick
+    hand_action(&mut conn, HandAction::StartDestroyBlock, second);
+    swing(&mut universe, &mut conn, 20);
+
+    // (3) The other Player receives BlockDestruction only when the stage rises
+    assert_eq!(take_destroy_stages(&mut conn2, entity_id), vec![(second, 0), (second, 1)]);
+
+    // (d) Player switches to destroying another block
+    hand_action(&mut conn, HandAction::StartDestroyBlock, third);
+    universe.service.tick();
+
+    // (4) The other Player receives BlockDestruction clearing the stage of the previous block
+    assert_eq!(take_destroy_stages(&mut conn2, entity_id), vec![(second, -1)]);
+}
+
+// Does the following:
+//  a. Try to pick up a stack of stone from an empty hotbar slot
+//  b. Put a stack of stone into the hotbar
+//  c. Pick the stack up onto the cursor
+//  d. Drop the cursor item outside of the window
+// Checks to see that:
+//  1. The click is rejected and the inventory is resent, no stone is created
+//  2. The cursor is cleared and an item entity is spawned with the stack
+#[test]
+fn drop_cursor_item() {
+    let (mut universe, mut conn) = common::create_universe_and_player();
+
+    // Skip joining packets
+    conn.skip_all_outgoing();
+
+    let stone = || ProtocolItemStack {
+        item: 1,
+        count: 64,
+        ..Default::default()
+    };
+    let pickup_stone = client::ContainerClick {
+        window_id: 0,
+        state_id: 0,
+        slot: 36,
+        button: 0,
+        click_type: ClickType::Pickup,
+        changed_slots: vec![client::ContainerClickChangedSlot {
+            slot: 36,
+            item: None,
+        }],
+        carried_item: Some(stone()),
+    };
+
+    // (a) Try to pick up a stack of stone from an empty hotbar slot
+    conn.write_packet(&pickup_stone).unwrap();
+    universe.service.tick();
+
+    // (1) The click is rejected and the inventory is resent, no stone is created
+    let player = universe.service.the_world.service.players.get_mut_by_index(0).unwrap();
+    assert_eq!(*player.inventory.get_cursor(), ItemSlot::Empty);
+    conn.assert_outgoing_as::<ContainerSetContent, _>(|packet| {
+        assert_eq!(packet.window_id, 0);
+        assert!(packet.items.iter().all(Option::is_none));
+        assert!(packet.carried_item.is_none());
+    });
+    conn.skip_all_outgoing();
+
+    // (b) Put a stack of stone into the hotbar
+    player.inventory.set(InventorySlot::Hotbar(0), ItemStack::of(Item::Stone, 64).unwrap()).unwrap();
+    universe.service.tick();
+    conn.skip_all_outgoing();
+
+    // (c) Pick the stack up onto the cursor
+    conn.write_packet(&pickup_stone).unwrap();
+    conn.assert_none_outgoing();
+
+    // (d) Drop the cursor item outside of the window
+    conn.write_packet(&client::ContainerClick {
+        window_id: 0,
+        state_id: 0,
+        slot: -999,
+        button: 0,
+        click_type: ClickType::Pickup,
+        changed_slots: vec![],
+        carried_item: None,
+    })
+    .unwrap();
+
+    // (2) The cursor is cleared and an item entity is spawned with the stack
+    universe.service.tick();
+    conn.assert_outgoing(&ContainerSetSlot {
+        window_id: -1,
+        state_id: 0,
+        slot: -1,
+        item: None,
+    });
+    conn.assert_outgoing_as::<AddEntity, _>(|packet| {
+        assert_eq!(packet.entity_type, 44); // item
+        assert_eq!(packet.x, 40.0);
+        assert_eq!(packet.z, 40.0);
+    });
+    conn.assert_outgoing_as::<SetEntityData, _>(|_| {});
+    conn.assert_none_outgoing();
+}
+
+// Does the following:
+//  a. Connect a second Player with the same UUID as the first Player
+// Checks to see that:
+//  1. The first Player is kicked, and only the second Player remains
+//  2. The second Player never sees the first Player
+#[test]
+fn duplicate_login_kick_old() {
+    let (mut universe, mut conn) = common::create_universe_and_player();
+    let old_entity_id = universe.service.the_world.service.players.get_by_index(0).unwrap().entity_id;
+    conn.skip_all_outgoing();
+
+    // (a) Connect a second Player with the same UUID as the first Player
+    let conn2 = common::create_player(&mut universe);
+    universe.service.tick();
+
+    // (1) The first Player is kicked, and only the second Player remains
+    conn.skip_outgoing_until(PacketId::Disconnect as u8);
+    conn.assert_outgoing_as::<Disconnect, _>(|packet| {
+        assert!(packet.reason.contains("multiplayer.disconnect.duplicate_login"));
+    });
+    conn.assert_none_outgoing();
+
+    let players = &universe.service.the_world.service.players;
+    assert_eq!(players.len(), 1);
+    assert_ne!(players.get_by_index(0).unwrap().entity_id, old_entity_id);
+
+    // (2) The second Player never sees the first Player
+    assert!(!contains_packet(&conn2, PacketId::AddPlayer as u8));
+    assert!(!contains_packet(&conn2, PacketId::Disconnect as u8));
+}
+
+// Does the following:
+//  a. Only allow the existing Player to stay connected
+//  b. Connect a second Player with the same UUID as the first Player
+// Checks to see that:
+//  1. The second Player is kicked, and only the first Player remains
+#[test]
+fn duplicate_login_reject_new() {
+    let (mut universe, mut conn) = common::create_universe_and_player();
+    let old_entity_id = universe.service.the_world.service.players.get_by_index(0).unwrap().entity_id;
+    conn.skip_all_outgoing();
+
+    // (a) Only allow the existing Player to stay connected
+    universe.set_duplicate_login_policy(DuplicateLoginPolicy::RejectNew);
+
+    // (b) Connect a second Player with the same UUID as the first Player
+    let mut conn2 = common::create_player(&mut universe);
+    universe.service.tick();
+
+    // (1) The second Player is kicked, and only the first Player remains
+    conn2.skip_outgoing_until(PacketId::Disconnect as u8);
+    conn2.assert_outgoing_as::<Disconnect, _>(|packet| {
+        assert!(packet.reason.contains("multiplayer.disconnect.duplicate_login"));
+    });
+    conn2.assert_none_outgoing();
+    assert!(!contains_packet(&conn, PacketId::Disconnect as u8));
+
+    let players = &universe.service.the_world.service.players;
+    assert_eq!(players.len(), 1);
+    assert_eq!(players.get_by_index(0).unwrap().entity_id, old_entity_id);
+}
+
+// Does the following:
+//  a. Transfer the Player into another world
+//  b. Connect a second Player with the same UUID as the first Player
+// Checks to see that:
+//  1. The first Player is removed from the other world as soon as the second Player connects
+//  2. The first Player is kicked
+//  3. The second Player joins the default world
+#[test]
+fn duplicate_login_other_world() {
+    let (mut universe, mut conn) = common::create_universe_and_player();
+    let uuid = common::create_game_profile().uuid;
+
+    // (a) Transfer the Player into another world
+    let player = universe.service.the_world.service.players.get_mut_by_index(0).unwrap();
+    player.transfer(Box::new(|world, service, proto_player| {
+        let position = Position {
+            coord: Coordinate { x: 40.0, y: 224.0, z: 40.0 },
+            rot: Default::default(),
+        };
+        let other_world = &mut world.get_universe().service.other_world;
+        other_world.service.players.add(proto_player, service, position).unwrap();
+    }));
+    universe.service.tick();
+    conn.skip_all_outgoing();
+    assert_eq!(universe.service.other_world.service.players.len(), 1);
+    assert!(universe.is_online(uuid));
+
+    // (b) Connect a second Player with the same UUID as the first Player
+    let _conn2 = common::create_player(&mut universe);
+
+    // (1) The first Player is removed from the other world as soon as the second Player connects
+    assert_eq!(universe.service.other_world.service.players.len(), 0);
+    assert!(universe.is_online(uuid));
+    universe.service.tick();
+
+    // (2) The first Player is kicked
+    conn.skip_outgoing_until(PacketId::Disconnect as u8);
+    conn.assert_outgoing_as::<Disconnect, _>(|packet| {
+        assert!(packet.reason.contains("multiplayer.disconnect.duplicate_login"));
+    });
+    conn.assert_none_outgoing();
+    assert_eq!(universe.service.other_world.service.players.len(), 0);
+
+    // (3) The second Player joins the default world
+    assert_eq!(universe.service.the_world.service.players.len(), 1);
+    assert!(universe.is_online(uuid));
+}
+
+// Does the following:
+//  a. Player puts stacks into the crafting grid and onto the cursor, then closes the inventory
+//  b. Another Player does the same without closing the inventory, then disconnects
+//  c. The world stops keeping inventories on disconnect, and a third Player does the same and disconnects
+// Checks to see that:
+//  1. The stacks are moved into the inventory of the first Player
+//  2. Nothing is dropped when the second Player disconnects
+//  3. Both stacks are dropped when the third Player disconnects
+#[test]
+fn disconnect_inventory() {
+    let (mut universe, mut conn) = common::create_universe_and_player();
+    fn give_two_stacks(inventory: &mut impl InventoryHandler) {
+        inventory.set(InventorySlot::Hotbar(0), ItemStack::of(Item::Stone, 64).unwrap()).unwrap();
+        inventory.set(InventorySlot::Hotbar(1), ItemStack::of(Item::Stone, 64).unwrap()).unwrap();
+    }
+    fn fill_crafting_grid_and_cursor(conn: &mut common::FakePlayerConnection) {
+        let stone = || ProtocolItemStack {
+            item: 1,
+            count: 64,
+            ..Default::default()
+        };
+        let mut click = |slot, item, carried_item| {
+            conn.write_packet(&client::ContainerClick {
+                window_id: 0,
+                state_id: 0,
+                slot,
+                button: 0,
+                click_type: ClickType::Pickup,
+                changed_slots: vec![client::ContainerClickChangedSlot { slot, item }],
+                carried_item,
+            })
+            .unwrap();
+        };
+
+        click(36, None, Some(stone()));
+        click(1, Some(stone()), None);
+        click(37, None, Some(stone()));
+    }
+
+    // (a) Player puts stacks into the crafting grid and onto the cursor, then closes the inventory
+    let player = universe.service.the_world.service.players.get_mut_by_index(0).unwrap();
+    give_two_stacks(&mut player.inventory);
+    fill_crafting_grid_and_cursor(&mut conn);
+    conn.write_packet(&client::ContainerClose { window_id: 0 }).unwrap();
+
+    // (1) The stacks are moved into the inventory of the first Player
+    let player = universe.service.the_world.service.players.get_by_index(0).unwrap();
+    assert!(matches!(player.inventory.get(InventorySlot::CraftingInput(0)), Ok(ItemSlot::Empty)));
+    assert!(matches!(player.inventory.get_cursor(), ItemSlot::Empty));
+    assert!(matches!(player.inventory.get(InventorySlot::Hotbar(0)), Ok(ItemSlot::Filled(_))));
+    assert!(matches!(player.inventory.get(InventorySlot::Hotbar(1)), Ok(ItemSlot::Filled(_))));
+
+    // (b) Another Player does the same without closing the inventory, then disconnects
+    let mut conn2 = common::create_other_player(&mut universe, 1);
+    universe.service.tick();
+    conn.skip_all_outgoing();
+    let player = universe.service.the_world.service.players.get_mut_by_index(1).unwrap();
+    give_two_stacks(&mut player.inventory);
+    fill_crafting_grid_and_cursor(&mut conn2);
+    universe.service.the_world.service.players.get_mut_by_index(1).unwrap().disconnect();
+    universe.service.tick();
+    universe.service.tick();
+
+    // (2) Nothing is dropped when the second Player disconnects
+    assert_eq!(universe.service.the_world.service.players.len(), 1);
+    assert!(contains_packet(&conn, PacketId::RemoveEntities as u8));
+    assert_eq!(count_packets(&conn, PacketId::AddEntity as u8), 0);
+    conn.skip_all_outgoing();
+
+    // (c) The world stops keeping inventories on disconnect, and a third Player does the same and disconnects
+    universe.service.the_world.game_rules.keep_inventory_on_disconnect = false;
+    let mut conn3 = common::create_other_player(&mut universe, 2);
+    universe.service.tick();
+    conn.skip_all_outgoing();
+    let player = universe.service.the_world.service.players.get_mut_by_index(1).unwrap();
+    give_two_stacks(&mut player.inventory);
+    fill_crafting_grid_and_cursor(&mut conn3);
+    universe.service.the_world.service.players.get_mut_by_index(1).unwrap().disconnect();
+    universe.service.tick();
+    universe.service.tick();
+
+    // (3) Both stacks are dropped when the third Player disconnects
+    assert_eq!(universe.service.the_world.service.players.len(), 1);
+    assert_eq!(count_packets(&conn, PacketId::AddEntity as u8), 2);
+}
+
+// Does the following:
+//  a. Break a block close to the Player
+//  b. Play a global level event far away from the Player
+// Checks to see that:
+//  1. Player receives BlockUpdate and the block break LevelEvent
+//  2. Player receives the global LevelEvent, but not a non-global one
+#[test]
+fn level_event() {
+    let (mut universe, mut conn) = common::create_universe_and_player();
+
+    // Skip joining packets
+    conn.skip_all_outgoing();
+
+    // (a) Break a block close to the Player
+    let pos = BlockPosition { x: 40, y: 0, z: 40 };
+    assert_eq!(universe.service.the_world.break_block(pos), Some(1));
+
+    // (1) Player receives the block break LevelEvent and BlockUpdate
+    universe.service.tick();
+    conn.assert_outgoing(&LevelEvent {
+        event_type: LevelEventType::ParticlesDestroyBlock,
+        pos,
+        data: 1,
+        global: false,
+    });
+    conn.assert_outgoing(&BlockUpdate {
+        pos,
+        block_state: 0,
+    });
+    conn.assert_none_outgoing();
+
+    // (b) Play a global level event far away from the Player
+    let far_pos = BlockPosition { x: 72, y: 0, z: 72 };
+    let world = &mut universe.service.the_world;
+    world.play_level_event(LevelEventType::SoundWitherBossSpawn, far_pos, 0, true);
+    world.play_level_event(LevelEventType::SoundOpenWoodenDoor, far_pos, 0, false);
+
+    // (2) Player receives the global LevelEvent, but not a non-global one
+    universe.service.tick();
+    conn.assert_outgoing(&LevelEvent {
+        event_type: LevelEventType::SoundWitherBossSpawn,
+        pos: far_pos,
+        data: 0,
+        global: true,
+    });
+    conn.assert_none_outgoing();
+}
+
+// Does the following:
+//  a. Teleport the Player, never confirming the teleport
+//  b. Tick until the teleport is given up on
+// Checks to see that:
+//  1. Player receives the teleport, and it is resent while unconfirmed
+//  2. Player movement is ignored while the teleport is unconfirmed
+//  3. Player movement is accepted again once the teleport is given up on
+#[test]
+fn teleport_timeout() {
+    let (mut universe, mut conn) = common::create_universe_and_player();
+
+    // Skip joining packets
+    conn.skip_all_outgoing();
+
+    // (a) Teleport the Player, never confirming the teleport
+    let players = &mut universe.service.the_world.service.players;
+    let player = players.get_mut_by_index(0).unwrap();
+    player
+        .teleport(Position {
+            coord: Coordinate {
+                x: 44.0,
+                y: 224.0,
+                z: 44.0,
+            },
+            rot: Default::default(),
+        })
+        .unwrap();
+
+    // (1) Player receives the teleport, and it is resent while unconfirmed
+    universe.service.tick();
+    let expected_teleport = |id| PlayerPosition {
+        x: 44.0,
+        y: 224.0,
+        z: 44.0,
+        yaw: 0.0,
+        pitch: 0.0,
+        relative_arguments: 0,
+        id,
+        dismount_vehicle: false,
+    };
+    conn.assert_outgoing(&expected_teleport(1));
+    conn.assert_none_outgoing();
+
+    // (2) Player movement is ignored while the teleport is unconfirmed
+    let move_packet = client::MovePlayerPos {
+        x: 46.0,
+        y: 224.0,
+        z: 46.0,
+        on_ground: false,
+    };
+    conn.write_packet(&move_packet).unwrap();
+    universe.service.tick();
+    let players = &universe.service.the_world.service.players;
+    assert_eq!(players.get_by_index(0).unwrap().position.coord.x, 44.0);
+
+    for id in 2..=4 {
+        for _ in 0..20 {
+            universe.service.tick();
+        }
+        conn.assert_outgoing(&expected_teleport(id));
+        conn.assert_none_outgoing();
+    }
+
+    // (b) Tick until the teleport is given up on
+    for _ in 0..20 {
+        universe.service.tick();
+    }
+    conn.assert_none_outgoing();
+
+    // (3) Player movement is accepted again once the teleport is given up on
+    conn.write_packet(&move_packet).unwrap();
+    universe.service.tick();
+    let players = &universe.service.the_world.service.players;
+    assert_eq!(players.get_by_index(0).unwrap().position.coord.x, 46.0);
+}
+
+// Does the following:
+//  a. Create a team and add the Player to it
+//  b. Connect a second player, add them to the team and disconnect them
+// Checks to see that:
+//  1. Player receives the team, and the team entry
+//  2. Second player receives the existing team when joining
+//  3. Player receives the team entry removal when the second player disconnects
+#[test]
+fn team_entries() {
+    let (mut universe, mut conn) = common::create_universe_and_player();
+
+    // Skip joining packets
+    conn.skip_all_outgoing();
+
+    // (a) Create a team and add the Player to it
+    let mut team = Team::new("red");
+    team.color = ChatFormatting::Red;
+    team.collision_rule = CollisionRule::Never;
+    let world = &mut universe.service.the_world;
+    assert!(world.add_team(team));
+    assert!(world.add_team_entry("red", "Moulberry"));
+
+    // (1) Player receives the team, and the team entry
+    universe.service.tick();
+    let parameters = || SetPlayerTeamParameters {
+        display_name: "{\"text\": \"red\"}",
+        options: 0x3,
+        name_tag_visibility: "always",
+        collision_rule: "never",
+        color: ChatFormatting::Red,
+        prefix: "{\"text\": \"\"}",
+        suffix: "{\"text\": \"\"}",
+    };
+    conn.assert_outgoing(&SetPlayerTeam {
+        name: "red",
+        action: SetPlayerTeamAction::Create {
+            parameters: parameters(),
+            entries: vec![],
+        },
+    });
+    conn.assert_outgoing(&SetPlayerTeam {
+        name: "red",
+        action: SetPlayerTeamAction::AddEntries {
+            entries: vec!["Moulberry"],
+        },
+    });
+    conn.assert_none_outgoing();
+
+    // (b) Connect a second player
+    let mut conn2 = Box::from(common::FakePlayerConnection::new());
+    let mut profile = common::create_game_profile();
+    profile.username = "Other".into();
+    profile.uuid = 1;
+    universe.handle_player_connect(conn2.as_mut(), profile);
+    universe.service.tick();
+
+    // (2) Second player receives the existing team when joining
+    conn2.skip_outgoing_until(PacketId::SetPlayerTeam as u8);
+    conn2.assert_outgoing(&SetPlayerTeam {
+        name: "red",
+        action: SetPlayerTeamAction::Create {
+            parameters: parameters(),
+            entries: vec!["Moulberry"],
+        },
+    });
+
+    // (b) Add them to the team and disconnect them
+    assert!(universe.service.the_world.add_team_entry("red", "Other"));
+    universe.service.tick();
+    conn2.disconnect();
+    universe.service.tick();
+
+    // (3) Player receives the team entry removal when the second player disconnects
+    conn.skip_outgoing_until(PacketId::SetPlayerTeam as u8);
+    conn.assert_outgoing(&SetPlayerTeam {
+        name: "red",
+        action: SetPlayerTeamAction::AddEntries {
+            entries: vec!["Other"],
+        },
+    });
+    conn.skip_outgoing_until(PacketId::SetPlayerTeam as u8);
+    conn.assert_outgoing(&SetPlayerTeam {
+        name: "red",
+        action: SetPlayerTeamAction::RemoveEntries {
+            entries: vec!["Other"],
+        },
+    });
+    let team = universe.service.the_world.get_team("red").unwrap();
+    assert_eq!(team.entries(), &["Moulberry".to_string()]);
+}
+
+// Does the following:
+//  a. Reduce the movement speed of the Player, with a modifier
+//  b. Set the knockback resistance of an entity close to the Player
+// Checks to see that:
+//  1. Player receives UpdateAttributes for themselves with only the changed attribute
+//  2. Player receives UpdateAttributes for the entity
+#[test]
+fn attributes() {
+    let (mut universe, mut conn) = common::create_universe_and_player();
+
+    // Skip joining packets
+    conn.skip_all_outgoing();
+
+    // (a) Reduce the movement speed of the Player, with a modifier
+    let players = &mut universe.service.the_world.service.players;
+    let player = players.get_mut_by_index(0).unwrap();
+    let player_id = player.entity_id;
+    player.set_attribute(Attribute::MovementSpeed, 0.05);
+    let modifier = AttributeModifier {
+        uuid: 1234,
+        amount: -0.5,
+        operation: AttributeModifierOperation::MultiplyTotal,
+    };
+    player.attributes.add_modifier(Attribute::MovementSpeed, modifier);
+    assert_eq!(player.attributes.get(Attribute::MovementSpeed), 0.025);
+
+    // (1) Player receives UpdateAttributes for themselves with only the changed attribute
+    universe.service.tick();
+    conn.assert_outgoing(&UpdateAttributes {
+        entity_id: player_id.as_i32(),
+        attributes: vec![UpdateAttributesProperty {
+            key: "minecraft:generic.movement_speed",
+            value: 0.05,
+            modifiers: vec![UpdateAttributesModifier {
+                uuid: 1234,
+                amount: -0.5,
+                operation: AttributeModifierOperation::MultiplyTotal,
+            }],
+        }],
+    });
+    conn.assert_none_outgoing();
+
+    // (b) Set the knockback resistance of an entity close to the Player
+    let entity_position = Coordinate {
+        x: 40.0,
+        y: 224.0,
+        z: 40.0,
+    };
+    let entity_id = spawn_entity_at(&mut universe, entity_position);
+    let world = &mut universe.service.the_world;
+    assert!(world.set_entity_attribute(entity_id, Attribute::KnockbackResistance, 1.0));
+
+    // (2) Player receives UpdateAttributes for the entity
+    universe.service.tick();
+    conn.skip_outgoing(PacketId::AddEntity as u8);
+    conn.assert_outgoing(&UpdateAttributes {
+        entity_id: entity_id.as_i32(),
+        attributes: vec![UpdateAttributesProperty {
+            key: "minecraft:generic.knockback_resistance",
+            value: 1.0,
+            modifiers: vec![],
+        }],
+    });
+    conn.assert_none_outgoing();
+}
+
+// Does the following:
+//  a. Give an entity close to the Player a custom name that is always visible
+//  b. Connect a second Player near the entity
+//  c. Clear the custom name of the entity
+// Checks to see that:
+//  1. Player receives SetEntityData containing the name
+//  2. The second Player receives the name after the entity is spawned
+//  3. Player receives SetEntityData without the name
+#[test]
+fn entity_custom_name() {
+    let (mut universe, mut conn) = common::create_universe_and_player();
+
+    // Skip joining packets
+    conn.skip_all_outgoing();
+
+    // (a) Give an entity close to the Player a custom name that is always visible
+    let entity_position = Coordinate {
+        x: 40.0,
+        y: 224.0,
+        z: 40.0,
+    };
+    let entity_id = spawn_entity_at(&mut universe, entity_position);
+    let name = TextComponent::from("Bob").to_json().to_owned();
+    let world = &mut universe.service.the_world;
+    assert!(world.set_entity_custom_name(entity_id, Some("Bob".into())));
+    assert!(world.set_entity_custom_name_visible(entity_id, true));
+
+    // (1) Player receives SetEntityData containing the name
+    universe.service.tick();
+    assert!(contains_packet(&conn, PacketId::SetEntityData as u8));
+    assert!(contains_bytes(conn.outgoing_bytes.get_written(), name.as_bytes()));
+    conn.skip_all_outgoing();
+
+    // (b) Connect a second Player near the entity
+    let mut conn2 = common::create_other_player(&mut universe, 1);
+    universe.service.tick();
+
+    // (2) The second Player receives the name after the entity is spawned
+    conn2.skip_outgoing_until(PacketId::AddEntity as u8);
+    conn2.skip_outgoing(PacketId::AddEntity as u8);
+    assert!(contains_packet(&conn2, PacketId::SetEntityData as u8));
+    assert!(contains_bytes(conn2.outgoing_bytes.get_written(), name.as_bytes()));
+
+    // (c) Clear the custom name of the entity
+    conn.skip_all_outgoing();
+    let world = &mut universe.service.the_world;
+    assert!(world.set_entity_custom_name(entity_id, None));
+
+    // (3) Player receives SetEntityData without the name
+    universe.service.tick();
+    assert!(contains_packet(&conn, PacketId::SetEntityData as u8));
+    assert!(!contains_bytes(conn.outgoing_bytes.get_written(), name.as_bytes()));
+}
+
+// Does the following:
+//  a. Place a sign and open the sign editor for the Player
+//  b. Player sends the text for the sign, and then tries to change it again
+// Checks to see that:
+//  1. Player receives the sign and the sign editor
+//  2. Player receives the block entity data containing the text
+//  3. The text is stored in the block entity, and the second change is ignored
+#[test]
+fn sign_text() {
+    let (mut universe, mut conn) = common::create_universe_and_player();
+
+    // Skip joining packets
+    conn.skip_all_outgoing();
+
+    // (a) Place a sign and open the sign editor for the Player
+    let pos = BlockPosition { x: 41, y: 224, z: 41 };
+    let sign = Block::OakSign {
+        rotation: 0,
+        waterlogged: false,
+    }
+    .to_id();
+    let world = &mut universe.service.the_world;
+    world.set_block_i32(pos.x, pos.y, pos.z, sign);
+    assert!(world.get_sign_text(pos).is_none());
+
+    let player = world.service.players.get_mut_by_index(0).unwrap();
+    player.open_sign_editor(pos);
+
+    // (1) Player receives the sign and the sign editor
+    universe.service.tick();
+    conn.assert_outgoing(&OpenSignEditor { pos });
+    conn.assert_outgoing(&BlockUpdate {
+        pos,
+        block_state: sign as _,
+    });
+    conn.assert_none_outgoing();
+
+    // (b) Player sends the text for the sign, and then tries to change it again
+    conn.write_packet(&client::UpdateSign {
+        pos,
+        line1: "Hello",
+        line2: "\u{a7}cWorld",
+        line3: "",
+        line4: "\"quoted\"",
+    })
+    .unwrap();
+    conn.write_packet(&client::UpdateSign {
+        pos,
+        line1: "Changed",
+        line2: "",
+        line3: "",
+        line4: "",
+    })
+    .unwrap();
+
+    // (2) Player receives the block entity data containing the text
+    universe.service.tick();
+    conn.assert_outgoing_as(|packet: &mut BlockEntityData| {
+        assert_eq!(packet.pos, pos);
+        assert_eq!(packet.block_entity_type, 7);
+        let text = packet.nbt.find_root("Text1").unwrap().as_string().unwrap();
+        assert_eq!(text, r#"{"text": "Hello"}"#);
+    });
+    conn.assert_none_outgoing();
+
+    // (3) The text is stored in the block entity, and the second change is ignored
+    let lines = universe.service.the_world.get_sign_text(pos).unwrap();
+    assert_eq!(lines[0].to_json(), r#"{"text": "Hello"}"#);
+    assert_eq!(lines[1].to_json(), r#"{"text": "cWorld"}"#);
+    assert_eq!(lines[2].to_json(), r#"{"text": ""}"#);
+    assert_eq!(lines[3].to_json(), r#"{"text": "\"quoted\""}"#);
+
+    // Replacing the sign removes its text
+    universe.service.the_world.set_block_i32(pos.x, pos.y, pos.z, 0);
+    assert!(universe.service.the_world.get_sign_text(pos).is_none());
+}
+
+// Does the following:
+//  a. Player uses a bed
+//  b. Player is respawned
+//  c. The bed is removed, and the Player is respawned again
+// Checks to see that:
+//  1. Player receives a message, and its spawn point is set to the bed
+//  2. Player is moved to the bed
+//  3. Player is told the bed is missing, and is moved to the world spawn
+#[test]
+fn bed_spawn_point() {
+    let (mut universe, mut conn) = common::create_universe_and_player();
+
+    // Skip joining packets
+    conn.skip_all_outgoing();
+
+    let bed_pos = BlockPosition { x: 42, y: 224, z: 42 };
+    let bed = Block::RedBed {
+        facing: Direction::North,
+        occupied: false,
+        part: Part::Head,
+    };
+    universe
+        .service
+        .the_world
+        .set_block_i32(bed_pos.x, bed_pos.y, bed_pos.z, bed.to_id());
+    universe.service.tick();
+    conn.skip_all_outgoing();
+
+    // (a) Player uses a bed
+    conn.write_packet(&client::UseItemOn {
+        hand: Hand::Main,
+        block_hit: BlockHitResult {
+            position: bed_pos,
+            direction: ProtocolDirection::Up,
+            offset_x: 0.5,
+            offset_y: 0.5625,
+            offset_z: 0.5,
+            is_inside: false,
+        },
+        sequence: 1,
+    })
+    .unwrap();
+
+    // (1) Player receives a message, and its spawn point is set to the bed
+    universe.service.tick();
+    conn.assert_outgoing(&SystemChat {
+        message: r#"{"text": "Respawn point set"}"#,
+        overlay: false,
+    });
+    conn.skip_all_outgoing();
+
+    let player = universe.service.the_world.service.players.get_by_index(0).unwrap();
+    assert_eq!(player.spawn_point.unwrap().position, bed_pos);
+
+    // (b) Player is respawned
+    let world_spawn = Position {
+        coord: Coordinate {
+            x: 40.0,
+            y: 224.0,
+            z: 40.0,
+        },
+        rot: Default::default(),
+    };
+    let player = universe.service.the_world.service.players.get_mut_by_index(0).unwrap();
+    player.respawn(world_spawn).unwrap();
+
+    // (2) Player is moved to the bed
+    assert_eq!(
+        player.position.coord,
+        Coordinate {
+            x: 42.5,
+            y: 224.5625,
+            z: 42.5,
+        }
+    );
+    universe.service.tick();
+    conn.skip_all_outgoing();
+
+    // (c) The bed is removed, and the Player is respawned again
+    let world = &mut universe.service.the_world;
+    world.set_block_i32(bed_pos.x, bed_pos.y, bed_pos.z, 0);
+    let player = world.service.players.get_mut_by_index(0).unwrap();
+    player.respawn(world_spawn).unwrap();
+
+    // (3) Player is told the bed is missing, and is moved to the world spawn
+    assert_eq!(player.position.coord, world_spawn.coord);
+    assert!(player.spawn_point.is_none());
+    universe.service.tick();
+    conn.assert_outgoing(&GameEvent {
+        event_type: GameEventType::NoRespawnBlockAvailable,
+        param: 0.0,
+    });
+}
+
+// Does the following:
+//  a. Player runs `fill ~ ~ ~ ~5 ~5 ~5 stone`
+//  b. Player runs the same command again
+//  c. Player runs a fill that extends outside of the world
+// Checks to see that:
+//  1. The blocks are filled with stone and the Player is told how many blocks changed
+//  2. The command fails because no blocks changed
+//  3. The command fails without changing any blocks
+#[test]
+fn fill_command() {
+    #[brigadier("fill", {block_pos}, {block_pos}, {block_state})]
+    fn fill(player: &mut Player<common::DummyPlayerService>, from: &str, to: &str,
+            block: &str) -> CommandResult {
+        commands::fill(player, from, to, block)
+    }
+    let (dispatcher, _) = graphite_command::minecraft::create_dispatcher_and_brigadier_packet(fill);
+
+    let (mut universe, mut conn) = common::create_universe_and_player();
+
+    // Skip joining packets
+    conn.skip_all_outgoing();
+
+    // (a) Player runs `fill ~ ~ ~ ~5 ~5 ~5 stone`
+    let player = universe.service.the_world.service.players.get_mut_by_index(0).unwrap();
+    let result = dispatcher.dispatch_with_context("fill ~ ~ ~ ~5 ~5 ~5 stone", player);
+    assert!(matches!(result, CommandDispatchResult::Success(Ok(()))));
+
+    // (1) The blocks are filled with stone and the Player is told how many blocks changed
+    let world = &universe.service.the_world;
+    for x in 40..=45 {
+        for y in 224..=229 {
+            for z in 40..=45 {
+                assert_eq!(world.get_block_i32(x, y, z), Some(Block::Stone.to_id()));
+            }
+        }
+    }
+    assert_eq!(world.get_block_i32(46, 224, 40), Some(0));
+
+    universe.service.tick();
+    assert!(contains_packet(&conn, PacketId::SectionBlocksUpdate as u8));
+    conn.skip_outgoing_until(PacketId::SystemChat as u8);
+    conn.assert_outgoing(&SystemChat {
+        message: r#"{"text": "Successfully filled 216 block(s)"}"#,
+        overlay: false,
+    });
+    conn.skip_all_outgoing();
+
+    // (b) Player runs the same command again
+    let player = universe.service.the_world.service.players.get_mut_by_index(0).unwrap();
+    let result = dispatcher.dispatch_with_context("fill ~ ~ ~ ~5 ~5 ~5 stone", player);
+
+    // (2) The command fails because no blocks changed
+    assert!(matches!(result, CommandDispatchResult::Success(Err(_))));
+
+    // (c) Player runs a fill that extends outside of the world
+    let player = universe.service.the_world.service.players.get_mut_by_index(0).unwrap();
+    let result = dispatcher.dispatch_with_context("fill ~ ~ ~ ~100 ~ ~ minecraft:dirt", player);
+
+    // (3) The command fails without changing any blocks
+    assert!(matches!(result, CommandDispatchResult::Success(Err(_))));
+    assert_eq!(universe.service.the_world.get_block_i32(40, 224, 40), Some(Block::Stone.to_id()));
+    universe.service.tick();
+    assert!(!contains_packet(&conn, PacketId::BlockUpdate as u8));
+    assert!(!contains_packet(&conn, PacketId::SectionBlocksUpdate as u8));
+}
+
+// Does the following:
+//  a. Player runs `fill ~ ~ ~ ~3 ~ ~ cobblestone_wall`
+//  b. Player runs `fill ~ ~ ~2 ~3 ~ ~2 redstone_wire`
+// Checks to see that:
+//  1. The walls connect to each other, only the ends have a post
+//  2. The dust forms a line
+#[test]
+fn fill_command_walls_and_redstone() {
+    #[brigadier("fill", {block_pos}, {block_pos}, {block_state})]
+    fn fill(player: &mut Player<common::DummyPlayerService>, from: &str, to: &str,
+            block: &str) -> CommandResult {
+        commands::fill(player, from, to, block)
+    }
+    let (dispatcher, _) = graphite_command::minecraft::create_dispatcher_and_brigadier_packet(fill);
+
+    let (mut universe, _conn) = common::create_universe_and_player();
+
+    let wall = |east, west, up| Block::CobblestoneWall {
+        east,
+        north: WallConnection::None,
+        south: WallConnection::None,
+        up,
+        waterlogged: false,
+        west,
+    };
+
+    // (a) Player runs `fill ~ ~ ~ ~3 ~ ~ cobblestone_wall`
+    let player = universe.service.the_world.service.players.get_mut_by_index(0).unwrap();
+    let result = dispatcher.dispatch_with_context("fill ~ ~ ~ ~3 ~ ~ cobblestone_wall", player);
+    assert!(matches!(result, CommandDispatchResult::Success(Ok(()))));
+
+    // (1) The walls connect to each other, only the ends have a post
+    let world = &universe.service.the_world;
+    assert_eq!(world.get_block_i32(40, 224, 40), Some(wall(WallConnection::Low, WallConnection::None, true).to_id()));
+    for x in 41..=42 {
+        assert_eq!(world.get_block_i32(x, 224, 40), Some(wall(WallConnection::Low, WallConnection::Low, false).to_id()));
+    }
+    assert_eq!(world.get_block_i32(43, 224, 40), Some(wall(WallConnection::None, WallConnection::Low, true).to_id()));
+
+    // (b) Player runs `fill ~ ~ ~2 ~3 ~ ~2 redstone_wire`
+    let player = universe.service.the_world.service.players.get_mut_by_index(0).unwrap();
+    let result = dispatcher.dispatch_with_context("fill ~ ~ ~2 ~3 ~ ~2 redstone_wire", player);
+    assert!(matches!(result, CommandDispatchResult::Success(Ok(()))));
+
+    // (2) The dust forms a line
+    let line = Block::RedstoneWire {
+        east: WireConnection::Side,
+        north: WireConnection::None,
+        power: 0,
+        south: WireConnection::None,
+        west: WireConnection::Side,
+    };
+    let world = &universe.service.the_world;
+    for x in 40..=43 {
+        assert_eq!(world.get_block_i32(x, 224, 42), Some(line.to_id()));
+    }
+}
+
+// Does the following:
+//  a. Send a chat and an action bar message to the Player
+//  b. Broadcast a message to the world
+// Checks to see that:
+//  1. Player receives both messages with the correct overlay flag
+//  2. Player receives the broadcast message
+#[test]
+fn system_message() {
+    let (mut universe, mut conn) = common::create_universe_and_player();
+
+    // Skip joining packets
+    conn.skip_all_outgoing();
+
+    // (a) Send a chat and an action bar message to the Player
+    let player = universe.service.the_world.service.players.get_mut_by_index(0).unwrap();
+    player.send_system_message("Hello", false);
+    player.send_system_message("Action \"bar\"", true);
+
+    // (1) Player receives both messages with the correct overlay flag
+    universe.service.tick();
+    conn.assert_outgoing(&SystemChat {
+        message: r#"{"text": "Hello"}"#,
+        overlay: false,
+    });
+    conn.assert_outgoing(&SystemChat {
+        message: r#"{"text": "Action \"bar\""}"#,
+        overlay: true,
+    });
+    conn.assert_none_outgoing();
+
+    // (b) Broadcast a message to the world
+    universe.service.the_world.broadcast_system_message("Moulberry joined the game");
+
+    // (2) Player receives the broadcast message
+    universe.service.tick();
+    conn.assert_outgoing(&SystemChat {
+        message: r#"{"text": "Moulberry joined the game"}"#,
+        overlay: false,
+    });
+    conn.assert_none_outgoing();
+}
+
+// Creates a world using a flat chunk generator
+// Checks that the generated layers exist, including in chunks added by expanding the world
+#[test]
+fn generate_flat_chunks() {
+    let mut world = World::new_with_generated_chunks(FlatWorldService, 2, 24, 2);
+
+    let check_layers = |world: &World<FlatWorldService>, x: i32, z: i32| {
+        assert_eq!(world.get_block_i32(x, 0, z), Some(FlatWorldService::BEDROCK));
+        assert_eq!(world.get_block_i32(x, 1, z), Some(FlatWorldService::DIRT));
+        assert_eq!(world.get_block_i32(x, 2, z), Some(FlatWorldService::DIRT));
+        assert_eq!(world.get_block_i32(x, 3, z), Some(FlatWorldService::GRASS_BLOCK));
+        assert_eq!(world.get_block_i32(x, 4, z), Some(0));
+    };
+
+    check_layers(&world, 0, 0);
+    check_layers(&world, 31, 17);
+    assert_eq!(world.get_block_i32(32, 0, 0), None);
+
+    world.expand(1, 0, 1);
+    check_layers(&world, 40, 8);
+    check_layers(&world, 8, 40);
+    check_layers(&world, 47, 47);
+}
+
+// Does the following:
+//  a. Player falls 10 blocks onto stone, claiming to be on the ground while in mid-air
+//  b. Player falls 10 blocks into water
+// Checks to see that:
+//  1. Player takes 7 damage when landing, ignoring the on-ground claim in mid-air
+//  2. Player takes no damage when landing in water
+#[test]
+fn fall_damage() {
+    let (mut universe, mut conn) = common::create_universe_and_player();
+
+    // Skip joining packets
+    conn.skip_all_outgoing();
+
+    universe.service.the_world.set_block_i32(40, 213, 40, Block::Stone.to_id());
+
+    // (a) Player falls 10 blocks onto stone
+    move_player_y(&mut universe, &mut conn, 220.0, true);
+    move_player_y(&mut universe, &mut conn, 216.0, false);
+    move_player_y(&mut universe, &mut conn, 214.0, true);
+
+    // (1) Player takes 7 damage when landing
+    let player = universe.service.the_world.service.players.get_by_index(0).unwrap();
+    assert_eq!(player.get_health(), 13.0);
+    assert_eq!(player.fall_distance, 0.0);
+    conn.skip_outgoing_until(PacketId::SetHealth as u8);
+    conn.assert_outgoing(&SetHealth {
+        health: 13.0,
+        food: 20,
+        saturation: 5.0,
+    });
+    conn.skip_all_outgoing();
+
+    // (b) Player falls 10 blocks into water
+    universe.service.the_world.set_block_i32(40, 214, 40, Block::Water { level: 0 }.to_id());
+    move_player_y(&mut universe, &mut conn, 224.0, false);
+    move_player_y(&mut universe, &mut conn, 218.0, false);
+    move_player_y(&mut universe, &mut conn, 214.0, true);
+
+    // (2) Player takes no damage when landing in water
+    let player = universe.service.the_world.service.players.get_by_index(0).unwrap();
+    assert_eq!(player.get_health(), 13.0);
+}
+
+// Does the following:
+//  a. Freeze the world and start moving an entity using interpolation
+//  b. Step the world by 2 ticks
+//  c. Unfreeze the world
+// Checks to see that:
+//  1. The entity doesn't move and the game time doesn't advance while frozen
+//  2. The entity moves for exactly 2 ticks
+//  3. The entity finishes moving once unfrozen
+#[test]
+fn frozen_world() {
+    let (mut universe, mut conn) = common::create_universe_and_player();
+
+    // Skip joining packets
+    conn.skip_all_outgoing();
+
+    let entity_id = spawn_entity_at(
+        &mut universe,
+        Coordinate {
+            x: 40.0,
+            y: 224.0,
+            z: 40.0,
+        },
+    );
+    universe.service.tick();
+    conn.skip_all_outgoing();
+
+    let entity_x = |universe: &mut Universe<DummyUniverseService>| {
+        let entity = universe.service.the_world.get_entity_mut(entity_id).unwrap();
+        entity.get::<Viewable>().unwrap().coord.x
+    };
+
+    // (a) Freeze the world and start moving an entity using interpolation
+    let world = &mut universe.service.the_world;
+    let game_time = world.get_game_time();
+    world.set_frozen(true);
+    let target = Position {
+        coord: Coordinate {
+            x: 44.0,
+            y: 224.0,
+            z: 40.0,
+        },
+        rot: Default::default(),
+    };
+    assert!(world.set_interpolated_position(entity_id, target, 4));
+
+    // (1) The entity doesn't move and the game time doesn't advance while frozen
+    for _ in 0..5 {
+        universe.service.tick();
+    }
+    assert_eq!(entity_x(&mut universe), 40.0);
+    assert_eq!(universe.service.the_world.get_game_time(), game_time);
+    conn.assert_none_outgoing();
+
+    // (b) Step the world by 2 ticks
+    universe.service.the_world.step(2);
+
+    // (2) The entity moves for exactly 2 ticks
+    for _ in 0..5 {
+        universe.service.tick();
+    }
+    assert_eq!(entity_x(&mut universe), 42.0);
+    assert_eq!(universe.service.the_world.get_game_time(), game_time + 2);
+    for _ in 0..2 {
+        conn.assert_outgoing_as::<MoveEntityPosRot, _>(|packet| {
+            assert_eq!(packet.delta_x, 4096);
+        });
+    }
+    conn.assert_none_outgoing();
+
+    // (c) Unfreeze the world
+    universe.service.the_world.set_frozen(false);
+
+    // (3) The entity finishes moving once unfrozen
+    universe.service.tick();
+    universe.service.tick();
+    assert_eq!(entity_x(&mut universe), 44.0);
+    assert!(!universe.service.the_world.is_frozen());
+}
+
+// Does the following:
+//  a. The world advances a tick
+//  b. The doDaylightCycle game rule is set to false, and the world advances for a second
+//  c. Game rules are set using unknown names and invalid values
+// Checks to see that:
+//  1. The time of day advances
+//  2. The time of day stops advancing, and Player receives a negative time of day
+//  3. The game rules are rejected, without changing any rules
+#[test]
+fn game_rules() {
+    let (mut universe, mut conn) = common::create_universe_and_player();
+
+    // Skip joining packets
+    conn.skip_all_outgoing();
+
+    // (a) The world advances a tick
+    let day_time = universe.service.the_world.get_day_time();
+    universe.service.tick();
+
+    // (1) The time of day advances
+    assert_eq!(universe.service.the_world.get_day_time(), day_time + 1);
+    conn.skip_all_outgoing();
+
+    // (b) The doDaylightCycle game rule is set to false, and the world advances for a second
+    let world = &mut universe.service.the_world;
+    world.game_rules.set("doDaylightCycle", "false").unwrap();
+    assert!(!world.game_rules.do_daylight_cycle);
+    assert_eq!(world.game_rules.get("doDaylightCycle").unwrap(), "false");
+    for _ in 0..20 {
+        universe.service.tick();
+    }
+
+    // (2) The time of day stops advancing, and Player receives a negative time of day
+    assert_eq!(universe.service.the_world.get_day_time(), day_time + 1);
+    conn.skip_outgoing_until(PacketId::SetTime as u8);
+    conn.assert_outgoing_as::<SetTime, _>(|packet| {
+        assert_eq!(packet.day_time, -(day_time as i64 + 1));
+    });
+
+    // (c) Game rules are set using unknown names and invalid values
+    let game_rules = &mut universe.service.the_world.game_rules;
+    let unchanged = game_rules.clone();
+    let unknown = game_rules.set("doEverything", "true");
+    let invalid = game_rules.set("randomTickSpeed", "fast");
+
+    // (3) The game rules are rejected, without changing any rules
+    assert!(matches!(unknown, Err(GameRuleError::UnknownRule(_))));
+    assert!(matches!(invalid, Err(GameRuleError::InvalidValue { rule: "randomTickSpeed", .. })));
+    assert_eq!(*game_rules, unchanged);
+}
+
+// Sends typed game events to the Player
+// Checks that they are written with the correct event type and parameter
+#[test]
+fn game_event_kind() {
+    let (mut universe, mut conn) = common::create_universe_and_player();
+
+    // Skip joining packets
+    conn.skip_all_outgoing();
+
+    let player = universe.service.the_world.service.players.get_mut_by_index(0).unwrap();
+    player.send_game_event(GameEventKind::ChangeGameMode(GameMode::Creative));
+    player.send_game_event(GameEventKind::WinGame { show_credits: true });
+    player.send_game_event(GameEventKind::RainLevelChange(0.5));
+
+    universe.service.tick();
+    conn.assert_outgoing_as::<GameEvent, _>(|packet| {
+        assert_eq!(packet.event_type as u8, 3);
+        assert_eq!(packet.param, 1.0);
+    });
+    conn.assert_outgoing_as::<GameEvent, _>(|packet| {
+        assert_eq!(packet.event_type as u8, 4);
+        assert_eq!(packet.param, 1.0);
+    });
+    conn.assert_outgoing_as::<GameEvent, _>(|packet| {
+        assert_eq!(packet.event_type as u8, 7);
+        assert_eq!(packet.param, 0.5);
+    });
+    conn.assert_none_outgoing();
+}
+
+// Creates two identical flat chunks with the payload cache enabled
+// Checks that they share a payload, and that a modified chunk gets its own
+#[test]
+fn chunk_payload_cache() {
+    let mut service = FlatWorldService;
+    let chunks = vec![service.generate_chunk(0, 0, 24), service.generate_chunk(1, 0, 24)];
+    let mut grid = ChunkGrid::new(chunks, 2, 24, 1);
+    grid.enable_payload_cache();
+
+    let write_chunks = |grid: &mut ChunkGrid| {
+        let mut write_buffer = WriteBuffer::new();
+        for (x, z, chunk) in grid.enumerate_mut() {
+            chunk.write(&mut write_buffer, x as _, z as _).unwrap();
+        }
+    };
+
+    write_chunks(&mut grid);
+    assert_eq!(grid.get_payload_cache().unwrap().len(), 1);
+
+    grid.get_mut(1, 0).unwrap().set_block(0, 10, 0, FlatWorldService::DIRT);
+    write_chunks(&mut grid);
+    assert_eq!(grid.get_payload_cache().unwrap().len(), 2);
+}
+
+// Does the following:
+//  a. Make the Player look at a point with their feet as the anchor
+//  b. Make the Player look at an entity with their eyes as the anchor
+// Checks to see that:
+//  1. Player receives the look at packet, and the server-side rotation faces the point
+//  2. Player receives the look at packet targeting the entity
+#[test]
+fn look_at() {
+    let (mut universe, mut conn) = common::create_universe_and_player();
+
+    // Skip joining packets
+    conn.skip_all_outgoing();
+
+    // (a) Make the Player look at a point with their feet as the anchor
+    let target = Coordinate {
+        x: 30.0,
+        y: 224.0,
+        z: 40.0,
+    };
+    let player = universe.service.the_world.service.players.get_mut_by_index(0).unwrap();
+    player.look_at(target, LookAnchor::Feet).unwrap();
+
+    // (1) Player receives the look at packet, and the server-side rotation faces the point
+    assert_eq!(player.position.rot.yaw, 90.0);
+    assert_eq!(player.position.rot.pitch, 0.0);
+    universe.service.tick();
+    conn.assert_outgoing_as::<PlayerLookAt, _>(|packet| {
+        assert_eq!(packet.anchor, LookAnchor::Feet);
+        assert_eq!((packet.x, packet.y, packet.z), (30.0, 224.0, 40.0));
+        assert!(packet.entity.is_none());
+    });
+    conn.assert_none_outgoing();
+
+    // (b) Make the Player look at an entity with their eyes as the anchor
+    let entity_id = spawn_entity_at(
+        &mut universe,
+        Coordinate {
+            x: 40.0,
+            y: 224.0,
+            z: 50.0,
+        },
+    );
+    universe.service.tick();
+    conn.skip_all_outgoing();
+
+    let player = universe.service.the_world.service.players.get_mut_by_index(0).unwrap();
+    assert!(player
+        .look_at_entity(entity_id, LookAnchor::Eyes, LookAnchor::Feet)
+        .unwrap());
+
+    // (2) Player receives the look at packet targeting the entity
+    assert_eq!(player.position.rot.yaw, 0.0);
+    assert!(player.position.rot.pitch > 0.0);
+    universe.service.tick();
+    conn.assert_outgoing_as::<PlayerLookAt, _>(|packet| {
+        assert_eq!(packet.anchor, LookAnchor::Eyes);
+        assert_eq!((packet.x, packet.y, packet.z), (40.0, 224.0, 50.0));
+        let entity = packet.entity.as_ref().unwrap();
+        assert_eq!(entity.entity_id, entity_id.as_i32());
+        assert_eq!(entity.entity_anchor, LookAnchor::Feet);
+    });
+    conn.assert_none_outgoing();
+}
+
+// Does the following:
+//  a. Player starts sprinting while underwater
+//  b. Water is removed and a block is placed above the player's head
+//  c. Player stops sprinting
+// Checks to see that:
+//  1. Player enters the swimming pose, with a horizontal bounding box
+//  2. Player stays in the swimming pose (crawling) because there is no room to stand up
+//  3. Player stands up once the block is removed
+#[test]
+fn swimming_and_crawling() {
+    let (mut universe, mut conn) = common::create_universe_and_player();
+
+    // Skip joining packets
+    conn.skip_all_outgoing();
+
+    // (a) Player starts sprinting while underwater
+    universe.service.the_world.set_block_i32(40, 224, 40, Block::Water { level: 0 }.to_id());
+    universe.service.the_world.set_block_i32(40, 225, 40, Block::Water { level: 0 }.to_id());
+
+    let player = universe.service.the_world.service.players.get_by_index(0).unwrap();
+    conn.write_packet(&client::PlayerMoveAction {
+        id: player.entity_id.as_i32(),
+        action: MoveAction::StartSprinting,
+        data: 0,
+    })
+    .unwrap();
+    universe.service.tick();
+
+    // (1) Player enters the swimming pose, with a horizontal bounding box
+    let player = universe.service.the_world.service.players.get_by_index(0).unwrap();
+    assert!(player.is_swimming());
+    assert_eq!(player.metadata.pose, Pose::Swimming);
+    let bounding_box = player.get_bounding_box();
+    assert!((bounding_box.maxs.y - bounding_box.mins.y - 0.6).abs() < 0.001);
+
+    // (b) Water is removed and a block is placed above the player's head
+    universe.service.the_world.set_block_i32(40, 224, 40, Block::Air.to_id());
+    universe.service.the_world.set_block_i32(40, 225, 40, Block::Stone.to_id());
+    universe.service.tick();
+
+    // (2) Player stays in the swimming pose (crawling) because there is no room to stand up
+    let player = universe.service.the_world.service.players.get_by_index(0).unwrap();
+    assert!(!player.is_swimming());
+    assert_eq!(player.metadata.pose, Pose::Swimming);
+    let entity_id = player.entity_id;
+
+    // (c) Player stops sprinting
+    universe.service.the_world.set_block_i32(40, 225, 40, Block::Air.to_id());
+    conn.write_packet(&client::PlayerMoveAction {
+        id: entity_id.as_i32(),
+        action: MoveAction::StopSprinting,
+        data: 0,
+    })
+    .unwrap();
+    universe.service.tick();
+
+    // (3) Player stands up once the block is removed
+    let player = universe.service.the_world.service.players.get_by_index(0).unwrap();
+    assert_eq!(player.metadata.pose, Pose::Standing);
+}
+
+// Does the following:
+//  a. Two players join the universe
+//  b. A custom registry is added, then a third player joins
+// Checks to see that:
+//  1. Both players are sent the same registry codec, which is only encoded once
+//  2. The third player is sent the re-encoded registry codec containing the custom registry
+#[test]
+fn registry_codec_cache() {
+    let (mut universe, mut conn) = common::create_universe_and_player();
+
+    // (a) Two players join the universe
+    let registry_codec = universe.get_registry_codec().to_bytes().to_vec();
+    let encoded_ptr = universe.get_registry_codec().to_bytes().as_ptr();
+    let mut conn2 = common::create_other_player(&mut universe, 1);
+
+    // (1) Both players are sent the same registry codec, which is only encoded once
+    assert_eq!(universe.get_registry_codec().to_bytes().as_ptr(), encoded_ptr);
+    assert!(contains_bytes(conn.outgoing_bytes.get_written(), &registry_codec));
+    assert!(contains_bytes(conn2.outgoing_bytes.get_written(), &registry_codec));
+    conn.skip_all_outgoing();
+    conn2.skip_all_outgoing();
+
+    // (b) A custom registry is added, then a third player joins
+    let registry_codec_mut = universe.get_registry_codec_mut();
+    registry_codec_mut.insert_root("graphite:custom", NBTNode::Byte(1));
+    let conn3 = common::create_other_player(&mut universe, 2);
+
+    // (2) The third player is sent the re-encoded registry codec containing the custom registry
+    let custom_registry_codec = universe.get_registry_codec().to_bytes().to_vec();
+    assert_ne!(custom_registry_codec, registry_codec);
+    assert!(contains_bytes(conn3.outgoing_bytes.get_written(), &custom_registry_codec));
+}
+
+// Does the following:
+//  a. Place two unconnected fences with a gap between them
+//  b. Place a block in the gap
+// Checks to see that:
+//  1. Both fences connect to the block
+//  2. Player receives the BlockUpdate for the block, followed by a single SectionBlocksUpdate for the fences
+#[test]
+fn neighbor_updates() {
+    let (mut universe, mut conn) = common::create_universe_and_player();
+
+    let fence = |east, west| Block::OakFence {
+        east,
+        north: false,
+        south: false,
+        waterlogged: false,
+        west,
+    };
+
+    // (a) Place two unconnected fences with a gap between them
+    universe.service.the_world.set_block_i32(40, 224, 38, fence(false, false).to_id());
+    universe.service.the_world.set_block_i32(42, 224, 38, fence(false, false).to_id());
+    universe.service.tick();
+    conn.skip_all_outgoing();
+
+    // (b) Place a block in the gap
+    universe.service.the_world.set_block_i32(41, 224, 38, Block::Stone.to_id());
+
+    // (1) Both fences connect to the block
+    let west_fence = fence(true, false);
+    let east_fence = fence(false, true);
+    assert_eq!(universe.service.the_world.get_block_i32(40, 224, 38), Some(west_fence.to_id()));
+    assert_eq!(universe.service.the_world.get_block_i32(42, 224, 38), Some(east_fence.to_id()));
+
+    // (2) Player receives the BlockUpdate for the block, followed by a single SectionBlocksUpdate for the fences
+    universe.service.tick();
+    conn.assert_outgoing(&BlockUpdate {
+        pos: BlockPosition { x: 41, y: 224, z: 38 },
+        block_state: Block::Stone.to_id() as _,
+    });
+    conn.assert_outgoing(&SectionBlocksUpdate {
+        section_pos: SectionBlocksUpdate::pack_section_pos(2, 14, 2),
+        suppress_light_updates: false,
+        blocks: vec![
+            SectionBlocksUpdate::pack_block(10, 0, 6, east_fence.to_id()),
+            SectionBlocksUpdate::pack_block(8, 0, 6, west_fence.to_id()),
+        ],
+    });
+    conn.assert_none_outgoing();
+}
+
+// Does the following:
+//  a. Place two unconnected fences
+//  b. Fill the block west of the first fence with stone
+//  c. Clone the stone to the block west of the second fence
+// Checks to see that:
+//  1. The first fence connects to the filled block
+//  2. The second fence connects to the cloned block
+#[test]
+fn neighbor_updates_fill_and_clone() {
+    let (mut universe, _conn) = common::create_universe_and_player();
+
+    let fence = |west| Block::OakFence {
+        east: false,
+        north: false,
+        south: false,
+        waterlogged: false,
+        west,
+    };
+    let pos = |x| BlockPosition { x, y: 224, z: 38 };
+
+    // (a) Place two unconnected fences
+    let world = &mut universe.service.the_world;
+    world.set_block_i32(41, 224, 38, fence(false).to_id());
+    world.set_block_i32(44, 224, 38, fence(false).to_id());
+
+    // (b) Fill the block west of the first fence with stone
+    assert_eq!(world.fill(pos(40), pos(40), Block::Stone.to_id()), Some(1));
+
+    // (1) The first fence connects to the filled block
+    assert_eq!(world.get_block_i32(41, 224, 38), Some(fence(true).to_id()));
+    assert_eq!(world.get_block_i32(44, 224, 38), Some(fence(false).to_id()));
+
+    // (c) Clone the stone to the block west of the second fence
+    assert_eq!(world.clone_blocks(pos(40), pos(40), pos(43)), Some(1));
+
+    // (2) The second fence connects to the cloned block
+    assert_eq!(world.get_block_i32(43, 224, 38), Some(Block::Stone.to_id()));
+    assert_eq!(world.get_block_i32(44, 224, 38), Some(fence(true).to_id()));
+}
+
+// Does the following:
+//  a. Place a chest holding stone, and clone it
+//  b. Place an empty chest, and clone it over the copy
+// Checks to see that:
+//  1. Both chests hold the stone
+//  2. The copy is empty, and the original chest still holds the stone
+#[test]
+fn clone_chest_contents() {
+    let mut universe = common::create_universe();
+    let world = &mut universe.service.the_world;
+
+    let chest = Block::Chest {
+        facing: Direction::North,
+        block_type: ChestType::Single,
+        waterlogged: false,
+    };
+    let original = BlockPosition { x: 40, y: 224, z: 38 };
+    let copy = BlockPosition { x: 43, y: 224, z: 38 };
+    let empty = BlockPosition { x: 46, y: 224, z: 38 };
+    let stone = ItemStack::of(Item::Stone, 5).unwrap();
+
+    // (a) Place a chest holding stone, and clone it
+    world.set_block_i32(original.x, original.y, original.z, chest.to_id());
+    assert!(world.set_chest_item(original, 0, ItemSlot::Filled(stone.clone())));
+    assert_eq!(world.clone_blocks(original, original, copy), Some(1));
+
+    // (1) Both chests hold the stone
+    for pos in [original, copy] {
+        assert_eq!(world.get_chest_items(pos).unwrap()[0], ItemSlot::Filled(stone.clone()));
+    }
+
+    // (b) Place an empty chest, and clone it over the copy
+    world.set_block_i32(empty.x, empty.y, empty.z, chest.to_id());
+    assert_eq!(world.clone_blocks(empty, empty, copy), Some(0));
+
+    // (2) The copy is empty, and the original chest still holds the stone
+    assert!(world.get_chest_items(copy).unwrap().iter().all(|item| *item == ItemSlot::Empty));
+    assert_eq!(world.get_chest_items(original).unwrap()[0], ItemSlot::Filled(stone));
+}
+
+// Does the following:
+//  a. Place two walls next to each other
+//  b. Place two redstone dust next to each other
+// Checks to see that:
+//  1. The walls connect to each other
+//  2. The dust forms a line
+#[test]
+fn neighbor_updates_walls_and_redstone() {
+    let (mut universe, _conn) = common::create_universe_and_player();
+
+    let wall = |east, west| Block::CobblestoneWall {
+        east,
+        north: WallConnection::None,
+        south: WallConnection::None,
+        up: true,
+        waterlogged: false,
+        west,
+    };
+    let dust = |east, west| Block::RedstoneWire {
+        east,
+        north: WireConnection::None,
+        power: 0,
+        south: WireConnection::None,
+        west,
+    };
+
+    // (a) Place two walls next to each other
+    let world = &mut universe.service.the_world;
+    world.set_block_i32(40, 224, 38, wall(WallConnection::None, WallConnection::None).to_id());
+    world.set_block_i32(41, 224, 38, wall(WallConnection::None, WallConnection::None).to_id());
+
+    // (1) The walls connect to each other
+    assert_eq!(world.get_block_i32(40, 224, 38), Some(wall(WallConnection::Low, WallConnection::None).to_id()));
+    assert_eq!(world.get_block_i32(41, 224, 38), Some(wall(WallConnection::None, WallConnection::Low).to_id()));
+
+    // (b) Place two redstone dust next to each other
+    world.set_block_i32(40, 224, 40, dust(WireConnection::None, WireConnection::None).to_id());
+    world.set_block_i32(41, 224, 40, dust(WireConnection::None, WireConnection::None).to_id());
+
+    // (2) The dust forms a line
+    let line = dust(WireConnection::Side, WireConnection::Side).to_id();
+    assert_eq!(world.get_block_i32(40, 224, 40), Some(line));
+    assert_eq!(world.get_block_i32(41, 224, 40), Some(line));
+}
+
+// Does the following:
+//  a. Place a chest, then place a second chest next to it facing the same way
+//  b. Break the second chest
+// Checks to see that:
+//  1. Both chests form a double chest, with a left and right half
+//  2. Either half is backed by both chests, with 54 slots
+//  3. The remaining chest is a single chest again, with 27 slots
+#[test]
+fn double_chest() {
+    let (mut universe, _conn) = common::create_universe_and_player();
+
+    let first = BlockPosition { x: 40, y: 224, z: 38 };
+    let second = BlockPosition { x: 41, y: 224, z: 38 };
+    let chest = |block_type| Block::Chest {
+        facing: Direction::North,
+        block_type,
+        waterlogged: false,
+    };
+
+    // (a) Place a chest, then place a second chest next to it facing the same way
+    let world = &mut universe.service.the_world;
+    for pos in [first, second] {
+        let (mut ctx, place_position) = world
+            .create_placement_context(
+                pos,
+                ProtocolDirection::Up,
+                (0.5, 0.0, 0.5),
+                Rotation { yaw: 0.0, pitch: 0.0 },
+                Item::Chest,
+            )
+            .unwrap();
+        let block = Item::Chest.try_place(&mut ctx).unwrap();
+        assert_eq!(place_position, pos);
+        world.set_block_i32(pos.x, pos.y, pos.z, block.to_id());
+    }
+
+    // (1) Both chests form a double chest, with a left and right half
+    assert_eq!(world.get_block_i32(first.x, first.y, first.z), Some(chest(ChestType::Left).to_id()));
+    assert_eq!(world.get_block_i32(second.x, second.y, second.z), Some(chest(ChestType::Right).to_id()));
+
+    // (2) Either half is backed by both chests, with 54 slots
+    assert_eq!(world.get_chest_container(first), Some(vec![second, first]));
+    assert_eq!(world.get_chest_container(second), Some(vec![second, first]));
+    assert_eq!(world.get_chest_size(first), Some(54));
+    assert_eq!(world.get_chest_size(second), Some(54));
+
+    // (b) Break the second chest
+    world.break_block(second);
+
+    // (3) The remaining chest is a single chest again, with 27 slots
+    assert_eq!(world.get_block_i32(first.x, first.y, first.z), Some(chest(ChestType::Single).to_id()));
+    assert_eq!(world.get_chest_container(first), Some(vec![first]));
+    assert_eq!(world.get_chest_size(first), Some(27));
+    assert_eq!(world.get_chest_size(second), None);
+}
+
+// Does the following:
+//  a. Place a chest and store an item in it
+//  b. Place a second chest next to it, forming a double chest
+//  c. Open the double chest for two Players, from either half
+//  d. The first Player moves an item from the cursor into the second half of the window
+//  e. The first Player claims to pick up a stack that isn't in the container
+// Checks to see that:
+//  1. The item stored in the single chest survives the merge, in the second half of the container
+//  2. Both Players receive a generic_9x6 window with the whole container
+//  3. The slot change is stored in the half that the slot belongs to
+//  4. The second Player receives the slot change, the first Player doesn't have it sent back
+//  5. The click is rejected and the window is resent to the first Player, the container is unchanged
+#[test]
+fn double_chest_container() {
+    let (mut universe, mut conn) = common::create_universe_and_player();
+    let mut other_conn = common::create_other_player(&mut universe, 1);
+
+    let first = BlockPosition { x: 40, y: 224, z: 38 };
+    let second = BlockPosition { x: 41, y: 224, z: 38 };
+    let stone = ItemStack::of(Item::Stone, 5).unwrap();
+
+    // (a) Place a chest and store an item in it
+    // (b) Place a second chest next to it, forming a double chest
+    let world = &mut universe.service.the_world;
+    for pos in [first, second] {
+        let (mut ctx, _) = world
+            .create_placement_context(
+                pos,
+                ProtocolDirection::Up,
+                (0.5, 0.0, 0.5),
+                Rotation { yaw: 0.0, pitch: 0.0 },
+                Item::Chest,
+            )
+            .unwrap();
+        let block = Item::Chest.try_place(&mut ctx).unwrap();
+        world.set_block_i32(pos.x, pos.y, pos.z, block.to_id());
+
+        if pos == first {
+            assert!(world.set_chest_item(first, 0, ItemSlot::Filled(stone.clone())));
+        }
+    }
+
+    // (1) The item stored in the single chest survives the merge, in the second half of the container
+    let items = world.get_chest_items(second).unwrap();
+    assert_eq!(items.len(), 54);
+    assert_eq!(items[27], ItemSlot::Filled(stone.clone()));
+    assert_eq!(items.iter().filter(|item| **item != ItemSlot::Empty).count(), 1);
+
+    universe.service.tick();
+    conn.skip_all_outgoing();
+    other_conn.skip_all_outgoing();
+
+    // (c) Open the double chest for two Players, from either half
+    let players = &mut universe.service.the_world.service.players;
+    players.get_mut_by_index(0).unwrap().inventory.set_cursor(ItemSlot::Filled(ItemStack::of(Item::Stone, 2).unwrap()));
+    assert!(players.get_mut_by_index(0).unwrap().open_chest(first));
+    assert!(players.get_mut_by_index(1).unwrap().open_chest(second));
+    universe.service.tick();
+
+    // (2) Both Players receive a generic_9x6 window with the whole container
+    for conn in [&mut conn, &mut other_conn] {
+        conn.assert_outgoing_as::<OpenScreen, _>(|packet| {
+            assert_eq!(packet.window_id, 1);
+            assert_eq!(packet.window_type, 5);
+        });
+        conn.assert_outgoing_as::<ContainerSetContent, _>(|packet| {
+            assert_eq!(packet.window_id, 1);
+            assert_eq!(packet.items.len(), 54 + 36);
+            assert_eq!(packet.items[27].as_ref().map(|item| (item.item, item.count)), Some((1, 5)));
+        });
+        conn.skip_all_outgoing();
+    }
+
+    // (d) The first Player moves an item from the cursor into the second half of the window
+    conn.write_packet(&client::ContainerClick {
+        window_id: 1,
+        state_id: 0,
+        slot: 30,
+        button: 0,
+        click_type: ClickType::Pickup,
+        changed_slots: vec![client::ContainerClickChangedSlot {
+            slot: 30,
+            item: Some(ProtocolItemStack {
+                item: 1,
+                count: 2,
+                ..Default::default()
+            }),
+        }],
+        carried_item: None,
+    })
+    .unwrap();
+    universe.service.tick();
+
+    // (3) The slot change is stored in the half that the slot belongs to
+    let world = &universe.service.the_world;
+    let items = world.get_chest_items(first).unwrap();
+    assert_eq!(items[30], ItemSlot::Filled(ItemStack::of(Item::Stone, 2).unwrap()));
+    assert_eq!(world.get_chest_items(first), world.get_chest_items(second));
+
+    // (4) The second Player receives the slot change, the first Player doesn't have it sent back
+    conn.assert_none_outgoing();
+    other_conn.assert_outgoing_as::<ContainerSetSlot, _>(|packet| {
+        assert_eq!(packet.window_id, 1);
+        assert_eq!(packet.slot, 30);
+        assert_eq!(packet.item.as_ref().map(|item| (item.item, item.count)), Some((1, 2)));
+    });
+    other_conn.skip_all_outgoing();
+
+    // (e) The first Player claims to pick up a stack that isn't in the container
+    conn.write_packet(&client::ContainerClick {
+        window_id: 1,
+        state_id: 0,
+        slot: 31,
+        button: 0,
+        click_type: ClickType::Pickup,
+        changed_slots: vec![client::ContainerClickChangedSlot {
+            slot: 31,
+            item: None,
+        }],
+        carried_item: Some(ProtocolItemStack {
+            item: 1,
+            count: 64,
+            ..Default::default()
+        }),
+    })
+    .unwrap();
+    universe.service.tick();
+
+    // (5) The click is rejected and the window is resent to the first Player, the container is unchanged
+    conn.assert_outgoing_as::<ContainerSetContent, _>(|packet| {
+        assert_eq!(packet.window_id, 1);
+        assert_eq!(packet.items[30].as_ref().map(|item| (item.item, item.count)), Some((1, 2)));
+        assert!(packet.items[31].is_none());
+        assert!(packet.carried_item.is_none());
+    });
+    conn.assert_none_outgoing();
+    other_conn.assert_none_outgoing();
+    let player = universe.service.the_world.service.players.get_by_index(0).unwrap();
+    assert_eq!(*player.inventory.get_cursor(), ItemSlot::Empty);
+    let items = universe.service.the_world.get_chest_items(first).unwrap();
+    assert_eq!(items.iter().filter(|item| **item != ItemSlot::Empty).count(), 2);
+}
+
+// Does the following:
+//  a. Place the left half of a double chest without a right half
+//  b. Open the chest
+//  c. Move an item from the cursor into the first inventory slot of the window
+// Checks to see that:
+//  1. The half is treated as a single chest, the Player receives a generic_9x3 window
+//  2. The item is moved into the inventory, the chest is unchanged
+#[test]
+fn orphan_chest_half() {
+    let (mut universe, mut conn) = common::create_universe_and_player();
+    let pos = BlockPosition { x: 40, y: 224, z: 38 };
+
+    // (a) Place the left half of a double chest without a right half
+    let world = &mut universe.service.the_world;
+    let block = Block::Chest { facing: Direction::North, block_type: ChestType::Left, waterlogged: false };
+    world.set_block_i32(pos.x, pos.y, pos.z, block.to_id());
+    assert_eq!(world.get_chest_items(pos).unwrap().len(), 27);
+
+    universe.service.tick();
+    conn.skip_all_outgoing();
+
+    // (b) Open the chest
+    let player = universe.service.the_world.service.players.get_mut_by_index(0).unwrap();
+    player.inventory.set_cursor(ItemSlot::Filled(ItemStack::of(Item::Stone, 2).unwrap()));
+    assert!(player.open_chest(pos));
+    universe.service.tick();
+
+    // (1) The half is treated as a single chest, the Player receives a generic_9x3 window
+    conn.assert_outgoing_as::<OpenScreen, _>(|packet| {
+        assert_eq!(packet.window_id, 1);
+        assert_eq!(packet.window_type, 2);
+    });
+    conn.assert_outgoing_as::<ContainerSetContent, _>(|packet| {
+        assert_eq!(packet.items.len(), 27 + 36);
+    });
+    conn.skip_all_outgoing();
+
+    // (c) Move an item from the cursor into the first inventory slot of the window
+    conn.write_packet(&client::ContainerClick {
+        window_id: 1,
+        state_id: 0,
+        slot: 27,
+        button: 0,
+        click_type: ClickType::Pickup,
+        changed_slots: vec![client::ContainerClickChangedSlot {
+            slot: 27,
+            item: Some(ProtocolItemStack {
+                item: 1,
+                count: 2,
+                ..Default::default()
+            }),
+        }],
+        carried_item: None,
+    })
+    .unwrap();
+    universe.service.tick();
+
+    // (2) The item is moved into the inventory, the chest is unchanged
+    conn.assert_none_outgoing();
+    let player = universe.service.the_world.service.players.get_by_index(0).unwrap();
+    assert_eq!(*player.inventory.get_cursor(), ItemSlot::Empty);
+    assert_eq!(
+        *player.inventory.get(InventorySlot::Main(0)).unwrap(),
+        ItemSlot::Filled(ItemStack::of(Item::Stone, 2).unwrap())
+    );
+    let items = universe.service.the_world.get_chest_items(pos).unwrap();
+    assert!(items.iter().all(|item| *item == ItemSlot::Empty));
+}
+
+// Does the following:
+//  a. Transfer the Player into a world where entities aren't limited by the view distance
+//  b. Spawn a zombie (tracking range 80) and an item (tracking range 64) 72 blocks away from the Player
+//  c. Move the item 64 blocks away from the Player
+//  d. Move the item back and forth just past its tracking range
+//  e. Move the item just past its tracking range plus the margin, and the zombie to its tracking range plus the margin
+//  f. Move the zombie just past its tracking range plus the margin
+// Checks to see that:
+//  1. Player only receives AddEntity for the zombie
+//  2. Player receives AddEntity for the item
+//  3. Player *DOES NOT* receive RemoveEntities, the item is still within the margin
+//  4. Player only receives RemoveEntities for the item
+//  5. Player receives RemoveEntities for the zombie
+#[test]
+fn entity_tracking_range() {
+    let (mut universe, mut conn) = common::create_universe_and_player();
+
+    // (a) Transfer the Player into a world where entities aren't limited by the view distance
+    let player = universe.service.the_world.service.players.get_mut_by_index(0).unwrap();
+    player.transfer(Box::new(|world, _, proto_player| {
+        let position = Position {
+            coord: Coordinate { x: 104.0, y: 224.0, z: 104.0 },
+            rot: Default::default(),
+        };
+        let wide_world = &mut world.get_universe().service.wide_world;
+        wide_world.service.players.add(proto_player, Default::default(), position).unwrap();
+    }));
+    universe.service.tick();
+    conn.skip_all_outgoing();
+    assert_eq!(universe.service.wide_world.service.players.len(), 1);
+
+    // (b) Spawn a zombie (tracking range 80) and an item (tracking range 64) 72 blocks away from the Player
+    let spawn = |universe: &mut Universe<DummyUniverseService>, entity_type: EntityType| {
+        let entity_id = universe.new_entity_id();
+        let entity = BasicEntity {
+            entity_id,
+            entity_type: entity_type as _,
+        };
+        let position = Coordinate {
+            x: 176.0,
+            y: 224.0,
+            z: 104.0,
+        };
+        universe.service.wide_world.push_entity((), position, entity, entity_id);
+        entity_id
+    };
+    let zombie_id = spawn(&mut universe, EntityType::Zombie);
+    let item_id = spawn(&mut universe, EntityType::Item);
+
+    let world = &mut universe.service.wide_world;
+    let zombie = world.get_entity_mut(zombie_id).unwrap();
+    assert_eq!(zombie.get::<Viewable>().unwrap().tracking_range, 80.0);
+    let item = world.get_entity_mut(item_id).unwrap();
+    assert_eq!(item.get::<Viewable>().unwrap().tracking_range, 64.0);
+
+    // (1) Player only receives AddEntity for the zombie
+    universe.service.tick();
+    conn.assert_outgoing_as(|packet: &mut AddEntity| {
+        assert_eq!(packet.id, zombie_id.as_i32());
+    });
+    conn.assert_none_outgoing();
+
+    // (c) Move the item 64 blocks away from the Player
+    let move_entity = |universe: &mut Universe<DummyUniverseService>, entity_id: EntityId, x: f32| {
+        let mut entity = universe.service.wide_world.get_entity_mut(entity_id).unwrap();
+        entity.get_mut::<Viewable>().unwrap().coord.x = x;
+    };
+    move_entity(&mut universe, item_id, 168.0);
+    universe.service.tick();
+
+    // (2) Player receives AddEntity for the item
+    conn.assert_outgoing_as(|packet: &mut AddEntity| {
+        assert_eq!(packet.id, item_id.as_i32());
+        assert_eq!(packet.x, 168.0);
+    });
+    conn.assert_none_outgoing();
+
+    // (d) Move the item back and forth just past its tracking range
+    for x in [170.0, 167.0, 172.0, 169.0, 172.0] {
+        move_entity(&mut universe, item_id, x);
+        universe.service.tick();
+
+        // (3) Player *DOES NOT* receive RemoveEntities, the item is still within the margin
+        conn.assert_none_outgoing();
+    }
+
+    // (e) Move the item just past its tracking range plus the margin, and the zombie to its tracking range plus the margin
+    move_entity(&mut universe, item_id, 172.5);
+    move_entity(&mut universe, zombie_id, 188.0);
+    universe.service.tick();
+
+    // (4) Player only receives RemoveEntities for the item
+    conn.assert_outgoing(&RemoveEntities {
+        entities: vec![item_id.as_i32()],
+    });
+    conn.assert_none_outgoing();
+
+    // (f) Move the zombie just past its tracking range plus the margin
+    move_entity(&mut universe, zombie_id, 188.5);
+    universe.service.tick();
+
+    // (5) Player receives RemoveEntities for the zombie
+    conn.assert_outgoing(&RemoveEntities {
+        entities: vec![zombie_id.as_i32()],
+    });
+    conn.assert_none_outgoing();
+}
+
+// Does the following:
+//  a. Start another universe, ticking on its own thread
+//  b. Transfer the Player to the other universe
+// Checks to see that:
+//  1. The Player is removed from the original world
+//  2. The Player joins the other universe
+#[test]
+fn transfer_universe() {
+    let (mut universe, mut conn) = common::create_universe_and_player();
+
+    // Skip joining packets
+    conn.skip_all_outgoing();
+
+    // (a) Start another universe, ticking on its own thread
+    let (joined_sender, joined) = std::sync::mpsc::channel();
+    let sender = Universe::create_and_start(
+        || ThreadedUniverseService { joined: joined_sender },
+        None,
+    );
+
+    // (b) Transfer the Player to the other universe
+    let player = universe.service.the_world.service.players.get_mut_by_index(0).unwrap();
+    player.transfer_universe(sender.clone());
+    universe.service.tick();
+
+    // (1) The Player is removed from the original world
+    assert_eq!(universe.service.the_world.service.players.len(), 0);
+    assert!(conn.redirected.is_some());
+
+    // (2) The Player joins the other universe
+    let profile = joined
+        .recv_timeout(std::time::Duration::from_secs(5))
+        .expect("player should have joined the other universe");
+    assert_eq!(profile.uuid, common::create_game_profile().uuid);
+}
+
+// Does the following:
+//  a. Player has non-default health, food, experience, selected slot and inventory
+//  b. Player is transferred back into the same world
+// Checks to see that:
+//  1. The health, food, experience, selected slot and inventory are sent again with the current values
+//  2. The abilities are sent again
+//  3. Respawning sends the same values again without changing them
+#[test]
+fn resync_hud() {
+    let (mut universe, mut conn) = common::create_universe_and_player();
+
+    // (a) Player has non-default health, food, experience, selected slot and inventory
+    let player = universe.service.the_world.service.players.get_mut_by_index(0).unwrap();
+    player.abilities.gamemode = GameMode::Creative;
+    conn.write_packet(&client::SetCreativeModeSlot {
+        slot: 36,
+        item: Some(ProtocolItemStack {
+            item: 1,
+            count: 64,
+            ..Default::default()
+        }),
+    })
+    .unwrap();
+
+    let player = universe.service.the_world.service.players.get_mut_by_index(0).unwrap();
+    player.set_health(12.0);
+    player.set_food(15, 3.0);
+    player.set_experience(Experience {
+        progress: 0.5,
+        level: 3,
+        total: 30,
+    });
+    player.selected_hotbar_slot = 4;
+    universe.service.tick();
+    conn.skip_all_outgoing();
+
+    // (b) Player is transferred back into the same world
+    let player = universe.service.the_world.service.players.get_mut_by_index(0).unwrap();
+    player.transfer(Box::new(|world, service, proto_player| {
+        let position = Position {
+            coord: Coordinate { x: 40.0, y: 224.0, z: 40.0 },
+            rot: Default::default(),
+        };
+        world.service.players.add(proto_player, service, position).unwrap();
+    }));
+    universe.service.tick();
+    universe.service.tick();
+
+    // (1) The health, food, experience, selected slot and inventory are sent again with the current values
+    let assert_hud = |conn: &mut common::FakePlayerConnection| {
+        conn.skip_outgoing_until(PacketId::SetHealth as u8);
+        conn.assert_outgoing(&SetHealth {
+            health: 12.0,
+            food: 15,
+            saturation: 3.0,
+        });
+        conn.assert_outgoing(&SetExperience {
+            experience_progress: 0.5,
+            level: 3,
+            total_experience: 30,
+        });
+        conn.assert_outgoing(&SetCarriedItem { slot: 4 });
+        conn.assert_outgoing_as::<ContainerSetContent, _>(|packet| {
+            assert_eq!(packet.items.len(), 46);
+            assert_eq!(packet.items[36].as_ref().map(|item| (item.item, item.count)), Some((1, 64)));
+            assert!(packet.items.iter().enumerate().all(|(index, item)| index == 36 || item.is_none()));
+            assert!(packet.carried_item.is_none());
+        });
+
+        // (2) The abilities are sent again
+        conn.skip_outgoing_until(PacketId::PlayerAbilities as u8);
+        conn.skip_all_outgoing();
+    };
+    assert_hud(&mut conn);
+
+    let player = universe.service.the_world.service.players.get_mut_by_index(0).unwrap();
+    assert_eq!(player.get_health(), 12.0);
+    assert_eq!(player.selected_hotbar_slot, 4);
+    assert_eq!(player.abilities.gamemode, GameMode::Creative);
+
+    // (3) Respawning sends the same values again without changing them
+    player.respawn(player.position).unwrap();
+    universe.service.tick();
+    assert_hud(&mut conn);
+
+    let player = universe.service.the_world.service.players.get_mut_by_index(0).unwrap();
+    assert_eq!(player.get_food(), 15);
+    assert_eq!(player.get_experience().level, 3);
+}
+
+// Does the following:
+//  a. Player sends a malformed packet
+//  b. Player service is changed to ignore packet errors
+//  c. Player sends a malformed packet, then a valid packet
+// Checks to see that:
+//  1. By default, the error is returned so that the player is disconnected
+//  2. When ignored, the malformed packet is skipped and the valid packet is handled
+#[test]
+fn packet_error_hook() {
+    let (mut universe, mut conn) = common::create_universe_and_player();
+
+    // SetCarriedItem with a missing byte
+    let malformed = [client::PacketId::SetCarriedItem as u8, 0];
+
+    // (a) Player sends a malformed packet
+    // (1) By default, the error is returned so that the player is disconnected
+    let error = conn.write_packet_bytes(&malformed).unwrap_err();
+    let error = error.downcast_ref::<PacketHandleError>().unwrap();
+    assert_eq!(error.packet_id, Some(client::PacketId::SetCarriedItem as u8));
+
+    // (b) Player service is changed to ignore packet errors
+    let player = universe.service.the_world.service.players.get_mut_by_index(0).unwrap();
+    player.service.ignore_packet_errors = true;
+
+    // (c) Player sends a malformed packet, then a valid packet
+    conn.write_packet_bytes(&malformed).unwrap();
+    conn.write_packet(&client::SetCarriedItem { slot: 3 }).unwrap();
+
+    // (2) When ignored, the malformed packet is skipped and the valid packet is handled
+    let player = universe.service.the_world.service.players.get_by_index(0).unwrap();
+    assert_eq!(player.selected_hotbar_slot, 3);
+}
+
+// Does the following:
+//  a. Server sends a play-state ping to the player
+//  b. Player responds with a pong
+// Checks to see that:
+//  1. The player receives a play-state Ping with the id
+//  2. The pong is handled and its id is recorded
+#[test]
+fn play_ping_pong() {
+    let (mut universe, mut conn) = common::create_universe_and_player();
+
+    // Skip joining packets
+    universe.service.tick();
+    conn.skip_all_outgoing();
+
+    // (a) Server sends a play-state ping to the player
+    let player = universe.service.the_world.service.players.get_mut_by_index(0).unwrap();
+    assert_eq!(player.get_last_pong_id(), None);
+    player.send_ping(42);
+    universe.service.tick();
+
+    // (1) The player receives a play-state Ping with the id
+    conn.skip_outgoing_until(PacketId::Ping as u8);
+    conn.assert_outgoing(&Ping { id: 42 });
+
+    // (b) Player responds with a pong
+    conn.write_packet(&client::Pong { id: 42 }).unwrap();
+
+    // (2) The pong is handled and its id is recorded
+    let player = universe.service.the_world.service.players.get_by_index(0).unwrap();
+    assert_eq!(player.get_last_pong_id(), Some(42));
+}
+
+// Does the following:
+//  a. Tick until just before the keep alive interval
+//  b. Tick once more, then respond to the keep alive
+//  c. Tick until the next keep alive, then don't respond
+// Checks to see that:
+//  1. No keep alive is sent before the configured interval
+//  2. A keep alive is sent once the interval has passed
+//  3. The next keep alive is sent one interval after the previous one
+//  4. The player is disconnected once the timeout has passed without a response
+#[test]
+fn keep_alive_interval() {
+    let (mut universe, mut conn) = common::create_universe_and_player();
+
+    let interval = <common::DummyPlayerService as PlayerService>::KEEP_ALIVE_INTERVAL_TICKS;
+    let timeout = <common::DummyPlayerService as PlayerService>::KEEP_ALIVE_TIMEOUT_TICKS;
+
+    // (a) Tick until just before the keep alive interval
+    for _ in 0..interval - 1 {
+        universe.service.tick();
+    }
+
+    // (1) No keep alive is sent before the configured interval
+    assert!(!contains_packet(&conn, PacketId::KeepAlive as u8));
+    conn.skip_all_outgoing();
+
+    // (b) Tick once more, then respond to the keep alive
+    // (2) A keep alive is sent once the interval has passed
+    universe.service.tick();
+    let mut id = 0;
+    conn.skip_outgoing_until(PacketId::KeepAlive as u8);
+    conn.assert_outgoing_as::<KeepAlive, _>(|packet| id = packet.id);
+    conn.write_packet(&client::KeepAlive { id }).unwrap();
+    conn.skip_all_outgoing();
+
+    // (c) Tick until the next keep alive, then don't respond
+    // (3) The next keep alive is sent one interval after the previous one
+    for _ in 0..interval - 1 {
+        universe.service.tick();
+    }
+    assert!(!contains_packet(&conn, PacketId::KeepAlive as u8));
+    universe.service.tick();
+    assert!(contains_packet(&conn, PacketId::KeepAlive as u8));
+
+    // (4) The player is disconnected once the timeout has passed without a response
+    for _ in 0..timeout - 1 {
+        universe.service.tick();
+    }
+    assert_eq!(universe.service.the_world.service.players.len(), 1);
+    universe.service.tick();
+    assert_eq!(universe.service.the_world.service.players.len(), 0);
+}
+
+// Does the following:
+//  a. Two players join the universe
+//  b. A packet is serialized once and written to both players
+// Checks to see that:
+//  1. The serialized bytes include the length and packet id
+//  2. Both players receive identical bytes
+#[test]
+fn write_raw_packets() {
+    let (mut universe, mut conn) = common::create_universe_and_player();
+    let mut conn2 = common::create_other_player(&mut universe, 1);
+
+    // (a) Two players join the universe
+    universe.service.tick();
+    conn.skip_all_outgoing();
+    conn2.skip_all_outgoing();
+
+    // (b) A packet is serialized once and written to both players
+    let packet = SystemChat {
+        message: r#"{"text":"Hello"}"#,
+        overlay: false,
+    };
+    let bytes = packet_helper::serialize_packet(&packet).unwrap();
+
+    // (1) The serialized bytes include the length and packet id
+    let mut remaining = bytes.as_slice();
+    match packet_helper::try_read_packet(&mut remaining).unwrap() {
+        PacketReadResult::Complete(packet_bytes) => assert_eq!(packet_bytes[0], PacketId::SystemChat as u8),
+        _ => panic!("expected a complete packet"),
+    }
+    assert!(remaining.is_empty());
+
+    let players = &mut universe.service.the_world.service.players;
+    players.get_mut_by_index(0).unwrap().packets.write_raw_packets(&bytes);
+    players.get_mut_by_index(1).unwrap().packets.write_raw_packets(&bytes);
+    universe.service.tick();
+
+    // (2) Both players receive identical bytes
+    for conn in [&mut conn, &mut conn2] {
+        assert!(contains_bytes(conn.outgoing_bytes.get_written(), &bytes));
+        conn.skip_outgoing_until(PacketId::SystemChat as u8);
+        conn.assert_outgoing(&packet);
+    }
+}
+
+// Does the following:
+//  a. Autosave is enabled with an interval of 5 ticks, and the world advances 5 ticks
+//  b. A block is changed, and the world advances 4 ticks
+//  c. The world advances 1 more tick
+//  d. The world advances 5 more ticks
+// Checks to see that:
+//  1. Nothing is saved, because no chunks changed
+//  2. Nothing is saved before the interval has passed
+//  3. Only the changed chunk is saved, including the new block
+//  4. Nothing is saved, because the chunk didn't change since the last save
+#[test]
+fn autosave() {
+    let (mut universe, mut conn) = common::create_universe_and_player();
+
+    // Skip joining packets
+    conn.skip_all_outgoing();
+
+    // (a) Autosave is enabled with an interval of 5 ticks, and the world advances 5 ticks
+    let (sender, receiver) = std::sync::mpsc::channel();
+    universe.service.the_world.enable_autosave(5, move |snapshots| {
+        sender.send(snapshots).unwrap();
+    });
+    for _ in 0..5 {
+        universe.service.tick();
+    }
+
+    // (1) Nothing is saved, because no chunks changed
+    assert!(receiver.recv_timeout(std::time::Duration::from_millis(50)).is_err());
+
+    // (b) A block is changed, and the world advances 4 ticks
+    universe.service.the_world.set_block_i32(20, 230, 36, Block::Stone.to_id());
+    for _ in 0..4 {
+        universe.service.tick();
+    }
+
+    // (2) Nothing is saved before the interval has passed
+    assert!(receiver.recv_timeout(std::time::Duration::from_millis(50)).is_err());
+
+    // (c) The world advances 1 more tick
+    universe.service.tick();
+
+    // (3) Only the changed chunk is saved, including the new block
+    let snapshots = receiver.recv_timeout(std::time::Duration::from_secs(5)).unwrap();
+    assert_eq!(snapshots.len(), 1);
+    assert_eq!((snapshots[0].chunk_x, snapshots[0].chunk_z), (1, 2));
+    let section = &snapshots[0].sections[230 / 16];
+    assert_eq!(section.block_palette.get(4, 230 % 16, 4), Block::Stone.to_id());
+
+    // (d) The world advances 5 more ticks
+    for _ in 0..5 {
+        universe.service.tick();
+    }
+
+    // (4) Nothing is saved, because the chunk didn't change since the last save
+    assert!(receiver.recv_timeout(std::time::Duration::from_millis(50)).is_err());
+}
+
+// Helper functions
+
+fn spawn_entity_at(
+    universe: &mut Universe<DummyUniverseService>,
+    position: Coordinate,
+) -> EntityId {
+    let entity_id = universe.new_entity_id();
+    let test_entity = BasicEntity {
+        entity_id,
+        entity_type: 6,
+    };
+    universe
+        .service
+        .the_world
+        .push_entity((), position, test_entity, entity_id);
+    entity_id
+}
+
+fn move_entity_to(
+    universe: &mut Universe<DummyUniverseService>,
+    entity_id: EntityId,
+    coordinate: Coordinate,
+) {
+    let mut entity = universe
+        .service
+        .the_world
+        .get_entity_mut(entity_id)
+        .expect("entity must exist");
+    let mut viewable = entity
+        .get_mut::<Viewable>()
+        .expect("entity must have viewable component");
+    viewable.coord.x = coordinate.x;
+    viewable.coord.y = coordinate.y;
+    viewable.coord.z = coordinate.z;
+}
+
+fn move_player_y(
+    universe: &mut Universe<DummyUniverseService>,
+    conn: &mut common::FakePlayerConnection,
+    y: f64,
+    on_ground: bool,
+) {
+    conn.write_packet(&client::MovePlayerPos {
+        x: 40.0,
+        y,
+        z: 40.0,
+        on_ground,
+    })
+    .unwrap();
+    universe.service.tick();
+}
+
+fn contains_bytes(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack.windows(needle.len()).any(|window| window == needle)
+}
+
+// Reads all outgoing packets, returning the position and stage of every BlockDestruction sent for the entity
+fn take_destroy_stages(conn: &mut common::FakePlayerConnection, entity_id: EntityId) -> Vec<(BlockPosition, i8)> {
+    let mut stages = Vec::new();
+    loop {
+        let mut bytes = conn.outgoing_bytes.get_written();
+        let packet_id = match packet_helper::try_read_packet(&mut bytes) {
+            Ok(PacketReadResult::Complete(packet_bytes)) => packet_bytes[0],
+            _ => return stages,
+        };
+
+        if packet_id == PacketId::BlockDestruction as u8 {
+            conn.assert_outgoing_as(|packet: &mut BlockDestruction| {
+                assert_eq!(packet.entity_id, entity_id.as_i32());
+                stages.push((packet.location, packet.destroy_stage));
+            });
+        } else {
+            conn.skip_outgoing(packet_id);
+        }
+    }
+}
+
+fn contains_packet(conn: &common::FakePlayerConnection, packet_id: u8) -> bool {
+    count_packets(conn, packet_id) > 0
+}
+
+fn count_packets(conn: &common::FakePlayerConnection, packet_id: u8) -> usize {
+    let mut count = 0;
+    let mut bytes = conn.outgoing_bytes.get_written();
+    while let Ok(PacketReadResult::Complete(packet_bytes)) = packet_helper::try_read_packet(&mut bytes) {
+        if packet_bytes.first() == Some(&packet_id) {
+            count += 1;
+        }
+    }
+    count
 }
@@ -0,0 +1,359 @@
+use std::collections::HashSet;
+
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{
+    braced, parenthesized,
+    parse::{Parse, ParseStream},
+    parse_macro_input, token, LitStr, Token,
+};
+
+// Parse types for component!
+
+struct Modifier {
+    pub ident: syn::Ident,
+    pub argument: Option<LitStr>,
+}
+
+impl Parse for Modifier {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let ident = input.parse()?;
+        let _bang_token: Token![!] = input.parse()?;
+
+        // Modifiers such as `link!("...")` take a single string argument
+        let argument = if input.peek(token::Paren) {
+            let content;
+            parenthesized!(content in input);
+            Some(content.parse()?)
+        } else {
+            None
+        };
+
+        Ok(Self { ident, argument })
+    }
+}
+
+enum NodeContent {
+    Text(LitStr),
+    Group(Vec<Node>),
+    Argument(syn::Ident),
+}
+
+struct Node {
+    pub modifiers: Vec<Modifier>,
+    pub content: NodeContent,
+}
+
+impl Parse for Node {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut modifiers = Vec::new();
+        while input.peek(syn::Ident) && input.peek2(Token![!]) {
+            modifiers.push(input.parse()?);
+        }
+
+        let content = if input.peek(token::Brace) {
+            let content;
+            braced!(content in input);
+            NodeContent::Group(parse_nodes(&content)?)
+        } else if input.peek(LitStr) {
+            NodeContent::Text(input.parse()?)
+        } else if input.peek(Token![#]) {
+            let _pound_token: Token![#] = input.parse()?;
+            NodeContent::Argument(input.parse()?)
+        } else {
+            return Err(input.error("expected string, `{ ... }` or `#argument`"));
+        };
+
+        Ok(Self { modifiers, content })
+    }
+}
+
+fn parse_nodes(input: ParseStream) -> syn::Result<Vec<Node>> {
+    let mut nodes = Vec::new();
+    while !input.is_empty() {
+        nodes.push(input.parse()?);
+    }
+    Ok(nodes)
+}
+
+struct ComponentInput {
+    pub nodes: Vec<Node>,
+}
+
+impl Parse for ComponentInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let nodes = parse_nodes(input)?;
+        if nodes.is_empty() {
+            return Err(input.error("expected at least one component"));
+        }
+        Ok(Self { nodes })
+    }
+}
+
+// Code generation
+
+/// JSON that is being generated, static JSON is written directly while arguments are
+/// only known at runtime
+#[derive(Default)]
+struct JsonWriter {
+    parts: Vec<JsonPart>,
+}
+
+enum JsonPart {
+    Static(String),
+    Argument(syn::Ident),
+}
+
+impl JsonWriter {
+    fn push_str(&mut self, string: &str) {
+        if let Some(JsonPart::Static(last)) = self.parts.last_mut() {
+            last.push_str(string);
+        } else {
+            self.parts.push(JsonPart::Static(string.into()));
+        }
+    }
+
+    fn push(&mut self, char: char) {
+        self.push_str(char.encode_utf8(&mut [0; 4]));
+    }
+
+    fn push_string(&mut self, string: &str) {
+        let mut escaped = String::with_capacity(string.len() + 2);
+        escaped.push('"');
+        for char in string.chars() {
+            match char {
+                '"' => escaped.push_str("\\\""),
+                '\\' => escaped.push_str("\\\\"),
+                '\n' => escaped.push_str("\\n"),
+                '\r' => escaped.push_str("\\r"),
+                '\t' => escaped.push_str("\\t"),
+                char if char.is_control() => escaped.push_str(&format!("\\u{:04x}", char as u32)),
+                char => escaped.push(char),
+            }
+        }
+        escaped.push('"');
+        self.push_str(&escaped);
+    }
+
+    fn push_argument(&mut self, argument: syn::Ident) {
+        self.parts.push(JsonPart::Argument(argument));
+    }
+
+    fn into_tokens(mut self) -> proc_macro2::TokenStream {
+        // Entire component is known at compile time
+        if let [JsonPart::Static(json)] = self.parts.as_slice() {
+            return quote!(graphite_text_component::TextComponent::Static(#json));
+        }
+
+        let mut format_string = String::new();
+        let mut arguments = Vec::new();
+        for part in self.parts.drain(..) {
+            match part {
+                JsonPart::Static(json) => {
+                    format_string.push_str(&json.replace('{', "{{").replace('}', "}}"))
+                }
+                JsonPart::Argument(argument) => {
+                    format_string.push_str("{}");
+                    arguments.push(argument);
+                }
+            }
+        }
+
+        quote!(graphite_text_component::TextComponent::Owned(format!(
+            #format_string,
+            #(graphite_text_component::TextComponent::from(#arguments).to_json()),*
+        )))
+    }
+}
+
+const COLORS: [&str; 16] = [
+    "black",
+    "dark_blue",
+    "dark_green",
+    "dark_aqua",
+    "dark_red",
+    "dark_purple",
+    "gold",
+    "gray",
+    "dark_gray",
+    "blue",
+    "green",
+    "aqua",
+    "red",
+    "light_purple",
+    "yellow",
+    "white",
+];
+
+const STYLES: [&str; 5] = [
+    "bold",
+    "italic",
+    "underlined",
+    "strikethrough",
+    "obfuscated",
+];
+
+fn write_modifiers(modifiers: &[Modifier], writer: &mut JsonWriter) -> syn::Result<()> {
+    let mut keys = HashSet::new();
+    for modifier in modifiers {
+        let name = modifier.ident.to_string();
+
+        let expect_argument = || {
+            modifier
+                .argument
+                .as_ref()
+                .map(LitStr::value)
+                .ok_or_else(|| {
+                    syn::Error::new(
+                        modifier.ident.span(),
+                        format!("`{}!` requires an argument, eg. `{}!(\"...\")`", name, name),
+                    )
+                })
+        };
+
+        let key = match name.as_str() {
+            color if COLORS.contains(&color) => {
+                writer.push_str(", \"color\": ");
+                writer.push_string(color);
+                "color"
+            }
+            "color" => {
+                let color = expect_argument()?;
+                let is_hex = color.len() == 7
+                    && color.starts_with('#')
+                    && color[1..].chars().all(|char| char.is_ascii_hexdigit());
+                if !is_hex && !COLORS.contains(&color.as_str()) {
+                    return Err(syn::Error::new(
+                        modifier.ident.span(),
+                        "color must be a color name or a hex color, eg. `#ff0000`",
+                    ));
+                }
+                writer.push_str(", \"color\": ");
+                writer.push_string(&color);
+                "color"
+            }
+            style if STYLES.contains(&style) => {
+                writer.push_str(", ");
+                writer.push_string(style);
+                writer.push_str(": true");
+                style
+            }
+            "link" | "run_command" | "suggest_command" | "copy" => {
+                let action = match name.as_str() {
+                    "link" => "open_url",
+                    "copy" => "copy_to_clipboard",
+                    action => action,
+                };
+                writer.push_str(", \"clickEvent\": {\"action\": ");
+                writer.push_string(action);
+                writer.push_str(", \"value\": ");
+                writer.push_string(&expect_argument()?);
+                writer.push('}');
+                "clickEvent"
+            }
+            "hover" => {
+                writer.push_str(", \"hoverEvent\": {\"action\": \"show_text\", \"contents\": ");
+                writer.push_str("{\"text\": ");
+                writer.push_string(&expect_argument()?);
+                writer.push_str("}}");
+                "hoverEvent"
+            }
+            _ => {
+                return Err(syn::Error::new(
+                    modifier.ident.span(),
+                    format!("unknown modifier `{}!`", name),
+                ))
+            }
+        };
+
+        if !keys.insert(key.to_owned()) {
+            return Err(syn::Error::new(
+                modifier.ident.span(),
+                format!("`{}!` conflicts with an earlier modifier", name),
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn write_node(node: Node, writer: &mut JsonWriter) -> syn::Result<()> {
+    match node.content {
+        NodeContent::Text(text) => {
+            writer.push_str("{\"text\": ");
+            writer.push_string(&text.value());
+            write_modifiers(&node.modifiers, writer)?;
+            writer.push('}');
+        }
+        NodeContent::Argument(argument) if node.modifiers.is_empty() => {
+            writer.push_argument(argument);
+        }
+        NodeContent::Argument(argument) => {
+            // Modifiers are applied to the argument by making it a child
+            writer.push_str("{\"text\": \"\"");
+            write_modifiers(&node.modifiers, writer)?;
+            writer.push_str(", \"extra\": [");
+            writer.push_argument(argument);
+            writer.push_str("]}");
+        }
+        NodeContent::Group(children) => {
+            writer.push_str("{\"text\": \"\"");
+            write_modifiers(&node.modifiers, writer)?;
+            write_extra(children, writer)?;
+            writer.push('}');
+        }
+    }
+    Ok(())
+}
+
+fn write_extra(children: Vec<Node>, writer: &mut JsonWriter) -> syn::Result<()> {
+    if children.is_empty() {
+        return Ok(());
+    }
+
+    writer.push_str(", \"extra\": [");
+    for (index, child) in children.into_iter().enumerate() {
+        if index > 0 {
+            writer.push_str(", ");
+        }
+        write_node(child, writer)?;
+    }
+    writer.push(']');
+    Ok(())
+}
+
+fn component_inner(input: ComponentInput) -> syn::Result<proc_macro2::TokenStream> {
+    let mut writer = JsonWriter::default();
+
+    let mut nodes = input.nodes;
+    if nodes.len() == 1 {
+        write_node(nodes.remove(0), &mut writer)?;
+    } else {
+        // Several top-level components are joined using an empty parent
+        writer.push_str("{\"text\": \"\"");
+        write_extra(nodes, &mut writer)?;
+        writer.push('}');
+    }
+
+    Ok(writer.into_tokens())
+}
+
+/// Creates a `TextComponent`, eg. `component!(red! bold! { "Hello " yellow!{"world"} })`
+///
+/// A component is a string, a group of components in braces, or an argument such as
+/// `#name`, which is converted using `TextComponent::from`. Components can be preceded
+/// by modifiers:
+/// - colors, eg. `red!` or `color!("#ff8000")`
+/// - styles: `bold!`, `italic!`, `underlined!`, `strikethrough!` and `obfuscated!`
+/// - click events: `link!(url)`, `run_command!(command)`, `suggest_command!(command)`
+///   and `copy!(text)`
+/// - hover text: `hover!(text)`
+///
+/// Components without arguments are converted to JSON at compile time
+#[proc_macro]
+pub fn component(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as ComponentInput);
+    match component_inner(input) {
+        Ok(tokens) => tokens.into(),
+        Err(error) => error.to_compile_error().into(),
+    }
+}
@@ -0,0 +1,282 @@
+use crate::{push_escaped, TextComponent};
+
+/// Builds a text component, eg. `Component::new().text("Hello").color("red").bold()`
+///
+/// Use `append` for components with several parts, children inherit the style of their parent
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Component {
+    content: Content,
+    with: Vec<Component>,
+    color: Option<String>,
+    bold: bool,
+    click_run_command: Option<String>,
+    hover_text: Option<Box<Component>>,
+    extra: Vec<Component>,
+}
+
+impl Component {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn text(mut self, text: impl Into<String>) -> Self {
+        self.content = Content::Text(text.into());
+        self
+    }
+
+    /// Uses a translation from the client's language instead of text, eg.
+    /// `multiplayer.player.joined`, see `with` for the arguments of the translation
+    pub fn translate(mut self, key: impl Into<String>) -> Self {
+        self.content = Content::Translate(key.into());
+        self
+    }
+
+    /// Adds an argument to a translation, each `%s` in the translation is replaced by the
+    /// next argument
+    ///
+    /// Only translations have arguments, they are ignored if the content is text
+    pub fn with(mut self, argument: impl Into<Component>) -> Self {
+        self.with.push(argument.into());
+        self
+    }
+
+    /// Sets the color, either a color name such as `red` or a hex color such as `#ff0000`
+    pub fn color(mut self, color: impl Into<String>) -> Self {
+        self.color = Some(color.into());
+        self
+    }
+
+    pub fn bold(mut self) -> Self {
+        self.bold = true;
+        self
+    }
+
+    /// Runs `command` as the player when the component is clicked, eg. `/spawn`
+    pub fn click_run_command(mut self, command: impl Into<String>) -> Self {
+        self.click_run_command = Some(command.into());
+        self
+    }
+
+    pub fn hover_text(mut self, hover_text: impl Into<Component>) -> Self {
+        self.hover_text = Some(Box::new(hover_text.into()));
+        self
+    }
+
+    pub fn append(mut self, component: impl Into<Component>) -> Self {
+        self.extra.push(component.into());
+        self
+    }
+
+    pub fn to_json(&self) -> String {
+        let mut result = String::new();
+        self.write_json(&mut result);
+        result
+    }
+
+    fn write_json(&self, result: &mut String) {
+        match &self.content {
+            Content::Text(text) => {
+                result.push_str("{\"text\": \"");
+                push_escaped(result, text);
+                result.push('"');
+            }
+            Content::Translate(key) => {
+                result.push_str("{\"translate\": \"");
+                push_escaped(result, key);
+                result.push('"');
+                write_components(result, "with", &self.with);
+            }
+        }
+
+        if let Some(color) = &self.color {
+            result.push_str(", \"color\": \"");
+            push_escaped(result, color);
+            result.push('"');
+        }
+
+        if self.bold {
+            result.push_str(", \"bold\": true");
+        }
+
+        if let Some(command) = &self.click_run_command {
+            result.push_str(", \"clickEvent\": {\"action\": \"run_command\", \"value\": \"");
+            push_escaped(result, command);
+            result.push_str("\"}");
+        }
+
+        if let Some(hover_text) = &self.hover_text {
+            result.push_str(", \"hoverEvent\": {\"action\": \"show_text\", \"contents\": ");
+            hover_text.write_json(result);
+            result.push('}');
+        }
+
+        write_components(result, "extra", &self.extra);
+
+        result.push('}');
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Content {
+    Text(String),
+    Translate(String),
+}
+
+impl Default for Content {
+    fn default() -> Self {
+        Content::Text(String::new())
+    }
+}
+
+// Writes `components` as a JSON array, if there are any
+fn write_components(result: &mut String, key: &str, components: &[Component]) {
+    if components.is_empty() {
+        return;
+    }
+
+    result.push_str(", \"");
+    result.push_str(key);
+    result.push_str("\": [");
+    for (index, component) in components.iter().enumerate() {
+        if index > 0 {
+            result.push_str(", ");
+        }
+        component.write_json(result);
+    }
+    result.push(']');
+}
+
+impl From<&str> for Component {
+    fn from(text: &str) -> Self {
+        Component::new().text(text)
+    }
+}
+
+impl From<String> for Component {
+    fn from(text: String) -> Self {
+        Component::new().text(text)
+    }
+}
+
+impl From<Component> for TextComponent {
+    fn from(component: Component) -> Self {
+        TextComponent::Owned(component.to_json())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Component;
+
+    #[test]
+    fn two_part_message() {
+        let component = Component::new().text("Server ").color("red").bold().append(
+            Component::new()
+                .text("\"restarting\"")
+                .color("yellow")
+                .click_run_command("/server lobby")
+                .hover_text("Click to go to the lobby"),
+        );
+
+        assert_eq!(
+            component.to_json(),
+            concat!(
+                r#"{"text": "Server ", "color": "red", "bold": true, "extra": ["#,
+                r#"{"text": "\"restarting\"", "color": "yellow", "#,
+                r#""clickEvent": {"action": "run_command", "value": "/server lobby"}, "#,
+                r#""hoverEvent": {"action": "show_text", "contents": {"text": "Click to go to the lobby"}}}]}"#
+            )
+        );
+
+        let json: serde_json::Value = serde_json::from_str(&component.to_json()).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "text": "Server ",
+                "color": "red",
+                "bold": true,
+                "extra": [{
+                    "text": "\"restarting\"",
+                    "color": "yellow",
+                    "clickEvent": { "action": "run_command", "value": "/server lobby" },
+                    "hoverEvent": {
+                        "action": "show_text",
+                        "contents": { "text": "Click to go to the lobby" }
+                    }
+                }]
+            })
+        );
+    }
+
+    #[test]
+    fn join_message() {
+        let component = Component::new()
+            .translate("multiplayer.player.joined")
+            .color("yellow")
+            .with(
+                Component::new()
+                    .text("Steve")
+                    .click_run_command("/msg Steve"),
+            );
+
+        assert_eq!(
+            component.to_json(),
+            concat!(
+                r#"{"translate": "multiplayer.player.joined", "with": [{"text": "Steve", "#,
+                r#""clickEvent": {"action": "run_command", "value": "/msg Steve"}}], "#,
+                r#""color": "yellow"}"#
+            )
+        );
+
+        let json: serde_json::Value = serde_json::from_str(&component.to_json()).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "translate": "multiplayer.player.joined",
+                "with": [{
+                    "text": "Steve",
+                    "clickEvent": { "action": "run_command", "value": "/msg Steve" }
+                }],
+                "color": "yellow"
+            })
+        );
+
+        // Keys are escaped like text
+        let component = Component::new().translate("custom.\"key\"");
+        assert_eq!(component.to_json(), r#"{"translate": "custom.\"key\""}"#);
+    }
+
+    #[test]
+    fn translation_arguments() {
+        let component = Component::new()
+            .translate("chat.type.text")
+            .with("Steve \"the miner\"")
+            .with(Component::new().translate("item.minecraft.diamond"));
+
+        // Arguments are written in the order they were added
+        let json: serde_json::Value = serde_json::from_str(&component.to_json()).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "translate": "chat.type.text",
+                "with": [
+                    { "text": "Steve \"the miner\"" },
+                    { "translate": "item.minecraft.diamond" }
+                ]
+            })
+        );
+
+        // Text doesn't have arguments
+        let component = Component::new().text("Steve").with("ignored");
+        assert_eq!(component.to_json(), r#"{"text": "Steve"}"#);
+    }
+
+    #[test]
+    fn escape_newline() {
+        let component = Component::new().text("a\nb");
+        assert_eq!(component.to_json(), r#"{"text": "a\nb"}"#);
+
+        let json: serde_json::Value = serde_json::from_str(&component.to_json()).unwrap();
+        assert_eq!(json, serde_json::json!({ "text": "a\nb" }));
+    }
+}
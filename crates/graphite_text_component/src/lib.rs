@@ -1,3 +1,8 @@
+pub use graphite_text_component_macros::component;
+
+mod component;
+pub use component::Component;
+
 pub enum TextComponent {
     Static(&'static str),
     Owned(String),
@@ -12,6 +17,21 @@ impl TextComponent {
     }
 }
 
+// Escapes the string so it can be used inside a JSON string, the same way as the `component!` macro
+fn push_escaped(result: &mut String, string: &str) {
+    for char in string.chars() {
+        match char {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            '\r' => result.push_str("\\r"),
+            '\t' => result.push_str("\\t"),
+            char if char.is_control() => result.push_str(&format!("\\u{:04x}", char as u32)),
+            char => result.push(char),
+        }
+    }
+}
+
 impl From<String> for TextComponent {
     fn from(string: String) -> Self {
         string.as_str().into()
@@ -22,50 +42,23 @@ impl From<&str> for TextComponent {
     fn from(string: &str) -> Self {
         let mut result = String::new();
         result.push_str("{\"text\": \"");
-        let mut last_end = 0;
-        // also escape backslashes
-        for (start, part) in string.match_indices('"') {
-            result.push_str(unsafe { string.get_unchecked(last_end..start) });
-            result.push_str("\\\"");
-            last_end = start + part.len();
-        }
-        result.push_str(unsafe { string.get_unchecked(last_end..string.len()) });
+        push_escaped(&mut result, string);
         result.push_str("\"}");
 
         TextComponent::Owned(result)
     }
 }
 
-/*pub use text_component_macros::component;
-
-#[test]
-pub fn something() {
-    component!(
-        red! bold! link!("https://twitch.tv/moulberry2") {
-            // "Text " yellow!{"here"} " After" arg
-        }
-    )
-}*/
+#[cfg(test)]
+mod tests {
+    use super::TextComponent;
 
-/*
+    #[test]
+    fn escape_control_characters() {
+        let component: TextComponent = "a\nb\t\u{1}".into();
+        assert_eq!(component.to_json(), r#"{"text": "a\nb\t\u0001"}"#);
 
-TODO:
-let unfinished_component = component! {
-    red! bold! link!("https://twitch.tv/moulberry2") {
-        "Text " yellow!{"here"} " After" #arg
+        let json: serde_json::Value = serde_json::from_str(component.to_json()).unwrap();
+        assert_eq!(json, serde_json::json!({ "text": "a\nb\t\u{1}" }));
     }
-};
-
-let component = Component::new()
-    .color("Red")
-    .style(Style::Bold)
-    .link("https://twitch.tv/moulberry2")
-    .append(
-        Component::new()
-            .with_text("Text")
-            .append(Component::new().with_text("here").color("yellow")
-            .append(Component::new().with_text("After")))
-            .append(Component::new().with_text(arg)))
-    );
-
-*/
+}
@@ -0,0 +1,51 @@
+use graphite_text_component::{component, TextComponent};
+
+#[test]
+pub fn static_component() {
+    let component = component!(
+        red! bold! link!("https://twitch.tv/moulberry2") {
+            "Text " yellow!{"here"} " After"
+        }
+    );
+
+    assert!(matches!(component, TextComponent::Static(_)));
+    assert_eq!(
+        component.to_json(),
+        concat!(
+            r#"{"text": "", "color": "red", "bold": true, "#,
+            r#""clickEvent": {"action": "open_url", "value": "https://twitch.tv/moulberry2"}, "#,
+            r#""extra": [{"text": "Text "}, {"text": "", "color": "yellow", "extra": [{"text": "here"}]}, "#,
+            r#"{"text": " After"}]}"#
+        )
+    );
+
+    // The generated JSON must be valid
+    let json: serde_json::Value = serde_json::from_str(component.to_json()).unwrap();
+    assert_eq!(json["extra"][1]["extra"][0]["text"], "here");
+}
+
+#[test]
+pub fn component_with_arguments() {
+    let name = "Steve \"the miner\"";
+    let component = component!(
+        "Welcome, " gold! #name hover!("Click to\nmessage") suggest_command!("/msg ") {"!"}
+    );
+
+    let json: serde_json::Value = serde_json::from_str(component.to_json()).unwrap();
+    assert_eq!(
+        json,
+        serde_json::json!({
+            "text": "",
+            "extra": [
+                { "text": "Welcome, " },
+                { "text": "", "color": "gold", "extra": [{ "text": "Steve \"the miner\"" }] },
+                {
+                    "text": "",
+                    "hoverEvent": { "action": "show_text", "contents": { "text": "Click to\nmessage" } },
+                    "clickEvent": { "action": "suggest_command", "value": "/msg " },
+                    "extra": [{ "text": "!" }]
+                }
+            ]
+        })
+    );
+}
@@ -9,11 +9,14 @@ use graphite_command::brigadier;
 use graphite_command::types::CommandResult;
 use graphite_concierge::Concierge;
 use graphite_concierge::ConciergeService;
+use graphite_concierge::serverlist;
+use graphite_concierge::serverlist::StatusBuilder;
 use graphite_net::network_handler::UninitializedConnection;
 use graphite_mc_protocol::types::GameProfile;
 use graphite_mc_protocol::types::Pose;
 use graphite_server::UniverseTicker;
 use graphite_server::WorldTicker;
+use graphite_server::commands;
 use rand::Rng;
 use graphite_server::entity::components::BasicEntity;
 use graphite_server::entity::components::PlayerNPC;
@@ -36,25 +39,16 @@ use graphite_server::world::WorldService;
 
 struct MyConciergeImpl {
     universe_sender: Sender<(UninitializedConnection, GameProfile)>,
+    favicon: String,
 }
 
 impl ConciergeService for MyConciergeImpl {
     fn get_serverlist_response(&mut self) -> String {
-        "{\
-            \"version\": {
-                \"name\": \"1.19.1\",
-                \"protocol\": 760
-            },
-            \"players\": {
-                \"max\": 0,
-                \"online\": 0,
-                \"sample\": []
-            },
-            \"description\": {
-                \"text\": \"Hello world\"
-            },
-            \"favicon\": \"data:image/png;base64,iVBORw0KGgoAAAANSUhEUgAAAEAAAABACAIAAAAlC+aJAAABGklEQVRo3u2aUQ7EIAhEbcNReiPP6Y16F/djk/1bozJASYffJu08BRxMj957yRxnSR4EIMDbAQTylrvWwdOrNTuAY6+NjhV7YiwDbEg3xVgDUKq3wIgp4rtW1FqYAEwuMAQDk0L/FE/q02TUqVR/tTb4vGkDBaTQjL4xIU/i91gJVNeDV8gZ+HnIorAGCJAAwKIBAACAhixyIvsyKL3Qg0bKqzXnbZlNoXmH/NwitvBkeuC1Ira2lk5daBvDAn6/iH9qAi+Fyva9EDDvlYTxVkJZx/RCBMgHgO1L3IEXAmANn+SV7r0DRk5b0im2BfAfaCRcn/JYkBIXwXejDzmPJZ1iVwCHAfrgD08EIAABCEAAAhCAAAQgwG58AEFWdXlZzlUbAAAAAElFTkSuQmCC\"
-        }".into()
+        StatusBuilder::new()
+            .version("1.19.1", 760)
+            .description("Hello world".into())
+            .favicon(&self.favicon)
+            .into()
     }
 
     fn accept_player(
@@ -170,7 +164,7 @@ fn main() {
     fn save(player: &mut Player<MyPlayerService>) -> CommandResult {
         let world = player.get_world();
         let chunks = world.get_chunks();
-        let output = graphite_magma::to_magma(chunks, 0);
+        let output = graphite_magma::to_magma_compressed(chunks, 0);
 
         let dest_path = env::current_dir().unwrap().join("world.magma");
         let mut f = File::create(&dest_path).unwrap();
@@ -193,16 +187,31 @@ fn main() {
         Ok(())
     }
 
+    #[brigadier("setblock", {block_pos}, {block_state})]
+    fn setblock(player: &mut Player<MyPlayerService>, pos: &str, block: &str) -> CommandResult {
+        commands::setblock(player, pos, block)
+    }
+
+    #[brigadier("fill", {block_pos}, {block_pos}, {block_state})]
+    fn fill(player: &mut Player<MyPlayerService>, from: &str, to: &str, block: &str) -> CommandResult {
+        commands::fill(player, from, to, block)
+    }
+
+    #[brigadier("clone", {block_pos}, {block_pos}, {block_pos})]
+    fn clone(player: &mut Player<MyPlayerService>, from: &str, to: &str, destination: &str) -> CommandResult {
+        commands::clone(player, from, to, destination)
+    }
+
     my_function.merge(entity_test).unwrap();
     my_function.merge(spawn_player).unwrap();
     my_function.merge(fly).unwrap();
     my_function.merge(glow_up).unwrap();
     my_function.merge(gamemode).unwrap();
+    my_function.merge(setblock).unwrap();
+    my_function.merge(fill).unwrap();
+    my_function.merge(clone).unwrap();
     my_function.merge(save).unwrap();
 
-    let (dispatcher, packet) =
-        graphite_command::minecraft::create_dispatcher_and_brigadier_packet(my_function);
-
     let universe_sender = Universe::create_and_start(
         || MyUniverseService {
             the_world: {
@@ -214,7 +223,7 @@ fn main() {
 
 
                 let start = Instant::now();
-                let (chunk_list, _) = graphite_magma::from_magma(vec.as_slice()).unwrap();
+                let (chunk_list, _) = graphite_magma::from_magma_compressed(vec.as_slice()).unwrap();
                 println!("loading world took: {:?}", Instant::now().duration_since(start));
 
                 World::new(MyWorldService {
@@ -225,10 +234,13 @@ fn main() {
                 // }, 6, 24, 6)
             },
         },
-        Some((dispatcher, packet)),
+        Some(my_function),
     );
 
-    Concierge::bind("127.0.0.1:25565", MyConciergeImpl { universe_sender }).unwrap();
+    let favicon_path = env::current_dir().unwrap().join("favicon.png");
+    let favicon = serverlist::favicon_from_png(&favicon_path).unwrap();
+
+    Concierge::bind("127.0.0.1:25565", MyConciergeImpl { universe_sender, favicon }).unwrap();
 }
 
 // universe